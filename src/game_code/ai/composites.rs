@@ -0,0 +1,130 @@
+// :: Composite nodes ::
+// Nodes that run other nodes:
+//
+//   - Sequence: runs its children in order, failing as soon as one fails
+//   - Selector: tries its children in order, until one doesn't fail
+//   - Repeat: runs its child over and over (forever, or a number of times)
+//   - Invert: turns its child's Success into Failure, and the other way around
+use super::{BehaviorContext, BehaviorNode, Status};
+
+pub struct Sequence {
+    children: Vec<Box<dyn BehaviorNode>>,
+    current: usize,
+}
+pub fn sequence(children: Vec<Box<dyn BehaviorNode>>) -> Sequence {
+    Sequence { children, current: 0 }
+}
+impl BehaviorNode for Sequence {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status {
+        // Children that finish straight away (e.g., a Condition) don't cost a frame
+        while let Some(child) = self.children.get_mut(self.current) {
+            match child.tick(context) {
+                Status::Running => return Status::Running,
+                Status::Success => {
+                    child.reset();
+                    self.current += 1;
+                },
+                Status::Failure => {
+                    child.reset();
+                    return Status::Failure;
+                },
+            }
+        }
+        Status::Success
+    }
+    fn reset(&mut self) {
+        if let Some(child) = self.children.get_mut(self.current) {
+            child.reset();
+        }
+        self.current = 0;
+    }
+}
+
+pub struct Selector {
+    children: Vec<Box<dyn BehaviorNode>>,
+    current: usize,
+}
+pub fn selector(children: Vec<Box<dyn BehaviorNode>>) -> Selector {
+    Selector { children, current: 0 }
+}
+impl BehaviorNode for Selector {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status {
+        while let Some(child) = self.children.get_mut(self.current) {
+            match child.tick(context) {
+                Status::Running => return Status::Running,
+                Status::Success => {
+                    child.reset();
+                    return Status::Success;
+                },
+                Status::Failure => {
+                    child.reset();
+                    self.current += 1;
+                },
+            }
+        }
+        Status::Failure
+    }
+    fn reset(&mut self) {
+        if let Some(child) = self.children.get_mut(self.current) {
+            child.reset();
+        }
+        self.current = 0;
+    }
+}
+
+pub struct Repeat {
+    child: Box<dyn BehaviorNode>,
+    times: Option<u32>, // None repeats forever
+    done: u32,
+}
+// Run `child` again every time it finishes, forever
+pub fn repeat(child: impl BehaviorNode) -> Repeat {
+    Repeat { child: Box::new(child), times: None, done: 0 }
+}
+impl Repeat {
+    // Only run the child `times` times, then succeed
+    pub fn times(mut self, times: u32) -> Self {
+        self.times = Some(times);
+        self
+    }
+}
+impl BehaviorNode for Repeat {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status {
+        if self.times.map_or(false, |times| self.done >= times) {
+            return Status::Success;
+        }
+        // At most one pass a frame, so a child that finishes straight away
+        // can't loop forever
+        if self.child.tick(context) != Status::Running {
+            self.child.reset();
+            self.done += 1;
+            if self.times.map_or(false, |times| self.done >= times) {
+                return Status::Success;
+            }
+        }
+        Status::Running
+    }
+    fn reset(&mut self) {
+        self.child.reset();
+        self.done = 0;
+    }
+}
+
+pub struct Invert {
+    child: Box<dyn BehaviorNode>,
+}
+pub fn invert(child: impl BehaviorNode) -> Invert {
+    Invert { child: Box::new(child) }
+}
+impl BehaviorNode for Invert {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status {
+        match self.child.tick(context) {
+            Status::Running => Status::Running,
+            Status::Success => Status::Failure,
+            Status::Failure => Status::Success,
+        }
+    }
+    fn reset(&mut self) {
+        self.child.reset();
+    }
+}
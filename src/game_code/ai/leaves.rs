@@ -0,0 +1,229 @@
+// :: Leaf nodes ::
+// The standard things a behavior can do:
+//
+//   - MoveTo: walk to a point (or to what was last perceived, or an
+//     enemy's CurrentTarget, following it as it moves), around walls;
+//     succeeds on arrival, fails if there's no way there
+//   - Wait: stand around for a while
+//   - PlayAnim: play an animation state through once
+//   - FaceTarget: turn towards a point (or what was last perceived, or an
+//     enemy's CurrentTarget)
+//   - Condition: succeed or fail depending on a check
+use bevy::prelude::*;
+
+use super::{BehaviorContext, BehaviorNode, Status};
+use crate::{
+    animation::{AnimState, SpritesheetAnimator},
+    direction::Direction,
+    enemy::CurrentTarget,
+    movement::{MoveIntent, MovePath, Position},
+    pathfinding::FindPath,
+    perception::PerceivedTarget,
+};
+
+const ARRIVE_TOLERANCE: f32 = 4.0; // how close to a MoveTo's target counts as there, in pixels
+const REPATH_DISTANCE: f32 = 16.0; // how far (about a tile) a CurrentTarget moves before the way to it is found again
+
+// Where to go, or look
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Target {
+    Point(Vec2),
+    Perceived, // the entity's PerceivedTarget's last known position
+    Current, // where the entity's CurrentTarget is now
+}
+impl Target {
+    fn resolve(&self, context: &BehaviorContext) -> Option<Vec2> {
+        match self {
+            Target::Point(point) => Some(*point),
+            Target::Perceived => context.world.get::<PerceivedTarget>(context.entity)
+                .and_then(|perceived| perceived.last_known),
+            Target::Current => context.world.get::<CurrentTarget>(context.entity)
+                .and_then(|current| current.0)
+                .and_then(|target| context.world.get::<GlobalTransform>(target))
+                .map(|transform| transform.translation().truncate()),
+        }
+    }
+}
+
+pub struct MoveTo {
+    target: Target,
+    destination: Option<Vec2>, // where we're walking to, once we've started
+}
+impl MoveTo {
+    pub fn point(point: Vec2) -> Self {
+        Self { target: Target::Point(point), destination: None }
+    }
+    pub fn perceived() -> Self {
+        Self { target: Target::Perceived, destination: None }
+    }
+    pub fn current() -> Self {
+        Self { target: Target::Current, destination: None }
+    }
+}
+impl BehaviorNode for MoveTo {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status {
+        let destination = match self.destination {
+            // A CurrentTarget can move, so keep up with it
+            Some(destination) if self.target == Target::Current => {
+                match self.target.resolve(context) {
+                    Some(target) if target.distance(destination) > REPATH_DISTANCE => {
+                        self.destination = Some(target);
+                        context.world.entity_mut(context.entity).insert(FindPath::new(target));
+                        return Status::Running;
+                    },
+                    _ => destination,
+                }
+            },
+            Some(destination) => destination,
+            None => {
+                // Stop walking wherever the last MoveTo (or this one, before
+                // it was reset) was headed
+                let mut entity = context.world.entity_mut(context.entity);
+                entity.remove::<FindPath>();
+                let walking = entity.get::<MovePath>().map_or(false, |path| !path.is_empty());
+                if walking {
+                    if let Some(mut path) = entity.get_mut::<MovePath>() {
+                        path.clear();
+                    }
+                    if let Some(mut intent) = entity.get_mut::<MoveIntent>() {
+                        intent.0 = Vec2::ZERO;
+                    }
+                }
+                let destination = match self.target.resolve(context) {
+                    Some(destination) => destination,
+                    None => return Status::Failure,
+                };
+                self.destination = Some(destination);
+                context.world.entity_mut(context.entity).insert(FindPath::new(destination));
+                return Status::Running;
+            }
+        };
+        // Still working out the way, or walking it
+        let entity = context.world.entity(context.entity);
+        let walking = entity.get::<MovePath>().map_or(false, |path| !path.is_empty());
+        if entity.contains::<FindPath>() || walking {
+            return Status::Running;
+        }
+        match entity.get::<Position>() {
+            Some(position) if position.current.distance(destination) <= ARRIVE_TOLERANCE => Status::Success,
+            _ => Status::Failure,
+        }
+    }
+    fn reset(&mut self) {
+        self.destination = None;
+    }
+}
+
+pub struct Wait {
+    seconds: f32,
+    elapsed: f32,
+}
+impl Wait {
+    pub fn new(seconds: f32) -> Self {
+        Self { seconds, elapsed: 0.0 }
+    }
+}
+impl BehaviorNode for Wait {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status {
+        self.elapsed += context.delta_seconds;
+        if self.elapsed >= self.seconds { Status::Success } else { Status::Running }
+    }
+    fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+}
+
+// Plays `state` once (see `SpritesheetAnimator::play_once_then`), then
+// goes back to whatever was playing before, and succeeds
+pub struct PlayAnim<S: AnimState> {
+    state: S,
+    started: bool,
+}
+impl<S: AnimState> PlayAnim<S> {
+    pub fn new(state: S) -> Self {
+        Self { state, started: false }
+    }
+}
+impl<S: AnimState> BehaviorNode for PlayAnim<S> {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status {
+        let mut animator = match context.world.get_mut::<SpritesheetAnimator<S>>(context.entity) {
+            Some(animator) => animator,
+            None => return Status::Failure,
+        };
+        if self.started {
+            let playing = animator.cur_state == self.state && !animator.finished;
+            return if playing { Status::Running } else { Status::Success };
+        }
+        let return_state = animator.cur_state.clone();
+        match animator.play_once_then(self.state.clone(), return_state) {
+            Ok(true) => {
+                self.started = true;
+                Status::Running
+            },
+            Ok(false) => Status::Running, // try again once the current animation can be interrupted
+            Err(err) => {
+                warn!("{}", err);
+                Status::Failure
+            },
+        }
+    }
+    fn reset(&mut self) {
+        self.started = false;
+    }
+}
+
+pub struct FaceTarget {
+    target: Target,
+}
+impl FaceTarget {
+    pub fn point(point: Vec2) -> Self {
+        Self { target: Target::Point(point) }
+    }
+    pub fn perceived() -> Self {
+        Self { target: Target::Perceived }
+    }
+    pub fn current() -> Self {
+        Self { target: Target::Current }
+    }
+}
+impl BehaviorNode for FaceTarget {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status {
+        let target = match self.target.resolve(context) {
+            Some(target) => target,
+            None => return Status::Failure,
+        };
+        let position = match context.world.get::<GlobalTransform>(context.entity) {
+            Some(transform) => transform.translation().truncate(),
+            None => return Status::Failure,
+        };
+        let facing = match Direction::from_vec2(target - position) {
+            Some(facing) => facing,
+            None => return Status::Success, // already there
+        };
+        match context.world.get_mut::<Direction>(context.entity) {
+            Some(mut direction) => {
+                if *direction != facing {
+                    *direction = facing;
+                }
+                Status::Success
+            },
+            None => Status::Failure,
+        }
+    }
+}
+
+type Check = Box<dyn Fn(&World, Entity) -> bool + Send + Sync>;
+
+pub struct Condition {
+    check: Check,
+}
+impl Condition {
+    pub fn new(check: impl Fn(&World, Entity) -> bool + Send + Sync + 'static) -> Self {
+        Self { check: Box::new(check) }
+    }
+}
+impl BehaviorNode for Condition {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status {
+        if (self.check)(context.world, context.entity) { Status::Success } else { Status::Failure }
+    }
+}
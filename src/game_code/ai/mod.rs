@@ -0,0 +1,120 @@
+// :: Behavior trees ::
+// Enemy and NPC behaviors built out of small, reusable pieces instead of
+// one-off systems. A BehaviorTree is a tree of nodes, ticked once a frame:
+// leaves do things (walk somewhere, wait, play an animation), and
+// composites decide which children run and in what order:
+//
+//     BehaviorTree::new(repeat(sequence(vec![
+//         Box::new(MoveTo::point(well)),
+//         Box::new(PlayAnim::new(VillagerAnim::DrawWater)),
+//         Box::new(Wait::new(2.0)),
+//         Box::new(MoveTo::point(house)),
+//         Box::new(Wait::new(5.0)),
+//     ])))
+//
+// Each tick, a node reports whether it's still Running, or finished with
+// Success or Failure. Nodes get the whole World, so new leaves can use any
+// component or resource; implement BehaviorNode for them like the leaves
+// in leaves.rs. A Condition wraps a check as a leaf, for branching:
+//
+//     selector(vec![
+//         Box::new(sequence(vec![Box::new(Condition::new(sees_player)), Box::new(MoveTo::perceived())])),
+//         Box::new(Wait::new(1.0)),
+//     ])
+use bevy::prelude::*;
+
+use crate::{
+    animation::DirectionalAnimationSystem,
+    movement::{follow_move_paths, MovementSystem},
+};
+
+mod composites;
+mod leaves;
+
+pub use composites::{invert, repeat, selector, sequence, Invert, Repeat, Selector, Sequence};
+pub use leaves::{Condition, FaceTarget, MoveTo, PlayAnim, Target, Wait};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Status {
+    Running,
+    Success,
+    Failure,
+}
+
+// What a node can see and change while it's ticked
+pub struct BehaviorContext<'w> {
+    pub world: &'w mut World,
+    pub entity: Entity, // the entity whose tree it is
+    pub delta_seconds: f32,
+}
+
+pub trait BehaviorNode: Send + Sync + 'static {
+    fn tick(&mut self, context: &mut BehaviorContext) -> Status;
+    // Forget any progress, so the node starts over the next time it's ticked.
+    // Called when it finishes, and when it's interrupted.
+    fn reset(&mut self) {}
+}
+
+#[derive(Component)]
+pub struct BehaviorTree {
+    root: Option<Box<dyn BehaviorNode>>, // only None while it's being ticked
+    pub paused: bool,
+    status: Status, // how the last tick went
+}
+impl BehaviorTree {
+    pub fn new(root: impl BehaviorNode) -> Self {
+        Self { root: Some(Box::new(root)), paused: false, status: Status::Running }
+    }
+    // How the tree's last tick went. A finished tree starts over on its next
+    // tick, so wrap the root in `repeat` for behaviors meant to go on forever.
+    pub fn status(&self) -> Status {
+        self.status
+    }
+    // Start the tree over from the beginning
+    pub fn restart(&mut self) {
+        if let Some(root) = &mut self.root {
+            root.reset();
+        }
+        self.status = Status::Running;
+    }
+}
+
+// Trees are ticked in this label, which runs before movement and animation
+#[derive(SystemLabel)]
+pub struct BehaviorTreeSystem;
+
+pub struct BehaviorTreePlugin;
+impl Plugin for BehaviorTreePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(tick_behavior_trees
+            .label(BehaviorTreeSystem)
+            .before(follow_move_paths)
+            .before(MovementSystem)
+            .before(DirectionalAnimationSystem));
+    }
+}
+
+// Needs the whole World, since nodes can touch anything
+fn tick_behavior_trees(world: &mut World) {
+    let delta_seconds = world.resource::<Time>().delta_seconds();
+    let entities: Vec<Entity> = world.query_filtered::<Entity, With<BehaviorTree>>().iter(world).collect();
+    for entity in entities {
+        // Take the root out while it's ticked, so it can borrow the World
+        let mut root = match world.get_mut::<BehaviorTree>(entity) {
+            Some(mut tree) if !tree.paused => match tree.root.take() {
+                Some(root) => root,
+                None => continue,
+            },
+            _ => continue,
+        };
+        let status = root.tick(&mut BehaviorContext { world, entity, delta_seconds });
+        if status != Status::Running {
+            root.reset();
+        }
+        // The entity may have been despawned by a node
+        if let Some(mut tree) = world.get_mut::<BehaviorTree>(entity) {
+            tree.root = Some(root);
+            tree.status = status;
+        }
+    }
+}
@@ -0,0 +1,186 @@
+// :: Importing animations from Aseprite ::
+// Aseprite (https://www.aseprite.org) can export a spritesheet image along
+// with a JSON file describing every frame's rectangle and duration, plus the
+// animation "tags" the artist defined. This loader reads that JSON (saved
+// with the extension `.aseprite.json`) and builds both:
+//   - a TextureAtlas, as the labeled asset "atlas", and
+//   - an AnimationSet with one state per tag, as the main asset.
+// So a character can be set up with no hand-counted frame indices at all:
+//
+//     texture_atlas: asset_server.load("images/thomas.aseprite.json#atlas"),
+//     AnimationSource::<PlayerAnim>::new(asset_server.load("images/thomas.aseprite.json")),
+//
+// Export from Aseprite with "Array" or "Hash" frames, and with "Tags" enabled.
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    math::Rect,
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use super::{
+    loader::{AnimationDef, FrameDef},
+    AnimationSet, AnimationStyle, Frame,
+};
+
+#[derive(Deserialize)]
+struct AsepriteRect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+struct AsepriteSize {
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+struct AsepriteFrame {
+    frame: AsepriteRect,
+    duration: u32, // in milliseconds
+}
+
+// Aseprite writes frames either as a list ("Array" export), or as a map from
+// file names to frames ("Hash" export). Hash exports only keep their frame
+// order with serde_json's "preserve_order" feature, so prefer Array exports.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AsepriteFrames {
+    Array(Vec<AsepriteFrame>),
+    Hash(serde_json::Map<String, serde_json::Value>),
+}
+
+#[derive(Deserialize)]
+struct AsepriteTag {
+    name: String,
+    from: usize,
+    to: usize,
+    direction: String, // "forward", "reverse" or "pingpong"
+    #[serde(default)]
+    repeat: Option<String>, // how many times to play; missing means forever
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AsepriteMeta {
+    image: String,
+    size: AsepriteSize,
+    #[serde(default)]
+    frame_tags: Vec<AsepriteTag>,
+}
+
+#[derive(Deserialize)]
+struct AsepriteFile {
+    frames: AsepriteFrames,
+    meta: AsepriteMeta,
+}
+impl AsepriteFile {
+    fn frame_list(self) -> Result<(Vec<AsepriteFrame>, AsepriteMeta), serde_json::Error> {
+        let frames = match self.frames {
+            AsepriteFrames::Array(frames) => frames,
+            AsepriteFrames::Hash(map) => map.into_iter()
+                .map(|(_, value)| serde_json::from_value(value))
+                .collect::<Result<_, _>>()?,
+        };
+        Ok((frames, self.meta))
+    }
+}
+
+// Turn an Aseprite tag into one of our animations
+fn tag_to_animation(tag: &AsepriteTag, frames: &[AsepriteFrame]) -> AnimationDef {
+    let mut indices: Vec<usize> = (tag.from..=tag.to).collect();
+    let looping = match tag.direction.as_str() {
+        "reverse" => AnimationStyle::Reverse,
+        "pingpong" => AnimationStyle::PingPong,
+        _ => AnimationStyle::Looping,
+    };
+    // A tag set to play only once
+    let looping = if tag.repeat.as_deref() == Some("1") {
+        if matches!(looping, AnimationStyle::Reverse) {
+            indices.reverse();
+        }
+        AnimationStyle::Once
+    } else {
+        looping
+    };
+    AnimationDef {
+        frame_durations: indices.iter()
+            .map(|idx| frames.get(*idx).map_or(0.1, |f| f.duration as f32 / 1000.0))
+            .collect(),
+        frames: indices.into_iter().map(|idx| FrameDef::Full(Frame::new(idx))).collect(),
+        fps: None,
+        looping: Some(looping),
+        flip: false,
+        priority: 0,
+        frame_tags: HashMap::new(),
+        start_offset: Default::default(),
+        offset: (0.0, 0.0),
+    }
+}
+
+#[derive(Default)]
+pub struct AsepriteLoader;
+impl AssetLoader for AsepriteLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let file: AsepriteFile = serde_json::from_slice(bytes)?;
+            let (frames, meta) = file.frame_list()?;
+
+            // The image path in the JSON is relative to the JSON file
+            let image_path = load_context.path()
+                .parent()
+                .map_or_else(|| meta.image.clone().into(), |dir| dir.join(&meta.image));
+            let image_asset_path = AssetPath::new(image_path, None);
+            let image: Handle<Image> = load_context.get_handle(image_asset_path.clone());
+
+            // Build the atlas from each frame's rectangle
+            let mut atlas = TextureAtlas::new_empty(image, Vec2::new(meta.size.w, meta.size.h));
+            for frame in frames.iter() {
+                let rect = &frame.frame;
+                atlas.add_texture(Rect {
+                    min: Vec2::new(rect.x, rect.y),
+                    max: Vec2::new(rect.x + rect.w, rect.y + rect.h),
+                });
+            }
+            load_context.set_labeled_asset("atlas",
+                LoadedAsset::new(atlas).with_dependency(image_asset_path));
+
+            // One animation state per tag. Without tags, the whole sheet
+            // becomes a single looping state named "default".
+            let mut states = HashMap::new();
+            for tag in meta.frame_tags.iter() {
+                states.insert(tag.name.clone(), tag_to_animation(tag, &frames));
+            }
+            let start = match meta.frame_tags.first() {
+                Some(tag) => tag.name.clone(),
+                None => {
+                    let whole_sheet = AsepriteTag {
+                        name: "default".to_string(),
+                        from: 0,
+                        to: frames.len().saturating_sub(1),
+                        direction: "forward".to_string(),
+                        repeat: None,
+                    };
+                    states.insert(whole_sheet.name.clone(), tag_to_animation(&whole_sheet, &frames));
+                    whole_sheet.name
+                },
+            };
+            load_context.set_default_asset(LoadedAsset::new(AnimationSet { start, fallback: None, states }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite.json"]
+    }
+}
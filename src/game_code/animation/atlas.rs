@@ -0,0 +1,171 @@
+// :: Spritesheet grids ::
+// Most spritesheets are a grid of equally-sized frames, cut up with
+// `TextureAtlas::from_grid`. Atlases made through `AtlasGrids::add` remember
+// their grid, so when the image changes on disk (e.g., an artist adds a
+// column to thomas_walk.png while the game runs) the atlas is rebuilt to
+// match, instead of showing the new image cut up along the old rectangles.
+//
+// The grid can also live in a small `.atlas.ron` file next to the image,
+// which loads straight into a TextureAtlas (and is rebuilt the same way):
+//
+//     (
+//         image: "thomas_walk.png", // relative to this file
+//         tile_size: (16.0, 32.0),
+//         columns: 15,
+//         rows: 1,
+//         padding: None,            // optional, e.g. Some((1.0, 1.0))
+//         offset: None,             // optional
+//     )
+//
+//     texture_atlas: asset_server.load("images/thomas_walk.atlas.ron"),
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+
+// How a spritesheet image is cut up into frames
+#[derive(Clone, Copy, Debug, TypeUuid)]
+#[uuid = "5d2f0c39-8a47-4f0e-9b6e-3c1d7a2e94b1"]
+pub struct AtlasGrid {
+    pub tile_size: Vec2, // the size of each frame, in pixels
+    pub columns: usize,
+    pub rows: usize,
+    pub padding: Option<Vec2>, // optional. the gap between frames
+    pub offset: Option<Vec2>, // optional. where the first frame starts
+}
+impl AtlasGrid {
+    pub fn new(tile_size: Vec2, columns: usize, rows: usize) -> Self {
+        Self { tile_size, columns, rows, padding: None, offset: None }
+    }
+    pub fn with_padding(mut self, padding: Vec2) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+    pub fn build(&self, texture: Handle<Image>) -> TextureAtlas {
+        TextureAtlas::from_grid(texture, self.tile_size, self.columns, self.rows,
+                                self.padding, self.offset)
+    }
+}
+
+// The grid of every atlas that should be rebuilt when its image changes
+#[derive(Resource, Default)]
+pub struct AtlasGrids(HashMap<Handle<TextureAtlas>, AtlasGrid>);
+impl AtlasGrids {
+    // Cut `texture` up into an atlas, and keep it up to date with the image
+    pub fn add(&mut self,
+               texture_atlases: &mut Assets<TextureAtlas>,
+               texture: Handle<Image>,
+               grid: AtlasGrid) -> Handle<TextureAtlas> {
+        let handle = texture_atlases.add(grid.build(texture));
+        self.0.insert(handle.clone_weak(), grid);
+        handle
+    }
+}
+
+pub(super) fn rebuild_grid_atlases(
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut atlas_grids: ResMut<AtlasGrids>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    for event in image_events.iter() {
+        let image = match event {
+            AssetEvent::Modified { handle } => handle,
+            _ => continue,
+        };
+        // Forget atlases that have been unloaded
+        atlas_grids.0.retain(|atlas, _| texture_atlases.contains(atlas));
+
+        for (atlas_handle, grid) in atlas_grids.0.iter() {
+            let uses_image = texture_atlases.get(atlas_handle)
+                .map_or(false, |atlas| atlas.texture == *image);
+            if uses_image {
+                if let Some(atlas) = texture_atlases.get_mut(atlas_handle) {
+                    // (the event's handle is weak, so keep the atlas's own)
+                    *atlas = grid.build(atlas.texture.clone());
+                    info!("Rebuilt texture atlas for modified image {:?}", image);
+                }
+            }
+        }
+    }
+}
+
+// The contents of a `.atlas.ron` file
+#[derive(Deserialize)]
+struct AtlasGridFile {
+    image: String,
+    tile_size: (f32, f32),
+    columns: usize,
+    rows: usize,
+    #[serde(default)]
+    padding: Option<(f32, f32)>,
+    #[serde(default)]
+    offset: Option<(f32, f32)>,
+}
+
+#[derive(Default)]
+pub struct AtlasGridLoader;
+impl AssetLoader for AtlasGridLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let file: AtlasGridFile = ron::de::from_bytes(bytes)?;
+            let grid = AtlasGrid {
+                tile_size: file.tile_size.into(),
+                columns: file.columns,
+                rows: file.rows,
+                padding: file.padding.map(Vec2::from),
+                offset: file.offset.map(Vec2::from),
+            };
+
+            // The image path is relative to the .atlas.ron file
+            let image_path = load_context.path()
+                .parent()
+                .map_or_else(|| file.image.clone().into(), |dir| dir.join(&file.image));
+            let image_asset_path = AssetPath::new(image_path, None);
+            let image: Handle<Image> = load_context.get_handle(image_asset_path.clone());
+
+            load_context.set_default_asset(
+                LoadedAsset::new(grid.build(image)).with_dependency(image_asset_path));
+            // Kept alongside the atlas, so it can be rebuilt when the image changes
+            load_context.set_labeled_asset("grid", LoadedAsset::new(grid));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["atlas.ron"]
+    }
+}
+
+// Once a `.atlas.ron` file has loaded, remember its atlas's grid
+pub(super) fn track_loaded_atlas_grids(
+    mut grid_events: EventReader<AssetEvent<AtlasGrid>>,
+    asset_server: Res<AssetServer>,
+    grid_assets: Res<Assets<AtlasGrid>>,
+    mut atlas_grids: ResMut<AtlasGrids>,
+) {
+    for event in grid_events.iter() {
+        let handle = match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+            AssetEvent::Removed { .. } => continue,
+        };
+        let (grid, path) = match (grid_assets.get(handle), asset_server.get_handle_path(handle)) {
+            (Some(grid), Some(path)) => (grid, path),
+            _ => continue,
+        };
+        // The atlas is the main asset of the same file
+        let atlas_path = AssetPath::new(path.path().to_path_buf(), None);
+        let atlas: Handle<TextureAtlas> = asset_server.get_handle(atlas_path);
+        atlas_grids.0.insert(atlas.clone_weak(), *grid);
+    }
+}
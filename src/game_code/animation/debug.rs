@@ -0,0 +1,83 @@
+// :: Animation debug overlay ::
+// Only compiled with the "animation-debug" feature. Draws each animated
+// entity's current state, frame index and fps just above it, so state
+// machine bugs (like a stuck "move-*" state) are visible at a glance.
+// Press F1 to show or hide the overlay.
+use bevy::prelude::*;
+
+use super::{AnimState, AnimationSystem, SpritesheetAnimator};
+use crate::ui::UI_FONT;
+
+const DEBUG_FONT_SIZE: f32 = 8.0;
+const DEBUG_LABEL_OFFSET: Vec3 = Vec3::new(0.0, 24.0, 10.0); // above the sprite, drawn on top
+const TOGGLE_KEY: KeyCode = KeyCode::F1;
+
+// The text child showing an animator's state
+#[derive(Component)]
+pub struct AnimationDebugLabel;
+
+#[derive(Resource)]
+pub struct ShowAnimationDebug(pub bool);
+
+pub(super) fn add_debug_systems<S: AnimState>(app: &mut App) {
+    app.insert_resource(ShowAnimationDebug(true))
+        .add_system(spawn_debug_labels::<S>)
+        .add_system(update_debug_labels::<S>.after(AnimationSystem))
+        .add_system(toggle_debug_labels);
+}
+
+fn spawn_debug_labels<S: AnimState>(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    query: Query<Entity, Added<SpritesheetAnimator<S>>>,
+) {
+    for entity in &query {
+        let label = commands.spawn((
+            AnimationDebugLabel,
+            Text2dBundle {
+                text: Text::from_section("", TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: DEBUG_FONT_SIZE,
+                    color: Color::YELLOW,
+                }).with_alignment(TextAlignment::BOTTOM_CENTER),
+                transform: Transform::from_translation(DEBUG_LABEL_OFFSET),
+                ..default()
+            },
+        )).id();
+        commands.entity(entity).add_child(label);
+    }
+}
+
+fn update_debug_labels<S: AnimState>(
+    animators: Query<(&SpritesheetAnimator<S>, &Children)>,
+    mut labels: Query<&mut Text, With<AnimationDebugLabel>>,
+) {
+    for (animator, children) in &animators {
+        for child in children.iter() {
+            if let Ok(mut text) = labels.get_mut(*child) {
+                text.sections[0].value = format!(
+                    "{:?}{}\nframe {} @ {:.1} fps",
+                    animator.cur_state,
+                    if animator.is_paused() { " (paused)" } else { "" },
+                    animator.cur_frame_idx,
+                    animator.cur_fps,
+                );
+            }
+        }
+    }
+}
+
+fn toggle_debug_labels(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut show: ResMut<ShowAnimationDebug>,
+    mut labels: Query<&mut Visibility, With<AnimationDebugLabel>>,
+) {
+    if keyboard_input.just_pressed(TOGGLE_KEY) {
+        show.0 = !show.0;
+    }
+    for mut visibility in &mut labels {
+        if visibility.is_visible != show.0 {
+            visibility.is_visible = show.0;
+        }
+    }
+}
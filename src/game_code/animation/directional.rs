@@ -0,0 +1,114 @@
+// :: Directional animation ::
+// Most of a character's states are an action ("stand", "move") combined
+// with the direction they're facing ("move-up-left"). A DirectionalAnimator
+// maps each (action, Direction) pair to an animation state, and keeps the
+// entity's SpritesheetAnimator in sync with its `Direction` component.
+// Gameplay code then only has to set the action and direction:
+//
+//     directional.action = PlayerAction::Move;
+//     *direction = Direction::NW; // plays PlayerAnim::MoveUpLeft
+//
+// An action can fall back to another action's states, played faster or slower,
+// for characters that don't have animations for it (e.g., no "run-*" states,
+// so "move-*" is played at double speed instead).
+//
+// Add `DirectionalAnimationPlugin::<Action, State>::default()` to your App.
+use std::{hash::Hash, marker::PhantomData};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{AnimState, AnimationSystem, SpritesheetAnimator};
+use crate::direction::Direction;
+
+// Anything that can name an action, usually a small enum
+pub trait AnimAction: Clone + Eq + Hash + Send + Sync + 'static {}
+impl<T: Clone + Eq + Hash + Send + Sync + 'static> AnimAction for T {}
+
+#[derive(Component)]
+pub struct DirectionalAnimator<A: AnimAction, S: AnimState> {
+    pub action: A,
+    states: HashMap<(A, Direction), S>,
+    fallback_actions: HashMap<A, (A, f32)>, // the action to use instead, and its fps multiplier
+}
+impl<A: AnimAction, S: AnimState> DirectionalAnimator<A, S> {
+    pub fn new(action: A) -> Self {
+        Self { action, states: HashMap::default(), fallback_actions: HashMap::default() }
+    }
+    // Use `state` for `action` when facing `direction`
+    pub fn with(mut self, action: A, direction: Direction, state: S) -> Self {
+        self.states.insert((action, direction), state);
+        self
+    }
+    // Set the states for all eight directions of an action at once,
+    // in the order of `Direction::ALL` (clockwise, starting from north)
+    pub fn with_all(mut self, action: A, states: [S; 8]) -> Self {
+        for (direction, state) in Direction::ALL.into_iter().zip(states) {
+            self.states.insert((action.clone(), direction), state);
+        }
+        self
+    }
+    // When the animator has no state for `action`, play `fallback`'s state
+    // instead, with its fps multiplied by `fps_scale`
+    pub fn with_fallback_action(mut self, action: A, fallback: A, fps_scale: f32) -> Self {
+        self.fallback_actions.insert(action, (fallback, fps_scale));
+        self
+    }
+    pub fn state_for(&self, action: &A, direction: Direction) -> Option<&S> {
+        self.states.get(&(action.clone(), direction))
+    }
+
+    // The state (and fps override, if falling back) to play
+    // for the current action, given the states the animator has
+    fn resolve(&self, direction: Direction, animator: &SpritesheetAnimator<S>) -> Option<(S, Option<f32>)> {
+        let state = self.state_for(&self.action, direction);
+        if let Some(state) = state {
+            if animator.states.contains_key(state) {
+                return Some((state.clone(), None));
+            }
+        }
+        match self.fallback_actions.get(&self.action) {
+            Some((fallback, fps_scale)) => {
+                let state = self.state_for(fallback, direction)?;
+                let anim = animator.states.get(state)?;
+                Some((state.clone(), Some(anim.fps * fps_scale)))
+            },
+            // Let the animator warn about (or fall back from) the missing state
+            None => state.map(|state| (state.clone(), None)),
+        }
+    }
+}
+
+// Systems that set the action or Direction should run `.before()` this
+#[derive(SystemLabel)]
+pub struct DirectionalAnimationSystem;
+
+pub struct DirectionalAnimationPlugin<A: AnimAction, S: AnimState>(PhantomData<(A, S)>);
+impl<A: AnimAction, S: AnimState> Default for DirectionalAnimationPlugin<A, S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<A: AnimAction, S: AnimState> Plugin for DirectionalAnimationPlugin<A, S> {
+    fn build(&self, app: &mut App) {
+        app.add_system(apply_directional_states::<A, S>
+            .label(DirectionalAnimationSystem)
+            .before(AnimationSystem));
+    }
+}
+
+fn apply_directional_states<A: AnimAction, S: AnimState>(
+    mut query: Query<(&DirectionalAnimator<A, S>, &Direction, &mut SpritesheetAnimator<S>)>,
+) {
+    for (directional, direction, mut animator) in &mut query {
+        if let Some((state, fps_override)) = directional.resolve(*direction, &animator) {
+            let wanted_fps = fps_override
+                .or_else(|| animator.states.get(&state).map(|anim| anim.fps))
+                .unwrap_or(animator.cur_fps);
+            if animator.cur_state != state || animator.cur_fps != wanted_fps {
+                if let Err(err) = animator.set_state(state, fps_override) {
+                    warn!("{}", err);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+// :: Animation layers ::
+// A character can be drawn from several stacked sprites (body, hair, armor,
+// weapon...), "paper-doll" style. Each layer is a child entity with its own
+// SpriteSheetBundle and a LinkedAnimator component, and plays in lockstep
+// with its parent: change the parent's state, and every layer follows.
+//
+// - If a layer has its own SpritesheetAnimator<S>, it follows the parent's
+//   state and frame index, so a layer can use different frames per state
+//   (its animator should define the same states as the parent's).
+// - Otherwise, the layer just shows the same texture index as its parent,
+//   which is all you need for a layer drawn over a copy of the parent's sheet.
+//
+// Layers aren't animated by `animate_sprites` themselves; their timing
+// always comes from the parent.
+use bevy::prelude::*;
+
+use super::{AnimState, SpritesheetAnimator};
+
+#[derive(Component)]
+pub struct LinkedAnimator;
+
+pub(super) fn sync_linked_animators<S: AnimState>(
+    parents: Query<(&SpritesheetAnimator<S>, &TextureAtlasSprite), Without<LinkedAnimator>>,
+    mut layers: Query<(
+        &Parent,
+        Option<&mut SpritesheetAnimator<S>>,
+        &mut TextureAtlasSprite,
+        &mut Handle<TextureAtlas>,
+    ), With<LinkedAnimator>>,
+) {
+    for (parent, animator, mut sprite, mut texture_atlas_handle) in &mut layers {
+        let (parent_animator, parent_sprite) = match parents.get(parent.get()) {
+            Ok(parent) => parent,
+            Err(_) => continue,
+        };
+
+        // Layers tint, flash and shift along with their parent
+        if sprite.color != parent_sprite.color {
+            sprite.color = parent_sprite.color;
+        }
+        sprite.anchor = parent_sprite.anchor.clone();
+
+        match animator {
+            Some(mut animator) => {
+                // Follow the parent's state and frame
+                if animator.cur_state == parent_animator.cur_state
+                    && animator.cur_frame_idx == parent_animator.cur_frame_idx {
+                    continue;
+                }
+                animator.cur_state = parent_animator.cur_state.clone();
+                animator.cur_frame_idx = parent_animator.cur_frame_idx;
+                animator.reversing = parent_animator.reversing;
+                animator.finished = parent_animator.finished;
+
+                // Remember the layer's own atlas, for animations without one
+                if animator.base_atlas.is_none() {
+                    animator.base_atlas = Some(texture_atlas_handle.clone());
+                }
+                if let Some(anim) = animator.states.get(&animator.cur_state) {
+                    let wanted_atlas = anim.atlas.clone()
+                        .unwrap_or_else(|| animator.base_atlas.clone().unwrap());
+                    if *texture_atlas_handle != wanted_atlas {
+                        *texture_atlas_handle = wanted_atlas;
+                    }
+                    if let Some(frame) = anim.frames.get(animator.cur_frame_idx) {
+                        frame.apply_to_sprite(&mut sprite);
+                    }
+                }
+            },
+            None => {
+                // Mirror the parent's sprite
+                if sprite.index != parent_sprite.index {
+                    sprite.index = parent_sprite.index;
+                }
+                sprite.flip_x = parent_sprite.flip_x;
+                sprite.flip_y = parent_sprite.flip_y;
+            },
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::HandleId,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{HashMap, HashSet},
+};
+use serde::{de::IntoDeserializer, Deserialize};
+
+use super::{
+    AnimState, AnimationStyle, Frame, SpritesheetAnimation, SpritesheetAnimator, StartOffset,
+    DEFAULT_ANIMATION_FPS,
+};
+
+// :: Loading animations from files ::
+// Instead of hardcoding every state in `setup`, a character's animations
+// can live in a `.anim.ron` (or `.anim.json`) file under `assets/animations/`.
+// An AnimationDef is one entry in that file. "flip" mirrors the whole
+// animation horizontally, so you don't have to write negative frame ids by hand.
+#[derive(Deserialize)]
+pub struct AnimationDef {
+    pub frames: Vec<FrameDef>,
+    #[serde(default)]
+    pub fps: Option<f32>,
+    #[serde(default)]
+    pub looping: Option<AnimationStyle>,
+    #[serde(default)]
+    pub flip: bool,
+    #[serde(default)]
+    pub frame_durations: Vec<f32>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub frame_tags: std::collections::HashMap<usize, Vec<String>>,
+    #[serde(default)]
+    pub start_offset: StartOffset,
+    #[serde(default)]
+    pub offset: (f32, f32), // in pixels, e.g. (-8.0, 0.0) to shift left by 8
+}
+// Each frame in a file is either a frame id in our shorthand format
+// (e.g. -7), or a full Frame, e.g. (index: 6, flip_y: true, rotation: 90).
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum FrameDef {
+    Id(i32),
+    Full(Frame),
+}
+impl FrameDef {
+    pub fn to_frame(&self) -> Frame {
+        match self {
+            FrameDef::Id(id) => Frame::from_id(*id),
+            FrameDef::Full(frame) => *frame,
+        }
+    }
+}
+
+impl AnimationDef {
+    pub fn to_animation(&self) -> SpritesheetAnimation {
+        let frames = self.frames.iter()
+            .map(|def| if self.flip { def.to_frame().flipped_x() } else { def.to_frame() })
+            .collect();
+        SpritesheetAnimation {
+            frames,
+            fps: self.fps.unwrap_or(DEFAULT_ANIMATION_FPS),
+            looping: self.looping.unwrap_or(AnimationStyle::Looping),
+            frame_durations: self.frame_durations.clone(),
+            priority: self.priority,
+            atlas: None, // animations loaded from files use the entity's atlas
+            frame_tags: self.frame_tags.iter()
+                .map(|(idx, tags)| (*idx, tags.clone()))
+                .collect(),
+            start_offset: self.start_offset,
+            offset: self.offset.into(),
+        }
+    }
+}
+
+// The whole file: a start state plus a map from state names to animations.
+// State names are written as strings in the file, and are turned into the
+// animator's state type when applied (so "move-up-left" becomes
+// `PlayerAnim::MoveUpLeft` for an enum with `#[serde(rename_all = "kebab-case")]`).
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "a1b04bef-926a-4849-a77e-cc9b39ee4aab"]
+pub struct AnimationSet {
+    pub start: String,
+    #[serde(default)]
+    pub fallback: Option<String>, // state to play when a missing state is requested
+    pub states: std::collections::HashMap<String, AnimationDef>,
+}
+impl AnimationSet {
+    pub fn to_states<S: AnimState>(&self) -> HashMap<S, SpritesheetAnimation> {
+        let mut states = HashMap::default();
+        for (name, def) in self.states.iter() {
+            match parse_state::<S>(name) {
+                Some(state) => { states.insert(state, def.to_animation()); },
+                None => warn!("Unknown animation state \"{}\" in animation file", name),
+            }
+        }
+        states
+    }
+    pub fn start_state<S: AnimState>(&self) -> Option<S> {
+        parse_state::<S>(&self.start)
+    }
+    pub fn fallback_state<S: AnimState>(&self) -> Option<S> {
+        self.fallback.as_ref().and_then(|name| parse_state::<S>(name))
+    }
+}
+
+// Turn a state name from a file into a state value
+pub fn parse_state<S: AnimState>(name: &str) -> Option<S> {
+    S::deserialize(name.into_deserializer())
+        .map_err(|_: serde::de::value::Error| ())
+        .ok()
+}
+
+// Put this on an entity to give it a SpritesheetAnimator<S> built from an
+// animation file, once the file has loaded.
+#[derive(Component)]
+pub struct AnimationSource<S: AnimState> {
+    pub handle: Handle<AnimationSet>,
+    _state: PhantomData<S>,
+}
+impl<S: AnimState> AnimationSource<S> {
+    pub fn new(handle: Handle<AnimationSet>) -> Self {
+        Self { handle, _state: PhantomData }
+    }
+}
+
+// Attach a SpritesheetAnimator to an entity once its animation file has
+// loaded (or straight away, if it already had), and refresh the animator's
+// states whenever the file is edited.
+pub(super) fn apply_animation_sets<S: AnimState>(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<AnimationSet>>,
+    animation_sets: Res<Assets<AnimationSet>>,
+    mut query: Query<(
+        Entity,
+        &AnimationSource<S>,
+        ChangeTrackers<AnimationSource<S>>,
+        Option<&mut SpritesheetAnimator<S>>,
+    )>,
+) {
+    let mut loaded = HashSet::default();
+    for event in events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            loaded.insert(handle.id());
+        }
+    }
+    // Each set's start state, or None if the set can't be used, so every
+    // set is only checked (and warned about) once
+    let mut start_states: HashMap<HandleId, Option<S>> = HashMap::default();
+    for (entity, source, tracker, animator) in &mut query {
+        let handle = source.handle.id();
+        let new_source = tracker.is_added() && animator.is_none();
+        if !new_source && !loaded.contains(&handle) {
+            continue;
+        }
+        let set = match animation_sets.get(&source.handle) {
+            Some(set) => set,
+            None => continue, // it's attached once the set loads
+        };
+        let start_state = match start_states.entry(handle).or_insert_with(|| check_set(set)) {
+            Some(state) => state.clone(),
+            None => continue,
+        };
+        match animator {
+            // Hot-reload: swap in the new states, staying in the
+            // current state if the file still defines it
+            Some(mut animator) => {
+                let cur_state = animator.cur_state.clone();
+                animator.states = set.to_states();
+                animator.fallback_state = set.fallback_state();
+                if animator.force_state(cur_state, None).is_err() {
+                    if let Err(err) = animator.force_state(start_state, None) {
+                        warn!("{}", err);
+                    }
+                }
+            },
+            None => {
+                match SpritesheetAnimator::new(set.to_states(), start_state) {
+                    Ok(mut animator) => {
+                        animator.fallback_state = set.fallback_state();
+                        commands.entity(entity).insert(animator);
+                    },
+                    Err(err) => warn!("Couldn't load animations: {}", err),
+                }
+            },
+        }
+    }
+}
+
+// A set's start state, if the set can be played
+fn check_set<S: AnimState>(set: &AnimationSet) -> Option<S> {
+    let start_state = match set.start_state::<S>() {
+        Some(state) => state,
+        None => {
+            warn!("Unknown start state \"{}\" in animation file", set.start);
+            return None;
+        },
+    };
+    // Check every state before touching any animator, so a mistake
+    // in an edited file leaves the animations already playing alone
+    // (a bad frame duration would otherwise panic once it's played)
+    if let Err(err) = set.to_states::<S>().iter().try_for_each(|(state, anim)| anim.validate(state)) {
+        warn!("Couldn't load animations (any already playing are kept): {}", err);
+        return None;
+    }
+    Some(start_state)
+}
@@ -0,0 +1,895 @@
+// :: Spritesheet animation ::
+// Everything needed to animate a TextureAtlasSprite, for the player or any
+// other entity. Add `SpriteAnimationPlugin` to your App, then give an entity
+// a `SpriteSheetBundle` plus either a `SpritesheetAnimator` or an
+// `AnimationSource` (see loader.rs, or aseprite.rs for Aseprite exports).
+//
+// Animators are generic over their state type, so each kind of character
+// can name its states with its own enum (e.g., `PlayerAnim::MoveUpLeft`),
+// and a typo in a state name becomes a compile error. Add one plugin per
+// state type: `SpriteAnimationPlugin::<PlayerAnim>::default()`.
+use std::{fmt::Debug, hash::Hash, marker::PhantomData, time::Duration};
+
+use bevy::{prelude::*, reflect::GetTypeRegistration, sprite::Anchor, utils::{HashMap, HashSet}};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::asset_loader::RonOrJsonLoader;
+
+mod aseprite;
+mod atlas;
+#[cfg(feature = "animation-debug")]
+mod debug;
+mod directional;
+mod layers;
+mod loader;
+
+pub use atlas::{AtlasGrid, AtlasGrids};
+pub use directional::{DirectionalAnimationPlugin, DirectionalAnimationSystem, DirectionalAnimator};
+pub use layers::LinkedAnimator;
+pub use loader::{parse_state, AnimationSet, AnimationSource};
+
+// Anything that can name an animation state. You won't need to implement
+// this yourself: any enum deriving these traits qualifies automatically.
+// (Reflect and FromReflect let inspectors like bevy-inspector-egui edit
+// animators at runtime; Default is the state of a freshly-reflected animator.)
+pub trait AnimState: Clone + Eq + Hash + Debug + Default + DeserializeOwned
+    + Reflect + FromReflect + GetTypeRegistration + Send + Sync + 'static {}
+impl<T: Clone + Eq + Hash + Debug + Default + DeserializeOwned
+    + Reflect + FromReflect + GetTypeRegistration + Send + Sync + 'static> AnimState for T {}
+
+pub struct SpriteAnimationPlugin<S: AnimState>(PhantomData<S>);
+impl<S: AnimState> Default for SpriteAnimationPlugin<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: AnimState> Plugin for SpriteAnimationPlugin<S> {
+    fn build(&self, app: &mut App) {
+        // The animation file format is shared by every state type,
+        // so only register it once
+        if !app.world.contains_resource::<Assets<AnimationSet>>() {
+            app.add_asset::<AnimationSet>()
+                .add_asset_loader(RonOrJsonLoader::<AnimationSet>::new(&["anim.ron", "anim.json"]))
+                .init_asset_loader::<aseprite::AsepriteLoader>()
+                .add_asset::<AtlasGrid>()
+                .init_asset_loader::<atlas::AtlasGridLoader>()
+                .init_resource::<AtlasGrids>()
+                .add_system(atlas::track_loaded_atlas_grids.before(atlas::rebuild_grid_atlases))
+                .add_system(atlas::rebuild_grid_atlases);
+        }
+        // Let inspectors and debug UIs see and edit animators
+        app.register_type::<S>()
+            .register_type::<SpritesheetAnimator<S>>()
+            .register_type::<SpritesheetAnimation>()
+            .register_type::<AnimationStyle>()
+            .register_type::<StartOffset>()
+            .register_type::<Frame>()
+            .register_type::<AnimationTimer>();
+
+        app.init_resource::<AnimationSpeed>()
+            .add_event::<AnimationFinished<S>>()
+            .add_event::<AnimationFrameEvent>()
+            .add_system(loader::apply_animation_sets::<S>.before(AnimationSystem))
+            .add_system(animate_sprites::<S>.label(AnimationSystem))
+            .add_system(layers::sync_linked_animators::<S>.after(AnimationSystem));
+
+        #[cfg(feature = "animation-debug")]
+        debug::add_debug_systems::<S>(app);
+    }
+}
+
+// Systems that change animator states should run `.before(AnimationSystem)`,
+// so the change is shown on the same frame
+#[derive(SystemLabel)]
+pub struct AnimationSystem;
+
+// Sent when an `AnimationStyle::Once` animation reaches its last frame,
+// so gameplay systems can react (e.g., return to "stand" after an attack)
+pub struct AnimationFinished<S: AnimState> {
+    pub entity: Entity,
+    pub state: S,
+}
+
+// Sent when a frame with a tag (e.g., "footstep" or "hit") is displayed,
+// so audio and gameplay can sync up with the animation
+pub struct AnimationFrameEvent {
+    pub entity: Entity,
+    pub tag: String,
+}
+
+// Scales how fast every animation in the game plays. Set it below 1.0
+// for slow motion (or a brief hit-stop), or above 1.0 to fast-forward
+// (up to 64x).
+#[derive(Resource, Deref, DerefMut)]
+pub struct AnimationSpeed(pub f32);
+impl Default for AnimationSpeed {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+// A timer for animations
+#[derive(Component, Deref, DerefMut, Default, Reflect, FromReflect)]
+#[reflect(Component)]
+pub struct AnimationTimer(pub Timer);
+
+// How the animation should continue after it reaches the last frame
+#[derive(Clone, Copy, Deserialize, Reflect, FromReflect)]
+pub enum AnimationStyle {
+    Once,     // Play once and end at last frame
+    Looping,  // Loop from frame 1 to n, then from 1 to n, ad infinitum
+    PingPong, // Play from frame 1 to n, then back from n to 1, ad infinitum
+    Reverse,  // Loop backwards, from frame n to 1, then from n to 1, ad infinitum
+}
+impl AnimationStyle {
+    // The index of the frame an animation with this style starts on
+    pub fn first_frame_idx(&self, num_frames: usize) -> usize {
+        match self {
+            AnimationStyle::Reverse => num_frames.saturating_sub(1),
+            _ => 0,
+        }
+    }
+
+    // Given the current frame index, and whether a PingPong animation is
+    // currently playing backwards, returns the next frame index and the
+    // new direction. Returns None when a Once animation has no frames left.
+    pub fn advance(&self, cur_idx: usize, num_frames: usize,
+                   reversing: bool) -> Option<(usize, bool)> {
+        if num_frames == 0 {
+            return None;
+        }
+        let last_idx = num_frames - 1;
+        match self {
+            AnimationStyle::Once => {
+                if cur_idx >= last_idx { None } else { Some((cur_idx + 1, false)) }
+            },
+            AnimationStyle::Looping => {
+                if cur_idx >= last_idx { Some((0, false)) } else { Some((cur_idx + 1, false)) }
+            },
+            AnimationStyle::Reverse => {
+                if cur_idx == 0 { Some((last_idx, false)) } else { Some((cur_idx - 1, false)) }
+            },
+            AnimationStyle::PingPong => {
+                if last_idx == 0 {
+                    Some((0, false))
+                } else if reversing {
+                    // Bounce off the first frame
+                    if cur_idx == 0 { Some((1, false)) } else { Some((cur_idx - 1, true)) }
+                } else {
+                    // Bounce off the last frame
+                    if cur_idx >= last_idx { Some((last_idx - 1, true)) } else { Some((cur_idx + 1, false)) }
+                }
+            },
+        }
+    }
+}
+
+// Where an animation starts playing when an entity switches to it.
+// When lots of NPCs share an animation, a Random offset keeps them from
+// blinking and walking in perfect sync.
+#[derive(Clone, Copy, Default, Deserialize, Reflect, FromReflect)]
+pub enum StartOffset {
+    #[default]
+    None,         // start on the first frame
+    Fixed(usize), // start this many frames in
+    Random,       // start on a random frame, partway through it
+}
+
+// A single frame of an animation: which texture of the TextureAtlas to show,
+// and how to mirror and rotate it. Mirroring and rotating lets top-down games
+// reuse more of a spritesheet (e.g., a "walk-up" drawn as a flipped "walk-down").
+// A frame's rotation turns the entity's Transform, on top of its own rotation.
+#[derive(Clone, Copy, Default, Deserialize, Reflect, FromReflect)]
+pub struct Frame {
+    pub index: usize, // the TextureAtlas index, starting at 0
+    #[serde(default)]
+    pub flip_x: bool,
+    #[serde(default)]
+    pub flip_y: bool,
+    #[serde(default)]
+    pub rotation: f32, // counterclockwise, in degrees
+}
+impl Frame {
+    pub fn new(index: usize) -> Self {
+        Self { index, ..default() }
+    }
+    // Convert a frame id in our shorthand format: the TextureAtlas' index + 1,
+    // or negative to represent an x-flipped texture. (Ids are i32s, so
+    // spritesheets can have far more than 127 frames.)
+    pub fn from_id(id: i32) -> Self {
+        Self {
+            index: id.unsigned_abs().saturating_sub(1) as usize,
+            flip_x: id < 0,
+            ..default()
+        }
+    }
+    pub fn flipped_x(mut self) -> Self {
+        self.flip_x = !self.flip_x;
+        self
+    }
+    pub fn flipped_y(mut self) -> Self {
+        self.flip_y = !self.flip_y;
+        self
+    }
+    pub fn rotated(mut self, degrees: f32) -> Self {
+        self.rotation = degrees;
+        self
+    }
+    // Set the sprite's texture and mirroring to show this frame
+    pub fn apply_to_sprite(&self, sprite: &mut TextureAtlasSprite) {
+        sprite.index = self.index;
+        sprite.flip_x = self.flip_x;
+        sprite.flip_y = self.flip_y;
+    }
+}
+
+// A SpritesheetAnimation is a series of Frames from a TextureAtlas,
+// making up a single animation. The "fps" is how fast to display the animation.
+// Individual frames can be held longer (or shorter) with "frame_durations",
+// e.g. a windup pose held for 0.5s followed by quick follow-through frames.
+// The "priority" decides which states may interrupt this one before it
+// finishes: only states with an equal or higher priority can. So giving
+// "attack" a priority of 1 means "move-*" (priority 0) must wait for it.
+// An animation can also use its own TextureAtlas, for characters whose
+// animations are split across several spritesheets. Animations without
+// one use the atlas the entity was spawned with.
+// Frames can be tagged (e.g., frame 1 with "footstep") to send an
+// AnimationFrameEvent every time that frame is displayed.
+// Frames that are bigger than the rest (like a wide attack swing) can be
+// shifted by a pixel "offset", so the character's body stays in place
+// (flipped frames are shifted the other way).
+pub const DEFAULT_ANIMATION_FPS: f32 = 5.0;
+#[derive(Reflect, FromReflect)]
+pub struct SpritesheetAnimation {
+    pub frames: Vec<Frame>, // the frames of the animation
+    pub fps: f32, // how quickly to go to the next frame, in frames per second
+    pub looping: AnimationStyle, // whether and how to loop the animation
+    pub frame_durations: Vec<f32>, // optional. seconds to hold each frame; missing entries use fps
+    pub priority: i32, // lower-priority states can't interrupt this one until it finishes
+    pub atlas: Option<Handle<TextureAtlas>>, // optional. the spritesheet these frames come from
+    pub frame_tags: HashMap<usize, Vec<String>>, // optional. tags for frames, by frame index
+    pub start_offset: StartOffset, // optional. which frame to start on
+    pub offset: Vec2, // optional. how far to shift every frame, in pixels
+}
+impl SpritesheetAnimation {
+    // Make an animation from frame ids in our shorthand format
+    // (see `Frame::from_id`), e.g. vec![-10, -11, -10, -12]
+    pub fn from_frames(frames: Vec<i32>) -> Self {
+        Self::from_frame_list(frames.into_iter().map(Frame::from_id).collect())
+    }
+    pub fn from_frame_list(frames: Vec<Frame>) -> Self {
+        Self {
+            frames,
+            fps: DEFAULT_ANIMATION_FPS,
+            looping: AnimationStyle::Looping,
+            frame_durations: Vec::new(),
+            priority: 0,
+            atlas: None,
+            frame_tags: HashMap::default(),
+            start_offset: StartOffset::None,
+            offset: Vec2::ZERO,
+        }
+    }
+    pub fn with_start_offset(mut self, start_offset: StartOffset) -> Self {
+        self.start_offset = start_offset;
+        self
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+    // Tag a frame (by its index in this animation), e.g. .with_frame_tag(1, "footstep")
+    pub fn with_frame_tag(mut self, frame_idx: usize, tag: &str) -> Self {
+        self.frame_tags.entry(frame_idx).or_default().push(tag.to_string());
+        self
+    }
+    pub fn with_atlas(mut self, atlas: Handle<TextureAtlas>) -> Self {
+        self.atlas = Some(atlas);
+        self
+    }
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+    pub fn with_frame_durations(mut self, frame_durations: Vec<f32>) -> Self {
+        self.frame_durations = frame_durations;
+        self
+    }
+    // Check that this animation can actually be played
+    pub fn validate<S: AnimState>(&self, state: &S) -> Result<(), AnimatorError<S>> {
+        if self.frames.is_empty() {
+            return Err(AnimatorError::NoFrames(state.clone()));
+        }
+        if !valid_fps(self.fps) {
+            return Err(AnimatorError::InvalidFps { state: state.clone(), fps: self.fps });
+        }
+        for (frame_idx, duration) in self.frame_durations.iter().enumerate() {
+            if !(duration.is_finite() && *duration > 0.0) {
+                return Err(AnimatorError::InvalidFrameDuration {
+                    state: state.clone(), frame_idx, duration: *duration,
+                });
+            }
+        }
+        Ok(())
+    }
+    // The frame to start on, and a timer for it, taking the start offset into account
+    pub fn start(&self, fps: f32) -> (usize, Timer) {
+        let num_frames = self.frames.len();
+        let first_idx = self.looping.first_frame_idx(num_frames);
+        let offset_idx = |offset: usize| {
+            if num_frames == 0 {
+                return first_idx;
+            }
+            match self.looping {
+                AnimationStyle::Reverse => (first_idx + num_frames - offset % num_frames) % num_frames,
+                _ => (first_idx + offset) % num_frames,
+            }
+        };
+        match self.start_offset {
+            StartOffset::None => {
+                (first_idx, Timer::from_seconds(self.frame_duration(first_idx, fps), TimerMode::Repeating))
+            },
+            StartOffset::Fixed(offset) => {
+                let idx = offset_idx(offset);
+                (idx, Timer::from_seconds(self.frame_duration(idx, fps), TimerMode::Repeating))
+            },
+            StartOffset::Random => {
+                let mut rng = rand::thread_rng();
+                let idx = offset_idx(rng.gen_range(0..num_frames.max(1)));
+                let duration = self.frame_duration(idx, fps);
+                let mut timer = Timer::from_seconds(duration, TimerMode::Repeating);
+                timer.set_elapsed(Duration::from_secs_f32(rng.gen::<f32>() * duration));
+                (idx, timer)
+            },
+        }
+    }
+    // How long to display the frame at `frame_idx`, in seconds
+    pub fn frame_duration(&self, frame_idx: usize, fps: f32) -> f32 {
+        match self.frame_durations.get(frame_idx) {
+            Some(duration) => *duration,
+            None => 1.0 / fps,
+        }
+    }
+}
+
+// A SpriteAnimator is a map from "states" (usually an enum)
+// to individual animations.
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct SpritesheetAnimator<S: AnimState> {
+    pub states: HashMap<S, SpritesheetAnimation>,
+    pub timer: AnimationTimer,
+    pub cur_state: S,
+    pub cur_frame_idx: usize,
+    pub cur_fps: f32, // the frame rate of the current state, including any override
+    pub finished: bool, // whether a Once animation has played its last frame
+    pub reversing: bool, // whether a PingPong animation is on its way back to frame 1
+    pub queued_state: Option<(S, Option<f32>)>, // state (and fps override) to play once this one finishes
+    pub base_atlas: Option<Handle<TextureAtlas>>, // the entity's own atlas, for animations without one
+    pub frame_changed: bool, // whether the current frame still needs to be shown on the sprite
+    pub paused: bool, // whether the animation is frozen on its current frame
+    pub fallback_state: Option<S>, // optional. state to play when asked for a state that doesn't exist
+    pub tint: Color, // color multiplied over every frame (white means no tint)
+    pub flash: Option<(Color, u32)>, // color to show instead of the tint, and for how many more game frames
+    pub frames_left_in_pass: Option<usize>, // set by `play_once_then`. frames to show before finishing
+    #[reflect(ignore)]
+    warned_states: HashSet<S>, // missing states we've already warned about
+    #[reflect(ignore)]
+    applied_rotation: f32, // the shown frame's rotation, in degrees, on top of the entity's own
+    #[reflect(ignore)]
+    base_anchor: Option<Anchor>, // the sprite's own anchor, while an animation's offset replaces it
+}
+// An animator with no states, only needed so reflection can create
+// animators. Use `new` or `builder` to make one that can play.
+impl<S: AnimState> Default for SpritesheetAnimator<S> {
+    fn default() -> Self {
+        Self {
+            states: HashMap::default(),
+            timer: AnimationTimer::default(),
+            cur_state: S::default(),
+            cur_frame_idx: 0,
+            cur_fps: DEFAULT_ANIMATION_FPS,
+            finished: false,
+            reversing: false,
+            queued_state: None,
+            base_atlas: None,
+            frame_changed: false,
+            paused: false,
+            fallback_state: None,
+            tint: Color::WHITE,
+            flash: None,
+            frames_left_in_pass: None,
+            warned_states: HashSet::default(),
+            applied_rotation: 0.0,
+            base_anchor: None,
+        }
+    }
+}
+impl<S: AnimState> SpritesheetAnimator<S> {
+    // Make an animator, checking that every animation is playable
+    // and that the start state exists
+    pub fn new(states: HashMap<S, SpritesheetAnimation>,
+               start_state: S) -> Result<Self, AnimatorError<S>> {
+        for (state, anim) in states.iter() {
+            anim.validate(state)?;
+        }
+        let anim = match states.get(&start_state) {
+            Some(anim) => anim,
+            None => return Err(AnimatorError::StateNotFound(start_state)),
+        };
+        let fps = anim.fps;
+        let (first_idx, timer) = anim.start(fps);
+        Ok(Self {
+            timer: AnimationTimer(timer),
+            states: states,
+            cur_state: start_state,
+            cur_frame_idx: first_idx,
+            cur_fps: fps,
+            finished: false,
+            reversing: false,
+            queued_state: None,
+            base_atlas: None,
+            frame_changed: true,
+            paused: false,
+            fallback_state: None,
+            tint: Color::WHITE,
+            flash: None,
+            frames_left_in_pass: None,
+            warned_states: HashSet::default(),
+            applied_rotation: 0.0,
+            base_anchor: None,
+        })
+    }
+    // Play `state` whenever a state that doesn't exist is requested,
+    // e.g. so a character missing some animations stands still instead of freezing
+    pub fn with_fallback(mut self, state: S) -> Self {
+        self.fallback_state = Some(state);
+        self
+    }
+    pub fn builder() -> SpritesheetAnimatorBuilder<S> {
+        SpritesheetAnimatorBuilder {
+            states: HashMap::default(),
+            start_state: None,
+            fallback_state: None,
+        }
+    }
+    // Freeze the animation on its current frame (e.g., for cutscenes
+    // and freeze-frames), until `resume` is called. State changes still
+    // show the new state's first frame while paused.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Tint the sprite until `clear_tint` is called (e.g., blue while frozen)
+    pub fn set_tint(&mut self, color: Color) {
+        self.tint = color;
+    }
+    pub fn clear_tint(&mut self) {
+        self.tint = Color::WHITE;
+    }
+    // Show `color` over the sprite for the next `frames` game frames, then
+    // go back to the tint (e.g., a white flash when the character is hit).
+    // Flashes keep counting down while the animation is paused.
+    pub fn flash(&mut self, color: Color, frames: u32) {
+        self.flash = if frames > 0 { Some((color, frames)) } else { None };
+    }
+    pub fn is_flashing(&self) -> bool {
+        self.flash.is_some()
+    }
+
+    // Whether `state_name` may interrupt the current state right now.
+    // A finished animation can always be interrupted.
+    pub fn can_interrupt(&self, state_name: &S) -> bool {
+        if self.finished {
+            return true;
+        }
+        let priority_of = |state: &S| self.states.get(state).map_or(0, |anim| anim.priority);
+        priority_of(state_name) >= priority_of(&self.cur_state)
+    }
+
+    // Change to a new state, if the current state can be interrupted by it.
+    // Returns Ok(false) if the change isn't allowed (yet), and an error if
+    // the state doesn't exist or the fps override is invalid.
+    // The sprite itself is updated by `animate_sprites`, so any system
+    // with access to the animator can change its state.
+    pub fn set_state(&mut self,
+        state_name: S,
+        fps_override: Option<f32>, // Optional. Provide a different frame rate.
+    ) -> Result<bool, AnimatorError<S>> {
+        let state_name = match self.resolve_state(state_name)? {
+            Some(state_name) => state_name,
+            None => return Ok(true), // already playing the fallback
+        };
+        if !self.can_interrupt(&state_name) {
+            return Ok(false);
+        }
+        self.force_state(state_name, fps_override)?;
+        Ok(true)
+    }
+
+    // Like `set_state`, but if the current state can't be interrupted yet,
+    // remember the new state and play it as soon as the current one finishes.
+    // (Queueing again replaces the previously queued state.)
+    pub fn set_or_queue_state(&mut self,
+        state_name: S,
+        fps_override: Option<f32>,
+    ) -> Result<bool, AnimatorError<S>> {
+        if self.set_state(state_name.clone(), fps_override)? {
+            return Ok(true);
+        }
+        self.queued_state = Some((state_name, fps_override));
+        Ok(false)
+    }
+
+    // Play `state_name` through once, then switch to `return_state`,
+    // e.g. `play_once_then(PlayerAnim::Attack, PlayerAnim::StandDown)`.
+    // Looping animations are stopped after one pass (for PingPong, there
+    // and back again). Returns Ok(false) if the current state can't be
+    // interrupted yet, like `set_state`.
+    pub fn play_once_then(&mut self,
+        state_name: S,
+        return_state: S,
+    ) -> Result<bool, AnimatorError<S>> {
+        let return_state = match self.resolve_state(return_state)? {
+            Some(return_state) => return_state,
+            None => self.cur_state.clone(), // the fallback, which is playing right now
+        };
+        if !self.set_state(state_name, None)? {
+            return Ok(false);
+        }
+        if let Some(anim) = self.states.get(&self.cur_state) {
+            let num_frames = anim.frames.len();
+            self.frames_left_in_pass = Some(match anim.looping {
+                AnimationStyle::PingPong => (2 * num_frames).saturating_sub(2).max(1),
+                _ => num_frames,
+            });
+        }
+        self.queued_state = Some((return_state, None));
+        Ok(true)
+    }
+
+    // If `state_name` doesn't exist, swap it for the fallback state (warning
+    // once per missing state). Returns None if the fallback is already playing.
+    fn resolve_state(&mut self, state_name: S) -> Result<Option<S>, AnimatorError<S>> {
+        if self.states.contains_key(&state_name) {
+            return Ok(Some(state_name));
+        }
+        match self.fallback_state.clone() {
+            Some(fallback) if self.states.contains_key(&fallback) => {
+                if self.warned_states.insert(state_name.clone()) {
+                    warn!("Animation state {:?} not found, playing {:?} instead", state_name, fallback);
+                }
+                if self.cur_state == fallback {
+                    Ok(None)
+                } else {
+                    Ok(Some(fallback))
+                }
+            },
+            _ => Err(AnimatorError::StateNotFound(state_name)),
+        }
+    }
+
+    // Move on to the next frame of the current animation, starting that
+    // frame's timer from zero. Returns true if a Once animation just finished.
+    fn next_frame(&mut self) -> bool {
+        let anim = match self.states.get(&self.cur_state) {
+            Some(anim) => anim,
+            None => return false,
+        };
+        let num_frames = anim.frames.len();
+
+        // A one-off pass (see `play_once_then`) ends like a Once animation
+        let pass_over = match self.frames_left_in_pass {
+            Some(frames_left) => frames_left <= 1,
+            None => false,
+        };
+        let advanced = if pass_over {
+            None
+        } else {
+            anim.looping.advance(self.cur_frame_idx, num_frames, self.reversing)
+        };
+        let (next_idx, next_reversing, just_finished) = match advanced {
+            Some((idx, reversing)) => (idx, reversing, false),
+            None => (self.cur_frame_idx, self.reversing, !self.finished && num_frames > 0),
+        };
+        if let Some(frames_left) = self.frames_left_in_pass.as_mut() {
+            *frames_left = frames_left.saturating_sub(1);
+        }
+        let duration = anim.frame_duration(next_idx, self.cur_fps);
+
+        self.timer = AnimationTimer(Timer::from_seconds(duration, TimerMode::Repeating));
+        if next_idx != self.cur_frame_idx || next_reversing != self.reversing {
+            self.cur_frame_idx = next_idx;
+            self.reversing = next_reversing;
+            self.frame_changed = true;
+        }
+        if just_finished {
+            self.finished = true;
+        }
+        just_finished
+    }
+
+    // Change to a new state, ignoring priorities
+    pub fn force_state(&mut self,
+        state_name: S,
+        fps_override: Option<f32>,
+    ) -> Result<(), AnimatorError<S>> {
+        let state_name = match self.resolve_state(state_name)? {
+            Some(state_name) => state_name,
+            None => return Ok(()),
+        };
+        match self.states.get(&state_name) {
+            Some(state) => {
+                let fps = if let Some(fps_o) = fps_override {fps_o} else {state.fps};
+                if !valid_fps(fps) {
+                    return Err(AnimatorError::InvalidFps { state: state_name, fps });
+                }
+                let (first_idx, timer) = state.start(fps);
+                self.timer = AnimationTimer(timer);
+                self.cur_state = state_name;
+                self.cur_frame_idx = first_idx;
+                self.cur_fps = fps;
+                self.finished = false;
+                self.reversing = false;
+                self.queued_state = None;
+                self.frames_left_in_pass = None;
+                self.frame_changed = true;
+                Ok(())
+            },
+            None => Err(AnimatorError::StateNotFound(state_name)),
+        }
+    }
+
+    // Jump to the last frame of the current state, as if it had played
+    // through, e.g. to show a pose it ends on straight away
+    pub fn skip_to_end(&mut self) {
+        let num_frames = match self.states.get(&self.cur_state) {
+            Some(anim) => anim.frames.len(),
+            None => return,
+        };
+        let last_idx = num_frames.saturating_sub(1);
+        if self.cur_frame_idx != last_idx {
+            self.cur_frame_idx = last_idx;
+            self.frame_changed = true;
+        }
+        self.finished = true;
+        self.queued_state = None;
+        self.frames_left_in_pass = None;
+    }
+}
+
+// Builds a SpritesheetAnimator one state at a time, e.g.
+//     SpritesheetAnimator::builder()
+//         .state(PlayerAnim::StandDown, SpritesheetAnimation::from_frames(vec![1]))
+//         .start(PlayerAnim::StandDown)
+//         .build()?
+pub struct SpritesheetAnimatorBuilder<S: AnimState> {
+    states: HashMap<S, SpritesheetAnimation>,
+    start_state: Option<S>,
+    fallback_state: Option<S>,
+}
+impl<S: AnimState> SpritesheetAnimatorBuilder<S> {
+    pub fn state(mut self, state: S, animation: SpritesheetAnimation) -> Self {
+        self.states.insert(state, animation);
+        self
+    }
+    pub fn start(mut self, state: S) -> Self {
+        self.start_state = Some(state);
+        self
+    }
+    pub fn fallback(mut self, state: S) -> Self {
+        self.fallback_state = Some(state);
+        self
+    }
+    pub fn build(self) -> Result<SpritesheetAnimator<S>, AnimatorError<S>> {
+        let mut animator = match self.start_state {
+            Some(start_state) => SpritesheetAnimator::new(self.states, start_state)?,
+            None => return Err(AnimatorError::MissingStartState),
+        };
+        if let Some(fallback) = self.fallback_state {
+            if !animator.states.contains_key(&fallback) {
+                return Err(AnimatorError::StateNotFound(fallback));
+            }
+            animator.fallback_state = Some(fallback);
+        }
+        Ok(animator)
+    }
+}
+
+// What can go wrong when setting up or using an animator. Bad animation
+// data shows up here, so games can report it instead of crashing mid-play.
+#[derive(Debug)]
+pub enum AnimatorError<S: AnimState> {
+    MissingStartState, // the builder was never given a start state
+    StateNotFound(S),
+    NoFrames(S),
+    InvalidFps { state: S, fps: f32 },
+    InvalidFrameDuration { state: S, frame_idx: usize, duration: f32 },
+}
+impl<S: AnimState> std::fmt::Display for AnimatorError<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AnimatorError::MissingStartState =>
+                write!(f, "No start state was given for the animator"),
+            AnimatorError::StateNotFound(state) =>
+                write!(f, "Animation state {:?} not found", state),
+            AnimatorError::NoFrames(state) =>
+                write!(f, "Animation state {:?} has no frames", state),
+            AnimatorError::InvalidFps { state, fps } =>
+                write!(f, "Animation state {:?} has fps {}, but frames per second must be positive", state, fps),
+            AnimatorError::InvalidFrameDuration { state, frame_idx, duration } =>
+                write!(f, "Animation state {:?} has duration {} for frame {}, but durations must be positive",
+                       state, duration, frame_idx),
+        }
+    }
+}
+impl<S: AnimState> std::error::Error for AnimatorError<S> {}
+
+// The most frames one animator may advance in a single tick, so a huge
+// frame hitch (or a tiny frame duration) can't stall the game
+const MAX_FRAMES_PER_TICK: usize = 64;
+// The fastest AnimationSpeed played; anything more (or infinite) would
+// overflow the tick's Duration
+const MAX_ANIMATION_SPEED: f32 = 64.0;
+
+fn valid_fps(fps: f32) -> bool {
+    fps.is_finite() && fps > 0.0
+}
+
+pub fn animate_sprites<S: AnimState>(
+    time: Res<Time>,
+    speed: Res<AnimationSpeed>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    mut finished_events: EventWriter<AnimationFinished<S>>,
+    mut frame_events: EventWriter<AnimationFrameEvent>,
+    mut query: Query<(
+        Entity,
+        &mut SpritesheetAnimator<S>,
+        &mut TextureAtlasSprite,
+        &mut Transform,
+        &mut Handle<TextureAtlas>,
+    ), Without<LinkedAnimator>>,
+) {
+    for (entity, mut animator, mut sprite, mut transform, mut texture_atlas_handle) in &mut query {
+        // Remember the atlas the entity was spawned with, for animations without their own
+        if animator.base_atlas.is_none() {
+            animator.base_atlas = Some(texture_atlas_handle.clone());
+        }
+
+        // Paused animators keep their current frame, and their timer
+        // picks up where it left off once resumed.
+        // Time left over after a frame ends carries into the next frame, and
+        // a long tick can skip several frames, so playback speed doesn't
+        // drift at low frame rates.
+        let mut redraw = false; // whether a skipped frame's sprite still needs showing
+        if !animator.paused {
+            let speed = if speed.0.is_nan() { 0.0 } else { speed.0.clamp(0.0, MAX_ANIMATION_SPEED) };
+            let mut remaining = time.delta().mul_f32(speed);
+            for _ in 0..MAX_FRAMES_PER_TICK {
+                let left_in_frame = animator.timer.duration().saturating_sub(animator.timer.elapsed());
+                if remaining < left_in_frame || animator.finished {
+                    animator.timer.tick(remaining);
+                    break;
+                }
+                remaining -= left_in_frame;
+
+                // A frame entered earlier in this tick is skipped over
+                // without being shown, but its tags still count
+                if animator.frame_changed {
+                    if let Some(anim) = animator.states.get(&animator.cur_state) {
+                        send_frame_tags(entity, anim, animator.cur_frame_idx, &mut frame_events);
+                    }
+                    animator.frame_changed = false;
+                    redraw = true;
+                }
+
+                if animator.next_frame() {
+                    finished_events.send(AnimationFinished {
+                        entity,
+                        state: animator.cur_state.clone(),
+                    });
+                    // Play the state that was waiting for this one to finish
+                    if let Some((queued, fps_override)) = animator.queued_state.take() {
+                        if let Err(err) = animator.force_state(queued, fps_override) {
+                            warn!("{}", err);
+                        }
+                    }
+                }
+            }
+        }
+
+        // :: Apply tint and flash ::
+        let color = match animator.flash {
+            Some((color, _)) => color,
+            None => animator.tint,
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+        if let Some((color, frames_left)) = animator.flash {
+            animator.flash = if frames_left > 1 { Some((color, frames_left - 1)) } else { None };
+        }
+
+        // :: Display the current frame ::
+        // State changes (from any system) and frame advances are both
+        // applied here, so only this system needs to touch the sprite.
+        if animator.frame_changed || redraw {
+            let animator = &mut *animator; // so its fields can be borrowed separately
+            let send_tags = animator.frame_changed;
+            animator.frame_changed = false;
+            let mut rotation = None;
+            if let Some(anim) = animator.states.get(&animator.cur_state) {
+                // Make sure the entity uses the right atlas for this animation
+                let wanted_atlas = anim.atlas.clone()
+                    .unwrap_or_else(|| animator.base_atlas.clone().unwrap());
+                if *texture_atlas_handle != wanted_atlas {
+                    *texture_atlas_handle = wanted_atlas;
+                }
+
+                // Set the sprite frame, flip and rotation values
+                if let Some(frame) = anim.frames.get(animator.cur_frame_idx) {
+                    frame.apply_to_sprite(&mut sprite);
+                    if let Some(texture_atlas) = texture_atlases.get(&*texture_atlas_handle) {
+                        sprite.index %= texture_atlas.textures.len();
+
+                        // Shift the sprite by the animation's offset (mirrored
+                        // along with the frame), which the anchor measures in
+                        // fractions of the frame's size. Animations without
+                        // one show the sprite with its own anchor.
+                        let frame_size = texture_atlas.textures[sprite.index].size();
+                        if anim.offset != Vec2::ZERO && frame_size.min_element() > 0.0 {
+                            let mut offset = anim.offset;
+                            if sprite.flip_x {
+                                offset.x = -offset.x;
+                            }
+                            if sprite.flip_y {
+                                offset.y = -offset.y;
+                            }
+                            if animator.base_anchor.is_none() {
+                                animator.base_anchor = Some(sprite.anchor.clone());
+                            }
+                            sprite.anchor = Anchor::Custom(-offset / frame_size);
+                        } else if let Some(anchor) = animator.base_anchor.take() {
+                            sprite.anchor = anchor;
+                        }
+                    }
+                    rotation = Some(frame.rotation);
+                }
+
+                // Send events for the frame's tags, unless they were
+                // already sent while skipping over it
+                if send_tags {
+                    send_frame_tags(entity, anim, animator.cur_frame_idx, &mut frame_events);
+                }
+            }
+
+            // Turn the entity from the last frame's rotation to this one's,
+            // keeping whatever rotation it has of its own
+            if let Some(rotation) = rotation.filter(|rotation| *rotation != animator.applied_rotation) {
+                let turn = (rotation - animator.applied_rotation).to_radians();
+                transform.rotation *= Quat::from_rotation_z(turn);
+                animator.applied_rotation = rotation;
+            }
+        }
+    }
+}
+
+fn send_frame_tags(
+    entity: Entity,
+    anim: &SpritesheetAnimation,
+    frame_idx: usize,
+    frame_events: &mut EventWriter<AnimationFrameEvent>,
+) {
+    if let Some(tags) = anim.frame_tags.get(&frame_idx) {
+        for tag in tags {
+            frame_events.send(AnimationFrameEvent { entity, tag: tag.clone() });
+        }
+    }
+}
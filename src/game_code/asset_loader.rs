@@ -0,0 +1,64 @@
+// :: RON or JSON assets ::
+// Most of the game's data files (animations, cutscenes, shops, ...) are
+// written in RON, or in JSON for anyone who'd rather, with the parser
+// picked from the file extension. A RonOrJsonLoader loads any asset type
+// that can be deserialized, from files with the given extensions:
+//
+//     app.add_asset::<ShopDef>()
+//         .add_asset_loader(RonOrJsonLoader::<ShopDef>::new(&["shop.ron", "shop.json"]));
+//
+// Assets that need more than parsing (e.g. ids filled in from the keys
+// they're listed under, or other assets loaded along with them) are
+// finished by a hook, which makes the LoadedAsset:
+//
+//     RonOrJsonLoader::<RecipeBook>::new(&["recipes.ron", "recipes.json"]).with_process(name_recipes)
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::{Asset, AssetLoader, LoadContext, LoadedAsset},
+    utils::BoxedFuture,
+};
+use serde::de::DeserializeOwned;
+
+type ProcessFn<T> = fn(T, &mut LoadContext) -> LoadedAsset<T>;
+
+pub struct RonOrJsonLoader<T: Asset> {
+    extensions: &'static [&'static str],
+    process: ProcessFn<T>,
+    _asset: PhantomData<fn() -> T>,
+}
+impl<T: Asset> RonOrJsonLoader<T> {
+    pub fn new(extensions: &'static [&'static str]) -> Self {
+        Self { extensions, process: |asset, _| LoadedAsset::new(asset), _asset: PhantomData }
+    }
+    pub fn with_process(mut self, process: ProcessFn<T>) -> Self {
+        self.process = process;
+        self
+    }
+}
+
+impl<T: Asset + DeserializeOwned> AssetLoader for RonOrJsonLoader<T> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            // Pick the parser from the file extension
+            let is_json = load_context.path().extension()
+                .map_or(false, |ext| ext == "json");
+            let asset: T = if is_json {
+                serde_json::from_slice(bytes)?
+            } else {
+                ron::de::from_bytes(bytes)?
+            };
+            let asset = (self.process)(asset, load_context);
+            load_context.set_default_asset(asset);
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.extensions
+    }
+}
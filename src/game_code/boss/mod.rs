@@ -0,0 +1,308 @@
+// :: Bosses ::
+// Big enemies fought in an arena. A Boss is an Enemy with Stats, and a
+// list of phases: each one is a behavior tree (see ai/mod.rs) that takes
+// over once the boss's hp falls to a fraction of its max_hp:
+//
+//     commands.spawn((
+//         Enemy,
+//         Stats::new(30, 2, 1),
+//         Boss::new("slime_king", "Slime King")
+//             .with_phase(1.0, || BehaviorTree::new(repeat(sequence(vec![..]))))
+//             .with_phase(0.5, || BehaviorTree::new(repeat(sequence(vec![..]))))
+//             .with_victory_cutscene(asset_server.load("cutscenes/slime_king.cutscene.ron")),
+//         ..
+//     ));
+//
+// The fight starts when the player walks into the boss's BossArena (a
+// TriggerZone): the boss wakes up in its first phase, the arena's
+// ArenaGates close (they're solid, and shown, until the fight's over), and
+// the boss's health bar appears (see ui.rs). Until then the boss just
+// stands there.
+//
+//     commands.spawn((
+//         BossArena::new("slime_king"),
+//         TriggerZone::new(Vec2::new(160.0, 120.0)),
+//         SpatialBundle::from_transform(Transform::from_xyz(0.0, 200.0, 0.0)),
+//     ));
+//     commands.spawn((ArenaGate::new("slime_king", Vec2::new(32.0, 16.0)), SpriteBundle { .. }));
+//
+// Beating a boss sends BossDefeated, sets the flag "defeated_<id>" (so it
+// doesn't come back), sends QuestEvent::Custom("defeated_<id>") for quest
+// objectives, and plays its victory cutscene, if it has one. Every phase
+// change sends a BossPhaseChanged, e.g. for a roar or a change of music.
+//
+// If the player dies and comes back (see game_over/mod.rs), bosses they
+// were fighting go back to waiting, at full health, with their gates open.
+use bevy::prelude::*;
+
+use crate::{
+    ai::BehaviorTree,
+    collision::{Collider, TriggerEnter},
+    combat::{DamageSystem, Died, Stats},
+    cutscene::{Cutscene, CutscenePlayer},
+    flags::GameFlags,
+    game_over::PlayerRespawned,
+    movement::MoveIntent,
+    player::Player,
+    quest::QuestEvent,
+};
+
+mod ui;
+
+type PhaseTree = Box<dyn Fn() -> BehaviorTree + Send + Sync>;
+
+struct BossPhase {
+    threshold: f32, // the fraction of max_hp at or below which it starts
+    tree: PhaseTree,
+}
+
+#[derive(Component)]
+pub struct Boss {
+    pub id: String,
+    pub name: String, // shown over its health bar
+    pub victory_cutscene: Option<Handle<Cutscene>>,
+    phases: Vec<BossPhase>, // from the highest threshold down
+    phase: Option<usize>, // None until the fight starts
+}
+impl Boss {
+    pub fn new(id: &str, name: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            victory_cutscene: None,
+            phases: Vec::new(),
+            phase: None,
+        }
+    }
+    // Its behavior once its hp is at or below `threshold` (0.0 to 1.0) of
+    // its max_hp. The phase with the highest threshold is its first.
+    pub fn with_phase(
+        mut self,
+        threshold: f32,
+        tree: impl Fn() -> BehaviorTree + Send + Sync + 'static,
+    ) -> Self {
+        self.phases.push(BossPhase { threshold, tree: Box::new(tree) });
+        self.phases.sort_by(|a, b| b.threshold.total_cmp(&a.threshold));
+        self
+    }
+    pub fn with_victory_cutscene(mut self, cutscene: Handle<Cutscene>) -> Self {
+        self.victory_cutscene = Some(cutscene);
+        self
+    }
+    pub fn flag(&self) -> String {
+        format!("defeated_{}", self.id)
+    }
+    // The index of the phase it's in, once the fight's started
+    pub fn phase(&self) -> Option<usize> {
+        self.phase
+    }
+    pub fn is_fighting(&self) -> bool {
+        self.phase.is_some()
+    }
+    // The last phase whose threshold it's reached
+    fn phase_for(&self, stats: &Stats) -> usize {
+        let fraction = stats.hp as f32 / stats.max_hp() as f32;
+        self.phases.iter().rposition(|phase| fraction <= phase.threshold).unwrap_or(0)
+    }
+}
+
+// Where a boss is fought. Its fight starts when the player walks into the
+// entity's TriggerZone.
+#[derive(Component, Clone, Debug)]
+pub struct BossArena {
+    pub boss: String, // the Boss's id
+}
+impl BossArena {
+    pub fn new(boss: &str) -> Self {
+        Self { boss: boss.to_string() }
+    }
+}
+
+// Blocks a way out of an arena while its boss is being fought
+#[derive(Component, Clone, Debug)]
+pub struct ArenaGate {
+    pub boss: String, // the Boss's id
+    pub size: Vec2, // of its Collider while it's closed
+}
+impl ArenaGate {
+    pub fn new(boss: &str, size: Vec2) -> Self {
+        Self { boss: boss.to_string(), size }
+    }
+}
+
+pub struct BossFightStarted {
+    pub boss: Entity,
+    pub id: String,
+}
+
+pub struct BossPhaseChanged {
+    pub boss: Entity,
+    pub phase: usize,
+}
+
+pub struct BossDefeated {
+    pub boss: Entity,
+    pub id: String,
+}
+
+pub struct BossPlugin;
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BossFightStarted>()
+            .add_event::<BossPhaseChanged>()
+            .add_event::<BossDefeated>()
+            .add_startup_system(ui::spawn_boss_bar)
+            .add_system(add_bosses)
+            .add_system(add_arena_gates)
+            .add_system(start_boss_fights)
+            .add_system(change_boss_phases.after(DamageSystem))
+            .add_system(defeat_bosses.after(DamageSystem))
+            .add_system(close_arena_gates.after(start_boss_fights))
+            .add_system(open_arena_gates.after(defeat_bosses))
+            .add_system(reset_boss_fights)
+            .add_system(ui::update_boss_bar.after(change_boss_phases));
+    }
+}
+
+// Bosses that have already been beaten don't come back
+fn add_bosses(mut commands: Commands, flags: Res<GameFlags>, bosses: Query<(Entity, &Boss), Added<Boss>>) {
+    for (entity, boss) in &bosses {
+        if flags.is_set(&boss.flag()) {
+            commands.entity(entity).despawn_recursive();
+        } else if boss.phases.is_empty() {
+            warn!("Boss \"{}\" has no phases, so it won't do anything", boss.id);
+        }
+    }
+}
+
+// Gates start open
+fn add_arena_gates(mut gates: Query<&mut Visibility, Added<ArenaGate>>) {
+    for mut visibility in &mut gates {
+        visibility.is_visible = false;
+    }
+}
+
+fn start_boss_fights(
+    mut commands: Commands,
+    mut enters: EventReader<TriggerEnter>,
+    mut started: EventWriter<BossFightStarted>,
+    mut phase_changes: EventWriter<BossPhaseChanged>,
+    arenas: Query<&BossArena>,
+    players: Query<(), With<Player>>,
+    mut bosses: Query<(Entity, &mut Boss, &Stats)>,
+) {
+    for enter in enters.iter() {
+        let arena = match arenas.get(enter.zone) {
+            Ok(arena) if players.contains(enter.sensor) => arena,
+            _ => continue,
+        };
+        let (entity, mut boss, stats) = match bosses.iter_mut().find(|(_, boss, _)| boss.id == arena.boss) {
+            Some(boss) => boss,
+            None => continue, // beaten already
+        };
+        if boss.is_fighting() || stats.is_dead() || boss.phases.is_empty() {
+            continue;
+        }
+        let phase = boss.phase_for(stats);
+        boss.phase = Some(phase);
+        commands.entity(entity).insert((boss.phases[phase].tree)());
+        started.send(BossFightStarted { boss: entity, id: boss.id.clone() });
+        phase_changes.send(BossPhaseChanged { boss: entity, phase });
+    }
+}
+
+// Switch to a new behavior when a threshold's crossed
+fn change_boss_phases(
+    mut commands: Commands,
+    mut phase_changes: EventWriter<BossPhaseChanged>,
+    mut bosses: Query<(Entity, &mut Boss, &Stats), Changed<Stats>>,
+) {
+    for (entity, mut boss, stats) in &mut bosses {
+        let current = match boss.phase {
+            Some(current) if !stats.is_dead() => current,
+            _ => continue,
+        };
+        // Phases only go forwards, even if it's healed
+        let phase = boss.phase_for(stats);
+        if phase > current {
+            boss.phase = Some(phase);
+            commands.entity(entity).insert((boss.phases[phase].tree)());
+            phase_changes.send(BossPhaseChanged { boss: entity, phase });
+        }
+    }
+}
+
+fn defeat_bosses(
+    mut died: EventReader<Died>,
+    mut defeated: EventWriter<BossDefeated>,
+    mut quest_events: EventWriter<QuestEvent>,
+    mut flags: ResMut<GameFlags>,
+    mut cutscenes: ResMut<CutscenePlayer>,
+    mut bosses: Query<&mut Boss>,
+) {
+    for event in died.iter() {
+        let mut boss = match bosses.get_mut(event.entity) {
+            Ok(boss) => boss,
+            Err(_) => continue,
+        };
+        boss.phase = None;
+        flags.set(&boss.flag(), true);
+        quest_events.send(QuestEvent::Custom(boss.flag()));
+        if let Some(cutscene) = &boss.victory_cutscene {
+            cutscenes.play(cutscene.clone());
+        }
+        defeated.send(BossDefeated { boss: event.entity, id: boss.id.clone() });
+    }
+}
+
+fn close_arena_gates(
+    mut commands: Commands,
+    mut started: EventReader<BossFightStarted>,
+    mut gates: Query<(Entity, &ArenaGate, &mut Visibility)>,
+) {
+    for event in started.iter() {
+        for (entity, gate, mut visibility) in gates.iter_mut().filter(|(_, gate, _)| gate.boss == event.id) {
+            commands.entity(entity).insert(Collider::new(gate.size));
+            visibility.is_visible = true;
+        }
+    }
+}
+
+fn open_arena_gates(
+    mut commands: Commands,
+    mut defeated: EventReader<BossDefeated>,
+    mut gates: Query<(Entity, &ArenaGate, &mut Visibility)>,
+) {
+    for event in defeated.iter() {
+        for (entity, _, mut visibility) in gates.iter_mut().filter(|(_, gate, _)| gate.boss == event.id) {
+            commands.entity(entity).remove::<Collider>();
+            visibility.is_visible = false;
+        }
+    }
+}
+
+fn reset_boss_fights(
+    mut commands: Commands,
+    mut respawned: EventReader<PlayerRespawned>,
+    mut bosses: Query<(Entity, &mut Boss, &mut Stats, Option<&mut MoveIntent>)>,
+    mut gates: Query<(Entity, &ArenaGate, &mut Visibility)>,
+) {
+    if respawned.iter().count() == 0 {
+        return;
+    }
+    for (entity, mut boss, mut stats, intent) in &mut bosses {
+        if !boss.is_fighting() || stats.is_dead() {
+            continue;
+        }
+        boss.phase = None;
+        stats.hp = stats.max_hp();
+        commands.entity(entity).remove::<BehaviorTree>();
+        if let Some(mut intent) = intent {
+            intent.0 = Vec2::ZERO;
+        }
+        for (gate_entity, _, mut visibility) in gates.iter_mut().filter(|(_, gate, _)| gate.boss == boss.id) {
+            commands.entity(gate_entity).remove::<Collider>();
+            visibility.is_visible = false;
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// :: Boss health bar ::
+// The boss's name over a long bar along the bottom of the screen, shown
+// while a fight is on, and hidden again once it's won.
+use bevy::prelude::*;
+
+use super::Boss;
+use crate::{combat::Stats, ui::UI_FONT};
+
+const FONT_SIZE: f32 = 16.0;
+const MARGIN: f32 = 24.0;
+const BAR_WIDTH: f32 = 320.0;
+const BAR_HEIGHT: f32 = 10.0;
+const BORDER: f32 = 2.0;
+const BORDER_COLOR: Color = Color::rgba(0.05, 0.05, 0.1, 0.85);
+const EMPTY_COLOR: Color = Color::rgb(0.25, 0.08, 0.08);
+const FILL_COLOR: Color = Color::rgb(0.85, 0.15, 0.2);
+
+#[derive(Component)]
+pub(super) struct BossBar;
+
+#[derive(Component)]
+pub(super) struct BossBarName;
+
+#[derive(Component)]
+pub(super) struct BossBarFill;
+
+pub(super) fn spawn_boss_bar(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // A full-width row, to center the bar in
+    commands.spawn((
+        BossBar,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect { left: Val::Px(0.0), right: Val::Px(0.0), bottom: Val::Px(MARGIN), ..default() },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            z_index: ZIndex::Global(i32::MAX - 5), // over the world, under toasts and menus
+            visibility: Visibility { is_visible: false },
+            ..default()
+        },
+    )).with_children(|row| {
+        row.spawn((
+            BossBarName,
+            TextBundle::from_section("", TextStyle {
+                font: asset_server.load(UI_FONT),
+                font_size: FONT_SIZE,
+                color: Color::WHITE,
+            }),
+        ));
+        row.spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(BAR_WIDTH + BORDER * 2.0), Val::Px(BAR_HEIGHT + BORDER * 2.0)),
+                padding: UiRect::all(Val::Px(BORDER)),
+                ..default()
+            },
+            background_color: BORDER_COLOR.into(),
+            ..default()
+        }).with_children(|border| {
+            border.spawn(NodeBundle {
+                style: Style { size: Size::new(Val::Percent(100.0), Val::Percent(100.0)), ..default() },
+                background_color: EMPTY_COLOR.into(),
+                ..default()
+            }).with_children(|empty| {
+                empty.spawn((
+                    BossBarFill,
+                    NodeBundle {
+                        style: Style { size: Size::new(Val::Percent(100.0), Val::Percent(100.0)), ..default() },
+                        background_color: FILL_COLOR.into(),
+                        ..default()
+                    },
+                ));
+            });
+        });
+    });
+}
+
+pub(super) fn update_boss_bar(
+    bosses: Query<(&Boss, &Stats)>,
+    mut bars: Query<&mut Visibility, With<BossBar>>,
+    mut names: Query<&mut Text, With<BossBarName>>,
+    mut fills: Query<&mut Style, With<BossBarFill>>,
+) {
+    let fighting = bosses.iter().find(|(boss, _)| boss.is_fighting());
+    for mut visibility in &mut bars {
+        if visibility.is_visible != fighting.is_some() {
+            visibility.is_visible = fighting.is_some();
+        }
+    }
+    let (boss, stats) = match fighting {
+        Some(fighting) => fighting,
+        None => return,
+    };
+    for mut text in &mut names {
+        if text.sections[0].value != boss.name {
+            text.sections[0].value = boss.name.clone();
+        }
+    }
+    let percent = Val::Percent(100.0 * stats.hp.max(0) as f32 / stats.max_hp() as f32);
+    for mut style in &mut fills {
+        if style.size.width != percent {
+            style.size.width = percent;
+        }
+    }
+}
@@ -0,0 +1,32 @@
+// :: Camera bounds ::
+// Keeps the camera from showing anything outside the map. Give the camera
+// a CameraBounds with the map's rectangle (in world coordinates); when the
+// map is smaller than the view, the camera stays centered on it instead.
+use bevy::{math::Rect, prelude::*};
+
+#[derive(Component, Clone, Copy)]
+pub struct CameraBounds(pub Rect);
+
+// Half the size of what a camera can see, in world units
+pub fn half_view_size(projection: &OrthographicProjection, transform: &Transform) -> Vec2 {
+    Vec2::new(projection.right - projection.left, projection.top - projection.bottom)
+        * 0.5 * projection.scale * transform.scale.truncate()
+}
+
+pub(super) fn clamp_to_bounds(
+    mut cameras: Query<(&CameraBounds, &OrthographicProjection, &mut Transform)>,
+) {
+    for (bounds, projection, mut transform) in &mut cameras {
+        let half_view = half_view_size(projection, &transform);
+        let (min, max) = (bounds.0.min + half_view, bounds.0.max - half_view);
+        let pos = transform.translation.truncate();
+        let clamp_axis = |value: f32, min: f32, max: f32| {
+            if min > max { (min + max) / 2.0 } else { value.clamp(min, max) }
+        };
+        let clamped = Vec2::new(clamp_axis(pos.x, min.x, max.x), clamp_axis(pos.y, min.y, max.y));
+        if clamped != pos {
+            transform.translation.x = clamped.x;
+            transform.translation.y = clamped.y;
+        }
+    }
+}
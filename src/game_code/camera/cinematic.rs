@@ -0,0 +1,141 @@
+// :: Cinematic camera ::
+// Scripted camera moves for cutscenes: pan to a point and zoom, over a set
+// time, with easing. Shots are queued on the camera's CameraCinematic and
+// play one after another; while any are playing, the camera stops following
+// its target (and ignores zones), then eases back once they're done:
+//
+//     cinematic.queue(CameraShot::new(Vec2::new(200.0, 80.0), 2.0).with_zoom(1.5));
+//     cinematic.queue(CameraShot::hold(1.0));
+//
+// A CinematicFinished event is sent when the last shot ends, so a cutscene
+// can wait for it.
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use super::CameraFollow;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    #[default]
+    EaseInOut,
+}
+impl Easing {
+    // Map progress through a shot (0.0 to 1.0) to how far along the move is
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CameraShot {
+    pub target: Option<Vec2>, // where to pan to; None stays put
+    pub zoom: Option<f32>, // None keeps the current zoom
+    pub duration: f32, // in seconds
+    pub easing: Easing,
+}
+impl CameraShot {
+    pub fn new(target: Vec2, duration: f32) -> Self {
+        Self { target: Some(target), zoom: None, duration, easing: Easing::default() }
+    }
+    // Stay where the camera is for a while
+    pub fn hold(duration: f32) -> Self {
+        Self { target: None, zoom: None, duration, easing: Easing::Linear }
+    }
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = Some(zoom);
+        self
+    }
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+}
+
+// Where the playing shot started from
+struct ShotStart {
+    position: Vec2,
+    scale: f32,
+    elapsed: f32,
+}
+
+// Added automatically to cameras with a CameraFollow
+#[derive(Component, Default)]
+pub struct CameraCinematic {
+    shots: VecDeque<CameraShot>,
+    start: Option<ShotStart>, // Some while the front shot is playing
+}
+impl CameraCinematic {
+    pub fn queue(&mut self, shot: CameraShot) {
+        self.shots.push_back(shot);
+    }
+    // Drop every shot, handing the camera straight back to its CameraFollow
+    pub fn stop(&mut self) {
+        self.shots.clear();
+        self.start = None;
+    }
+    pub fn is_playing(&self) -> bool {
+        !self.shots.is_empty()
+    }
+}
+
+// Sent when a camera's last queued shot ends
+pub struct CinematicFinished {
+    pub camera: Entity,
+}
+
+pub(super) fn add_cinematics(
+    mut commands: Commands,
+    cameras: Query<Entity, (With<CameraFollow>, Without<CameraCinematic>)>,
+) {
+    for entity in &cameras {
+        commands.entity(entity).insert(CameraCinematic::default());
+    }
+}
+
+pub(super) fn play_cinematics(
+    time: Res<Time>,
+    mut events: EventWriter<CinematicFinished>,
+    mut cameras: Query<(Entity, &mut CameraCinematic, &mut Transform, &mut OrthographicProjection)>,
+) {
+    for (entity, mut cinematic, mut transform, mut projection) in &mut cameras {
+        if !cinematic.is_playing() {
+            continue;
+        }
+        let shot = cinematic.shots[0];
+        let start = cinematic.start.get_or_insert(ShotStart {
+            position: transform.translation.truncate(),
+            scale: projection.scale,
+            elapsed: 0.0,
+        });
+        start.elapsed += time.delta_seconds();
+        let progress = if shot.duration > 0.0 { start.elapsed / shot.duration } else { 1.0 };
+        let t = shot.easing.apply(progress);
+
+        let target = shot.target.unwrap_or(start.position);
+        let position = start.position.lerp(target, t);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        if let Some(zoom) = shot.zoom {
+            let wanted = 1.0 / zoom.max(f32::EPSILON);
+            projection.scale = start.scale + (wanted - start.scale) * t;
+        }
+
+        if progress >= 1.0 {
+            cinematic.shots.pop_front();
+            cinematic.start = None;
+            if !cinematic.is_playing() {
+                events.send(CinematicFinished { camera: entity });
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// :: Following a target ::
+// The camera eases towards its target instead of being glued to it, and
+// leads slightly in the direction the target is moving, so the player sees
+// more of where they're going than where they've been.
+use bevy::prelude::*;
+
+use super::{ActiveCameraZone, CameraCinematic, CameraZone};
+use crate::movement::MoveIntent;
+
+#[derive(Component)]
+pub struct CameraFollow {
+    pub target: Entity,
+    pub smoothing: f32, // how quickly the camera catches up; higher is snappier
+    pub look_ahead: f32, // how far ahead of a moving target to look, in pixels
+}
+impl CameraFollow {
+    pub fn new(target: Entity) -> Self {
+        Self { target, smoothing: 5.0, look_ahead: 16.0 }
+    }
+    pub fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+    pub fn with_look_ahead(mut self, look_ahead: f32) -> Self {
+        self.look_ahead = look_ahead;
+        self
+    }
+}
+
+// Where a CameraFollow would like the camera to be
+pub(super) fn follow_point(follow: &CameraFollow,
+                           targets: &Query<(&Transform, Option<&MoveIntent>), Without<CameraFollow>>) -> Option<Vec2> {
+    let (transform, intent) = targets.get(follow.target).ok()?;
+    let look_ahead = intent.map_or(Vec2::ZERO, |intent| intent.clamp_length_max(1.0) * follow.look_ahead);
+    Some(transform.translation.truncate() + look_ahead)
+}
+
+pub(super) fn follow_targets(
+    time: Res<Time>,
+    targets: Query<(&Transform, Option<&MoveIntent>), Without<CameraFollow>>,
+    zones: Query<&CameraZone>,
+    mut cameras: Query<(&CameraFollow, Option<&ActiveCameraZone>, Option<&CameraCinematic>, &mut Transform)>,
+) {
+    for (follow, active_zone, cinematic, mut transform) in &mut cameras {
+        if cinematic.map_or(false, |cinematic| cinematic.is_playing()) {
+            continue;
+        }
+        let mut wanted = match follow_point(follow, &targets) {
+            Some(point) => point,
+            None => continue,
+        };
+        // Easing onto a zone's locked axes is what makes entering it smooth
+        if let Some(zone) = active_zone.and_then(|active| active.0).and_then(|zone| zones.get(zone).ok()) {
+            wanted = zone.apply_locks(wanted);
+        }
+        // Framerate-independent easing: the same fraction of the remaining
+        // distance is covered every second, however many frames that is
+        let t = 1.0 - (-follow.smoothing * time.delta_seconds()).exp();
+        let pos = transform.translation.truncate().lerp(wanted, t);
+        transform.translation.x = pos.x;
+        transform.translation.y = pos.y;
+    }
+}
@@ -0,0 +1,57 @@
+// :: Camera ::
+// Everything that decides what the camera looks at. Add `CameraPlugin`
+// to your App, then give the camera entity the components it needs
+// (e.g., a CameraFollow to track the player). The camera that draws the
+// world needs a PixelPerfectCamera.
+use bevy::prelude::*;
+
+use crate::movement::MovementSystem;
+
+mod bounds;
+mod cinematic;
+mod follow;
+mod pixel_perfect;
+mod split_screen;
+mod zones;
+
+pub use bounds::{half_view_size, CameraBounds};
+pub use cinematic::{CameraCinematic, CameraShot, CinematicFinished, Easing};
+pub use follow::CameraFollow;
+pub use pixel_perfect::{PixelPerfect, PixelPerfectCamera};
+pub use split_screen::{SecondSplitScreenCamera, SplitScreen};
+pub use zones::{whole_zoom, ActiveCameraZone, CameraZone};
+
+// Systems that move the camera are labeled CameraSystem, and
+// run after everything else has moved for the frame
+#[derive(SystemLabel)]
+pub struct CameraSystem;
+
+pub struct CameraPlugin;
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PixelPerfect>()
+            .add_startup_system(pixel_perfect::setup_screen)
+            .add_system(pixel_perfect::setup_world_cameras)
+            .add_system(pixel_perfect::scale_to_window)
+            .add_event::<CinematicFinished>()
+            .add_system(split_screen::setup_split_screens)
+            .add_system(split_screen::update_split_screens
+                .after(MovementSystem)
+                .before(zones::find_active_zones))
+            .add_system(zones::add_active_zones)
+            .add_system(cinematic::add_cinematics)
+            .add_system(zones::find_active_zones.after(MovementSystem))
+            .add_system(zones::zoom_to_zones.label(CameraSystem).after(zones::find_active_zones))
+            .add_system(follow::follow_targets
+                .label(CameraSystem)
+                .after(MovementSystem)
+                .after(zones::find_active_zones))
+            .add_system(cinematic::play_cinematics
+                .label(CameraSystem)
+                .after(follow::follow_targets)
+                .after(zones::zoom_to_zones))
+            .add_system(bounds::clamp_to_bounds
+                .label(CameraSystem)
+                .after(cinematic::play_cinematics));
+    }
+}
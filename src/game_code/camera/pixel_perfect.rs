@@ -0,0 +1,180 @@
+// :: Pixel-perfect rendering ::
+// The world is drawn at a small, fixed resolution (PixelPerfect::resolution),
+// then scaled up to the window by a whole number, with black bars filling
+// any leftover space. Every art pixel is always the same number of screen
+// pixels, so sprites never shimmer or stretch unevenly when the window
+// is resized.
+//
+// Mark the world camera with PixelPerfectCamera; it's set up to draw into
+// an image, and a second camera shows that image on the screen (along
+// with the UI, which stays at full resolution).
+//
+// The world can also be drawn in bigger blocks (a mosaic, e.g. for a
+// fade; see fade.rs), by drawing it into a smaller image and stretching
+// that further. Split screens are never pixelated, since their viewports
+// are measured in the full-size image's pixels.
+//
+//     pixel_perfect.set_pixelation(4); // 4x4 world pixels to a block
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages},
+        view::RenderLayers,
+    },
+};
+
+// Everything on the screen camera's layer is only seen by it
+const SCREEN_LAYER: u8 = 31;
+
+#[derive(Resource)]
+pub struct PixelPerfect {
+    pub resolution: UVec2, // the size the world is drawn at, in pixels
+    scale: u32, // how many screen pixels each world pixel currently takes
+    pixelation: u32, // how many world pixels across each block drawn is
+    image: Handle<Image>,
+}
+impl Default for PixelPerfect {
+    fn default() -> Self {
+        Self { resolution: UVec2::new(400, 300), scale: 1, pixelation: 1, image: Handle::default() }
+    }
+}
+impl PixelPerfect {
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+    pub fn pixelation(&self) -> u32 {
+        self.pixelation
+    }
+    // 1 to draw the world normally
+    pub fn set_pixelation(&mut self, block: u32) {
+        self.pixelation = block.max(1);
+    }
+    // Where a point in the window (in pixels, from the bottom-left corner)
+    // lands in the world camera's view, or None if it's in the black bars
+    pub fn window_to_viewport(&self, window: &Window, window_pos: Vec2) -> Option<Vec2> {
+        let window_center = Vec2::new(window.width(), window.height()) / 2.0;
+        let resolution = self.resolution.as_vec2();
+        let viewport_pos = (window_pos - window_center) / self.scale as f32 + resolution / 2.0;
+        let inside = viewport_pos.cmpge(Vec2::ZERO).all() && viewport_pos.cmple(resolution).all();
+        if inside { Some(viewport_pos) } else { None }
+    }
+}
+
+// The camera that draws the world
+#[derive(Component)]
+pub struct PixelPerfectCamera;
+
+// The sprite showing the world on the screen
+#[derive(Component)]
+struct ScreenSprite;
+
+pub(super) fn setup_screen(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut pixel_perfect: ResMut<PixelPerfect>,
+) {
+    let size = Extent3d {
+        width: pixel_perfect.resolution.x,
+        height: pixel_perfect.resolution.y,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("pixel_perfect_target"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+        },
+        ..default()
+    };
+    image.resize(size); // fill with zeroes
+    pixel_perfect.image = images.add(image);
+
+    let screen_layer = RenderLayers::layer(SCREEN_LAYER);
+    commands.spawn((
+        ScreenSprite,
+        SpriteBundle {
+            texture: pixel_perfect.image.clone(),
+            ..default()
+        },
+        screen_layer,
+    ));
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                priority: 100, // after every world camera
+                ..default()
+            },
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::Custom(Color::BLACK), // the letterbox bars
+            },
+            ..default()
+        },
+        screen_layer,
+    ));
+}
+
+// Point world cameras at the image instead of the window
+pub(super) fn setup_world_cameras(
+    mut commands: Commands,
+    pixel_perfect: Res<PixelPerfect>,
+    mut cameras: Query<(Entity, &mut Camera), Added<PixelPerfectCamera>>,
+) {
+    for (entity, mut camera) in &mut cameras {
+        camera.target = RenderTarget::Image(pixel_perfect.image.clone());
+        // The UI is drawn by the screen camera, at full resolution
+        commands.entity(entity).insert(UiCameraConfig { show_ui: false });
+    }
+}
+
+// Scale the world up by as much as fits in the window, in whole steps,
+// and by its pixelation on top of that
+pub(super) fn scale_to_window(
+    windows: Res<Windows>,
+    mut pixel_perfect: ResMut<PixelPerfect>,
+    mut images: ResMut<Assets<Image>>,
+    mut sprites: Query<&mut Transform, With<ScreenSprite>>,
+    mut cameras: Query<(&Camera, &mut Transform), (With<PixelPerfectCamera>, Without<ScreenSprite>)>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let fits = Vec2::new(window.width(), window.height()) / pixel_perfect.resolution.as_vec2();
+    let scale = (fits.min_element().floor() as u32).max(1);
+    if pixel_perfect.scale != scale {
+        pixel_perfect.scale = scale;
+    }
+    let split = cameras.iter().any(|(camera, _)| camera.viewport.is_some());
+    let block = if split { 1 } else { pixel_perfect.pixelation };
+
+    // A block of world pixels to each of the image's pixels, with the
+    // world cameras seeing as much as they would at full size
+    let size = (pixel_perfect.resolution + UVec2::splat(block - 1)) / block;
+    let resized = images.get(&pixel_perfect.image).map_or(false, |image| image.size() != size.as_vec2());
+    if resized {
+        if let Some(image) = images.get_mut(&pixel_perfect.image) {
+            image.resize(Extent3d { width: size.x, height: size.y, ..default() });
+        }
+    }
+    for (_, mut transform) in &mut cameras {
+        let wanted = Vec3::new(block as f32, block as f32, 1.0);
+        if transform.scale != wanted {
+            transform.scale = wanted;
+        }
+    }
+    for mut transform in &mut sprites {
+        let stretch = (scale * block) as f32;
+        let wanted = Vec3::new(stretch, stretch, 1.0);
+        if transform.scale != wanted {
+            transform.scale = wanted;
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// :: Split screen ::
+// For two local players. While they're close together, one camera follows
+// the point between them; once they wander far apart, the screen splits
+// into a left half following the first player and a right half following
+// the second, and merges again when they come back together.
+//
+// Add a SplitScreen to the main camera (along with its CameraFollow and
+// PixelPerfectCamera); the second camera is spawned automatically:
+//
+//     commands.spawn((SplitScreen::new([player_1, player_2]), CameraFollow::new(player_1), ...));
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::camera::Viewport,
+};
+
+use super::{CameraBounds, CameraFollow, PixelPerfect, PixelPerfectCamera};
+
+#[derive(Component)]
+pub struct SplitScreen {
+    pub players: [Entity; 2],
+    pub split_distance: f32, // split when the players are further apart than this, in pixels
+    pub merge_distance: f32, // merge when they're closer than this; less than split_distance,
+                             // so the screen doesn't flicker between the two
+    split: bool,
+    anchor: Option<Entity>, // the point between the players
+    second_camera: Option<Entity>,
+}
+impl SplitScreen {
+    pub fn new(players: [Entity; 2]) -> Self {
+        Self {
+            players,
+            split_distance: 240.0,
+            merge_distance: 160.0,
+            split: false,
+            anchor: None,
+            second_camera: None,
+        }
+    }
+    pub fn with_distances(mut self, split_distance: f32, merge_distance: f32) -> Self {
+        self.split_distance = split_distance;
+        self.merge_distance = merge_distance;
+        self
+    }
+    pub fn is_split(&self) -> bool {
+        self.split
+    }
+}
+
+// The camera for the right half of a split screen
+#[derive(Component)]
+pub struct SecondSplitScreenCamera;
+
+// Sits between the two players, for the shared camera to follow
+#[derive(Component)]
+struct SplitScreenAnchor;
+
+pub(super) fn setup_split_screens(
+    mut commands: Commands,
+    mut split_screens: Query<(&mut SplitScreen, &Transform, Option<&CameraBounds>), Added<SplitScreen>>,
+) {
+    for (mut split_screen, transform, bounds) in &mut split_screens {
+        let anchor = commands.spawn((SplitScreenAnchor, Transform::from_translation(transform.translation))).id();
+        let mut second_camera = commands.spawn((
+            SecondSplitScreenCamera,
+            CameraFollow::new(split_screen.players[1]),
+            PixelPerfectCamera,
+            Camera2dBundle {
+                camera: Camera {
+                    is_active: false,
+                    priority: 1, // after the main camera, which clears the whole image
+                    ..default()
+                },
+                camera_2d: Camera2d {
+                    clear_color: ClearColorConfig::None, // don't wipe out the left half
+                },
+                transform: *transform,
+                ..default()
+            },
+        ));
+        if let Some(bounds) = bounds {
+            second_camera.insert(*bounds);
+        }
+        split_screen.anchor = Some(anchor);
+        split_screen.second_camera = Some(second_camera.id());
+    }
+}
+
+pub(super) fn update_split_screens(
+    pixel_perfect: Res<PixelPerfect>,
+    players: Query<&Transform, (Without<Camera>, Without<SplitScreenAnchor>)>,
+    mut anchors: Query<&mut Transform, (With<SplitScreenAnchor>, Without<Camera>)>,
+    mut main_cameras: Query<(&mut SplitScreen, &mut CameraFollow, &mut Camera, &Transform)>,
+    mut second_cameras: Query<(&mut Camera, &mut Transform), (With<SecondSplitScreenCamera>, Without<SplitScreen>)>,
+) {
+    for (mut split_screen, mut follow, mut camera, camera_transform) in &mut main_cameras {
+        let (anchor, second_camera) = match (split_screen.anchor, split_screen.second_camera) {
+            (Some(anchor), Some(second_camera)) => (anchor, second_camera),
+            _ => continue,
+        };
+        let positions = match (players.get(split_screen.players[0]), players.get(split_screen.players[1])) {
+            (Ok(first), Ok(second)) => [first.translation.truncate(), second.translation.truncate()],
+            _ => continue,
+        };
+
+        let distance = positions[0].distance(positions[1]);
+        let split = if split_screen.split {
+            distance > split_screen.merge_distance
+        } else {
+            distance > split_screen.split_distance
+        };
+        if let Ok(mut anchor_transform) = anchors.get_mut(anchor) {
+            let midpoint = (positions[0] + positions[1]) / 2.0;
+            anchor_transform.translation.x = midpoint.x;
+            anchor_transform.translation.y = midpoint.y;
+        }
+        let wanted_target = if split { split_screen.players[0] } else { anchor };
+        if follow.target != wanted_target {
+            follow.target = wanted_target;
+        }
+        if split == split_screen.split {
+            continue;
+        }
+        split_screen.split = split;
+
+        // Each half gets its own side of the image
+        let resolution = pixel_perfect.resolution;
+        let half_size = UVec2::new(resolution.x / 2, resolution.y);
+        camera.viewport = if split {
+            Some(Viewport { physical_position: UVec2::ZERO, physical_size: half_size, ..default() })
+        } else {
+            None
+        };
+        if let Ok((mut second, mut second_transform)) = second_cameras.get_mut(second_camera) {
+            second.is_active = split;
+            second.viewport = Some(Viewport {
+                physical_position: UVec2::new(resolution.x - half_size.x, 0),
+                physical_size: half_size,
+                ..default()
+            });
+            // Start from the shared view, and ease over to the second player
+            if split {
+                second_transform.translation = camera_transform.translation;
+            }
+        }
+    }
+}
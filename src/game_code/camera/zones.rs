@@ -0,0 +1,118 @@
+// :: Camera zones ::
+// Areas of the map where the camera behaves differently, e.g. zoomed in
+// inside a small house, or locked to one height along a side-scrolling
+// corridor. While a camera's target is inside a CameraZone, the camera
+// uses that zone's settings, easing into and out of them:
+//
+//     commands.spawn(CameraZone::new(house_rect).with_zoom(2.0));
+//     commands.spawn(CameraZone::new(corridor_rect).with_lock_y(120.0));
+//
+// Where zones overlap, the smallest one wins. Zooms are rounded to whole
+// factors (2.0, 3.0, or 0.5 to zoom out), so every world pixel stays the
+// same size on screen once the camera's settled (see whole_zoom).
+use bevy::{math::Rect, prelude::*};
+
+use super::{CameraCinematic, CameraFollow};
+
+const ZONE_TRANSITION_SPEED: f32 = 4.0; // how quickly zoom eases between zones; higher is snappier
+
+#[derive(Component, Clone, Copy)]
+pub struct CameraZone {
+    pub area: Rect, // in world coordinates
+    pub zoom: f32, // 2.0 shows everything twice as big; rounded with whole_zoom
+    pub lock_x: Option<f32>, // keep the camera at this x
+    pub lock_y: Option<f32>, // keep the camera at this y
+}
+impl CameraZone {
+    pub fn new(area: Rect) -> Self {
+        Self { area, zoom: 1.0, lock_x: None, lock_y: None }
+    }
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+    pub fn with_lock_x(mut self, x: f32) -> Self {
+        self.lock_x = Some(x);
+        self
+    }
+    pub fn with_lock_y(mut self, y: f32) -> Self {
+        self.lock_y = Some(y);
+        self
+    }
+
+    // Move `point` onto this zone's locked axes
+    pub fn apply_locks(&self, point: Vec2) -> Vec2 {
+        Vec2::new(self.lock_x.unwrap_or(point.x), self.lock_y.unwrap_or(point.y))
+    }
+}
+
+// The nearest zoom that draws each world pixel as a whole number of
+// pixels (zooming in), or a whole number of world pixels as one (zooming
+// out), since anything in between draws some pixels bigger than others
+pub fn whole_zoom(zoom: f32) -> f32 {
+    if zoom >= 1.0 {
+        zoom.round()
+    } else {
+        1.0 / (1.0 / zoom.max(f32::EPSILON)).round()
+    }
+}
+
+// The zone a camera's target is in, if any. Added automatically to
+// cameras with a CameraFollow.
+#[derive(Component, Default)]
+pub struct ActiveCameraZone(pub Option<Entity>);
+
+pub(super) fn add_active_zones(
+    mut commands: Commands,
+    cameras: Query<Entity, (With<CameraFollow>, Without<ActiveCameraZone>)>,
+) {
+    for entity in &cameras {
+        commands.entity(entity).insert(ActiveCameraZone::default());
+    }
+}
+
+pub(super) fn find_active_zones(
+    mut cameras: Query<(&CameraFollow, &mut ActiveCameraZone)>,
+    targets: Query<&Transform, Without<CameraFollow>>,
+    zones: Query<(Entity, &CameraZone)>,
+) {
+    for (follow, mut active) in &mut cameras {
+        let target = match targets.get(follow.target) {
+            Ok(transform) => transform.translation.truncate(),
+            Err(_) => continue,
+        };
+        let area = |zone: &CameraZone| zone.area.width() * zone.area.height();
+        let zone = zones.iter()
+            .filter(|(_, zone)| zone.area.contains(target))
+            .min_by(|(_, a), (_, b)| area(a).total_cmp(&area(b)))
+            .map(|(entity, _)| entity);
+        if active.0 != zone {
+            active.0 = zone;
+        }
+    }
+}
+
+// Ease each camera's zoom towards its zone's (or back to 1.0 outside zones)
+pub(super) fn zoom_to_zones(
+    time: Res<Time>,
+    mut cameras: Query<(&ActiveCameraZone, Option<&CameraCinematic>, &mut OrthographicProjection)>,
+    zones: Query<&CameraZone>,
+) {
+    let t = 1.0 - (-ZONE_TRANSITION_SPEED * time.delta_seconds()).exp();
+    for (active, cinematic, mut projection) in &mut cameras {
+        if cinematic.map_or(false, |cinematic| cinematic.is_playing()) {
+            continue;
+        }
+        let zoom = active.0.and_then(|zone| zones.get(zone).ok()).map_or(1.0, |zone| zone.zoom);
+        let wanted = 1.0 / whole_zoom(zoom);
+        if projection.scale == wanted {
+            continue;
+        }
+        // Snap once close enough, so the scale settles on exactly `wanted`
+        projection.scale = if (projection.scale - wanted).abs() < 0.001 {
+            wanted
+        } else {
+            projection.scale + (wanted - projection.scale) * t
+        };
+    }
+}
@@ -1,97 +1,174 @@
-use bevy::{prelude::*, utils::HashMap};
+use bevy::prelude::*;
+use serde::Deserialize;
 
-#[derive(Component)]
-struct Player;
+mod ai;
+mod animation;
+mod asset_loader;
+mod boss;
+mod camera;
+mod chest;
+mod clock;
+mod collision;
+mod combat;
+mod crafting;
+mod cutscene;
+mod dialogue;
+mod direction;
+mod enemy;
+mod fade;
+mod flags;
+mod footsteps;
+mod game_over;
+mod hud;
+mod input;
+mod interaction;
+mod inventory;
+mod movement;
+mod npc;
+mod pathfinding;
+mod pause;
+mod perception;
+mod pickup;
+mod player;
+mod quest;
+mod settings;
+mod shop;
+mod spatial;
+mod spawner;
+mod swimming;
+mod tilemap;
+mod toast;
+mod ui;
+mod warp;
+mod ysort;
 
-#[derive(Component)]
-enum Direction {
-    N, NE, E, SE, S, SW, W, NW,
-}
+use ai::BehaviorTreePlugin;
+use animation::{
+    AnimationSet, AnimationSource, DirectionalAnimationPlugin, DirectionalAnimationSystem,
+    DirectionalAnimator, SpriteAnimationPlugin,
+};
+use boss::BossPlugin;
+use camera::{CameraBounds, CameraFollow, CameraPlugin, PixelPerfectCamera};
+use chest::{chest_animator, Chest, ChestPlugin};
+use clock::ClockPlugin;
+use collision::{Collider, CollisionGrid, CollisionPlugin, Separation, TileShape, TriggerSensor, TriggerZone};
+use combat::{CombatPlugin, DamageAnimationPlugin, HitInvulnerability, MeleeAnimationPlugin, MeleeAttack, Stats};
+use crafting::{CraftingPlugin, RecipeRegistry, Workbench};
+use cutscene::{CutsceneAnimationPlugin, CutscenePlugin, CutsceneTrigger};
+use dialogue::{Dialogue, DialogueChoice, DialoguePlugin, DialogueSource, Readable};
+use direction::Direction;
+use enemy::EnemyPlugin;
+use fade::FadePlugin;
+use flags::{FlagCondition, FlagTrigger, FlagsPlugin};
+use footsteps::FootstepPlugin;
+use game_over::{Checkpoint, GameOverAnimationPlugin, GameOverPlugin, PlayerDeathAnimation};
+use hud::HudPlugin;
+use input::{PlayerInput, PlayerInputPlugin};
+use interaction::{Interactable, InteractionPlugin};
+use inventory::{Equipment, Inventory, InventoryPlugin, ItemRegistry};
+use movement::{MoveIntent, MovePath, MoveSpeed, MovementPlugin, Sprint};
+use npc::{
+    Follower, NpcAction, NpcAnimationPlugin, NpcBundle, NpcPlugin, Patrol, PatrolMode, PatrolPath,
+    Schedule, ScheduledBehavior, Wander,
+};
+use pathfinding::PathfindingPlugin;
+use pause::PausePlugin;
+use perception::{Perceivable, PerceptionPlugin};
+use pickup::{Pickup, PickupPlugin};
+use player::{Player, PlayerPlugin, PlayerState};
+use quest::{Objective, QuestDef, QuestLog, QuestPlugin};
+use settings::SettingsPlugin;
+use shop::{Merchant, ShopPlugin};
+use spatial::SpatialHashPlugin;
+use spawner::SpawnerPlugin;
+use swimming::{Swimmer, SwimmingPlugin};
+use tilemap::{Terrain, TileLayerKind, Tilemap, TilemapPlugin};
+use toast::ToastPlugin;
+use warp::{CurrentMap, WarpPlugin};
+use ysort::{YSort, YSortPlugin};
 
-// A timer for animations
-#[derive(Component, Deref, DerefMut)]
-struct AnimationTimer(Timer);
+// How much faster the player moves while sprinting
+const SPRINT_MULTIPLIER: f32 = 2.0;
+// And how much slower while swimming
+const SWIM_MULTIPLIER: f32 = 0.5;
+// How many item slots the player has, and how much money they start with
+const INVENTORY_SIZE: usize = 24;
+const STARTING_MONEY: u32 = 10;
+// How much damage the player can take
+const PLAYER_HP: i32 = 10;
 
-// How the animation should continue after it reaches the last frame
-enum AnimationStyle {
-    Once,    // Play once and end at last frame
-    Looping, // Loop from frame 1 to n, then from 1 to n, ad infinitum
+// The player's animation states. In animation files, these are written
+// in kebab-case, e.g. "move-up-left" for MoveUpLeft.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Deserialize, Reflect, FromReflect)]
+#[serde(rename_all = "kebab-case")]
+enum PlayerAnim {
+    #[default]
+    StandDown, StandDownLeft, StandLeft, StandUpLeft,
+    StandUp, StandUpRight, StandRight, StandDownRight,
+    MoveDown, MoveDownLeft, MoveLeft, MoveUpLeft,
+    MoveUp, MoveUpRight, MoveRight, MoveDownRight,
+    RunDown, RunDownLeft, RunLeft, RunUpLeft,
+    RunUp, RunUpRight, RunRight, RunDownRight,
+    SwimDown, SwimDownLeft, SwimLeft, SwimUpLeft,
+    SwimUp, SwimUpRight, SwimRight, SwimDownRight,
+    AttackDown, AttackDownLeft, AttackLeft, AttackUpLeft,
+    AttackUp, AttackUpRight, AttackRight, AttackDownRight,
+    Die,
 }
 
-// A SpritesheetAnimation is a series of indexes for a TextureAtlas,
-// referencing the frames to use for a single animation. The "fps" is
-// how fast to display the animation.
-// NOTE: You will be able to use negative frame id's to represent x-flipped textures
-const DEFAULT_ANIMATION_FPS: f32 = 5.0;
-struct SpritesheetAnimation {
-    frames: Vec<i8>, // the frames of the animation, as the TextureAtlas' indices + 1
-    fps: f32, // how quickly to go to the next frame, in frames per second
-    looping: AnimationStyle // whether and how to loop the animation
-}
-impl SpritesheetAnimation {
-    fn from_frames(frames: Vec<i8>) -> Self {
-        Self {
-            frames,
-            fps: DEFAULT_ANIMATION_FPS,
-            looping: AnimationStyle::Looping
-        }
-    }
+// What the player is doing; combined with their Direction,
+// this picks the animation state (see `player_animations`)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum PlayerAction {
+    Stand,
+    Move,
+    Run,
+    Swim,
+    Attack,
 }
 
-// A SpriteAnimator is a map from "states" (strings)
-// to individual animations.
-#[derive(Component)]
-struct SpritesheetAnimator {
-    states: HashMap<String, SpritesheetAnimation>,
-    timer: AnimationTimer,
-    cur_state: String,
-    cur_frame_idx: usize,
+// Which state to play for each action and direction.
+// Directions are listed clockwise, starting from north (up).
+fn player_animations() -> DirectionalAnimator<PlayerAction, PlayerAnim> {
+    use PlayerAnim::*;
+    DirectionalAnimator::new(PlayerAction::Stand)
+        .with_all(PlayerAction::Stand, [
+            StandUp, StandUpRight, StandRight, StandDownRight,
+            StandDown, StandDownLeft, StandLeft, StandUpLeft,
+        ])
+        .with_all(PlayerAction::Move, [
+            MoveUp, MoveUpRight, MoveRight, MoveDownRight,
+            MoveDown, MoveDownLeft, MoveLeft, MoveUpLeft,
+        ])
+        .with_all(PlayerAction::Run, [
+            RunUp, RunUpRight, RunRight, RunDownRight,
+            RunDown, RunDownLeft, RunLeft, RunUpLeft,
+        ])
+        .with_all(PlayerAction::Swim, [
+            SwimUp, SwimUpRight, SwimRight, SwimDownRight,
+            SwimDown, SwimDownLeft, SwimLeft, SwimUpLeft,
+        ])
+        .with_all(PlayerAction::Attack, [
+            AttackUp, AttackUpRight, AttackRight, AttackDownRight,
+            AttackDown, AttackDownLeft, AttackLeft, AttackUpLeft,
+        ])
+        // Until Thomas has "run-*" and "swim-*" animations, walk faster or slower instead
+        .with_fallback_action(PlayerAction::Run, PlayerAction::Move, SPRINT_MULTIPLIER)
+        .with_fallback_action(PlayerAction::Swim, PlayerAction::Move, SWIM_MULTIPLIER)
 }
-impl SpritesheetAnimator {
-    fn new(states: HashMap<String, SpritesheetAnimation>,
-           start_state: String) -> Self {
-        match states.get(&start_state) {
-            Some(anim) => {
-                if anim.fps as f32 == 0.0 {
-                    panic!("Frames per second must be positive, nonzero value")
-                }
-                Self {
-                    timer: AnimationTimer(Timer::from_seconds(1.0 / anim.fps, TimerMode::Repeating)),
-                    states: states,
-                    cur_state: start_state,
-                    cur_frame_idx: 0,
-                }
-            },
-            None => {
-                panic!("Start state {} not found", start_state)
-            },
-        }
-    }
-    fn set_state(&mut self,
-        state_name: String,
-        sprite: &mut TextureAtlasSprite,
-        fps_override: Option<f32>, // Optional. Provide a different frame rate.
-    ) -> bool {
-        match self.states.get(&state_name) {
-            Some(state) => {
-                let fps = if let Some(fps_o) = fps_override {fps_o} else {state.fps};
-                if fps as f32 == 0.0 {
-                    panic!("Frames per second must be positive, nonzero value")
-                }
-                self.cur_state = state_name;
-                self.cur_frame_idx = 0;
-                self.timer = AnimationTimer(Timer::from_seconds(1.0 / fps,
-                                            TimerMode::Repeating));
-                // Set the sprite frame and x-flip value
-                if let Some(texture_idx) = state.frames.get(0) {
-                    sprite.index = ((*texture_idx).abs()-1) as usize;
-                    sprite.flip_x = (*texture_idx) < 0; // flip texture if negative
-                }
-                true
-            },
-            None => false,
-        }
-    }
+
+// NPCs only stand and walk, so they share the player's "stand-*" and "move-*" states
+fn villager_animations() -> DirectionalAnimator<NpcAction, PlayerAnim> {
+    use PlayerAnim::*;
+    DirectionalAnimator::new(NpcAction::Stand)
+        .with_all(NpcAction::Stand, [
+            StandUp, StandUpRight, StandRight, StandDownRight,
+            StandDown, StandDownLeft, StandLeft, StandUpLeft,
+        ])
+        .with_all(NpcAction::Move, [
+            MoveUp, MoveUpRight, MoveRight, MoveDownRight,
+            MoveDown, MoveDownLeft, MoveLeft, MoveUpLeft,
+        ])
 }
 
 fn main() {
@@ -100,6 +177,13 @@ fn main() {
             // Set the image display format to nearest-neighbor for crisp pixels
             .set(ImagePlugin::default_nearest())
 
+            // Reload assets when they change on disk, so animation files
+            // can be edited while the game runs
+            .set(AssetPlugin {
+                watch_for_changes: true,
+                ..default()
+            })
+
             // Set the window name and size
             .set(WindowPlugin {
                 window: WindowDescriptor {
@@ -110,174 +194,402 @@ fn main() {
                 },
                 ..default()
             }))
+        .add_plugin(SpriteAnimationPlugin::<PlayerAnim>::default())
+        .add_plugin(DirectionalAnimationPlugin::<PlayerAction, PlayerAnim>::default())
+        .add_plugin(DirectionalAnimationPlugin::<NpcAction, PlayerAnim>::default())
+        .add_plugin(PlayerInputPlugin)
+        .add_plugin(MovementPlugin)
+        .add_plugin(SpatialHashPlugin)
+        .add_plugin(CollisionPlugin)
+        .add_plugin(PlayerPlugin)
+        .add_plugin(ClockPlugin)
+        .add_plugin(NpcPlugin)
+        .add_plugin(PathfindingPlugin)
+        .add_plugin(PerceptionPlugin)
+        .add_plugin(EnemyPlugin)
+        .add_plugin(BehaviorTreePlugin)
+        .add_plugin(SpawnerPlugin)
+        .add_plugin(NpcAnimationPlugin::<PlayerAnim>::default())
+        .add_plugin(FlagsPlugin)
+        .add_plugin(InteractionPlugin)
+        .add_plugin(DialoguePlugin)
+        .add_plugin(FadePlugin)
+        .add_plugin(CutscenePlugin)
+        .add_plugin(CutsceneAnimationPlugin::<PlayerAnim>::default())
+        .add_plugin(CombatPlugin)
+        .add_plugin(MeleeAnimationPlugin::<PlayerAnim>::default())
+        .add_plugin(DamageAnimationPlugin::<PlayerAnim>::default())
+        .add_plugin(BossPlugin)
+        .add_plugin(InventoryPlugin)
+        .add_plugin(PickupPlugin)
+        .add_plugin(ChestPlugin)
+        .add_plugin(CraftingPlugin)
+        .add_plugin(ShopPlugin)
+        .add_plugin(ToastPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(PausePlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(GameOverPlugin)
+        .add_plugin(GameOverAnimationPlugin::<PlayerAnim>::default())
+        .add_plugin(QuestPlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(YSortPlugin)
+        .add_plugin(TilemapPlugin)
+        .add_plugin(FootstepPlugin)
+        .add_plugin(SwimmingPlugin)
+        .add_plugin(WarpPlugin)
         .add_startup_system(setup)
-        .add_system(animate_sprites)
-        .add_system(player_input)
+        .add_system(player_animation
+            .after(player::update_walking_state)
+            .after(swimming::update_swimmers)
+            .before(DirectionalAnimationSystem))
         .run();
 }
 
 fn setup(mut commands: Commands,
          asset_server: Res<AssetServer>,
-         mut texture_atlases: ResMut<Assets<TextureAtlas>>) {
-
-    let texture_handle = asset_server.load("images/thomas_walk.png");
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle,
-                                Vec2::new(16.0, 32.0),
-                                15, 1, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-    let player_animations = SpritesheetAnimator::new(
-        HashMap::from([
-            ("stand-down".to_string(), SpritesheetAnimation::from_frames(vec![1])),
-            ("stand-down-left".to_string(), SpritesheetAnimation::from_frames(vec![4])),
-            ("stand-left".to_string(), SpritesheetAnimation::from_frames(vec![7])),
-            ("stand-up-left".to_string(), SpritesheetAnimation::from_frames(vec![10])),
-            ("stand-up".to_string(), SpritesheetAnimation::from_frames(vec![13])),
-            ("stand-up-right".to_string(), SpritesheetAnimation::from_frames(vec![-10])),
-            ("stand-right".to_string(), SpritesheetAnimation::from_frames(vec![-7])),
-            ("stand-down-right".to_string(), SpritesheetAnimation::from_frames(vec![-4])),
-            ("move-down".to_string(), SpritesheetAnimation::from_frames(vec![1, 2, 1, 3])),
-            ("move-down-left".to_string(), SpritesheetAnimation::from_frames(vec![4, 5, 4, 6])),
-            ("move-left".to_string(), SpritesheetAnimation::from_frames(vec![7, 8, 7, 9])),
-            ("move-up-left".to_string(), SpritesheetAnimation::from_frames(vec![10, 11, 10, 12])),
-            ("move-up".to_string(), SpritesheetAnimation::from_frames(vec![13, 14, 13, 15])),
-            ("move-up-right".to_string(), SpritesheetAnimation::from_frames(vec![-10, -11, -10, -12])),
-            ("move-right".to_string(), SpritesheetAnimation::from_frames(vec![-7, -8, -7, -9])),
-            ("move-down-right".to_string(), SpritesheetAnimation::from_frames(vec![-4, -5, -4, -6])),
-        ]),
-        "move-down".to_string()
-    );
+         mut item_registry: ResMut<ItemRegistry>,
+         mut recipe_registry: ResMut<RecipeRegistry>,
+         mut quest_log: ResMut<QuestLog>) {
+
+    // What the items Thomas can find are
+    item_registry.add_catalog(asset_server.load("items/meadow.items.ron"));
+    // and what he can make from them
+    recipe_registry.add_book(asset_server.load("recipes/meadow.recipes.ron"));
+
+    // How thomas_walk.png is cut into frames is described in its .atlas.ron
+    // file, and the atlas is rebuilt whenever either file changes on disk
+    let texture_atlas_handle: Handle<TextureAtlas> =
+        asset_server.load("images/thomas_walk.atlas.ron");
+
+    // The player's animations are defined in a file, and are attached
+    // to the entity once the file finishes loading (see `apply_animation_sets`)
+    let player_animation_set: Handle<AnimationSet> =
+        asset_server.load("animations/thomas.anim.ron");
+
+    // (Grouped into smaller tuples, since a bundle can only be 15 long)
+    let player = commands.spawn((
+        (
+            Player,
+            PlayerInput::default(), // any device; see `InputDevice` for local co-op
+            PlayerState::default(),
+            Inventory::new(INVENTORY_SIZE).with_currency(STARTING_MONEY),
+            Equipment::default(),
+            Stats::new(PLAYER_HP, 1, 0), // attack and defense come mostly from equipment
+            MeleeAttack::new(Vec2::splat(16.0), 12.0), // a swing just in front of him
+            HitInvulnerability(1.0), // a second to get away after being hurt
+        ),
+        (
+            Direction::S,
+            MoveIntent::default(),
+            MoveSpeed(32.0),
+            Sprint::new(SPRINT_MULTIPLIER),
+            Swimmer::default().with_speed_multiplier(SWIM_MULTIPLIER),
+            MovePath::default(),
+        ),
+        YSort::new(-16.0), // sorted by Thomas's feet
+        Collider::new(Vec2::new(10.0, 6.0)).with_offset(Vec2::new(0.0, -13.0)), // and blocked by them
+        Separation::default(), // nudged aside by (and nudging) villagers
+        TriggerSensor::default(),
+        Perceivable, // so enemies can spot him
+        player_animations(),
+        AnimationSource::<PlayerAnim>::new(player_animation_set.clone()),
+        PlayerDeathAnimation(PlayerAnim::Die), // played on a game over
+        SpriteSheetBundle {
+            texture_atlas: texture_atlas_handle.clone(),
+            ..default()  // Set remaining arguments to their default values
+        },
+    )).id();
 
-    commands.spawn(
-        Camera2dBundle {
-            transform: Transform::from_scale(Vec3::new(0.5, 0.5, 1.0)),
+    // A companion, who tags along behind Thomas
+    commands.spawn((
+        NpcBundle::new(32.0),
+        Follower::new(player, 20.0),
+        YSort::new(-16.0),
+        villager_animations(),
+        AnimationSource::<PlayerAnim>::new(player_animation_set.clone()),
+        SpriteSheetBundle {
+            texture_atlas: texture_atlas_handle.clone(),
             ..default()
-        }
-    );
+        },
+    ));
+
+    // A villager (borrowing Thomas's sprites) who ambles around the meadow,
+    // and will stop for a chat
     commands.spawn((
-        Player,
-        player_animations,
+        Name::new("villager"), // for cutscenes
+        NpcBundle::new(20.0),
+        Wander::new(48.0),
+        Interactable::new(24.0, "Talk"),
+        Dialogue::new()
+            .branch(FlagCondition::is_set("met_villager"), "again", None)
+            .set_flag("met_villager", true)
+            .line("Villager", "Oh! Hello there. Don't often see new faces in the meadow.")
+            .line("Villager", "Where are you headed?")
+            .choice(vec![
+                DialogueChoice::new("The pond.", "pond"),
+                DialogueChoice::new("Nowhere in particular.", "nowhere"),
+            ])
+            .node("pond")
+            .line("Villager", "Mind how you go. It's deeper than it looks.")
+            .node("nowhere")
+            .line("Villager", "The best kind of walk.")
+            .node("again")
+            .line("Villager", "Back again? Enjoy the sunshine."),
+        YSort::new(-16.0),
+        Collider::new(Vec2::new(10.0, 6.0)).with_offset(Vec2::new(0.0, -13.0)),
+        villager_animations(),
+        AnimationSource::<PlayerAnim>::new(player_animation_set.clone()),
+        SpriteSheetBundle {
+            texture_atlas: texture_atlas_handle.clone(),
+            transform: Transform::from_xyz(-80.0, 40.0, 0.0),
+            ..default()
+        },
+    ));
+
+    // And another, pacing up and down the path
+    commands.spawn((
+        NpcBundle::new(24.0),
+        PatrolPath(vec![Vec2::new(-160.0, 5.0), Vec2::new(160.0, 5.0)]),
+        Patrol::new(PatrolMode::PingPong).with_wait_time(2.0),
+        YSort::new(-16.0),
+        Collider::new(Vec2::new(10.0, 6.0)).with_offset(Vec2::new(0.0, -13.0)),
+        villager_animations(),
+        AnimationSource::<PlayerAnim>::new(player_animation_set.clone()),
+        SpriteSheetBundle {
+            texture_atlas: texture_atlas_handle.clone(),
+            transform: Transform::from_xyz(-160.0, 5.0, 0.0),
+            ..default()
+        },
+    ));
+
+    // And a shopkeeper, who minds their stall by the path during the day
+    // and walks home to the top of the map at night
+    let stall = Vec2::new(120.0, 5.0);
+    let home = Vec2::new(8.0, 213.0);
+    commands.spawn((
+        Name::new("shopkeeper"), // for quests
+        NpcBundle::new(24.0),
+        Schedule::new()
+            .with(9.0, 17.0, stall, ScheduledBehavior::Wander(16.0))
+            .with(17.0, 9.0, home, ScheduledBehavior::Stand),
+        Interactable::new(24.0, "Talk"),
+        // What they say is in a script, which can be edited while the game
+        // runs, and opens their shop
+        DialogueSource::new(asset_server.load("dialogue/meadow.dialogue")).with_node("shopkeeper"),
+        Merchant::new(asset_server.load("shops/meadow_stall.shop.ron")),
+        YSort::new(-16.0),
+        Collider::new(Vec2::new(10.0, 6.0)).with_offset(Vec2::new(0.0, -13.0)),
+        villager_animations(),
+        AnimationSource::<PlayerAnim>::new(player_animation_set),
         SpriteSheetBundle {
             texture_atlas: texture_atlas_handle,
-            ..default()  // Set remaining arguments to their default values
+            transform: Transform::from_translation(home.extend(0.0)),
+            ..default()
         },
     ));
+
+    // Something to do: the shopkeeper's dialogue sets bought_apple
+    quest_log.start(QuestDef::new("apples", "An Apple a Day")
+        .with_description("The shopkeeper sells the best apples in the valley.")
+        .stage("Buy an apple", vec![
+            Objective::talk_to("shopkeeper", "Visit the shopkeeper's stall"),
+            Objective::flag("bought_apple", "Buy an apple"),
+        ])
+        .stage("Share it", vec![Objective::talk_to("villager", "Find someone to share it with")]));
+
+    // A signpost at the crossroads (there's no sign tile yet, so it's invisible)
+    commands.spawn((
+        Interactable::new(16.0, "Read"),
+        Readable::new(vec![
+            "North: the old well. East: the pond.",
+            "South: Thomas's house. Don't forget to feed the cat.",
+        ]),
+        SpatialBundle::from_transform(Transform::from_xyz(-24.0, 24.0, 0.0)),
+    ));
+
+    // A workbench outside Thomas's house, for making things out of what he
+    // finds (there's no workbench tile yet either)
+    commands.spawn((
+        Interactable::new(16.0, "Craft"),
+        Workbench::default(),
+        SpatialBundle::from_transform(Transform::from_xyz(24.0, -56.0, 0.0)),
+    ));
+
+    // Things to pick up: windfall apples under the trees, stones by the pond,
+    // flowers, and a stick to wave about
+    for (item, count, x, y) in [("apple", 1, -40.0, 72.0), ("apple", 2, -8.0, 88.0), ("stone", 3, 136.0, 72.0), ("stick", 1, -104.0, 56.0), ("flower", 3, 56.0, -88.0)] {
+        commands.spawn((
+            Pickup::new(item, count),
+            SpatialBundle::from_transform(Transform::from_xyz(x, y, 0.0)),
+        ));
+    }
+
+    // A chest hidden among the trees, which stays open once it's been
+    // opened. There's no chest art yet, so it borrows the rock tile.
+    commands.spawn((
+        Chest::new("meadow_trees", asset_server.load("loot/meadow_chest.loot.ron")),
+        chest_animator(vec![ROCK as i32 + 1]).expect("the chest has a frame"),
+        Collider::new(Vec2::new(14.0, 8.0)).with_offset(Vec2::new(0.0, -4.0)),
+        SpriteSheetBundle {
+            texture_atlas: asset_server.load("images/overworld_tiles.atlas.ron"),
+            transform: Transform::from_xyz(-136.0, 104.0, 0.0),
+            ..default()
+        },
+    ));
+
+    // A cutscene plays the first time Thomas walks up to the pond, and the
+    // game remembers he's been there
+    commands.spawn((
+        CutsceneTrigger::new(asset_server.load("cutscenes/pond.cutscene.ron")),
+        FlagTrigger::new("seen_pond", true),
+        TriggerZone::new(Vec2::new(48.0, 16.0)),
+        SpatialBundle::from_transform(Transform::from_xyz(168.0, 40.0, 0.0)),
+    ));
+
+    // A small meadow for Thomas to walk around, centered on where he starts.
+    // It's the CurrentMap, so warping to another map replaces it.
+    let map = demo_map(asset_server.load("images/overworld_tiles.atlas.ron"));
+    let map_corner = map.rect().size() * Vec2::new(-0.5, 0.5);
+    let mut map_bounds = map.rect();
+    map_bounds.min += map_corner;
+    map_bounds.max += map_corner;
+    let collision = demo_collision(&map);
+    commands.spawn((map, collision, CurrentMap, SpatialBundle::from_transform(Transform::from_translation(map_corner.extend(0.0)))))
+        .with_children(|map| {
+            // A checkpoint on the way to the pond, so a game over brings
+            // Thomas back there rather than where he started
+            map.spawn((
+                Checkpoint,
+                TriggerZone::new(Vec2::new(16.0, 16.0)),
+                SpatialBundle::from_transform(Transform::from_translation((Vec2::new(120.0, 40.0) - map_corner).extend(0.0))),
+            ));
+        });
+
+    // The camera eases after the player, drawing the world at
+    // PixelPerfect::resolution and scaling it up to fit the window
+    commands.spawn((
+        CameraFollow::new(player),
+        CameraBounds(map_bounds),
+        PixelPerfectCamera,
+        Camera2dBundle::default(),
+    ));
 }
 
-fn animate_sprites(
-    time: Res<Time>,
-    texture_atlases: Res<Assets<TextureAtlas>>,
-    mut query: Query<(
-        &mut SpritesheetAnimator,
-        &mut TextureAtlasSprite,
-        &Handle<TextureAtlas>,
-    )>,
-) {
-    for (mut animator, mut sprite, texture_atlas_handle) in &mut query {
-        let timer = &mut animator.timer;
-        timer.tick(time.delta());
-        if timer.just_finished() {
-            // Get reference to spritesheet texture
-            let texture_atlas = texture_atlases.get(texture_atlas_handle).unwrap();
-
-            // Get reference to current animation and advance to next frame
-            let mut next_frame_idx: usize = animator.cur_frame_idx;
-            if let Some(anim) = animator.states.get(&animator.cur_state) {
-
-                // Advance to the index of the next frame
-                let num_frames = anim.frames.len();
-                if (animator.cur_frame_idx + 1) >= num_frames {
-                    if matches!(anim.looping, AnimationStyle::Looping) {
-                        next_frame_idx = 0;
-                    }
-                } else {
-                    next_frame_idx = animator.cur_frame_idx + 1;
-                }
+// Tiles in overworld_tiles.png
+const GRASS: u32 = 0;
+const DARK_GRASS: u32 = 1;
+const PATH: u32 = 2;
+const WATER: u32 = 3;
+const FLOWERS: u32 = 4;
+const ROCK: u32 = 5;
+const TREETOP: u32 = 6;
+const TRUNK: u32 = 7;
+
+// Grass with a path running through it, a pond, and some trees
+fn demo_map(tileset: Handle<TextureAtlas>) -> Tilemap {
+    let size = UVec2::new(40, 30);
+    let mut map = Tilemap::new(size, Vec2::splat(16.0), tileset);
+    map.terrain.extend([
+        (GRASS, Terrain::Grass), (DARK_GRASS, Terrain::Grass), (FLOWERS, Terrain::Grass),
+        (PATH, Terrain::Dirt), (WATER, Terrain::Water), (ROCK, Terrain::Stone), (TRUNK, Terrain::Wood),
+    ]);
+    // A cheap, repeatable scatter, so the map looks the same every run
+    let scatter = |x: u32, y: u32, salt: u32| (x * 73 + y * 151 + salt * 37) % 97;
 
-                // Set the sprite frame and x-flip value
-                let next_frame_texture = anim.frames.get(next_frame_idx);
-                if let Some(texture_idx) = next_frame_texture {
-                    sprite.index = (((*texture_idx).abs()-1) as usize) % texture_atlas.textures.len();
-                    sprite.flip_x = (*texture_idx) < 0; // flip texture if negative
+    let ground = map.add_layer("ground", TileLayerKind::Ground);
+    for y in 0..size.y {
+        for x in 0..size.x {
+            let in_pond = (x as f32 - 30.0).powi(2) / 25.0 + (y as f32 - 8.0).powi(2) / 9.0 <= 1.0;
+            let tile = if in_pond {
+                WATER
+            } else if x == 19 || x == 20 || y == 15 {
+                PATH
+            } else if scatter(x, y, 0) < 12 {
+                DARK_GRASS
+            } else {
+                GRASS
+            };
+            ground.set(UVec2::new(x, y), Some(tile));
+        }
+    }
+
+    let mut decorations = Vec::new();
+    let mut treetops = Vec::new();
+    for y in 1..size.y {
+        for x in 0..size.x {
+            if ground_tile(&map, x, y) != Some(GRASS) {
+                continue;
+            }
+            match scatter(x, y, 1) {
+                0..=2 if ground_tile(&map, x, y - 1) == Some(GRASS) => {
+                    decorations.push((UVec2::new(x, y), TRUNK));
+                    treetops.push((UVec2::new(x, y - 1), TREETOP));
                 }
+                3..=6 => decorations.push((UVec2::new(x, y), FLOWERS)),
+                7 => decorations.push((UVec2::new(x, y), ROCK)),
+                _ => {}
             }
-
-            animator.cur_frame_idx = next_frame_idx;
         }
     }
+    let decoration = map.add_layer("decoration", TileLayerKind::Decoration);
+    for (tile, value) in decorations {
+        decoration.set(tile, Some(value));
+    }
+    let overhang = map.add_layer("overhang", TileLayerKind::Overhang);
+    for (tile, value) in treetops {
+        overhang.set(tile, Some(value));
+    }
+    map
 }
 
-fn player_input (keyboard_input: Res<Input<KeyCode>>,
-                 time: Res<Time>,
-                 mut query: Query<(&mut SpritesheetAnimator,
-                                   &mut TextureAtlasSprite,
-                                   &mut Transform),
-                                   With<Player>>) {
-
-    let (mut animator,
-        mut sprite,
-        mut transform) = query.single_mut();
-
-    let move_speed: f32 = 32.0;
-    let mut move_dir: (f32, f32) = (0.0, 0.0); // (x_delta, y_delta)
-    let move_delta: (f32, f32);
-    let time_delta: f32 = time.delta_seconds();
-
-    let (left_pressed, up_pressed, right_pressed, down_pressed) =
-        (keyboard_input.pressed(KeyCode::Left), keyboard_input.pressed(KeyCode::Up),
-        keyboard_input.pressed(KeyCode::Right), keyboard_input.pressed(KeyCode::Down));
-
-    let mut facing: &str = "";
-    if left_pressed {
-        if up_pressed {
-            facing = "move-up-left";
-            move_dir = (-0.71, 0.71);
-        } else if down_pressed {
-            facing = "move-down-left";
-            move_dir = (-0.71, -0.71);
-        } else {
-            facing = "move-left";
-            move_dir = (-1.0, 0.0);
+// Rocks and tree trunks can't be walked through, and the pond can only
+// be swum through
+fn demo_collision(map: &Tilemap) -> CollisionGrid {
+    const SOLID_TILES: [(&str, u32); 2] = [("decoration", ROCK), ("decoration", TRUNK)];
+    let mut grid = CollisionGrid::new(map.size, map.tile_size);
+    for (layer_name, solid_tile) in SOLID_TILES {
+        if let Some(layer) = map.layer(layer_name) {
+            for (tile, value) in layer.iter() {
+                if value == solid_tile {
+                    grid.set_solid(tile, true);
+                }
+            }
         }
-    } else if right_pressed {
-        if up_pressed {
-            facing = "move-up-right";
-            move_dir = (0.71, 0.71);
-        } else if down_pressed {
-            facing = "move-down-right";
-            move_dir = (0.71, -0.71);
-        } else {
-            facing = "move-right";
-            move_dir = (1.0, 0.0);
+    }
+    if let Some(ground) = map.layer("ground") {
+        for (tile, value) in ground.iter() {
+            if value == WATER {
+                grid.set_shape(tile, TileShape::Water);
+            }
         }
-    } else if up_pressed {
-        facing = "move-up";
-        move_dir = (0.0, 1.0);
-    } else if down_pressed {
-        facing = "move-down";
-        move_dir = (0.0, -1.0);
     }
+    grid
+}
 
-    // :: Move character ::
-    // How far to move the character, in pixel coords:
-    move_delta = (move_dir.0 * move_speed * time_delta,
-                  move_dir.1 * move_speed * time_delta);
-    // Apply move delta to character position:
-    transform.translation.x += move_delta.0;
-    transform.translation.y += move_delta.1;
-
-    // :: Change character animation ::
-    // If a key is pressed and the state would change, update the anim:
-    if facing.len() > 0 && animator.cur_state != facing.to_string() {
-        animator.set_state(facing.to_string(), &mut sprite, None);
-    // If a key isn't pressed...
-    } else if facing.len() == 0 {
-        // check if the character animation is in a 'move'ing state,
-         if animator.cur_state.starts_with("move") {
-            // and if it is, set animator to the corresponding 'stand' state:
-            let stand_state = "stand".to_string() + &animator.cur_state[4..].to_string();
-            animator.set_state(stand_state, &mut sprite, None);
-         }
+fn ground_tile(map: &Tilemap, x: u32, y: u32) -> Option<u32> {
+    map.layer("ground").and_then(|layer| layer.get(UVec2::new(x, y)))
+}
+
+// Swing while attacking, swim while in water, walk (or run) while the
+// player's state is Walking, otherwise stand. The
+// DirectionalAnimator picks the state from this and the player's Direction
+// (which the movement system keeps facing the way they last moved).
+fn player_animation(mut query: Query<(&PlayerState,
+                                      &Sprint,
+                                      &Swimmer,
+                                      &mut DirectionalAnimator<PlayerAction, PlayerAnim>),
+                                      With<Player>>) {
+    for (state, sprint, swimmer, mut directional) in &mut query {
+        let action = match state {
+            PlayerState::Attacking => PlayerAction::Attack,
+            _ if swimmer.is_swimming() => PlayerAction::Swim,
+            PlayerState::Walking if sprint.active => PlayerAction::Run,
+            PlayerState::Walking => PlayerAction::Move,
+            _ => PlayerAction::Stand,
+        };
+        if directional.action != action {
+            directional.action = action;
+        }
     }
 }
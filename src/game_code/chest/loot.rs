@@ -0,0 +1,97 @@
+// :: Loot tables ::
+// What's inside a chest, written in a `.loot.ron` (or `.loot.json`) file
+// under `assets/loot/`. Each roll draws one entry, weighted by `weight`,
+// and entries in `always` are given every time:
+//
+//     (
+//         rolls: 2,
+//         entries: [
+//             (item: "apple", weight: 3, count: (1, 3)), // 1 to 3 apples
+//             (item: "stone", weight: 1),
+//         ],
+//         always: [(item: "old_key")],
+//         money: (5, 10),
+//     )
+use bevy::{prelude::*, reflect::TypeUuid};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::inventory::ItemStack;
+
+#[derive(Clone, Debug, Deserialize, TypeUuid)]
+#[uuid = "4d7e2b90-8c1a-4f3e-b6d5-0a9c3e8f1b27"]
+pub struct LootTable {
+    #[serde(default = "default_rolls")]
+    pub rolls: u32, // how many entries to draw
+    #[serde(default)]
+    pub entries: Vec<LootEntry>,
+    #[serde(default)]
+    pub always: Vec<LootEntry>, // given every time, on top of the rolls
+    #[serde(default)]
+    pub money: (u32, u32), // a range, inclusive
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LootEntry {
+    pub item: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32, // how likely it is, compared to the other entries
+    #[serde(default = "default_count")]
+    pub count: (u32, u32), // a range, inclusive
+}
+
+fn default_rolls() -> u32 {
+    1
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+fn default_count() -> (u32, u32) {
+    (1, 1)
+}
+
+// What came out of a loot table
+#[derive(Clone, Debug, Default)]
+pub struct Loot {
+    pub items: Vec<ItemStack>,
+    pub money: u32,
+}
+
+impl LootTable {
+    pub fn roll(&self, rng: &mut impl Rng) -> Loot {
+        let mut loot = Loot { money: random_count(rng, self.money), ..default() };
+        let total_weight: u32 = self.entries.iter().map(|entry| entry.weight).sum();
+        let rolled: Vec<&LootEntry> = (0..self.rolls).filter_map(|_| {
+            if total_weight == 0 {
+                return None;
+            }
+            let mut pick = rng.gen_range(0..total_weight);
+            self.entries.iter().find(|entry| {
+                if pick < entry.weight {
+                    return true;
+                }
+                pick -= entry.weight;
+                false
+            })
+        }).collect();
+        for entry in self.always.iter().chain(rolled) {
+            let count = random_count(rng, entry.count);
+            if count == 0 {
+                continue;
+            }
+            // Draws of the same item go in one stack
+            match loot.items.iter_mut().find(|stack| stack.item == entry.item) {
+                Some(stack) => stack.count += count,
+                None => loot.items.push(ItemStack { item: entry.item.clone(), count }),
+            }
+        }
+        loot
+    }
+}
+
+// A number between `min` and `max`, inclusive (in either order)
+fn random_count(rng: &mut impl Rng, (min, max): (u32, u32)) -> u32 {
+    rng.gen_range(min.min(max)..=min.max(max))
+}
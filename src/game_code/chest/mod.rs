@@ -0,0 +1,275 @@
+// :: Chests ::
+// Containers the player can open once, for whatever their LootTable (see
+// loot.rs) rolls. Opening one plays its "opening" animation, puts the loot
+// in the player's Inventory and sets the GameFlag `opened_<id>`, so the
+// chest is still open (and empty) when the map is loaded again. If the
+// flag is unset again (e.g. by loading the last checkpoint), so is the
+// chest closed:
+//
+//     commands.spawn((
+//         Chest::new("meadow_stump", asset_server.load("loot/meadow_chest.loot.ron")),
+//         chest_animator(vec![1, 2, 3])?, // closed, opening.., open
+//         SpriteSheetBundle { texture_atlas: asset_server.load("images/chest.atlas.ron"), .. },
+//     ));
+//
+// A chest gets an Interactable and a YSort, unless it has them already.
+// Loot that doesn't fit in the inventory is left on the ground as a Pickup
+// (on the CurrentMap, so it's gone once the player warps away).
+// Opening a chest sends a ChestOpened event (and a QuestEvent::Collected
+// for each item), and shows what was found in a toast.
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    animation::{AnimationStyle, AnimationSystem, AnimatorError, SpriteAnimationPlugin, SpritesheetAnimation, SpritesheetAnimator},
+    asset_loader::RonOrJsonLoader,
+    flags::GameFlags,
+    interaction::{Interactable, InteractionEvent, InteractionPrompt, InteractionSystem},
+    inventory::{Inventory, ItemRegistry, ItemStack},
+    pickup::Pickup,
+    quest::{QuestEvent, QuestSystem},
+    toast::Toasts,
+    warp::{map_local, CurrentMap},
+    ysort::YSort,
+};
+
+mod loot;
+
+pub use loot::{Loot, LootEntry, LootTable};
+
+const CHEST_RADIUS: f32 = 20.0;
+const CHEST_HEIGHT: f32 = 16.0;
+const CHEST_FPS: f32 = 10.0;
+const LEFTOVER_OFFSET: Vec3 = Vec3::new(0.0, -16.0, 0.0); // where loot that didn't fit is left
+
+// A chest's animation states. In animation files, these are written
+// in kebab-case, e.g. "opening".
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Deserialize, Reflect, FromReflect)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChestAnim {
+    #[default]
+    Closed,
+    Opening, // played once, ending on the open frame
+    Open,
+}
+
+// An animator for a chest from its frames, in our shorthand format (see
+// `Frame::from_id`): the first frame is the closed chest and the last is
+// the open one. Chests can also get their animations from a file, with an
+// AnimationSource<ChestAnim>.
+pub fn chest_animator(frames: Vec<i32>) -> Result<SpritesheetAnimator<ChestAnim>, AnimatorError<ChestAnim>> {
+    let (closed, open) = match (frames.first(), frames.last()) {
+        (Some(closed), Some(open)) => (*closed, *open),
+        _ => return Err(AnimatorError::NoFrames(ChestAnim::Opening)),
+    };
+    let mut opening = SpritesheetAnimation::from_frames(frames);
+    opening.fps = CHEST_FPS;
+    opening.looping = AnimationStyle::Once;
+    SpritesheetAnimator::builder()
+        .state(ChestAnim::Closed, SpritesheetAnimation::from_frames(vec![closed]))
+        .state(ChestAnim::Opening, opening)
+        .state(ChestAnim::Open, SpritesheetAnimation::from_frames(vec![open]))
+        .start(ChestAnim::Closed)
+        .build()
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Chest {
+    pub id: String, // unique across the game, since it names the chest's flag
+    pub loot: Handle<LootTable>,
+    opened: bool,
+}
+impl Chest {
+    pub fn new(id: &str, loot: Handle<LootTable>) -> Self {
+        Self { id: id.to_string(), loot, opened: false }
+    }
+    // The GameFlag that's set once the chest has been opened
+    pub fn flag(&self) -> String {
+        format!("opened_{}", self.id)
+    }
+    pub fn is_opened(&self) -> bool {
+        self.opened
+    }
+}
+
+pub struct ChestOpened {
+    pub chest: Entity,
+    pub opener: Entity,
+    pub loot: Loot,
+}
+
+pub struct ChestPlugin;
+impl Plugin for ChestPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(SpriteAnimationPlugin::<ChestAnim>::default())
+            .add_asset::<LootTable>()
+            .add_asset_loader(RonOrJsonLoader::<LootTable>::new(&["loot.ron", "loot.json"]))
+            .add_event::<ChestOpened>()
+            .add_system(add_chests)
+            .add_system(open_chests
+                .after(InteractionSystem)
+                .before(QuestSystem))
+            .add_system(close_chests.after(open_chests))
+            .add_system(show_chest_state
+                .after(add_chests)
+                .after(close_chests)
+                .before(AnimationSystem))
+            .add_system(chest_feedback.after(open_chests));
+    }
+}
+
+// Chests that were opened before (e.g., the last time this map was
+// loaded) start open, and only closed ones can be used
+fn add_chests(
+    mut commands: Commands,
+    flags: Res<GameFlags>,
+    mut chests: Query<(Entity, &mut Chest, Option<&Interactable>, Option<&YSort>), Added<Chest>>,
+) {
+    for (entity, mut chest, interactable, ysort) in &mut chests {
+        if flags.is_set(&chest.flag()) {
+            chest.opened = true;
+            commands.entity(entity).remove::<Interactable>();
+        } else if interactable.is_none() {
+            commands.entity(entity).insert(Interactable::new(CHEST_RADIUS, "Open"));
+        }
+        if ysort.is_none() {
+            commands.entity(entity).insert(YSort::new(-CHEST_HEIGHT / 2.0));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn open_chests(
+    mut commands: Commands,
+    items: Res<ItemRegistry>,
+    loot_tables: Res<Assets<LootTable>>,
+    mut flags: ResMut<GameFlags>,
+    mut interactions: EventReader<InteractionEvent>,
+    mut opened: EventWriter<ChestOpened>,
+    mut quest_events: EventWriter<QuestEvent>,
+    mut chests: Query<(&mut Chest, &GlobalTransform, Option<&mut SpritesheetAnimator<ChestAnim>>, Option<&Children>)>,
+    mut inventories: Query<&mut Inventory>,
+    prompts: Query<(), With<InteractionPrompt>>,
+    maps: Query<(Entity, &GlobalTransform), With<CurrentMap>>,
+) {
+    let mut rng = rand::thread_rng();
+    for event in interactions.iter() {
+        let (mut chest, transform, animator, children) = match chests.get_mut(event.target) {
+            Ok(chest) if !chest.0.opened => chest,
+            _ => continue,
+        };
+        let table = match loot_tables.get(&chest.loot) {
+            Some(table) => table,
+            None => {
+                warn!("Chest \"{}\" can't be opened until its loot table loads", chest.id);
+                continue;
+            },
+        };
+        let mut inventory = match inventories.get_mut(event.player) {
+            Ok(inventory) => inventory,
+            Err(_) => {
+                warn!("Chest \"{}\" was opened by something without an Inventory", chest.id);
+                continue;
+            },
+        };
+
+        let loot = table.roll(&mut rng);
+        inventory.add_currency(loot.money);
+        let mut leftovers: Vec<ItemStack> = Vec::new();
+        for stack in &loot.items {
+            let left = inventory.add(&items, &stack.item, stack.count);
+            if left < stack.count {
+                quest_events.send(QuestEvent::Collected { item: stack.item.clone(), count: stack.count - left });
+            }
+            if left > 0 {
+                leftovers.push(ItemStack { item: stack.item.clone(), count: left });
+            }
+        }
+        let map = maps.get_single().ok();
+        let position = transform.translation() + LEFTOVER_OFFSET;
+        let position = map.map_or(position, |(_, map)| map_local(map, position));
+        for stack in leftovers {
+            let pickup = commands.spawn((
+                Pickup::new(&stack.item, stack.count),
+                SpatialBundle::from_transform(Transform::from_translation(position)),
+            )).id();
+            if let Some((map, _)) = map {
+                commands.entity(map).add_child(pickup);
+            }
+        }
+
+        chest.opened = true;
+        flags.set(&chest.flag(), true);
+        if let Some(mut animator) = animator {
+            if let Err(error) = animator.set_state(ChestAnim::Opening, None) {
+                warn!("Couldn't play chest \"{}\" opening: {}", chest.id, error);
+            }
+        }
+        // It can't be used again, so its prompt goes too
+        commands.entity(event.target).remove::<Interactable>();
+        for child in children.iter().flat_map(|children| children.iter()) {
+            if prompts.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        opened.send(ChestOpened { chest: event.target, opener: event.player, loot });
+    }
+}
+
+// Opened chests whose flag has been unset since can be opened again
+fn close_chests(
+    mut commands: Commands,
+    flags: Res<GameFlags>,
+    mut chests: Query<(Entity, &mut Chest, Option<&mut SpritesheetAnimator<ChestAnim>>)>,
+) {
+    if !flags.is_changed() {
+        return;
+    }
+    for (entity, mut chest, animator) in &mut chests {
+        if !chest.opened || flags.is_set(&chest.flag()) {
+            continue;
+        }
+        chest.opened = false;
+        commands.entity(entity).insert(Interactable::new(CHEST_RADIUS, "Open"));
+        if let Some(mut animator) = animator {
+            if let Err(error) = animator.force_state(ChestAnim::Closed, None) {
+                warn!("Couldn't show chest \"{}\" closed: {}", chest.id, error);
+            }
+        }
+    }
+}
+
+// Open chests show their open frame, once they have an animator (chests
+// whose animations come from a file might not, when they're spawned)
+fn show_chest_state(mut chests: Query<(&Chest, &mut SpritesheetAnimator<ChestAnim>)>) {
+    for (chest, mut animator) in &mut chests {
+        if chest.opened && animator.cur_state == ChestAnim::Closed {
+            if let Err(error) = animator.set_state(ChestAnim::Open, None) {
+                warn!("Couldn't show chest \"{}\" open: {}", chest.id, error);
+            }
+        }
+    }
+}
+
+fn chest_feedback(
+    items: Res<ItemRegistry>,
+    mut toasts: ResMut<Toasts>,
+    mut opened: EventReader<ChestOpened>,
+) {
+    for event in opened.iter() {
+        let mut found: Vec<String> = event.loot.items.iter()
+            .map(|stack| match stack.count {
+                1 => items.name(&stack.item).to_string(),
+                count => format!("{} x{}", items.name(&stack.item), count),
+            })
+            .collect();
+        if event.loot.money > 0 {
+            found.push(format!("{} coins", event.loot.money));
+        }
+        if found.is_empty() {
+            toasts.show("It's empty.");
+        } else {
+            toasts.show(format!("Found {}!", found.join(", ")));
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// :: In-game clock ::
+// The time of day in the game world, which runs much faster than real
+// time (by default, a day lasts 24 minutes). Shops open and close by it,
+// NPCs go home by it (see Schedule), and lighting can follow it:
+//
+//     if clock.hour() >= 20.0 || clock.hour() < 6.0 { /* night */ }
+//
+// It stops while `paused` (e.g., in menus and cutscenes).
+use bevy::prelude::*;
+
+const HOURS_PER_DAY: f32 = 24.0;
+const DEFAULT_START_HOUR: f32 = 8.0;
+const DEFAULT_HOURS_PER_SECOND: f32 = 1.0 / 60.0; // an in-game hour every real minute
+
+#[derive(Resource, Clone, Debug)]
+pub struct GameClock {
+    pub hours_per_second: f32, // how fast in-game time passes
+    pub paused: bool,
+    day: u32,
+    hour: f32, // from 0.0 (midnight) up to 24.0
+}
+impl Default for GameClock {
+    fn default() -> Self {
+        Self::new(DEFAULT_START_HOUR)
+    }
+}
+impl GameClock {
+    // A clock on the first day, at `hour`
+    pub fn new(hour: f32) -> Self {
+        Self {
+            hours_per_second: DEFAULT_HOURS_PER_SECOND,
+            paused: false,
+            day: 0,
+            hour: hour.rem_euclid(HOURS_PER_DAY),
+        }
+    }
+    // The hour of the day, with the minutes as a fraction; 13.5 is 1:30pm
+    pub fn hour(&self) -> f32 {
+        self.hour
+    }
+    // How many days have gone by since the game started
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+    // Jump to `hour`, on the next day if it's already past (e.g., sleeping)
+    pub fn skip_to(&mut self, hour: f32) {
+        let hour = hour.rem_euclid(HOURS_PER_DAY);
+        if hour <= self.hour {
+            self.day += 1;
+        }
+        self.hour = hour;
+    }
+    pub fn advance(&mut self, hours: f32) {
+        let total = self.hour + hours;
+        self.day += (total / HOURS_PER_DAY).floor() as u32;
+        self.hour = total.rem_euclid(HOURS_PER_DAY);
+    }
+}
+
+pub struct ClockPlugin;
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameClock>()
+            .add_system(tick_clock);
+    }
+}
+
+fn tick_clock(time: Res<Time>, mut clock: ResMut<GameClock>) {
+    if !clock.paused {
+        let hours = time.delta_seconds() * clock.hours_per_second;
+        clock.advance(hours);
+    }
+}
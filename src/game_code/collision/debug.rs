@@ -0,0 +1,158 @@
+// :: Collision debug overlay ::
+// Only compiled with the "collision-debug" feature. Outlines every
+// Collider (red), TriggerZone (yellow) and blocking CollisionGrid tile
+// (blue; just the blocking part of half tiles, slopes and one-way edges)
+// over the map, so it's clear what's actually blocking the player.
+// Press F2 to show or hide the overlay.
+use bevy::{
+    math::Rect,
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology, view::NoFrustumCulling},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+    transform::TransformSystem,
+};
+
+use super::{Collider, CollisionGrid, TileShape, TriggerZone};
+
+const DEBUG_Z: f32 = 950.0; // over everything on the map, overhangs included
+const TOGGLE_KEY: KeyCode = KeyCode::F2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum DebugShapes {
+    Colliders,
+    Triggers,
+    Tiles,
+}
+impl DebugShapes {
+    fn color(&self) -> Color {
+        match self {
+            DebugShapes::Colliders => Color::RED,
+            DebugShapes::Triggers => Color::YELLOW,
+            DebugShapes::Tiles => Color::rgb(0.2, 0.4, 1.0),
+        }
+    }
+}
+
+// The mesh outlining one kind of shape
+#[derive(Component)]
+pub struct CollisionDebugLines(DebugShapes);
+
+#[derive(Resource)]
+pub struct ShowCollisionDebug(pub bool);
+
+pub(super) fn add_debug_systems(app: &mut App) {
+    app.insert_resource(ShowCollisionDebug(true))
+        .add_startup_system(spawn_debug_lines)
+        .add_system(toggle_debug_lines)
+        // Once everything has moved, so the outlines don't lag a frame behind
+        .add_system_to_stage(CoreStage::PostUpdate, update_debug_lines
+            .after(TransformSystem::TransformPropagate));
+}
+
+fn spawn_debug_lines(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for shapes in [DebugShapes::Tiles, DebugShapes::Triggers, DebugShapes::Colliders] {
+        commands.spawn((
+            CollisionDebugLines(shapes),
+            NoFrustumCulling, // the mesh changes every frame, so its bounds would be stale
+            MaterialMesh2dBundle {
+                mesh: meshes.add(outline_mesh(&[])).into(),
+                material: materials.add(ColorMaterial::from(shapes.color())),
+                transform: Transform::from_xyz(0.0, 0.0, DEBUG_Z),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update_debug_lines(
+    show: Res<ShowCollisionDebug>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    lines: Query<(&CollisionDebugLines, &Mesh2dHandle)>,
+    colliders: Query<(&Collider, &GlobalTransform)>,
+    triggers: Query<(&TriggerZone, &GlobalTransform)>,
+    grids: Query<(&CollisionGrid, &GlobalTransform)>,
+) {
+    if !show.0 {
+        return;
+    }
+    for (CollisionDebugLines(shapes), handle) in &lines {
+        let outlines: Vec<Vec<Vec2>> = match shapes {
+            DebugShapes::Colliders => colliders.iter()
+                .map(|(collider, transform)| rect_outline(collider.rect_at(transform.translation().truncate())))
+                .collect(),
+            DebugShapes::Triggers => triggers.iter()
+                .map(|(zone, transform)| rect_outline(zone.rect_at(transform.translation().truncate())))
+                .collect(),
+            DebugShapes::Tiles => grids.iter()
+                .flat_map(|(grid, transform)| {
+                    let corner = transform.translation().truncate();
+                    let size = grid.size().as_vec2() * grid.tile_size();
+                    grid.shapes_in(Rect::new(0.0, -size.y, size.x, 0.0))
+                        .map(move |(shape, rect)| {
+                            let tile = Rect::from_corners(rect.min + corner, rect.max + corner);
+                            match shape {
+                                TileShape::Slope(slope_corner) => TileShape::slope_triangle(slope_corner, tile).to_vec(),
+                                TileShape::OneWay(side) => {
+                                    let edge = TileShape::edge_rect(side, tile);
+                                    vec![edge.min, edge.max]
+                                }
+                                TileShape::Water => rect_outline(tile),
+                                _ => shape.solid_rect(tile).map(rect_outline).unwrap_or_default(),
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+        };
+        if let Some(mesh) = meshes.get_mut(&handle.0) {
+            *mesh = outline_mesh(&outlines);
+        }
+    }
+}
+
+fn toggle_debug_lines(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut show: ResMut<ShowCollisionDebug>,
+    mut lines: Query<&mut Visibility, With<CollisionDebugLines>>,
+) {
+    if keyboard_input.just_pressed(TOGGLE_KEY) {
+        show.0 = !show.0;
+    }
+    for mut visibility in &mut lines {
+        if visibility.is_visible != show.0 {
+            visibility.is_visible = show.0;
+        }
+    }
+}
+
+fn rect_outline(rect: Rect) -> Vec<Vec2> {
+    vec![rect.min, Vec2::new(rect.max.x, rect.min.y), rect.max, Vec2::new(rect.min.x, rect.max.y)]
+}
+
+// Each outline's points joined up (and closed, for more than two), as lines
+fn outline_mesh(outlines: &[Vec<Vec2>]) -> Mesh {
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+    for outline in outlines {
+        let first = positions.len() as u32;
+        let count = outline.len() as u32;
+        positions.extend(outline.iter().map(|point| [point.x, point.y, 0.0]));
+        let segments = if count > 2 { count } else { count.saturating_sub(1) };
+        for i in 0..segments {
+            indices.extend([first + i, first + (i + 1) % count]);
+        }
+    }
+
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
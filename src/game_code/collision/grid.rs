@@ -0,0 +1,181 @@
+// :: Tile collision ::
+// Which tiles of a map block movement (walls, water, tree trunks), and
+// how. A CollisionGrid goes on the map's entity, so it lines up with the
+// map's tiles: its Transform is the grid's top-left corner, and rows go
+// from the top down, like a Tilemap.
+//
+// Most blocking tiles are Solid, but a tile can also be half solid (a
+// low wall along one edge), a slope (a diagonal cliff or the side of a
+// staircase, which the player slides along), or a one-way edge (a ledge
+// that can be hopped down but not climbed, or a bridge's railing). Deep
+// Water is solid only for movers that aren't Swimmers (see swimming.rs).
+use bevy::{math::Rect, prelude::*};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TileSide {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+impl TileSide {
+    // The direction pointing out of the tile through this side
+    pub fn outward(&self) -> Vec2 {
+        match self {
+            TileSide::Top => Vec2::Y,
+            TileSide::Bottom => Vec2::NEG_Y,
+            TileSide::Left => Vec2::NEG_X,
+            TileSide::Right => Vec2::X,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TileCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum TileShape {
+    #[default]
+    Empty,
+    Solid,
+    Half(TileSide), // the half of the tile on that side is solid
+    Slope(TileCorner), // the triangle filling that corner is solid
+    // An edge along that side, which can be crossed leaving the tile
+    // but not entering it; e.g. OneWay(Bottom) can be walked down over,
+    // but not up
+    OneWay(TileSide),
+    Water, // solid, except to Swimmers
+}
+impl TileShape {
+    // Shapes by number, for map editors: 0 is empty, 1 is solid, 2-5 are
+    // halves, 6-9 are slopes, 10-13 are one-way edges (each group in
+    // top, bottom, left, right order; slopes in top-left, top-right,
+    // bottom-left, bottom-right), and 14 is water. Anything else is solid.
+    pub fn from_code(code: u32) -> Self {
+        use TileCorner::*;
+        use TileSide::*;
+        const SIDES: [TileSide; 4] = [Top, Bottom, Left, Right];
+        const CORNERS: [TileCorner; 4] = [TopLeft, TopRight, BottomLeft, BottomRight];
+        match code {
+            0 => TileShape::Empty,
+            2..=5 => TileShape::Half(SIDES[code as usize - 2]),
+            6..=9 => TileShape::Slope(CORNERS[code as usize - 6]),
+            10..=13 => TileShape::OneWay(SIDES[code as usize - 10]),
+            14 => TileShape::Water,
+            _ => TileShape::Solid,
+        }
+    }
+
+    // The part of a tile (whose rectangle is `tile`) that blocks movement
+    // in every direction, if it's a rectangle
+    pub fn solid_rect(&self, tile: Rect) -> Option<Rect> {
+        let center = tile.center();
+        match self {
+            TileShape::Solid => Some(tile),
+            TileShape::Half(TileSide::Top) => Some(Rect::from_corners(Vec2::new(tile.min.x, center.y), tile.max)),
+            TileShape::Half(TileSide::Bottom) => Some(Rect::from_corners(tile.min, Vec2::new(tile.max.x, center.y))),
+            TileShape::Half(TileSide::Left) => Some(Rect::from_corners(tile.min, Vec2::new(center.x, tile.max.y))),
+            TileShape::Half(TileSide::Right) => Some(Rect::from_corners(Vec2::new(center.x, tile.min.y), tile.max)),
+            _ => None,
+        }
+    }
+
+    // A one-way edge, as a flat rectangle along the tile's side
+    pub fn edge_rect(side: TileSide, tile: Rect) -> Rect {
+        match side {
+            TileSide::Top => Rect::new(tile.min.x, tile.max.y, tile.max.x, tile.max.y),
+            TileSide::Bottom => Rect::new(tile.min.x, tile.min.y, tile.max.x, tile.min.y),
+            TileSide::Left => Rect::new(tile.min.x, tile.min.y, tile.min.x, tile.max.y),
+            TileSide::Right => Rect::new(tile.max.x, tile.min.y, tile.max.x, tile.max.y),
+        }
+    }
+
+    // A slope's triangle, in a tile whose rectangle is `tile`
+    pub fn slope_triangle(corner: TileCorner, tile: Rect) -> [Vec2; 3] {
+        let (top_left, top_right) = (Vec2::new(tile.min.x, tile.max.y), tile.max);
+        let (bottom_left, bottom_right) = (tile.min, Vec2::new(tile.max.x, tile.min.y));
+        match corner {
+            TileCorner::TopLeft => [bottom_left, top_left, top_right],
+            TileCorner::TopRight => [top_left, top_right, bottom_right],
+            TileCorner::BottomLeft => [top_left, bottom_left, bottom_right],
+            TileCorner::BottomRight => [bottom_left, bottom_right, top_right],
+        }
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct CollisionGrid {
+    size: UVec2, // in tiles
+    tile_size: Vec2,
+    shapes: Vec<TileShape>,
+}
+impl CollisionGrid {
+    pub fn new(size: UVec2, tile_size: Vec2) -> Self {
+        Self { size, tile_size, shapes: vec![TileShape::Empty; (size.x * size.y) as usize] }
+    }
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+    pub fn tile_size(&self) -> Vec2 {
+        self.tile_size
+    }
+    pub fn shape(&self, tile: UVec2) -> TileShape {
+        self.index(tile).map_or(TileShape::Empty, |index| self.shapes[index])
+    }
+    pub fn set_shape(&mut self, tile: UVec2, shape: TileShape) {
+        match self.index(tile) {
+            Some(index) => self.shapes[index] = shape,
+            None => warn!("Tile {} is outside of the {}x{} collision grid", tile, self.size.x, self.size.y),
+        }
+    }
+    pub fn is_solid(&self, tile: UVec2) -> bool {
+        self.shape(tile) == TileShape::Solid
+    }
+    pub fn set_solid(&mut self, tile: UVec2, solid: bool) {
+        self.set_shape(tile, if solid { TileShape::Solid } else { TileShape::Empty });
+    }
+
+    // The non-empty tiles overlapping `area` (relative to the grid's
+    // top-left corner), with their rectangles
+    pub fn shapes_in(&self, area: Rect) -> impl Iterator<Item = (TileShape, Rect)> + '_ {
+        // Rows are counted downwards, so flip y to find them
+        let first = (Vec2::new(area.min.x, -area.max.y) / self.tile_size).floor().max(Vec2::ZERO);
+        let last = (Vec2::new(area.max.x, -area.min.y) / self.tile_size).ceil()
+            .min(self.size.as_vec2());
+        let (first, last) = (first.as_uvec2(), last.as_uvec2());
+        (first.y..last.y.max(first.y))
+            .flat_map(move |y| (first.x..last.x.max(first.x)).map(move |x| UVec2::new(x, y)))
+            .map(|tile| (self.shape(tile), self.tile_rect(tile)))
+            .filter(|(shape, _)| *shape != TileShape::Empty)
+    }
+
+    // A tile's rectangle, relative to the grid's top-left corner
+    pub fn tile_rect(&self, tile: UVec2) -> Rect {
+        let top_left = Vec2::new(tile.x as f32, -(tile.y as f32)) * self.tile_size;
+        Rect::from_corners(top_left, top_left + Vec2::new(self.tile_size.x, -self.tile_size.y))
+    }
+
+    // The tile containing `point` (relative to the grid's top-left corner),
+    // if it's on the grid
+    pub fn tile_at(&self, point: Vec2) -> Option<UVec2> {
+        let tile = (Vec2::new(point.x, -point.y) / self.tile_size).floor();
+        if tile.cmpge(Vec2::ZERO).all() && tile.cmplt(self.size.as_vec2()).all() {
+            Some(tile.as_uvec2())
+        } else {
+            None
+        }
+    }
+
+    fn index(&self, tile: UVec2) -> Option<usize> {
+        if tile.x < self.size.x && tile.y < self.size.y {
+            Some((tile.y * self.size.x + tile.x) as usize)
+        } else {
+            None
+        }
+    }
+}
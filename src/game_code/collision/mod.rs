@@ -0,0 +1,255 @@
+// :: Collision ::
+// Keeps moving things out of walls. Anything with a Collider and a
+// Position (i.e. anything that moves) is stopped by blocking tiles in a
+// CollisionGrid (including half tiles, slopes and one-way edges, and
+// water for anything that isn't a Swimmer; see grid.rs), and by Colliders that don't move: ones without a Position
+// (rocks, fences), and movers whose MoveIntent is zero (NPCs standing
+// still). Players standing still don't block anyone, and a Follower and
+// its leader never block each other, so neither can be stuck waiting on
+// the other. Nearby Colliders are found with the SpatialHash, so
+// the SpatialHashPlugin is needed too.
+//
+// Each movement step moves along x, then along y, stopping each axis
+// separately, so walking diagonally into a wall slides along it instead
+// of sticking. Areas that only notice things walking through them are
+// TriggerZones (see triggers.rs), and ground that moves things (conveyors,
+// ice, rafts) is a Surface or Carrier (see surfaces.rs). Moving things
+// don't block each other, but are eased apart (see separation.rs).
+use bevy::{math::Rect, prelude::*, transform::TransformSystem, utils::HashMap};
+
+use crate::{
+    movement::{MoveIntent, MovementStep, Position, Velocity, MOVEMENT_STAGE},
+    npc::Follower,
+    player::Player,
+    spatial::{SpatialHash, SpatialHashSystem},
+    swimming::Swimmer,
+};
+
+#[cfg(feature = "collision-debug")]
+mod debug;
+mod grid;
+mod separation;
+mod surfaces;
+mod triggers;
+
+pub use grid::{CollisionGrid, TileCorner, TileShape, TileSide};
+pub use separation::Separation;
+pub use surfaces::{Carrier, Surface, SurfaceKind};
+pub use triggers::{TriggerEnter, TriggerExit, TriggerSensor, TriggerZone};
+
+// How far an entity can be inside something before it counts as already
+// being inside it, to allow for rounding after being pushed out
+const SKIN: f32 = 0.01;
+
+// A box around an entity that other things can't move into
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Collider {
+    pub size: Vec2,
+    pub offset: Vec2, // from the entity's position to the box's center
+}
+impl Collider {
+    pub fn new(size: Vec2) -> Self {
+        Self { size, offset: Vec2::ZERO }
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+    // The box, for an entity at `position`
+    pub fn rect_at(&self, position: Vec2) -> Rect {
+        Rect::from_center_size(position + self.offset, self.size)
+    }
+}
+
+// Systems that need collisions settled should run `.after(CollisionSystem)`,
+// in MOVEMENT_STAGE
+#[derive(SystemLabel)]
+pub struct CollisionSystem;
+
+pub struct CollisionPlugin;
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TriggerEnter>()
+            .add_event::<TriggerExit>()
+            .add_system(surfaces::add_momentum)
+            .add_system_to_stage(MOVEMENT_STAGE, surfaces::apply_surfaces
+                .after(MovementStep)
+                .before(CollisionSystem))
+            .add_system_to_stage(MOVEMENT_STAGE, separation::separate_crowds
+                .after(MovementStep)
+                .after(surfaces::apply_surfaces)
+                .before(CollisionSystem))
+            .add_system_to_stage(MOVEMENT_STAGE, resolve_collisions
+                .label(CollisionSystem)
+                .after(MovementStep))
+            .add_system_to_stage(MOVEMENT_STAGE, surfaces::record_momentum.after(CollisionSystem))
+            // Once everything has moved and transforms are up to date; the
+            // events are read by the next frame's Update systems
+            .add_system_to_stage(CoreStage::PostUpdate, triggers::detect_triggers
+                .after(TransformSystem::TransformPropagate)
+                .after(SpatialHashSystem));
+
+        #[cfg(feature = "collision-debug")]
+        debug::add_debug_systems(app);
+    }
+}
+
+// Where an entity's feet are, for deciding what it's standing on: the
+// center of its Collider, or its position without one
+pub fn feet(collider: Option<&Collider>, position: Vec2) -> Vec2 {
+    collider.map_or(position, |collider| collider.rect_at(position).center())
+}
+
+// Whether two boxes overlap; boxes that only touch don't
+pub fn overlaps(a: Rect, b: Rect) -> bool {
+    a.min.x < b.max.x && a.max.x > b.min.x && a.min.y < b.max.y && a.max.y > b.min.y
+}
+
+// Something in the way of a step
+struct Blocker {
+    rect: Rect,
+    blocks: Option<Vec2>, // the only direction it stops movement in (for one-way edges), if any
+}
+
+// Redo each entity's last step one axis at a time, stopping at anything
+// solid, then slide it off any slopes it ended up on
+#[allow(clippy::too_many_arguments)]
+fn resolve_collisions(
+    spatial_hash: Res<SpatialHash>,
+    mut movers: Query<
+        (Entity, &Collider, &mut Position, Option<&mut Velocity>, Option<&MoveIntent>),
+        Without<Carrier>,
+    >,
+    obstacles: Query<(&Collider, &GlobalTransform), Without<Position>>,
+    grids: Query<(&CollisionGrid, &GlobalTransform)>,
+    carriers: Query<(&Carrier, &Position)>,
+    followers: Query<&Follower>,
+    players: Query<(), With<Player>>,
+    swimmers: Query<(), With<Swimmer>>,
+) {
+    // Tiles under a carrier can be stood on
+    let carried_areas: Vec<Rect> = carriers.iter()
+        .map(|(carrier, position)| carrier.rect_at(position.current))
+        .collect();
+    // Movers standing still block the others, like any other obstacle
+    let standing: HashMap<Entity, Rect> = movers.iter()
+        .filter(|(_, _, _, _, intent)| intent.map_or(false, |intent| intent.0 == Vec2::ZERO))
+        .filter(|(entity, ..)| !players.contains(*entity))
+        .map(|(entity, collider, position, _, _)| (entity, collider.rect_at(position.current)))
+        .collect();
+    let following = |a: Entity, b: Entity| followers.get(a).map_or(false, |follower| follower.leader == b);
+    for (entity, collider, mut position, mut velocity, _) in &mut movers {
+        let step = position.current - position.previous;
+        if step == Vec2::ZERO {
+            continue;
+        }
+        // Everything solid near the step
+        let swept = collider.rect_at(position.previous).union(collider.rect_at(position.current));
+        let mut blockers: Vec<Blocker> = spatial_hash.query_rect(swept).into_iter()
+            .filter(|other| *other != entity)
+            .filter_map(|other| match obstacles.get(other) {
+                Ok((obstacle, transform)) => Some(obstacle.rect_at(transform.translation().truncate())),
+                Err(_) if following(entity, other) || following(other, entity) => None,
+                Err(_) => standing.get(&other).copied(),
+            })
+            .filter(|rect| overlaps(*rect, swept))
+            .map(|rect| Blocker { rect, blocks: None })
+            .collect();
+        let mut slopes = Vec::new();
+        let swims = swimmers.contains(entity);
+        for (grid, transform) in &grids {
+            let corner = transform.translation().truncate();
+            let local = Rect::from_corners(swept.min - corner, swept.max - corner);
+            for (shape, rect) in grid.shapes_in(local) {
+                let tile = Rect::from_corners(rect.min + corner, rect.max + corner);
+                if carried_areas.iter().any(|area| area.contains(tile.center())) {
+                    continue;
+                }
+                match shape {
+                    TileShape::OneWay(side) => blockers.push(Blocker {
+                        rect: TileShape::edge_rect(side, tile),
+                        blocks: Some(-side.outward()),
+                    }),
+                    TileShape::Slope(slope_corner) => slopes.push(TileShape::slope_triangle(slope_corner, tile)),
+                    TileShape::Water if !swims => blockers.push(Blocker { rect: tile, blocks: None }),
+                    _ => blockers.extend(shape.solid_rect(tile).map(|rect| Blocker { rect, blocks: None })),
+                }
+            }
+        }
+        if blockers.is_empty() && slopes.is_empty() {
+            continue;
+        }
+
+        let shrink = |rect: Rect| Rect::from_center_size(rect.center(), rect.size() - Vec2::splat(SKIN * 2.0));
+        let mut pos = position.previous;
+        for axis in [Vec2::X, Vec2::Y] {
+            let along = step.dot(axis);
+            if along == 0.0 {
+                continue;
+            }
+            let start_inner = shrink(collider.rect_at(pos));
+            pos += axis * along;
+            let moved = collider.rect_at(pos);
+            // Only stop at things we've run into, not ones we were already
+            // inside (e.g., something spawned on top of us), so we can walk out
+            let pushes = blockers.iter()
+                .filter(|blocker| blocker.blocks.map_or(true, |blocks| blocks.dot(axis) * along > 0.0))
+                .map(|blocker| blocker.rect)
+                .filter(|solid| overlaps(moved, *solid) && !overlaps(start_inner, *solid))
+                .map(|solid| if along > 0.0 {
+                    solid.min.dot(axis) - moved.max.dot(axis)
+                } else {
+                    solid.max.dot(axis) - moved.min.dot(axis)
+                });
+            // Back off far enough to clear everything we ran into
+            let push = if along > 0.0 { pushes.fold(0.0, f32::min) } else { pushes.fold(0.0, f32::max) };
+            pos += axis * push;
+            // Whatever was pushing it this way has hit something too
+            if let Some(velocity) = velocity.as_mut().filter(|_| push != 0.0) {
+                velocity.0 -= axis * velocity.0.dot(axis);
+            }
+        }
+
+        // Slopes push straight out of their diagonal, which is what
+        // slides whatever walks into one along it
+        let start_inner = shrink(collider.rect_at(position.previous));
+        for triangle in slopes.iter() {
+            if separation(start_inner, triangle).is_some() {
+                continue;
+            }
+            if let Some(push) = separation(collider.rect_at(pos), triangle) {
+                pos += push;
+            }
+        }
+        position.current = pos;
+    }
+}
+
+// The shortest move that takes a box out of a triangle, if they overlap
+fn separation(rect: Rect, triangle: &[Vec2; 3]) -> Option<Vec2> {
+    let corners = [rect.min, Vec2::new(rect.max.x, rect.min.y), rect.max, Vec2::new(rect.min.x, rect.max.y)];
+    // The triangle's diagonal (its only edge that isn't along an axis)
+    let diagonal = (triangle[2] - triangle[0]).perp().normalize_or_zero();
+    let project = |points: &[Vec2], axis: Vec2| {
+        points.iter().map(|point| point.dot(axis)).fold((f32::MAX, f32::MIN), |(min, max), value| {
+            (min.min(value), max.max(value))
+        })
+    };
+    let mut best: Option<Vec2> = None;
+    for axis in [Vec2::X, Vec2::Y, diagonal] {
+        let (rect_min, rect_max) = project(&corners, axis);
+        let (tri_min, tri_max) = project(triangle, axis);
+        if rect_max <= tri_min || rect_min >= tri_max {
+            return None; // separated along this axis, so not overlapping at all
+        }
+        let push = if rect_min + rect_max < tri_min + tri_max {
+            axis * (tri_min - rect_max)
+        } else {
+            axis * (tri_max - rect_min)
+        };
+        if best.map_or(true, |best| push.length_squared() < best.length_squared()) {
+            best = Some(push);
+        }
+    }
+    best
+}
@@ -0,0 +1,68 @@
+// :: Separation ::
+// Moving things don't block each other (only things standing still do),
+// so crowds of NPCs would otherwise pile up on the same spot. Instead,
+// moving entities with a Separation whose Colliders overlap are eased
+// apart, a little each movement step, before collisions are resolved,
+// so nobody is ever pushed into a wall.
+use bevy::{math::Rect, prelude::*, utils::HashMap};
+
+use super::{overlaps, Collider};
+use crate::{
+    movement::{Position, MOVEMENT_TIMESTEP},
+    spatial::SpatialHash,
+};
+
+const DEFAULT_SEPARATION_SPEED: f32 = 24.0; // in pixels per second
+const QUERY_MARGIN: f32 = 4.0; // the SpatialHash is a frame behind, so look a little further
+
+// Pushes an entity away from others it overlaps, at up to `speed` pixels
+// per second. Needs a Collider and a Position.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Separation {
+    pub speed: f32,
+}
+impl Default for Separation {
+    fn default() -> Self {
+        Self { speed: DEFAULT_SEPARATION_SPEED }
+    }
+}
+
+pub(super) fn separate_crowds(
+    spatial_hash: Res<SpatialHash>,
+    mut crowd: Query<(Entity, &Separation, &Collider, &mut Position)>,
+) {
+    let dt = MOVEMENT_TIMESTEP as f32;
+    let rects: HashMap<Entity, Rect> = crowd.iter()
+        .map(|(entity, _, collider, position)| (entity, collider.rect_at(position.current)))
+        .collect();
+
+    let mut pushes: Vec<(Entity, Vec2)> = Vec::new();
+    for (entity, separation, _, _) in &crowd {
+        let rect = rects[&entity];
+        let nearby = Rect::from_center_size(rect.center(), rect.size() + Vec2::splat(QUERY_MARGIN * 2.0));
+        let mut push = Vec2::ZERO;
+        for other in spatial_hash.query_rect(nearby) {
+            let other_rect = match rects.get(&other) {
+                Some(other_rect) if other != entity && overlaps(rect, *other_rect) => *other_rect,
+                _ => continue,
+            };
+            // Straight away from the other's center; entities exactly on top of
+            // each other split left and right, by which was spawned first
+            let away = (rect.center() - other_rect.center()).try_normalize()
+                .unwrap_or(if entity.index() < other.index() { Vec2::NEG_X } else { Vec2::X });
+            // No further than halfway out of the overlap, since the other moves too
+            let overlap = rect.intersect(*other_rect).size();
+            let depth = if away.x.abs() > away.y.abs() { overlap.x } else { overlap.y };
+            push += away * (separation.speed * dt).min(depth / 2.0);
+        }
+        if push != Vec2::ZERO {
+            pushes.push((entity, push));
+        }
+    }
+
+    for (entity, push) in pushes {
+        if let Ok((_, _, _, mut position)) = crowd.get_mut(entity) {
+            position.current += push;
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// :: Surfaces ::
+// Ground that moves whatever stands on it. Where an entity's feet (the
+// center of its Collider, or its position without one) are decides which
+// surfaces it's on:
+//
+//   - Conveyor: pushes everything on it along at a fixed velocity
+//   - Ice: keeps things sliding; they only slowly speed up, stop or turn
+//   - Carrier: a moving platform, like a raft. Things on it move along
+//     with it, and can stand on it even over solid tiles (like water).
+//
+// The extra movement goes through collision like any other, so a conveyor
+// can't push the player through a wall. Carriers themselves aren't
+// blocked by anything; they go wherever they're steered (e.g., with a
+// MovePath).
+//
+//     commands.spawn((Surface::conveyor(belt_size, Vec2::new(24.0, 0.0)), SpatialBundle { .. }));
+//     commands.spawn((Carrier::new(raft_size), MoveIntent::default(), MoveSpeed(20.0), MovePath { .. }, SpriteBundle { .. }));
+use bevy::{math::Rect, prelude::*};
+
+use super::{feet, Collider};
+use crate::movement::{Position, MOVEMENT_TIMESTEP};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SurfaceKind {
+    Conveyor(Vec2), // in pixels per second
+    Ice { grip: f32 }, // how quickly movement changes, per second; lower is slipperier
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Surface {
+    pub size: Vec2,
+    pub offset: Vec2, // from the entity's position to the surface's center
+    pub kind: SurfaceKind,
+}
+impl Surface {
+    pub fn conveyor(size: Vec2, velocity: Vec2) -> Self {
+        Self { size, offset: Vec2::ZERO, kind: SurfaceKind::Conveyor(velocity) }
+    }
+    pub fn ice(size: Vec2, grip: f32) -> Self {
+        Self { size, offset: Vec2::ZERO, kind: SurfaceKind::Ice { grip } }
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn rect_at(&self, position: Vec2) -> Rect {
+        Rect::from_center_size(position + self.offset, self.size)
+    }
+}
+
+// A moving platform. Needs a Position (i.e. a MoveIntent) to carry anything.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Carrier {
+    pub size: Vec2,
+    pub offset: Vec2, // from the entity's position to the platform's center
+}
+impl Carrier {
+    pub fn new(size: Vec2) -> Self {
+        Self { size, offset: Vec2::ZERO }
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn rect_at(&self, position: Vec2) -> Rect {
+        Rect::from_center_size(position + self.offset, self.size)
+    }
+}
+
+// How a moving entity moved last step, so ice can keep it sliding. Added
+// automatically to anything with a Position.
+#[derive(Component, Default)]
+pub(super) struct Momentum {
+    step: Vec2, // the entity's own movement, after collisions
+    carried: Vec2, // what surfaces added this step
+}
+
+pub(super) fn add_momentum(
+    mut commands: Commands,
+    query: Query<Entity, (With<Position>, Without<Momentum>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(Momentum::default());
+    }
+}
+
+pub(super) fn apply_surfaces(
+    mut movers: Query<(Option<&Collider>, &mut Position, &mut Momentum), Without<Carrier>>,
+    surfaces: Query<(&Surface, &GlobalTransform)>,
+    carriers: Query<(&Carrier, &Position)>,
+) {
+    let dt = MOVEMENT_TIMESTEP as f32;
+    for (collider, mut position, mut momentum) in &mut movers {
+        let feet = feet(collider, position.previous);
+        let mut own = position.current - position.previous;
+        let mut carried = Vec2::ZERO;
+        let mut grip: Option<f32> = None;
+        for (surface, transform) in &surfaces {
+            if !surface.rect_at(transform.translation().truncate()).contains(feet) {
+                continue;
+            }
+            match surface.kind {
+                SurfaceKind::Conveyor(velocity) => carried += velocity * dt,
+                SurfaceKind::Ice { grip: ice_grip } => grip = Some(grip.map_or(ice_grip, |grip| grip.min(ice_grip))),
+            }
+        }
+        // On ice, ease from how we were moving towards how we want to move
+        if let Some(grip) = grip {
+            own = momentum.step.lerp(own, 1.0 - (-grip * dt).exp());
+        }
+        let riding = carriers.iter()
+            .find(|(carrier, carrier_position)| carrier.rect_at(carrier_position.previous).contains(feet));
+        if let Some((_, carrier_position)) = riding {
+            carried += carrier_position.current - carrier_position.previous;
+        }
+
+        momentum.carried = carried;
+        let current = position.previous + own + carried;
+        if position.current != current {
+            position.current = current;
+        }
+    }
+}
+
+// Remember how each entity really moved, once collisions have stopped it
+pub(super) fn record_momentum(mut movers: Query<(&Position, &mut Momentum)>) {
+    for (position, mut momentum) in &mut movers {
+        momentum.step = position.current - position.previous - momentum.carried;
+    }
+}
+
@@ -0,0 +1,94 @@
+// :: Triggers ::
+// Areas that notice when something walks into or out of them, without
+// blocking it: door warps, cutscene starts, music changes. Anything with a
+// TriggerSensor (usually the player) sends a TriggerEnter when its Collider
+// (or, without one, its position) starts overlapping a TriggerZone, and a
+// TriggerExit when it stops:
+//
+//     for enter in trigger_enters.iter() {
+//         if let Ok(door) = doors.get(enter.zone) { ... }
+//     }
+use bevy::{math::Rect, prelude::*, utils::HashSet};
+
+use super::{overlaps, Collider};
+use crate::spatial::SpatialHash;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TriggerZone {
+    pub size: Vec2,
+    pub offset: Vec2, // from the entity's position to the zone's center
+}
+impl TriggerZone {
+    pub fn new(size: Vec2) -> Self {
+        Self { size, offset: Vec2::ZERO }
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn rect_at(&self, position: Vec2) -> Rect {
+        Rect::from_center_size(position + self.offset, self.size)
+    }
+}
+
+// Lets an entity set off TriggerZones
+#[derive(Component, Default)]
+pub struct TriggerSensor {
+    inside: HashSet<Entity>, // the zones it's in
+}
+impl TriggerSensor {
+    pub fn is_inside(&self, zone: Entity) -> bool {
+        self.inside.contains(&zone)
+    }
+}
+
+pub struct TriggerEnter {
+    pub zone: Entity,
+    pub sensor: Entity,
+}
+
+pub struct TriggerExit {
+    pub zone: Entity,
+    pub sensor: Entity,
+}
+
+pub(super) fn detect_triggers(
+    spatial_hash: Res<SpatialHash>,
+    mut enters: EventWriter<TriggerEnter>,
+    mut exits: EventWriter<TriggerExit>,
+    mut sensors: Query<(Entity, &mut TriggerSensor, &GlobalTransform, Option<&Collider>)>,
+    zones: Query<(Entity, &TriggerZone, &GlobalTransform)>,
+) {
+    for (sensor, mut sensor_state, transform, collider) in &mut sensors {
+        let position = transform.translation().truncate();
+        let sensor_rect = match collider {
+            Some(collider) => collider.rect_at(position),
+            None => Rect::from_center_size(position, Vec2::ZERO),
+        };
+        let now_inside: HashSet<Entity> = spatial_hash.query_rect(sensor_rect).into_iter()
+            .filter_map(|entity| zones.get(entity).ok())
+            .filter(|(_, zone, zone_transform)| {
+                let zone_rect = zone.rect_at(zone_transform.translation().truncate());
+                if collider.is_some() {
+                    overlaps(sensor_rect, zone_rect)
+                } else {
+                    zone_rect.contains(position)
+                }
+            })
+            .map(|(zone, _, _)| zone)
+            .collect();
+        if now_inside == sensor_state.inside {
+            continue;
+        }
+        for zone in now_inside.difference(&sensor_state.inside) {
+            enters.send(TriggerEnter { zone: *zone, sensor });
+        }
+        for zone in sensor_state.inside.difference(&now_inside) {
+            // Zones that were despawned don't send exits
+            if zones.contains(*zone) {
+                exits.send(TriggerExit { zone: *zone, sensor });
+            }
+        }
+        sensor_state.inside = now_inside;
+    }
+}
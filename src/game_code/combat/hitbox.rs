@@ -0,0 +1,110 @@
+// :: Hitboxes and hurtboxes ::
+// A Hurtbox is where something can be hit; a Hitbox is a short-lived box
+// that damages the hurtboxes it overlaps. Anything with Stats and a
+// Collider gets a Hurtbox the same size (unless it has one already).
+//
+// Hitboxes only hurt things on the other side from their owner: enemies'
+// hitboxes hurt everything that isn't an Enemy, and everyone else's hurt
+// enemies. Each hitbox hits each target once, and follows its owner
+// around, at `offset` from it:
+//
+//     commands.spawn(Hitbox::new(player, stats.attack(), Vec2::splat(16.0), 0.1)
+//         .with_offset(direction.to_vec2() * 12.0));
+use bevy::{math::Rect, prelude::*};
+
+use super::{DamageEvent, Stats};
+use crate::{
+    collision::{overlaps, Collider},
+    enemy::Enemy,
+};
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Hurtbox {
+    pub size: Vec2,
+    pub offset: Vec2, // from the entity's position to the box's center
+}
+impl Hurtbox {
+    pub fn new(size: Vec2) -> Self {
+        Self { size, offset: Vec2::ZERO }
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn rect_at(&self, position: Vec2) -> Rect {
+        Rect::from_center_size(position + self.offset, self.size)
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Hitbox {
+    pub owner: Entity,
+    pub damage: i32,
+    pub size: Vec2,
+    pub offset: Vec2, // from the owner's position to the box's center
+    pub lifetime: Timer,
+    hit: Vec<Entity>, // what it's already hurt
+}
+impl Hitbox {
+    pub fn new(owner: Entity, damage: i32, size: Vec2, seconds: f32) -> Self {
+        Self {
+            owner,
+            damage,
+            size,
+            offset: Vec2::ZERO,
+            lifetime: Timer::from_seconds(seconds, TimerMode::Once),
+            hit: Vec::new(),
+        }
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+// Whether something on one side can hurt something on the other
+pub fn are_hostile(attacker_is_enemy: bool, target_is_enemy: bool) -> bool {
+    attacker_is_enemy != target_is_enemy
+}
+
+pub(super) fn add_hurtboxes(
+    mut commands: Commands,
+    query: Query<(Entity, &Collider), (With<Stats>, Without<Hurtbox>)>,
+) {
+    for (entity, collider) in &query {
+        commands.entity(entity).insert(Hurtbox::new(collider.size).with_offset(collider.offset));
+    }
+}
+
+pub(super) fn update_hitboxes(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut damage: EventWriter<DamageEvent>,
+    mut hitboxes: Query<(Entity, &mut Hitbox)>,
+    owners: Query<&GlobalTransform>,
+    hurtboxes: Query<(Entity, &Hurtbox, &GlobalTransform), With<Stats>>,
+    enemies: Query<(), With<Enemy>>,
+) {
+    for (entity, mut hitbox) in &mut hitboxes {
+        hitbox.lifetime.tick(time.delta());
+        let owner_position = match owners.get(hitbox.owner) {
+            Ok(transform) if !hitbox.lifetime.finished() => transform.translation().truncate(),
+            _ => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            },
+        };
+        let rect = Rect::from_center_size(owner_position + hitbox.offset, hitbox.size);
+        let owner_is_enemy = enemies.contains(hitbox.owner);
+        for (target, hurtbox, transform) in &hurtboxes {
+            if target == hitbox.owner
+                || hitbox.hit.contains(&target)
+                || !are_hostile(owner_is_enemy, enemies.contains(target))
+                || !overlaps(rect, hurtbox.rect_at(transform.translation().truncate())) {
+                continue;
+            }
+            hitbox.hit.push(target);
+            damage.send(DamageEvent::new(target, hitbox.damage).with_source(hitbox.owner));
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// :: Invincibility frames ::
+// A moment after being hurt when nothing else can hurt you, so one enemy
+// touching the player can't drain all their hp at once. Things with a
+// HitInvulnerability become Invulnerable for that many seconds whenever
+// they take damage, and damage to them is ignored until it wears off:
+//
+//     commands.spawn((Player, Stats::new(10, 1, 0), HitInvulnerability(1.0), ..));
+//
+// Anything can also be made Invulnerable directly, e.g. during a cutscene.
+// With `DamageAnimationPlugin::<State>::default()`, animated things flash
+// when they're hurt, and blink while they're invulnerable (using their
+// animator's flash and tint, so other tints still show).
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use super::{DamageSystem, DamageTaken};
+use crate::animation::{AnimState, AnimationSystem, SpritesheetAnimator};
+
+const HIT_FLASH_COLOR: Color = Color::rgb(1.0, 0.3, 0.3);
+const HIT_FLASH_FRAMES: u32 = 4;
+const BLINK_PERIOD: f32 = 0.15; // seconds for each blink, on and off
+const BLINK_ALPHA: f32 = 0.25;
+
+// How long to be invulnerable for after taking damage, in seconds
+#[derive(Component, Clone, Copy, Debug)]
+pub struct HitInvulnerability(pub f32);
+
+#[derive(Component, Deref, DerefMut)]
+pub struct Invulnerable(pub Timer);
+impl Invulnerable {
+    pub fn new(seconds: f32) -> Self {
+        Self(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+}
+
+pub struct DamageAnimationPlugin<S: AnimState>(PhantomData<S>);
+impl<S: AnimState> Default for DamageAnimationPlugin<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: AnimState> Plugin for DamageAnimationPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.add_system(flash_on_hit::<S>.after(DamageSystem).before(AnimationSystem))
+            .add_system(blink_invulnerable::<S>.after(wear_off_invulnerability).before(AnimationSystem))
+            // Once the Invulnerable has actually been removed
+            .add_system_to_stage(CoreStage::PostUpdate, stop_blinking::<S>);
+    }
+}
+
+pub(super) fn wear_off_invulnerability(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Invulnerable)>,
+) {
+    for (entity, mut invulnerable) in &mut query {
+        if invulnerable.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+fn flash_on_hit<S: AnimState>(
+    mut taken: EventReader<DamageTaken>,
+    mut animators: Query<&mut SpritesheetAnimator<S>>,
+) {
+    for event in taken.iter() {
+        if let Ok(mut animator) = animators.get_mut(event.target) {
+            animator.flash(HIT_FLASH_COLOR, HIT_FLASH_FRAMES);
+        }
+    }
+}
+
+fn blink_invulnerable<S: AnimState>(mut blinking: Query<(&Invulnerable, &mut SpritesheetAnimator<S>)>) {
+    for (invulnerable, mut animator) in &mut blinking {
+        let faded = (invulnerable.elapsed_secs() / BLINK_PERIOD) as u32 % 2 == 0;
+        let alpha = if faded { BLINK_ALPHA } else { 1.0 };
+        if animator.tint.a() != alpha {
+            animator.tint.set_a(alpha);
+        }
+    }
+}
+
+// Back to fully visible once it wears off
+fn stop_blinking<S: AnimState>(
+    removed: RemovedComponents<Invulnerable>,
+    mut animators: Query<&mut SpritesheetAnimator<S>, Without<Invulnerable>>,
+) {
+    for entity in removed.iter() {
+        if let Ok(mut animator) = animators.get_mut(entity) {
+            animator.tint.set_a(1.0);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// :: Melee attacks ::
+// Swinging a weapon (or a stick). While something with a MeleeAttack is
+// attacking, its animation code should play its "attack-*" states (see
+// `is_attacking`), and every frame of them tagged "hit" spawns a Hitbox
+// (see hitbox.rs) in front of it, in the Direction it's facing:
+//
+//     commands.spawn((Player, Stats::new(10, 1, 0), MeleeAttack::new(Vec2::splat(16.0), 10.0), ..));
+//
+//     // in its .anim.ron file, played Once and not interrupted by walking
+//     "attack-down": (frames: [16, 17, 18], looping: Some(Once), priority: 1, frame_tags: {1: ["hit"]}),
+//
+// Players attack when they press Attack (buffered, see input/buffer.rs),
+// and stand still as PlayerState::Attacking until it's over; anything
+// else attacks with `start`. An attack is over when its animation
+// finishes, or after `max_duration` seconds for characters without one.
+// Add `MeleeAnimationPlugin::<State>::default()` for each animation state
+// type that attacks are played with.
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use super::{hitbox::Hitbox, Stats};
+use crate::{
+    animation::{AnimState, AnimationFinished, AnimationFrameEvent, AnimationSystem},
+    direction::Direction,
+    input::{Action, InputBuffer},
+    player::{Player, PlayerControlLock, PlayerState},
+};
+
+const HIT_TAG: &str = "hit";
+const DEFAULT_HITBOX_TIME: f32 = 0.1; // in seconds
+const DEFAULT_MAX_DURATION: f32 = 0.5; // in seconds
+
+#[derive(Component, Clone, Debug)]
+pub struct MeleeAttack {
+    pub size: Vec2, // of the hitbox
+    pub reach: f32, // how far in front of the attacker the hitbox's center is
+    pub hitbox_time: f32, // how long each hitbox lasts, in seconds
+    pub max_duration: f32, // the longest an attack can last, in seconds
+    attacking: Option<f32>, // seconds since the attack started
+}
+impl MeleeAttack {
+    pub fn new(size: Vec2, reach: f32) -> Self {
+        Self {
+            size,
+            reach,
+            hitbox_time: DEFAULT_HITBOX_TIME,
+            max_duration: DEFAULT_MAX_DURATION,
+            attacking: None,
+        }
+    }
+    pub fn with_hitbox_time(mut self, seconds: f32) -> Self {
+        self.hitbox_time = seconds;
+        self
+    }
+    pub fn with_max_duration(mut self, seconds: f32) -> Self {
+        self.max_duration = seconds;
+        self
+    }
+    // Start an attack, unless one's already going. Returns whether it started.
+    pub fn start(&mut self) -> bool {
+        if self.attacking.is_some() {
+            return false;
+        }
+        self.attacking = Some(0.0);
+        true
+    }
+    pub fn stop(&mut self) {
+        self.attacking = None;
+    }
+    pub fn is_attacking(&self) -> bool {
+        self.attacking.is_some()
+    }
+}
+
+pub struct MeleeAnimationPlugin<S: AnimState>(PhantomData<S>);
+impl<S: AnimState> Default for MeleeAnimationPlugin<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: AnimState> Plugin for MeleeAnimationPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.add_system(finish_melee_attacks::<S>.after(AnimationSystem));
+    }
+}
+
+pub(super) fn start_player_attacks(
+    control_lock: Res<PlayerControlLock>,
+    mut input_buffer: ResMut<InputBuffer>,
+    mut players: Query<(&mut MeleeAttack, &PlayerState), With<Player>>,
+) {
+    if control_lock.is_locked() {
+        return;
+    }
+    for (mut melee, state) in &mut players {
+        if state.can_move() && !melee.is_attacking() && input_buffer.consume(Action::Attack) {
+            melee.start();
+        }
+    }
+}
+
+// Players stand still while they attack
+pub(super) fn update_attacking_state(mut players: Query<(&MeleeAttack, &mut PlayerState), Changed<MeleeAttack>>) {
+    for (melee, mut state) in &mut players {
+        if melee.is_attacking() && state.can_move() {
+            *state = PlayerState::Attacking;
+        } else if !melee.is_attacking() && *state == PlayerState::Attacking {
+            *state = PlayerState::Idle;
+        }
+    }
+}
+
+pub(super) fn time_out_melee_attacks(time: Res<Time>, mut attackers: Query<&mut MeleeAttack>) {
+    for mut melee in &mut attackers {
+        if let Some(elapsed) = melee.attacking {
+            let elapsed = elapsed + time.delta_seconds();
+            if elapsed >= melee.max_duration {
+                melee.stop();
+            } else {
+                // Without marking it changed every frame
+                melee.bypass_change_detection().attacking = Some(elapsed);
+            }
+        }
+    }
+}
+
+fn finish_melee_attacks<S: AnimState>(
+    mut finished: EventReader<AnimationFinished<S>>,
+    mut attackers: Query<&mut MeleeAttack>,
+) {
+    for event in finished.iter() {
+        if let Ok(mut melee) = attackers.get_mut(event.entity) {
+            if melee.is_attacking() {
+                melee.stop();
+            }
+        }
+    }
+}
+
+pub(super) fn spawn_melee_hitboxes(
+    mut commands: Commands,
+    mut frame_events: EventReader<AnimationFrameEvent>,
+    attackers: Query<(&MeleeAttack, &Direction, Option<&Stats>)>,
+) {
+    for event in frame_events.iter().filter(|event| event.tag == HIT_TAG) {
+        let (melee, direction, stats) = match attackers.get(event.entity) {
+            Ok(attacker) if attacker.0.is_attacking() => attacker,
+            _ => continue,
+        };
+        commands.spawn(
+            Hitbox::new(event.entity, stats.map_or(1, Stats::attack), melee.size, melee.hitbox_time)
+                .with_offset(direction.to_vec2() * melee.reach),
+        );
+    }
+}
@@ -0,0 +1,300 @@
+// :: Combat ::
+// Health and fighting, shared by the player and enemies. Anything that can
+// be hurt has Stats; anything that hurts it sends a DamageEvent, which goes
+// through the same steps whatever it came from (a sword, an arrow, a trap):
+//
+//   1. mitigation: the target's defense is taken off (but a hit always does
+//      at least 1 damage), and hits on things without Stats, or that are
+//      already dead or Invulnerable (unless it's damage over time), are
+//      dropped
+//   2. applying: what's left comes off the target's hp, and a DamageTaken
+//      event is sent
+//   3. death: a target whose hp reaches 0 sends a Died event, once
+//
+// Healing goes the same way, with a HealEvent, and a Healed event for
+// however much hp was actually restored.
+//
+//     damage.send(DamageEvent::new(enemy, attacker_stats.attack()).with_source(player));
+//     ...
+//     for died in died.iter() { .. }
+//
+// Systems sending DamageEvents should run `.before(DamageSystem)`, and ones
+// reading DamageTaken or Died `.after(DamageSystem)`. Equipment (see
+// inventory/equipment.rs) adds its bonuses to its wearer's Stats.
+//
+// Being hurt can make things Invulnerable for a moment (see
+// invulnerable.rs). Things are hit by Hitboxes overlapping their Hurtboxes (see hitbox.rs),
+// e.g. from a MeleeAttack (see melee.rs), and by Projectiles (see
+// projectile.rs). Hits can also poison, slow or stun (see status.rs).
+// Damage and healing pop up as numbers over whoever took it (see
+// numbers.rs).
+use bevy::prelude::*;
+
+use crate::{
+    animation::AnimationSystem,
+    input::InputSystem,
+    inventory::{Equipment, ItemRegistry, StatBonuses},
+};
+
+mod hitbox;
+mod invulnerable;
+mod melee;
+mod numbers;
+mod projectile;
+mod status;
+
+pub use hitbox::{are_hostile, Hitbox, Hurtbox};
+pub use invulnerable::{DamageAnimationPlugin, HitInvulnerability, Invulnerable};
+pub use melee::{MeleeAnimationPlugin, MeleeAttack};
+pub use projectile::{Projectile, ProjectileHit};
+pub use status::{
+    ApplyStatus, StatusAnimationPlugin, StatusEffect, StatusEffects, StatusIcons, StatusKind, StunAnimation,
+    MAX_POISON_STACKS, POISON_TICK,
+};
+
+const MIN_DAMAGE: i32 = 1; // what a hit does, however well defended the target is
+
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub struct Stats {
+    pub hp: i32,
+    pub max_hp: i32, // without equipment; see `max_hp()`
+    pub attack: i32,
+    pub defense: i32,
+    bonuses: StatBonuses, // from equipment
+}
+impl Stats {
+    // Stats at full health
+    pub fn new(max_hp: i32, attack: i32, defense: i32) -> Self {
+        Self { hp: max_hp, max_hp, attack, defense, bonuses: StatBonuses::default() }
+    }
+    // Each stat with its equipment bonuses added
+    pub fn max_hp(&self) -> i32 {
+        (self.max_hp + self.bonuses.max_hp).max(1)
+    }
+    pub fn attack(&self) -> i32 {
+        (self.attack + self.bonuses.attack).max(0)
+    }
+    pub fn defense(&self) -> i32 {
+        (self.defense + self.bonuses.defense).max(0)
+    }
+    pub fn bonuses(&self) -> StatBonuses {
+        self.bonuses
+    }
+    // Replace the equipment bonuses, keeping hp within the new max_hp
+    pub fn set_bonuses(&mut self, bonuses: StatBonuses) {
+        self.bonuses = bonuses;
+        self.hp = self.hp.min(self.max_hp());
+    }
+    pub fn is_dead(&self) -> bool {
+        self.hp <= 0
+    }
+    // Restore some hp, up to max_hp. The dead can't be healed.
+    pub fn heal(&mut self, amount: i32) {
+        if !self.is_dead() {
+            self.hp = (self.hp + amount.max(0)).min(self.max_hp());
+        }
+    }
+    // How much of `amount` gets through this one's defense
+    pub fn mitigate(&self, amount: i32) -> i32 {
+        if amount <= 0 {
+            return 0;
+        }
+        (amount - self.defense()).max(MIN_DAMAGE)
+    }
+}
+
+// A request to hurt something, before its defense is taken into account
+#[derive(Clone, Copy, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: i32,
+    pub source: Option<Entity>, // who or what did it, if anyone
+    pub over_time: bool, // e.g. poison; see `over_time`
+}
+impl DamageEvent {
+    pub fn new(target: Entity, amount: i32) -> Self {
+        Self { target, amount, source: None, over_time: false }
+    }
+    pub fn with_source(mut self, source: Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
+    // Damage that keeps ticking away: it isn't stopped by Invulnerable,
+    // and doesn't make its target invulnerable either
+    pub fn over_time(mut self) -> Self {
+        self.over_time = true;
+        self
+    }
+}
+
+// A request to restore some of something's hp
+#[derive(Clone, Copy, Debug)]
+pub struct HealEvent {
+    pub target: Entity,
+    pub amount: i32,
+}
+
+// Sent when hp has been restored
+#[derive(Clone, Copy, Debug)]
+pub struct Healed {
+    pub target: Entity,
+    pub amount: i32, // how much was actually restored
+    pub hp: i32,
+}
+
+// A DamageEvent that made it through mitigation, about to be applied
+#[derive(Clone, Copy, Debug)]
+pub struct MitigatedDamage {
+    pub target: Entity,
+    pub amount: i32,
+    pub source: Option<Entity>,
+    pub over_time: bool,
+}
+
+// Sent when damage has been taken off something's hp
+#[derive(Clone, Copy, Debug)]
+pub struct DamageTaken {
+    pub target: Entity,
+    pub amount: i32,
+    pub source: Option<Entity>,
+    pub over_time: bool,
+    pub hp: i32, // what's left
+}
+
+// Sent when something's hp reaches 0
+#[derive(Clone, Copy, Debug)]
+pub struct Died {
+    pub entity: Entity,
+    pub killer: Option<Entity>,
+}
+
+// The damage pipeline; see the top of this file
+#[derive(SystemLabel)]
+pub struct DamageSystem;
+
+pub struct CombatPlugin;
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<MitigatedDamage>()
+            .add_event::<DamageTaken>()
+            .add_event::<Died>()
+            .add_event::<HealEvent>()
+            .add_event::<Healed>()
+            .add_event::<ProjectileHit>()
+            .add_event::<ApplyStatus>()
+            .init_resource::<StatusIcons>()
+            .add_system(apply_equipment_bonuses)
+            .add_system(invulnerable::wear_off_invulnerability.before(DamageSystem))
+            .add_system(hitbox::add_hurtboxes)
+            .add_system(hitbox::update_hitboxes.before(DamageSystem))
+            .add_system(melee::start_player_attacks.after(InputSystem))
+            .add_system(melee::time_out_melee_attacks.after(melee::start_player_attacks))
+            .add_system(melee::update_attacking_state.after(melee::time_out_melee_attacks))
+            .add_system(melee::spawn_melee_hitboxes.after(AnimationSystem))
+            .add_system(projectile::add_projectiles)
+            .add_system(projectile::update_projectiles
+                .after(projectile::add_projectiles)
+                .before(DamageSystem))
+            .add_system(status::add_status_effects)
+            .add_system(status::apply_status_events.before(status::tick_status_effects))
+            .add_system(status::tick_status_effects.before(DamageSystem))
+            .add_system(status::apply_status_movement.after(DamageSystem))
+            .add_system(status::update_status_icons.after(DamageSystem))
+            .add_system(mitigate_damage.label(DamageSystem))
+            .add_system(apply_damage.label(DamageSystem).after(mitigate_damage))
+            .add_system(apply_healing.label(DamageSystem).after(apply_damage))
+            .add_system(numbers::spawn_damage_numbers.after(DamageSystem))
+            .add_system(numbers::float_damage_numbers);
+    }
+}
+
+fn mitigate_damage(
+    mut damage: EventReader<DamageEvent>,
+    mut mitigated: EventWriter<MitigatedDamage>,
+    targets: Query<(&Stats, Option<&Invulnerable>)>,
+) {
+    for event in damage.iter() {
+        let stats = match targets.get(event.target) {
+            Ok((stats, invulnerable)) if !stats.is_dead() && (invulnerable.is_none() || event.over_time) => stats,
+            _ => continue,
+        };
+        let amount = stats.mitigate(event.amount);
+        if amount > 0 {
+            mitigated.send(MitigatedDamage {
+                target: event.target,
+                amount,
+                source: event.source,
+                over_time: event.over_time,
+            });
+        }
+    }
+}
+
+fn apply_damage(
+    mut commands: Commands,
+    mut mitigated: EventReader<MitigatedDamage>,
+    mut taken: EventWriter<DamageTaken>,
+    mut died: EventWriter<Died>,
+    mut targets: Query<(&mut Stats, Option<&HitInvulnerability>)>,
+) {
+    let mut made_invulnerable: Vec<Entity> = Vec::new(); // this frame, before the component's added
+    for event in mitigated.iter() {
+        let (mut stats, hit_invulnerability) = match targets.get_mut(event.target) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+        if stats.is_dead() || (made_invulnerable.contains(&event.target) && !event.over_time) {
+            continue; // killed, or made invulnerable, by an earlier hit this frame
+        }
+        stats.hp = (stats.hp - event.amount).max(0);
+        taken.send(DamageTaken {
+            target: event.target,
+            amount: event.amount,
+            source: event.source,
+            over_time: event.over_time,
+            hp: stats.hp,
+        });
+        if stats.is_dead() {
+            died.send(Died { entity: event.target, killer: event.source });
+        } else if let Some(HitInvulnerability(seconds)) = hit_invulnerability.filter(|_| !event.over_time) {
+            commands.entity(event.target).insert(Invulnerable::new(*seconds));
+            made_invulnerable.push(event.target);
+        }
+    }
+}
+
+fn apply_healing(
+    mut heals: EventReader<HealEvent>,
+    mut healed: EventWriter<Healed>,
+    mut targets: Query<&mut Stats>,
+) {
+    for event in heals.iter() {
+        let mut stats = match targets.get_mut(event.target) {
+            Ok(stats) => stats,
+            Err(_) => continue,
+        };
+        let before = stats.hp;
+        stats.heal(event.amount);
+        if stats.hp > before {
+            healed.send(Healed { target: event.target, amount: stats.hp - before, hp: stats.hp });
+        }
+    }
+}
+
+// Keep the Stats of anything wearing Equipment up to date with it
+fn apply_equipment_bonuses(
+    items: Res<ItemRegistry>,
+    mut wearers: Query<(&Equipment, ChangeTrackers<Equipment>, &mut Stats)>,
+) {
+    for (equipment, equipment_tracker, mut stats) in &mut wearers {
+        // Everyone, if an item's definition might have changed
+        if !items.is_changed() && !equipment_tracker.is_changed() && !stats.is_added() {
+            continue;
+        }
+        let bonuses = equipment.bonuses(&items);
+        if stats.bonuses != bonuses {
+            stats.set_bonuses(bonuses);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+// :: Damage numbers ::
+// Numbers that pop up over things when they're hurt or healed, then float
+// up and fade away. They're spawned in the world (not on the UI), where
+// the thing was at the time, so they don't follow it around. Anything that
+// goes through the damage pipeline gets them: DamageTaken shows the damage
+// (green for damage over time, and red when it's the player being hurt),
+// and Healed shows "+N".
+use bevy::prelude::*;
+use rand::Rng;
+
+use super::{DamageTaken, Healed};
+use crate::{player::Player, ui::UI_FONT};
+
+const FONT_SIZE: f32 = 10.0;
+const START_HEIGHT: f32 = 16.0; // above the entity's position, in pixels
+const JITTER: f32 = 4.0; // so numbers landing together don't cover each other
+const RISE_SPEED: f32 = 20.0; // in pixels per second
+const LIFETIME: f32 = 0.8; // in seconds
+const FADE_TIME: f32 = 0.3; // at the end of the lifetime
+const NUMBER_Z: f32 = 990.0; // over everything y-sorted
+const DAMAGE_COLOR: Color = Color::WHITE;
+const PLAYER_DAMAGE_COLOR: Color = Color::rgb(1.0, 0.35, 0.35);
+const OVER_TIME_COLOR: Color = Color::rgb(0.55, 0.9, 0.4);
+const HEAL_COLOR: Color = Color::rgb(0.4, 1.0, 0.6);
+
+#[derive(Component)]
+pub(super) struct FloatingNumber {
+    age: f32, // in seconds
+}
+
+pub(super) fn spawn_damage_numbers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut taken: EventReader<DamageTaken>,
+    mut healed: EventReader<Healed>,
+    targets: Query<&GlobalTransform>,
+    players: Query<(), With<Player>>,
+) {
+    let damage = taken.iter().map(|event| {
+        let color = if event.over_time {
+            OVER_TIME_COLOR
+        } else if players.contains(event.target) {
+            PLAYER_DAMAGE_COLOR
+        } else {
+            DAMAGE_COLOR
+        };
+        (event.target, event.amount.to_string(), color)
+    });
+    let heals = healed.iter().map(|event| (event.target, format!("+{}", event.amount), HEAL_COLOR));
+    let mut rng = rand::thread_rng();
+    for (target, label, color) in damage.chain(heals) {
+        let position = match targets.get(target) {
+            Ok(transform) => transform.translation().truncate(),
+            Err(_) => continue,
+        };
+        let offset = Vec2::new(rng.gen_range(-JITTER..=JITTER), START_HEIGHT);
+        commands.spawn((
+            FloatingNumber { age: 0.0 },
+            Text2dBundle {
+                text: Text::from_section(label, TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: FONT_SIZE,
+                    color,
+                }).with_alignment(TextAlignment::BOTTOM_CENTER),
+                transform: Transform::from_translation((position + offset).extend(NUMBER_Z)),
+                ..default()
+            },
+        ));
+    }
+}
+
+pub(super) fn float_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut numbers: Query<(Entity, &mut FloatingNumber, &mut Transform, &mut Text)>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut number, mut transform, mut text) in &mut numbers {
+        number.age += dt;
+        if number.age >= LIFETIME {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.translation.y += RISE_SPEED * dt;
+        let alpha = ((LIFETIME - number.age) / FADE_TIME).min(1.0);
+        for section in &mut text.sections {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
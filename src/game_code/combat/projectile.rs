@@ -0,0 +1,133 @@
+// :: Projectiles ::
+// Things that fly in a straight line until they hit something: arrows,
+// slingshot stones, an enemy's fireballs. A projectile moves by its
+// `velocity` every frame, hurts the first hostile Hurtbox it touches (on
+// the same sides as Hitboxes; see hitbox.rs), stops at solid tiles in a
+// CollisionGrid, and is despawned when it hits something or its
+// lifetime runs out. How it looks is up to whatever it's spawned with:
+//
+//     commands.spawn((
+//         Projectile::new(player, stats.attack(), direction.to_vec2() * 160.0)
+//             .with_size(Vec2::splat(4.0))
+//             .with_lifetime(1.5),
+//         SpriteSheetBundle { texture_atlas: stone_atlas, transform: Transform::from_translation(start), .. },
+//     ));
+//
+// It can be animated like anything else, e.g. with a SpritesheetAnimator.
+// Every hit sends a ProjectileHit event (with no target for walls), e.g.
+// for a puff of dust or a sound.
+use bevy::{math::Rect, prelude::*};
+
+use super::{hitbox::Hurtbox, are_hostile, DamageEvent, Stats};
+use crate::{
+    collision::{overlaps, CollisionGrid, TileShape},
+    enemy::Enemy,
+};
+
+const DEFAULT_SIZE: Vec2 = Vec2::splat(4.0);
+const DEFAULT_LIFETIME: f32 = 2.0; // in seconds
+
+#[derive(Component, Clone, Debug)]
+pub struct Projectile {
+    pub owner: Entity,
+    pub damage: i32,
+    pub velocity: Vec2, // in pixels per second
+    pub size: Vec2, // of the box it hits things with
+    pub lifetime: Timer,
+    from_enemy: Option<bool>, // which side it's on, from its owner when it was spawned
+}
+impl Projectile {
+    pub fn new(owner: Entity, damage: i32, velocity: Vec2) -> Self {
+        Self {
+            owner,
+            damage,
+            velocity,
+            size: DEFAULT_SIZE,
+            lifetime: Timer::from_seconds(DEFAULT_LIFETIME, TimerMode::Once),
+            from_enemy: None,
+        }
+    }
+    pub fn with_size(mut self, size: Vec2) -> Self {
+        self.size = size;
+        self
+    }
+    pub fn with_lifetime(mut self, seconds: f32) -> Self {
+        self.lifetime = Timer::from_seconds(seconds, TimerMode::Once);
+        self
+    }
+}
+
+pub struct ProjectileHit {
+    pub projectile: Entity,
+    pub owner: Entity,
+    pub target: Option<Entity>, // None for a wall
+    pub position: Vec2,
+}
+
+// Projectiles keep their side even if their owner is gone by the time they land
+pub(super) fn add_projectiles(
+    mut projectiles: Query<&mut Projectile, Added<Projectile>>,
+    enemies: Query<(), With<Enemy>>,
+) {
+    for mut projectile in &mut projectiles {
+        projectile.from_enemy = Some(enemies.contains(projectile.owner));
+    }
+}
+
+pub(super) fn update_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut damage: EventWriter<DamageEvent>,
+    mut hits: EventWriter<ProjectileHit>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform)>,
+    hurtboxes: Query<(Entity, &Hurtbox, &GlobalTransform), With<Stats>>,
+    grids: Query<(&CollisionGrid, &GlobalTransform)>,
+    enemies: Query<(), With<Enemy>>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut projectile, mut transform) in &mut projectiles {
+        projectile.lifetime.tick(time.delta());
+        if projectile.lifetime.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let step = projectile.velocity * dt;
+        transform.translation += step.extend(0.0);
+        let position = transform.translation.truncate();
+        let rect = Rect::from_center_size(position, projectile.size);
+
+        let from_enemy = projectile.from_enemy.unwrap_or_else(|| enemies.contains(projectile.owner));
+        let target = hurtboxes.iter()
+            .filter(|(target, _, _)| *target != projectile.owner)
+            .filter(|(target, _, _)| are_hostile(from_enemy, enemies.contains(*target)))
+            .find(|(_, hurtbox, target_transform)| {
+                overlaps(rect, hurtbox.rect_at(target_transform.translation().truncate()))
+            })
+            .map(|(target, _, _)| target);
+        let hit_wall = target.is_none() && grids.iter().any(|(grid, grid_transform)| hits_tiles(grid, grid_transform, rect));
+        if target.is_none() && !hit_wall {
+            continue;
+        }
+
+        if let Some(target) = target {
+            damage.send(DamageEvent::new(target, projectile.damage).with_source(projectile.owner));
+        }
+        hits.send(ProjectileHit { projectile: entity, owner: projectile.owner, target, position });
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Whether a box (in world space) touches a solid tile. One-way edges let
+// projectiles through, and slopes stop them like walls.
+fn hits_tiles(grid: &CollisionGrid, grid_transform: &GlobalTransform, rect: Rect) -> bool {
+    let corner = grid_transform.translation().truncate();
+    let local = Rect::from_corners(rect.min - corner, rect.max - corner);
+    grid.shapes_in(local).any(|(shape, tile)| {
+        let solid = match shape {
+            TileShape::OneWay(_) => None,
+            TileShape::Slope(_) => Some(tile),
+            _ => shape.solid_rect(tile),
+        };
+        solid.map_or(false, |solid| overlaps(local, solid))
+    })
+}
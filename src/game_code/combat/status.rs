@@ -0,0 +1,348 @@
+// :: Status effects ::
+// Things that last a while after a hit: poison, slows and stuns. Anything
+// with Stats gets StatusEffects, and effects are put on it with an
+// ApplyStatus event (or with `apply`, given the StatusEffects itself):
+//
+//     status.send(ApplyStatus::new(target, StatusEffect::poison(1, 5.0)).with_source(attacker));
+//     status.send(ApplyStatus::new(target, StatusEffect::slow(0.5, 3.0)));
+//     status.send(ApplyStatus::new(target, StatusEffect::stun(1.0)));
+//
+// Applying an effect that's already there follows its kind's rules:
+//
+//   - Poison stacks, up to MAX_POISON_STACKS; it does its damage once per
+//     stack every POISON_TICK seconds
+//   - Slow doesn't stack; the strongest slow is kept
+//   - Stun doesn't stack
+//
+// and either way the effect lasts for whichever is longer, what it had
+// left or the new duration. Poison damage goes through the usual damage
+// pipeline, as damage over time (see DamageEvent::over_time). Slowed and
+// stunned things get a SpeedModifiers multiplier; stunned things can't
+// attack, and a stunned player can't do anything.
+//
+// With `StatusAnimationPlugin::<State>::default()`, animated things with
+// a StunAnimation play it while they're stunned. Affected things show a
+// small icon over their heads for each effect, drawn from StatusIcons (or
+// a plain colored square for kinds without one).
+use std::marker::PhantomData;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{melee::MeleeAttack, DamageEvent, Stats};
+use crate::{
+    animation::{AnimState, AnimationSystem, SpritesheetAnimator},
+    movement::SpeedModifiers,
+    player::{Player, PlayerControlLock},
+};
+
+pub const MAX_POISON_STACKS: u32 = 3;
+pub const POISON_TICK: f32 = 1.0; // seconds between poison damage
+const SLOW_REASON: &str = "slow";
+const STUN_REASON: &str = "stunned";
+const ICON_SIZE: f32 = 6.0;
+const ICON_HEIGHT: f32 = 18.0; // above the entity's position
+const ICON_SPACING: f32 = 8.0;
+const ICON_Z: f32 = 5.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum StatusKind {
+    Poison,
+    Slow,
+    Stun,
+}
+impl StatusKind {
+    pub const ALL: [StatusKind; 3] = [StatusKind::Poison, StatusKind::Slow, StatusKind::Stun];
+
+    // The icon's color when there's no image for it
+    fn color(&self) -> Color {
+        match self {
+            StatusKind::Poison => Color::rgb(0.4, 0.85, 0.3),
+            StatusKind::Slow => Color::rgb(0.35, 0.6, 1.0),
+            StatusKind::Stun => Color::rgb(1.0, 0.85, 0.2),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub strength: f32, // damage per stack for poison, a speed multiplier for slow
+    pub duration: f32, // in seconds
+}
+impl StatusEffect {
+    pub fn poison(damage: i32, seconds: f32) -> Self {
+        Self { kind: StatusKind::Poison, strength: damage as f32, duration: seconds }
+    }
+    pub fn slow(multiplier: f32, seconds: f32) -> Self {
+        Self { kind: StatusKind::Slow, strength: multiplier, duration: seconds }
+    }
+    pub fn stun(seconds: f32) -> Self {
+        Self { kind: StatusKind::Stun, strength: 0.0, duration: seconds }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ActiveStatus {
+    effect: StatusEffect,
+    remaining: f32, // seconds
+    stacks: u32,
+    until_tick: f32, // seconds until poison next does damage
+    source: Option<Entity>,
+}
+
+#[derive(Component, Clone, Default, Debug)]
+pub struct StatusEffects {
+    active: Vec<ActiveStatus>,
+}
+impl StatusEffects {
+    // Put an effect on, following its kind's stacking rules
+    pub fn apply(&mut self, effect: StatusEffect, source: Option<Entity>) {
+        let existing = match self.active.iter_mut().find(|active| active.effect.kind == effect.kind) {
+            Some(existing) => existing,
+            None => {
+                self.active.push(ActiveStatus {
+                    effect,
+                    remaining: effect.duration,
+                    stacks: 1,
+                    until_tick: POISON_TICK,
+                    source,
+                });
+                return;
+            },
+        };
+        existing.remaining = existing.remaining.max(effect.duration);
+        match effect.kind {
+            StatusKind::Poison => {
+                existing.stacks = (existing.stacks + 1).min(MAX_POISON_STACKS);
+                existing.effect.strength = existing.effect.strength.max(effect.strength);
+            },
+            StatusKind::Slow => existing.effect.strength = existing.effect.strength.min(effect.strength),
+            StatusKind::Stun => {},
+        }
+        if source.is_some() {
+            existing.source = source;
+        }
+    }
+    pub fn remove(&mut self, kind: StatusKind) {
+        self.active.retain(|active| active.effect.kind != kind);
+    }
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.active.iter().any(|active| active.effect.kind == kind)
+    }
+    pub fn is_stunned(&self) -> bool {
+        self.has(StatusKind::Stun)
+    }
+    // How many times an effect is stacked, or 0 if it isn't there
+    pub fn stacks(&self, kind: StatusKind) -> u32 {
+        self.get(kind).map_or(0, |active| active.stacks)
+    }
+    // Seconds left on an effect
+    pub fn remaining(&self, kind: StatusKind) -> Option<f32> {
+        self.get(kind).map(|active| active.remaining)
+    }
+    // The speed multiplier from slows, if there are any
+    pub fn slow(&self) -> Option<f32> {
+        self.get(StatusKind::Slow).map(|active| active.effect.strength)
+    }
+    pub fn kinds(&self) -> impl Iterator<Item = StatusKind> + '_ {
+        self.active.iter().map(|active| active.effect.kind)
+    }
+    fn get(&self, kind: StatusKind) -> Option<&ActiveStatus> {
+        self.active.iter().find(|active| active.effect.kind == kind)
+    }
+}
+
+pub struct ApplyStatus {
+    pub target: Entity,
+    pub effect: StatusEffect,
+    pub source: Option<Entity>,
+}
+impl ApplyStatus {
+    pub fn new(target: Entity, effect: StatusEffect) -> Self {
+        Self { target, effect, source: None }
+    }
+    pub fn with_source(mut self, source: Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
+
+// Images for the icons over affected things' heads
+#[derive(Resource, Default)]
+pub struct StatusIcons {
+    pub icons: HashMap<StatusKind, Handle<Image>>,
+}
+impl StatusIcons {
+    pub fn with_icon(mut self, kind: StatusKind, image: Handle<Image>) -> Self {
+        self.icons.insert(kind, image);
+        self
+    }
+}
+
+#[derive(Component)]
+struct StatusIcon;
+
+// The state to play while this is stunned
+#[derive(Component, Clone, Debug)]
+pub struct StunAnimation<S: AnimState>(pub S);
+
+pub struct StatusAnimationPlugin<S: AnimState>(PhantomData<S>);
+impl<S: AnimState> Default for StatusAnimationPlugin<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: AnimState> Plugin for StatusAnimationPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.add_system(play_stun_animations::<S>.after(tick_status_effects).before(AnimationSystem));
+    }
+}
+
+pub(super) fn add_status_effects(
+    mut commands: Commands,
+    query: Query<Entity, (With<Stats>, Without<StatusEffects>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(StatusEffects::default());
+    }
+}
+
+pub(super) fn apply_status_events(
+    mut events: EventReader<ApplyStatus>,
+    mut targets: Query<(&mut StatusEffects, &Stats)>,
+) {
+    for event in events.iter() {
+        match targets.get_mut(event.target) {
+            Ok((mut effects, stats)) if !stats.is_dead() => effects.apply(event.effect, event.source),
+            _ => {},
+        }
+    }
+}
+
+// Count effects down, poison things, and take off what's worn off. Only
+// marks StatusEffects changed when an effect comes or goes.
+pub(super) fn tick_status_effects(
+    time: Res<Time>,
+    mut damage: EventWriter<DamageEvent>,
+    mut query: Query<(Entity, &mut StatusEffects, &Stats)>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut effects, stats) in &mut query {
+        if effects.active.is_empty() {
+            continue;
+        }
+        if stats.is_dead() {
+            effects.clear();
+            continue;
+        }
+        for active in &mut effects.bypass_change_detection().active {
+            active.remaining -= dt;
+            if active.effect.kind != StatusKind::Poison {
+                continue;
+            }
+            active.until_tick -= dt;
+            if active.until_tick <= 0.0 {
+                active.until_tick += POISON_TICK;
+                let amount = active.effect.strength.round() as i32 * active.stacks as i32;
+                let mut event = DamageEvent::new(entity, amount).over_time();
+                if let Some(source) = active.source {
+                    event = event.with_source(source);
+                }
+                damage.send(event);
+            }
+        }
+        if effects.active.iter().any(|active| active.remaining <= 0.0) {
+            effects.active.retain(|active| active.remaining > 0.0);
+        }
+    }
+}
+
+// Slow and stop things, and keep stunned things from attacking
+pub(super) fn apply_status_movement(
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut query: Query<
+        (&StatusEffects, Option<&mut SpeedModifiers>, Option<&mut MeleeAttack>, Option<&Player>),
+        Changed<StatusEffects>,
+    >,
+) {
+    for (effects, modifiers, melee, player) in &mut query {
+        if let Some(mut modifiers) = modifiers {
+            match effects.slow() {
+                Some(multiplier) => modifiers.set(SLOW_REASON, multiplier),
+                None => modifiers.remove(SLOW_REASON),
+            }
+            match effects.is_stunned() {
+                true => modifiers.set(STUN_REASON, 0.0),
+                false => modifiers.remove(STUN_REASON),
+            }
+        }
+        if let Some(mut melee) = melee {
+            if effects.is_stunned() && melee.is_attacking() {
+                melee.stop();
+            }
+        }
+        if player.is_some() {
+            match effects.is_stunned() {
+                true => control_lock.lock(STUN_REASON),
+                false => control_lock.unlock(STUN_REASON),
+            }
+        }
+    }
+}
+
+fn play_stun_animations<S: AnimState>(
+    mut query: Query<(Entity, &StatusEffects, &StunAnimation<S>, &mut SpritesheetAnimator<S>)>,
+) {
+    for (entity, effects, stun, mut animator) in &mut query {
+        let stunned_state = animator.cur_state == stun.0;
+        let result = if effects.is_stunned() && !stunned_state {
+            animator.force_state(stun.0.clone(), None)
+        } else if !effects.is_stunned() && stunned_state {
+            animator.force_state(S::default(), None)
+        } else {
+            continue;
+        };
+        if let Err(error) = result {
+            warn!("Couldn't play the stun animation of {:?}: {}", entity, error);
+        }
+    }
+}
+
+// Respawn the icons of anything whose effects changed
+pub(super) fn update_status_icons(
+    mut commands: Commands,
+    icons: Res<StatusIcons>,
+    affected: Query<(Entity, &StatusEffects, Option<&Children>), Changed<StatusEffects>>,
+    existing: Query<(), With<StatusIcon>>,
+) {
+    for (entity, effects, children) in &affected {
+        for child in children.iter().flat_map(|children| children.iter()) {
+            if existing.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        let kinds: Vec<StatusKind> = StatusKind::ALL.into_iter().filter(|kind| effects.has(*kind)).collect();
+        let left = -(kinds.len() as f32 - 1.0) * ICON_SPACING / 2.0;
+        commands.entity(entity).with_children(|parent| {
+            for (i, kind) in kinds.into_iter().enumerate() {
+                let image = icons.icons.get(&kind).cloned();
+                parent.spawn((
+                    StatusIcon,
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: if image.is_some() { Color::WHITE } else { kind.color() },
+                            custom_size: Some(Vec2::splat(ICON_SIZE)),
+                            ..default()
+                        },
+                        texture: image.unwrap_or_default(),
+                        transform: Transform::from_xyz(left + i as f32 * ICON_SPACING, ICON_HEIGHT, ICON_Z),
+                        ..default()
+                    },
+                ));
+            }
+        });
+    }
+}
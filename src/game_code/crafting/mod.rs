@@ -0,0 +1,220 @@
+// :: Crafting ::
+// Turning items into other items. Recipes are written in `.recipes.ron`
+// (or `.recipes.json`) books under `assets/recipes/`, each taking some
+// items (its inputs) and making one (its output):
+//
+//     (
+//         recipes: {
+//             "sling": (
+//                 inputs: [(item: "stick", count: 1), (item: "stone", count: 2)],
+//                 output: (item: "sling", count: 1),
+//             ),
+//             // Only at a Workbench with this station, e.g. Workbench::new("campfire")
+//             "baked_apple": (inputs: [(item: "apple", count: 1)], output: (item: "baked_apple", count: 1), station: Some("campfire")),
+//         },
+//     )
+//
+// Books are added to the RecipeRegistry, which can say what an inventory
+// has the makings of:
+//
+//     for recipe in recipes.craftable(&inventory, None) { .. }
+//     recipe.craft(&items, &mut inventory)?;
+//
+// Crafting happens on the crafting screen (see ui.rs), which a Workbench
+// opens when it's used. Each thing made sends an ItemCrafted event (and a
+// QuestEvent::Collected).
+use bevy::{
+    asset::{LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+use serde::Deserialize;
+
+use crate::{
+    asset_loader::RonOrJsonLoader,
+    input::InputSystem,
+    interaction::{InteractionEvent, InteractionSystem},
+    inventory::{Inventory, ItemRegistry, ItemStack},
+    quest::QuestSystem,
+};
+
+mod ui;
+
+pub use ui::CraftingScreen;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Recipe {
+    #[serde(skip)]
+    pub id: String, // its key in the book
+    pub inputs: Vec<ItemStack>,
+    pub output: ItemStack,
+    #[serde(default)]
+    pub station: Option<String>, // the kind of Workbench it needs, if any
+}
+impl Recipe {
+    // Whether the inventory has all of the inputs
+    pub fn has_inputs(&self, inventory: &Inventory) -> bool {
+        self.inputs.iter().all(|input| inventory.has(&input.item, input.count))
+    }
+    // Whether it can be made at a station (None for anywhere)
+    pub fn can_make_at(&self, station: Option<&str>) -> bool {
+        self.station.is_none() || self.station.as_deref() == station
+    }
+    // Use up the inputs and add the output, or leave the inventory as it
+    // was if there's something missing or no room for what's made
+    pub fn craft(&self, items: &ItemRegistry, inventory: &mut Inventory) -> Result<(), CraftError> {
+        if let Some(missing) = self.inputs.iter().find(|input| !inventory.has(&input.item, input.count)) {
+            return Err(CraftError::MissingInput(missing.item.clone()));
+        }
+        // On a copy, since using up the inputs might make room for the output
+        let mut crafted = inventory.clone();
+        for input in &self.inputs {
+            crafted.remove(&input.item, input.count);
+        }
+        if crafted.add(items, &self.output.item, self.output.count) > 0 {
+            return Err(CraftError::NoRoom(self.output.item.clone()));
+        }
+        *inventory = crafted;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum CraftError {
+    MissingInput(String), // the item id
+    NoRoom(String),
+}
+impl std::fmt::Display for CraftError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CraftError::MissingInput(item) => write!(f, "Not enough \"{}\"", item),
+            CraftError::NoRoom(item) => write!(f, "No room for \"{}\"", item),
+        }
+    }
+}
+impl std::error::Error for CraftError {}
+
+#[derive(Clone, Debug, Deserialize, TypeUuid)]
+#[uuid = "e3a5c7d1-2b4f-4e6a-8c0d-7f9b1a3e5c82"]
+pub struct RecipeBook {
+    pub recipes: HashMap<String, Recipe>,
+}
+
+// Each recipe's id is the key it's listed under
+fn name_recipes(mut book: RecipeBook, _: &mut LoadContext) -> LoadedAsset<RecipeBook> {
+    for (id, recipe) in book.recipes.iter_mut() {
+        recipe.id = id.clone();
+    }
+    LoadedAsset::new(book)
+}
+
+// Every recipe there is, from all the books added to it
+#[derive(Resource, Default)]
+pub struct RecipeRegistry {
+    books: Vec<Handle<RecipeBook>>,
+    recipes: Vec<Recipe>, // sorted by id, so lists of them don't shuffle about
+    dirty: bool, // a book was added since the recipes were last gathered
+}
+impl RecipeRegistry {
+    // Recipes in later books replace ones with the same id in earlier ones
+    pub fn add_book(&mut self, book: Handle<RecipeBook>) {
+        self.books.push(book);
+        self.dirty = true;
+    }
+    pub fn get(&self, id: &str) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.id == id)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Recipe> {
+        self.recipes.iter()
+    }
+    // The recipes that can be made at a station (None for anywhere)
+    pub fn available_at<'a>(&'a self, station: Option<&'a str>) -> impl Iterator<Item = &'a Recipe> {
+        self.recipes.iter().filter(move |recipe| recipe.can_make_at(station))
+    }
+    // The recipes an inventory has all the inputs for, at a station
+    pub fn craftable<'a>(&'a self, inventory: &'a Inventory, station: Option<&'a str>) -> impl Iterator<Item = &'a Recipe> {
+        self.available_at(station).filter(move |recipe| recipe.has_inputs(inventory))
+    }
+}
+
+// Rebuilt whenever a book is added (in case it's loaded already) and
+// whenever one of its books (re)loads
+fn update_recipe_registry(
+    mut registry: ResMut<RecipeRegistry>,
+    books: Res<Assets<RecipeBook>>,
+    mut book_events: EventReader<AssetEvent<RecipeBook>>,
+) {
+    let reloaded = book_events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => registry.books.contains(handle),
+        AssetEvent::Removed { .. } => false,
+    });
+    if !reloaded && !registry.dirty {
+        return;
+    }
+    registry.dirty = false;
+    let mut recipes: HashMap<String, Recipe> = HashMap::default();
+    for book in registry.books.iter().filter_map(|handle| books.get(handle)) {
+        recipes.extend(book.recipes.iter().map(|(id, recipe)| (id.clone(), recipe.clone())));
+    }
+    let mut recipes: Vec<Recipe> = recipes.into_values().collect();
+    recipes.sort_by(|a, b| a.id.cmp(&b.id));
+    registry.recipes = recipes;
+}
+
+// Something to craft at, e.g. a workbench or a campfire. Using it opens
+// the crafting screen, with the recipes for its station.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Workbench {
+    pub station: Option<String>,
+}
+impl Workbench {
+    pub fn new(station: &str) -> Self {
+        Self { station: Some(station.to_string()) }
+    }
+}
+
+pub struct ItemCrafted {
+    pub crafter: Entity,
+    pub recipe: String,
+    pub item: String,
+    pub count: u32,
+}
+
+pub struct CraftingPlugin;
+impl Plugin for CraftingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<RecipeBook>()
+            .add_asset_loader(RonOrJsonLoader::<RecipeBook>::new(&["recipes.ron", "recipes.json"])
+                .with_process(name_recipes))
+            .init_resource::<RecipeRegistry>()
+            .init_resource::<CraftingScreen>()
+            .add_event::<ItemCrafted>()
+            .add_startup_system(ui::spawn_crafting_screen)
+            .add_system(update_recipe_registry)
+            .add_system(open_workbenches.after(InteractionSystem))
+            // Before the screen opens, so the press that opens it isn't
+            // also taken as crafting something
+            .add_system(ui::navigate_crafting_screen
+                .after(InputSystem)
+                .before(QuestSystem))
+            .add_system(ui::show_crafting_screen
+                .after(ui::navigate_crafting_screen)
+                .after(open_workbenches))
+            .add_system(ui::update_crafting_screen
+                .after(ui::show_crafting_screen)
+                .after(update_recipe_registry));
+    }
+}
+
+fn open_workbenches(
+    mut interactions: EventReader<InteractionEvent>,
+    mut screen: ResMut<CraftingScreen>,
+    workbenches: Query<&Workbench>,
+) {
+    for event in interactions.iter() {
+        if let Ok(workbench) = workbenches.get(event.target) {
+            screen.open(event.player, workbench.station.clone());
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// :: Crafting screen ::
+// A list of the recipes that can be made at a workbench, with what each
+// one takes and how many of it the crafter has. Recipes they don't have
+// the makings of are greyed out. MoveUp and MoveDown pick a recipe,
+// Interact crafts it, and Menu closes the screen. The player can't move
+// while it's open.
+use bevy::prelude::*;
+
+use super::{CraftError, ItemCrafted, Recipe, RecipeRegistry};
+use crate::{
+    input::{Action, Actions},
+    inventory::{Inventory, ItemRegistry},
+    player::PlayerControlLock,
+    quest::QuestEvent,
+    ui::{
+        show_menu_screen, spawn_menu_screen, FONT_SIZE, MESSAGE_COLOR, TITLE_SIZE, UI_FONT,
+        UNSELECTED_COLOR,
+    },
+};
+
+const CONTROL_LOCK: &str = "crafting";
+const INPUT_COLOR: Color = Color::rgb(0.8, 0.8, 0.8);
+const MISSING_COLOR: Color = Color::rgb(0.9, 0.4, 0.4);
+const UNAVAILABLE_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+
+// The crafting being done, if any
+#[derive(Resource, Default)]
+pub struct CraftingScreen {
+    pending: Option<(Entity, Option<String>)>, // opened once the player has control
+    open: Option<OpenCrafting>,
+}
+struct OpenCrafting {
+    crafter: Entity,
+    station: Option<String>,
+    cursor: usize,
+    message: String, // what happened last, e.g. "Made Sling"
+}
+impl CraftingScreen {
+    // Show the recipes for a station (None for the ones that can be made
+    // anywhere) to `crafter`, as soon as nothing else has hold of the player
+    pub fn open(&mut self, crafter: Entity, station: Option<String>) {
+        self.pending = Some((crafter, station));
+    }
+    pub fn close(&mut self) {
+        self.pending = None;
+        self.open = None;
+    }
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+}
+
+#[derive(Component)]
+pub(super) struct CraftingRoot;
+
+#[derive(Component)]
+pub(super) struct CraftingText;
+
+pub(super) fn spawn_crafting_screen(mut commands: Commands) {
+    spawn_menu_screen(&mut commands, CraftingRoot, CraftingText);
+}
+
+// Open a pending screen once the player's free, and show or hide it
+pub(super) fn show_crafting_screen(
+    mut screen: ResMut<CraftingScreen>,
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut roots: Query<&mut Visibility, With<CraftingRoot>>,
+) {
+    if screen.open.is_none() && screen.pending.is_some() && !control_lock.is_locked() {
+        let (crafter, station) = screen.pending.take().unwrap();
+        screen.open = Some(OpenCrafting { crafter, station, cursor: 0, message: String::new() });
+    }
+    show_menu_screen(screen.is_open(), CONTROL_LOCK, &mut control_lock, &mut roots);
+}
+
+pub(super) fn navigate_crafting_screen(
+    actions: Res<Actions>,
+    items: Res<ItemRegistry>,
+    recipes: Res<RecipeRegistry>,
+    mut screen: ResMut<CraftingScreen>,
+    mut inventories: Query<&mut Inventory>,
+    mut crafted: EventWriter<ItemCrafted>,
+    mut quest_events: EventWriter<QuestEvent>,
+) {
+    let open = match screen.open.as_mut() {
+        Some(open) => open,
+        None => return,
+    };
+    if actions.just_pressed(Action::Menu) {
+        screen.close();
+        return;
+    }
+    let mut inventory = match inventories.get_mut(open.crafter) {
+        Ok(inventory) => inventory,
+        Err(_) => return,
+    };
+    let listed: Vec<&Recipe> = recipes.available_at(open.station.as_deref()).collect();
+    if listed.is_empty() {
+        return;
+    }
+    if actions.just_pressed(Action::MoveUp) {
+        open.cursor = (open.cursor + listed.len() - 1) % listed.len();
+    } else if actions.just_pressed(Action::MoveDown) {
+        open.cursor = (open.cursor + 1) % listed.len();
+    } else if actions.just_pressed(Action::Interact) {
+        let recipe = listed[open.cursor.min(listed.len() - 1)];
+        let name = items.name(&recipe.output.item).to_string();
+        open.message = match recipe.craft(&items, &mut inventory) {
+            Ok(()) => {
+                let (item, count) = (recipe.output.item.clone(), recipe.output.count);
+                crafted.send(ItemCrafted { crafter: open.crafter, recipe: recipe.id.clone(), item: item.clone(), count });
+                quest_events.send(QuestEvent::Collected { item, count });
+                format!("Made {}", name)
+            },
+            Err(CraftError::MissingInput(_)) => "Missing something".to_string(),
+            Err(CraftError::NoRoom(_)) => format!("No room for {}", name),
+        };
+    }
+}
+
+pub(super) fn update_crafting_screen(
+    asset_server: Res<AssetServer>,
+    items: Res<ItemRegistry>,
+    recipes: Res<RecipeRegistry>,
+    screen: Res<CraftingScreen>,
+    inventories: Query<(&Inventory, ChangeTrackers<Inventory>)>,
+    mut texts: Query<&mut Text, With<CraftingText>>,
+) {
+    let open = match &screen.open {
+        Some(open) => open,
+        None => return,
+    };
+    let (inventory, inventory_tracker) = match inventories.get(open.crafter) {
+        Ok(inventory) => inventory,
+        Err(_) => return,
+    };
+    if !screen.is_changed() && !inventory_tracker.is_changed() && !items.is_changed() && !recipes.is_changed() {
+        return;
+    }
+
+    let font = asset_server.load(UI_FONT);
+    let style = |size: f32, color: Color| TextStyle { font: font.clone(), font_size: size, color };
+    let mut sections = vec![TextSection::new("Crafting\n\n", style(TITLE_SIZE, Color::WHITE))];
+
+    let listed: Vec<&Recipe> = recipes.available_at(open.station.as_deref()).collect();
+    if listed.is_empty() {
+        sections.push(TextSection::new("  Nothing to make here\n", style(FONT_SIZE, UNSELECTED_COLOR)));
+    }
+    for (index, recipe) in listed.iter().enumerate() {
+        let selected = index == open.cursor.min(listed.len() - 1);
+        let arrow = if selected { "> " } else { "  " };
+        let color = match (recipe.has_inputs(inventory), selected) {
+            (false, _) => UNAVAILABLE_COLOR,
+            (true, true) => Color::WHITE,
+            (true, false) => UNSELECTED_COLOR,
+        };
+        let name = match recipe.output.count {
+            1 => items.name(&recipe.output.item).to_string(),
+            count => format!("{} x{}", items.name(&recipe.output.item), count),
+        };
+        sections.push(TextSection::new(format!("{}{}\n", arrow, name), style(FONT_SIZE, color)));
+        // What the selected recipe takes, and how many of each the crafter has
+        if selected {
+            for input in &recipe.inputs {
+                let have = inventory.count(&input.item);
+                let input_color = if have >= input.count { INPUT_COLOR } else { MISSING_COLOR };
+                sections.push(TextSection::new(
+                    format!("      {} x{} ({})\n", items.name(&input.item), input.count, have),
+                    style(FONT_SIZE, input_color),
+                ));
+            }
+        }
+    }
+
+    sections.push(TextSection::new(format!("\n{}\n", open.message), style(FONT_SIZE, MESSAGE_COLOR)));
+    sections.push(TextSection::new("Interact: craft   Menu: close", style(FONT_SIZE, UNSELECTED_COLOR)));
+    for mut text in &mut texts {
+        text.sections = sections.clone();
+    }
+}
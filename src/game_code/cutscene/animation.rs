@@ -0,0 +1,69 @@
+// :: Cutscene animations ::
+// Cutscene files name animation states as text, but each animator has its
+// own state type. The CutsceneAnimations registry tries a name against
+// every state type added with a CutsceneAnimationPlugin:
+//
+//     app.add_plugin(CutsceneAnimationPlugin::<PlayerAnim>::default())
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use crate::animation::{parse_state, AnimState, SpritesheetAnimator};
+
+// Plays animations of one state type
+trait StateAnimations: Send + Sync {
+    // Start playing `state` once. None if the entity has no animator of this
+    // type, or the type has no such state; Some(false) if the current
+    // animation can't be interrupted yet.
+    fn play(&self, world: &mut World, entity: Entity, state: &str) -> Option<bool>;
+    fn is_playing(&self, world: &World, entity: Entity, state: &str) -> bool;
+}
+
+struct TypedAnimations<S: AnimState>(PhantomData<S>);
+impl<S: AnimState> StateAnimations for TypedAnimations<S> {
+    fn play(&self, world: &mut World, entity: Entity, state: &str) -> Option<bool> {
+        let state = parse_state::<S>(state)?;
+        let mut animator = world.get_mut::<SpritesheetAnimator<S>>(entity)?;
+        let return_state = animator.cur_state.clone();
+        match animator.play_once_then(state, return_state) {
+            Ok(started) => Some(started),
+            Err(err) => {
+                warn!("{}", err);
+                None
+            },
+        }
+    }
+    fn is_playing(&self, world: &World, entity: Entity, state: &str) -> bool {
+        let (state, animator) = match (parse_state::<S>(state), world.get::<SpritesheetAnimator<S>>(entity)) {
+            (Some(state), Some(animator)) => (state, animator),
+            _ => return false,
+        };
+        animator.cur_state == state && !animator.finished
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct CutsceneAnimations {
+    types: Vec<Box<dyn StateAnimations>>,
+}
+impl CutsceneAnimations {
+    pub(super) fn play(&self, world: &mut World, entity: Entity, state: &str) -> Option<bool> {
+        self.types.iter().find_map(|types| types.play(world, entity, state))
+    }
+    pub(super) fn is_playing(&self, world: &World, entity: Entity, state: &str) -> bool {
+        self.types.iter().any(|types| types.is_playing(world, entity, state))
+    }
+}
+
+pub struct CutsceneAnimationPlugin<S: AnimState>(PhantomData<S>);
+impl<S: AnimState> Default for CutsceneAnimationPlugin<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: AnimState> Plugin for CutsceneAnimationPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CutsceneAnimations>();
+        app.world.resource_mut::<CutsceneAnimations>().types.push(Box::new(TypedAnimations::<S>(PhantomData)));
+    }
+}
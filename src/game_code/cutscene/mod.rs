@@ -0,0 +1,186 @@
+// :: Cutscenes ::
+// Scripted scenes, written as data in `.cutscene.ron` (or `.cutscene.json`)
+// files under `assets/cutscenes/`. A Cutscene is a list of steps run one
+// after another: walk actors around, play their animations, pan the
+// camera, show dialogue, fade the screen. The player can't move while one
+// plays (unless it sets `lock_input: false`):
+//
+//     (
+//         steps: [
+//             FadeIn(1.0),
+//             Move(actor: "villager", to: (40.0, 20.0)),
+//             Face(actor: "player", direction: E),
+//             Say(speaker: "Villager", text: "There you are!"),
+//             Pan(to: (120.0, 5.0), duration: 2.0, zoom: Some(2.0)),
+//             Dialogue(path: "dialogue/meadow.dialogue", node: Some("intro")),
+//             ReturnCamera,
+//         ],
+//     )
+//
+// Actors are found by their Name component, except "player", which is the
+// player. Move, Anim and Pan wait until they're done before the next step
+// starts, unless they say `wait: false`, so several actors can move at once.
+// Animation states are given by name; the state types that can be played
+// need a CutsceneAnimationPlugin.
+//
+// Play one with the CutscenePlayer, or with a CutsceneTrigger on a
+// TriggerZone, to play it when the player walks in:
+//
+//     commands.spawn((
+//         CutsceneTrigger::new(asset_server.load("cutscenes/meeting.cutscene.ron")),
+//         TriggerZone::new(Vec2::new(32.0, 32.0)),
+//         SpatialBundle::from_transform(Transform::from_xyz(40.0, 20.0, 0.0)),
+//     ));
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::Deserialize;
+
+use crate::{
+    asset_loader::RonOrJsonLoader,
+    camera::CameraSystem,
+    collision::TriggerEnter,
+    dialogue::DialogueSystem,
+    direction::Direction,
+    movement::{follow_move_paths, MovementSystem},
+    player::Player,
+};
+
+mod animation;
+mod steps;
+
+pub use animation::{CutsceneAnimationPlugin, CutsceneAnimations};
+
+#[derive(Clone, Debug, Deserialize, TypeUuid)]
+#[uuid = "4256b0b8-df3d-43ee-a58f-e4a31db8ebaa"]
+pub struct Cutscene {
+    #[serde(default = "default_true")]
+    pub lock_input: bool, // whether the player can't move while it plays
+    pub steps: Vec<CutsceneStep>,
+}
+
+// Positions are in world pixels; durations in seconds
+#[derive(Clone, Debug, Deserialize)]
+pub enum CutsceneStep {
+    // Walk an actor to a point, finding a way around walls
+    Move {
+        actor: String,
+        to: (f32, f32),
+        #[serde(default = "default_true")]
+        wait: bool,
+    },
+    // Put an actor somewhere, instantly
+    Teleport { actor: String, to: (f32, f32) },
+    Face { actor: String, direction: Direction },
+    // Play an animation state once, then go back to what was playing
+    Anim {
+        actor: String,
+        state: String,
+        #[serde(default = "default_true")]
+        wait: bool,
+    },
+    Wait(f32),
+    // Pan the camera to a point (and zoom, rounded to a whole factor; see
+    // whole_zoom), and stay there until ReturnCamera (or the end of the cutscene)
+    Pan {
+        to: (f32, f32),
+        duration: f32,
+        #[serde(default)]
+        zoom: Option<f32>,
+        #[serde(default = "default_true")]
+        wait: bool,
+    },
+    // Hand the camera back to whatever it was following
+    ReturnCamera,
+    // Show one line of dialogue, and wait for the player to read it
+    Say { speaker: String, text: String },
+    // Show a conversation from a `.dialogue` file, from the start or from a
+    // node, and wait for it to end
+    Dialogue {
+        path: String,
+        #[serde(default)]
+        node: Option<String>,
+    },
+    FadeOut(f32), // to black
+    FadeIn(f32),
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// Plays cutscenes, one at a time
+#[derive(Resource, Default)]
+pub struct CutscenePlayer {
+    queue: VecDeque<Handle<Cutscene>>,
+    playing: Option<steps::Playing>,
+}
+impl CutscenePlayer {
+    // Play a cutscene once it's loaded, and after any already playing or queued
+    pub fn play(&mut self, cutscene: Handle<Cutscene>) {
+        self.queue.push_back(cutscene);
+    }
+    pub fn is_playing(&self) -> bool {
+        self.playing.is_some() || !self.queue.is_empty()
+    }
+}
+
+// Plays a cutscene when a player walks into the entity's TriggerZone
+#[derive(Component, Clone, Debug)]
+pub struct CutsceneTrigger {
+    pub cutscene: Handle<Cutscene>,
+    pub once: bool, // only play it the first time
+    played: bool,
+}
+impl CutsceneTrigger {
+    pub fn new(cutscene: Handle<Cutscene>) -> Self {
+        Self { cutscene, once: true, played: false }
+    }
+    // Play it every time the player walks in
+    pub fn repeating(mut self) -> Self {
+        self.once = false;
+        self
+    }
+}
+
+pub struct CutsceneFinished {
+    pub cutscene: Handle<Cutscene>,
+}
+
+pub struct CutscenePlugin;
+impl Plugin for CutscenePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Cutscene>()
+            .add_asset_loader(RonOrJsonLoader::<Cutscene>::new(&["cutscene.ron", "cutscene.json"]))
+            .init_resource::<CutscenePlayer>()
+            .init_resource::<CutsceneAnimations>()
+            .add_event::<CutsceneFinished>()
+            .add_system(trigger_cutscenes)
+            .add_system(steps::run_cutscenes
+                .after(trigger_cutscenes)
+                .after(DialogueSystem)
+                .before(follow_move_paths)
+                .before(MovementSystem)
+                .before(CameraSystem));
+    }
+}
+
+fn trigger_cutscenes(
+    mut enters: EventReader<TriggerEnter>,
+    mut player: ResMut<CutscenePlayer>,
+    mut triggers: Query<&mut CutsceneTrigger>,
+    players: Query<(), With<Player>>,
+) {
+    for enter in enters.iter() {
+        if !players.contains(enter.sensor) {
+            continue;
+        }
+        if let Ok(mut trigger) = triggers.get_mut(enter.zone) {
+            if trigger.once && trigger.played {
+                continue;
+            }
+            trigger.played = true;
+            player.play(trigger.cutscene.clone());
+        }
+    }
+}
@@ -0,0 +1,280 @@
+// :: Running cutscenes ::
+// The CutscenePlayer's playing cutscene is run each frame by an exclusive
+// system, since steps can touch nearly anything: actors, the camera, the
+// dialogue runner, the screen fade. Steps that finish straight away (e.g.,
+// Face) don't hold up the ones after them.
+use bevy::{asset::LoadState, prelude::*};
+
+use super::{Cutscene, CutsceneAnimations, CutsceneFinished, CutscenePlayer, CutsceneStep};
+use crate::{
+    camera::{whole_zoom, CameraCinematic, CameraShot},
+    dialogue::{Dialogue, DialogueRunner},
+    direction::Direction,
+    fade::{fade_in, fade_out, ScreenFade},
+    movement::{MovePath, Position},
+    pathfinding::FindPath,
+    player::{Player, PlayerControlLock, PlayerState},
+};
+
+const CONTROL_LOCK: &str = "cutscene";
+const PLAYER_ACTOR: &str = "player";
+
+pub(super) struct Playing {
+    handle: Handle<Cutscene>,
+    cutscene: Cutscene,
+    step: usize,
+    progress: Progress,
+}
+
+// How far along the current step is
+enum Progress {
+    Start,
+    Elapsed(f32),
+    Moving(Entity),
+    Animating(Entity),
+    LoadingDialogue(Handle<Dialogue>),
+    Talking,
+    Fading,
+}
+
+// Needs the whole World, since steps can touch anything
+pub(super) fn run_cutscenes(world: &mut World) {
+    let delta_seconds = world.resource::<Time>().delta_seconds();
+    let mut playing = match world.resource_mut::<CutscenePlayer>().playing.take() {
+        Some(playing) => playing,
+        None => match start_next(world) {
+            Some(playing) => playing,
+            None => return,
+        },
+    };
+    loop {
+        let step = match playing.cutscene.steps.get(playing.step) {
+            Some(step) => step.clone(),
+            None => {
+                finish(world, playing);
+                return;
+            },
+        };
+        if !run_step(world, &step, &mut playing.progress, delta_seconds) {
+            break;
+        }
+        playing.step += 1;
+        playing.progress = Progress::Start;
+    }
+    world.resource_mut::<CutscenePlayer>().playing = Some(playing);
+}
+
+// Start the next queued cutscene, once it's loaded
+fn start_next(world: &mut World) -> Option<Playing> {
+    let handle = world.resource::<CutscenePlayer>().queue.front()?.clone();
+    let cutscene = match world.resource::<Assets<Cutscene>>().get(&handle) {
+        Some(cutscene) => cutscene.clone(),
+        None => {
+            if world.resource::<AssetServer>().get_load_state(&handle) == LoadState::Failed {
+                warn!("Couldn't load a cutscene; skipping it");
+                world.resource_mut::<CutscenePlayer>().queue.pop_front();
+            }
+            return None;
+        },
+    };
+    world.resource_mut::<CutscenePlayer>().queue.pop_front();
+
+    if cutscene.lock_input {
+        world.resource_mut::<PlayerControlLock>().lock(CONTROL_LOCK);
+        for mut state in world.query_filtered::<&mut PlayerState, With<Player>>().iter_mut(world) {
+            if let Err(err) = state.transition(PlayerState::Cutscene) {
+                warn!("{}", err);
+            }
+        }
+    }
+    Some(Playing { handle, cutscene, step: 0, progress: Progress::Start })
+}
+
+// Hand everything back: the player, the camera and the screen
+fn finish(world: &mut World, playing: Playing) {
+    if playing.cutscene.lock_input {
+        world.resource_mut::<PlayerControlLock>().unlock(CONTROL_LOCK);
+        for mut state in world.query_filtered::<&mut PlayerState, With<Player>>().iter_mut(world) {
+            if *state == PlayerState::Cutscene {
+                *state = PlayerState::Idle;
+            }
+        }
+    }
+    for mut cinematic in world.query::<&mut CameraCinematic>().iter_mut(world) {
+        cinematic.stop();
+    }
+    if playing.cutscene.steps.iter().any(|step| matches!(step, CutsceneStep::FadeOut(_))) {
+        world.resource_mut::<ScreenFade>().clear();
+    }
+    world.resource_mut::<Events<CutsceneFinished>>().send(CutsceneFinished { cutscene: playing.handle });
+}
+
+// Run a step for a frame. True once it's done.
+fn run_step(world: &mut World, step: &CutsceneStep, progress: &mut Progress, delta_seconds: f32) -> bool {
+    match (step, &mut *progress) {
+        (CutsceneStep::Move { actor, to, wait }, Progress::Start) => {
+            let entity = match find_actor(world, actor) {
+                Some(entity) => entity,
+                None => return true,
+            };
+            world.entity_mut(entity).insert(FindPath::new(Vec2::from(*to)));
+            *progress = Progress::Moving(entity);
+            !wait
+        },
+        (CutsceneStep::Move { .. }, Progress::Moving(entity)) => {
+            // Still working out the way, or walking it
+            let entity = match world.get_entity(*entity) {
+                Some(entity) => entity,
+                None => return true,
+            };
+            let walking = entity.get::<MovePath>().map_or(false, |path| !path.is_empty());
+            !entity.contains::<FindPath>() && !walking
+        },
+
+        (CutsceneStep::Teleport { actor, to }, _) => {
+            if let Some(entity) = find_actor(world, actor) {
+                if let Some(mut path) = world.get_mut::<MovePath>(entity) {
+                    path.clear();
+                }
+                if let Some(mut position) = world.get_mut::<Position>(entity) {
+                    position.teleport(Vec2::from(*to));
+                }
+            }
+            true
+        },
+
+        (CutsceneStep::Face { actor, direction }, _) => {
+            if let Some(entity) = find_actor(world, actor) {
+                if let Some(mut facing) = world.get_mut::<Direction>(entity) {
+                    *facing = *direction;
+                }
+            }
+            true
+        },
+
+        (CutsceneStep::Anim { actor, state, wait }, Progress::Start) => {
+            let entity = match find_actor(world, actor) {
+                Some(entity) => entity,
+                None => return true,
+            };
+            let started = world.resource_scope(|world, animations: Mut<CutsceneAnimations>| {
+                animations.play(world, entity, state)
+            });
+            match started {
+                Some(true) => {
+                    *progress = Progress::Animating(entity);
+                    !wait
+                },
+                Some(false) => false, // try again once the current animation can be interrupted
+                None => {
+                    warn!("\"{}\" has no animation state \"{}\"", actor, state);
+                    true
+                },
+            }
+        },
+        (CutsceneStep::Anim { state, .. }, Progress::Animating(entity)) => {
+            let entity = *entity;
+            let animations = world.resource::<CutsceneAnimations>();
+            !animations.is_playing(world, entity, state)
+        },
+
+        (CutsceneStep::Wait(seconds), Progress::Start) => {
+            *progress = Progress::Elapsed(0.0);
+            *seconds <= 0.0
+        },
+        (CutsceneStep::Wait(seconds), Progress::Elapsed(elapsed)) => {
+            *elapsed += delta_seconds;
+            *elapsed >= *seconds
+        },
+
+        (CutsceneStep::Pan { to, duration, zoom, wait }, Progress::Start) => {
+            let mut shot = CameraShot::new(Vec2::from(*to), *duration);
+            if let Some(zoom) = zoom {
+                shot = shot.with_zoom(whole_zoom(*zoom));
+            }
+            for mut cinematic in world.query::<&mut CameraCinematic>().iter_mut(world) {
+                cinematic.stop();
+                cinematic.queue(shot);
+                cinematic.queue(CameraShot::hold(f32::MAX)); // until ReturnCamera
+            }
+            *progress = Progress::Elapsed(0.0);
+            !wait || *duration <= 0.0
+        },
+        (CutsceneStep::Pan { duration, .. }, Progress::Elapsed(elapsed)) => {
+            *elapsed += delta_seconds;
+            *elapsed >= *duration
+        },
+
+        (CutsceneStep::ReturnCamera, _) => {
+            for mut cinematic in world.query::<&mut CameraCinematic>().iter_mut(world) {
+                cinematic.stop();
+            }
+            true
+        },
+
+        (CutsceneStep::Say { speaker, text }, Progress::Start) => {
+            world.resource_mut::<DialogueRunner>().start(Dialogue::new().line(speaker, text), None);
+            *progress = Progress::Talking;
+            false
+        },
+        (CutsceneStep::Dialogue { path, .. }, Progress::Start) => {
+            let handle = world.resource::<AssetServer>().load(path.as_str());
+            *progress = Progress::LoadingDialogue(handle);
+            false
+        },
+        (CutsceneStep::Dialogue { path, node }, Progress::LoadingDialogue(handle)) => {
+            let dialogue = match world.resource::<Assets<Dialogue>>().get(handle) {
+                Some(dialogue) => dialogue.clone(),
+                None => {
+                    if world.resource::<AssetServer>().get_load_state(&*handle) == LoadState::Failed {
+                        warn!("Couldn't load dialogue \"{}\"", path);
+                        return true;
+                    }
+                    return false;
+                },
+            };
+            let mut runner = world.resource_mut::<DialogueRunner>();
+            match node {
+                Some(node) => runner.start_at(dialogue, node, None),
+                None => runner.start(dialogue, None),
+            }
+            *progress = Progress::Talking;
+            false
+        },
+        (CutsceneStep::Say { .. } | CutsceneStep::Dialogue { .. }, Progress::Talking) => {
+            !world.resource::<DialogueRunner>().is_active()
+        },
+
+        (CutsceneStep::FadeOut(seconds), Progress::Start) => {
+            world.resource_mut::<ScreenFade>().play(fade_out(*seconds));
+            *progress = Progress::Fading;
+            false
+        },
+        (CutsceneStep::FadeIn(seconds), Progress::Start) => {
+            world.resource_mut::<ScreenFade>().play(fade_in(*seconds));
+            *progress = Progress::Fading;
+            false
+        },
+        (CutsceneStep::FadeOut(_) | CutsceneStep::FadeIn(_), Progress::Fading) => {
+            !world.resource::<ScreenFade>().is_fading()
+        },
+
+        // Can't happen: each step only ever has the progress it sets
+        _ => true,
+    }
+}
+
+// An entity by its Name, or the player
+fn find_actor(world: &mut World, actor: &str) -> Option<Entity> {
+    let entity = if actor == PLAYER_ACTOR {
+        world.query_filtered::<Entity, With<Player>>().iter(world).next()
+    } else {
+        world.query::<(Entity, &Name)>().iter(world)
+            .find(|(_, name)| name.as_str() == actor)
+            .map(|(entity, _)| entity)
+    };
+    if entity.is_none() {
+        warn!("Cutscene actor \"{}\" isn't there", actor);
+    }
+    entity
+}
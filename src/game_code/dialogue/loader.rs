@@ -0,0 +1,77 @@
+// :: Loading dialogue from files ::
+// Loads `.dialogue` scripts (see script.rs) as Dialogue assets. Put a
+// DialogueSource on an Interactable to have it say a conversation from
+// one, starting from a node of your choosing (so one file can hold every
+// conversation for a character, or a whole map):
+//
+//     commands.spawn((
+//         Interactable::new(24.0, "Talk"),
+//         DialogueSource::new(asset_server.load("dialogue/meadow.dialogue")).with_node("mira"),
+//         ..
+//     ));
+//
+// Edits to the file are picked up the next time the conversation starts.
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    utils::BoxedFuture,
+};
+
+use super::{script::parse_script, Dialogue, DialogueStep};
+
+#[derive(Component, Clone, Debug)]
+pub struct DialogueSource {
+    pub handle: Handle<Dialogue>,
+    pub node: Option<String>, // where to start, or None for the first node in the file
+}
+impl DialogueSource {
+    pub fn new(handle: Handle<Dialogue>) -> Self {
+        Self { handle, node: None }
+    }
+    pub fn with_node(mut self, node: &str) -> Self {
+        self.node = Some(node.to_string());
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct DialogueLoader;
+impl AssetLoader for DialogueLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let script = parse_script(std::str::from_utf8(bytes)?)?;
+            let mut dialogue = script.dialogue;
+
+            // Give each speaker's lines their portrait
+            let mut dependencies = Vec::new();
+            for (speaker, path) in script.portraits {
+                let path = AssetPath::new(path.into(), None);
+                let portrait: Handle<Image> = load_context.get_handle(path.clone());
+                dependencies.push(path);
+                let steps = dialogue.nodes.iter_mut().flat_map(|node| node.steps.iter_mut());
+                for step in steps {
+                    if let DialogueStep::Line(line) = step {
+                        if line.speaker.as_ref() == Some(&speaker) {
+                            line.portrait = Some(portrait.clone());
+                        }
+                    }
+                }
+            }
+
+            let mut asset = LoadedAsset::new(dialogue);
+            for path in dependencies {
+                asset = asset.with_dependency(path);
+            }
+            load_context.set_default_asset(asset);
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["dialogue"]
+    }
+}
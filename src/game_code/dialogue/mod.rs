@@ -0,0 +1,413 @@
+// :: Dialogue ::
+// Conversations shown a line at a time in a text box at the bottom of the
+// screen, with the speaker's name and portrait. Each line types itself
+// out; pressing Interact shows the rest of it at once, or moves on to the
+// next line once it's all there. The player can't move while a
+// conversation is going.
+//
+// Conversations can branch: menus of choices are picked from with
+// MoveUp/MoveDown and Interact, and what's said can depend on (and set)
+// GameFlags. See nodes.rs for how a Dialogue is put together, or
+// script.rs for writing one in a `.dialogue` file instead.
+//
+// An Interactable with a Dialogue (e.g., an NPC) starts it when used:
+//
+//     commands.spawn((
+//         Interactable::new(24.0, "Talk"),
+//         Dialogue::new()
+//             .line("Mira", "Lovely day for it.")
+//             .line("Mira", "Mind the pond, it's deeper than it looks."),
+//         ..
+//     ));
+//
+// Signs and notes can use a Readable instead (see readable.rs). Anything
+// else (e.g., a cutscene) can start one with the DialogueRunner:
+//
+//     runner.start(Dialogue::new().line("", "The door is locked."), None);
+use bevy::prelude::*;
+
+use crate::{
+    flags::GameFlags,
+    input::{Action, Actions, InputSystem},
+    interaction::{InteractionEvent, InteractionSystem},
+    player::PlayerControlLock,
+};
+
+mod loader;
+mod nodes;
+mod readable;
+mod script;
+mod ui;
+
+pub use loader::{DialogueLoader, DialogueSource};
+pub use nodes::{Dialogue, DialogueChoice, DialogueLine, DialogueNode, DialogueStep};
+pub use readable::Readable;
+pub use script::{parse_script, DialogueScript, DialogueScriptError};
+
+const CONTROL_LOCK: &str = "dialogue";
+const DEFAULT_CHARS_PER_SECOND: f32 = 40.0;
+const MAX_UNSHOWN_STEPS: usize = 1000; // steps run in a row without showing anything, before giving up
+
+// The conversation being shown, if any
+#[derive(Resource)]
+pub struct DialogueRunner {
+    pub chars_per_second: f32, // how fast lines type out
+    active: Option<ActiveDialogue>,
+    events: Vec<DialogueEvent>, // not sent yet
+}
+struct ActiveDialogue {
+    dialogue: Dialogue,
+    node: usize, // where we are, in `dialogue`
+    step: usize,
+    entered: bool, // whether the step we're on has been started (shown, if it's a line or choice)
+    line: Option<DialogueLine>, // the line on screen
+    revealed: f32, // how many characters of the line are showing, counting partly typed ones
+    choices: Vec<DialogueChoice>, // the choices on offer, if we're at a menu
+    selected: usize,
+    speaker: Option<Entity>,
+}
+impl ActiveDialogue {
+    fn step(&self) -> Option<&DialogueStep> {
+        self.dialogue.nodes.get(self.node).and_then(|node| node.steps.get(self.step))
+    }
+    fn next_step(&mut self) {
+        self.step += 1;
+        self.entered = false;
+    }
+    // Go to the start of the node called `name`. False if there isn't one.
+    fn jump(&mut self, name: &str) -> bool {
+        match self.dialogue.find_node(name) {
+            Some(node) => {
+                self.node = node;
+                self.step = 0;
+                self.entered = false;
+                true
+            },
+            None => {
+                warn!("Dialogue has no node called \"{}\"", name);
+                false
+            },
+        }
+    }
+}
+impl Default for DialogueRunner {
+    fn default() -> Self {
+        Self { chars_per_second: DEFAULT_CHARS_PER_SECOND, active: None, events: Vec::new() }
+    }
+}
+impl DialogueRunner {
+    // Show `dialogue`, replacing any conversation already going.
+    // `speaker` is the entity that's talking, if there is one.
+    pub fn start(&mut self, dialogue: Dialogue, speaker: Option<Entity>) {
+        self.active = Some(ActiveDialogue {
+            dialogue,
+            node: 0,
+            step: 0,
+            entered: false,
+            line: None,
+            revealed: 0.0,
+            choices: Vec::new(),
+            selected: 0,
+            speaker,
+        });
+    }
+    // Like `start`, but from the node called `node`
+    pub fn start_at(&mut self, dialogue: Dialogue, node: &str, speaker: Option<Entity>) {
+        self.start(dialogue, speaker);
+        if let Some(active) = &mut self.active {
+            if !active.jump(node) {
+                self.active = None;
+            }
+        }
+    }
+    pub fn stop(&mut self) {
+        self.active = None;
+    }
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+    pub fn speaker(&self) -> Option<Entity> {
+        self.active.as_ref().and_then(|active| active.speaker)
+    }
+    // The line on screen. Stays up while a menu of choices is shown after it.
+    pub fn line(&self) -> Option<&DialogueLine> {
+        self.active.as_ref().and_then(|active| active.line.as_ref())
+    }
+    // As much of the line as has typed out so far
+    pub fn visible_text(&self) -> &str {
+        let (active, line) = match (&self.active, self.line()) {
+            (Some(active), Some(line)) => (active, line),
+            _ => return "",
+        };
+        match line.text.char_indices().nth(active.revealed as usize) {
+            Some((end, _)) => &line.text[..end],
+            None => &line.text,
+        }
+    }
+    // Whether the line is still typing out
+    pub fn is_revealing(&self) -> bool {
+        match (&self.active, self.line()) {
+            (Some(active), Some(line)) => (active.revealed as usize) < line.text.chars().count(),
+            _ => false,
+        }
+    }
+    pub fn reveal_all(&mut self) {
+        if let Some(active) = &mut self.active {
+            active.revealed = f32::MAX;
+        }
+    }
+    // Go on to the next step, once the line on screen has been read. Does
+    // nothing at a menu, which waits for `choose`.
+    pub fn advance(&mut self) {
+        if let Some(active) = &mut self.active {
+            if active.choices.is_empty() {
+                active.next_step();
+            }
+        }
+    }
+
+    // The choices on offer (empty unless we're at a menu)
+    pub fn choices(&self) -> &[DialogueChoice] {
+        self.active.as_ref().map_or(&[], |active| &active.choices)
+    }
+    pub fn selected(&self) -> usize {
+        self.active.as_ref().map_or(0, |active| active.selected)
+    }
+    pub fn select(&mut self, index: usize) {
+        if let Some(active) = &mut self.active {
+            active.selected = index.min(active.choices.len().saturating_sub(1));
+        }
+    }
+    // Pick the selected choice
+    pub fn choose(&mut self) {
+        let active = match &mut self.active {
+            Some(active) if !active.choices.is_empty() => active,
+            _ => return,
+        };
+        let choice = active.choices.swap_remove(active.selected);
+        active.choices.clear();
+        match choice.next {
+            Some(node) => {
+                if !active.jump(&node) {
+                    self.active = None;
+                }
+            },
+            None => active.next_step(),
+        }
+    }
+
+    fn type_out(&mut self, seconds: f32) {
+        let chars_per_second = self.chars_per_second;
+        if let Some(active) = &mut self.active {
+            active.revealed += seconds * chars_per_second;
+        }
+    }
+
+    fn is_settled(&self) -> bool {
+        self.active.as_ref().map_or(true, |active| active.entered)
+    }
+    // Run steps until we get to one that shows something (a line or a
+    // menu), or the conversation ends
+    fn settle(&mut self, flags: &mut GameFlags) {
+        let active = match &mut self.active {
+            Some(active) if !active.entered => active,
+            _ => return,
+        };
+        for _ in 0..MAX_UNSHOWN_STEPS {
+            let step = match active.step() {
+                Some(step) => step.clone(),
+                None => {
+                    // The end of the node
+                    self.active = None;
+                    return;
+                },
+            };
+            let carried_on = match step {
+                DialogueStep::Line(line) => {
+                    active.line = Some(line);
+                    active.revealed = 0.0;
+                    active.entered = true;
+                    return;
+                },
+                DialogueStep::Choice(choices) => {
+                    let offered: Vec<DialogueChoice> = choices.into_iter()
+                        .filter(|choice| choice.condition.as_ref().map_or(true, |condition| condition.check(flags)))
+                        .collect();
+                    if offered.is_empty() {
+                        active.next_step();
+                        continue;
+                    }
+                    active.choices = offered;
+                    active.selected = 0;
+                    active.revealed = f32::MAX; // the line asking stays up, in full
+                    active.entered = true;
+                    return;
+                },
+                DialogueStep::SetFlag(name, value) => {
+                    flags.set(&name, value);
+                    active.next_step();
+                    true
+                },
+                DialogueStep::Event(name) => {
+                    self.events.push(DialogueEvent { name, speaker: active.speaker });
+                    active.next_step();
+                    true
+                },
+                DialogueStep::Jump(node) => active.jump(&node),
+                DialogueStep::Branch { condition, then, otherwise } => {
+                    match if condition.check(flags) { Some(then) } else { otherwise } {
+                        Some(node) => active.jump(&node),
+                        None => {
+                            active.next_step();
+                            true
+                        },
+                    }
+                },
+                DialogueStep::End => false,
+            };
+            if !carried_on {
+                self.active = None;
+                return;
+            }
+        }
+        warn!("Dialogue ran {} steps without showing anything; do its jumps go in a loop?", MAX_UNSHOWN_STEPS);
+        self.active = None;
+    }
+}
+
+pub struct DialogueStarted {
+    pub speaker: Option<Entity>,
+}
+pub struct DialogueEnded {
+    pub speaker: Option<Entity>,
+}
+// Sent by an Event step
+pub struct DialogueEvent {
+    pub name: String,
+    pub speaker: Option<Entity>,
+}
+
+// Systems that start or read dialogue should run `.after(DialogueSystem)`
+#[derive(SystemLabel)]
+pub struct DialogueSystem;
+
+pub struct DialoguePlugin;
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Dialogue>()
+            .init_asset_loader::<DialogueLoader>()
+            .init_resource::<DialogueRunner>()
+            .add_event::<DialogueStarted>()
+            .add_event::<DialogueEnded>()
+            .add_event::<DialogueEvent>()
+            .add_startup_system(ui::spawn_dialogue_box)
+            // Advance before starting, so the press that starts a
+            // conversation doesn't also skip its first line
+            .add_system(advance_dialogue.label(DialogueSystem).after(InputSystem))
+            .add_system(start_dialogues
+                .label(DialogueSystem)
+                .after(advance_dialogue)
+                .after(InteractionSystem))
+            .add_system(settle_dialogue
+                .label(DialogueSystem)
+                .after(advance_dialogue)
+                .after(start_dialogues))
+            .add_system(lock_during_dialogue.after(DialogueSystem))
+            .add_system(ui::update_dialogue_box.after(DialogueSystem));
+    }
+}
+
+fn advance_dialogue(
+    time: Res<Time>,
+    actions: Res<Actions>,
+    mut runner: ResMut<DialogueRunner>,
+) {
+    if !runner.is_active() {
+        return;
+    }
+    let choices = runner.choices().len();
+    if choices > 0 {
+        // Pick from the menu, wrapping around at the ends
+        let selected = runner.selected();
+        if actions.just_pressed(Action::MoveUp) {
+            runner.select((selected + choices - 1) % choices);
+        } else if actions.just_pressed(Action::MoveDown) {
+            runner.select((selected + 1) % choices);
+        } else if actions.just_pressed(Action::Interact) {
+            runner.choose();
+        }
+    } else if actions.just_pressed(Action::Interact) {
+        if runner.is_revealing() {
+            runner.reveal_all();
+        } else {
+            runner.advance();
+        }
+    } else {
+        runner.type_out(time.delta_seconds());
+    }
+}
+
+fn start_dialogues(
+    mut interactions: EventReader<InteractionEvent>,
+    mut runner: ResMut<DialogueRunner>,
+    dialogue_assets: Res<Assets<Dialogue>>,
+    dialogues: Query<(Option<&Dialogue>, Option<&DialogueSource>, Option<&Readable>)>,
+) {
+    for event in interactions.iter() {
+        if runner.is_active() {
+            break;
+        }
+        match dialogues.get(event.target) {
+            Ok((Some(dialogue), ..)) => runner.start(dialogue.clone(), Some(event.target)),
+            Ok((None, Some(source), _)) => {
+                let dialogue = match dialogue_assets.get(&source.handle) {
+                    Some(dialogue) => dialogue.clone(),
+                    None => continue, // not loaded yet (or it failed to)
+                };
+                match &source.node {
+                    Some(node) => runner.start_at(dialogue, node, Some(event.target)),
+                    None => runner.start(dialogue, Some(event.target)),
+                }
+            },
+            Ok((None, None, Some(readable))) => runner.start(readable.to_dialogue(), Some(event.target)),
+            _ => (),
+        }
+    }
+}
+
+fn settle_dialogue(
+    mut runner: ResMut<DialogueRunner>,
+    mut flags: ResMut<GameFlags>,
+    mut events: EventWriter<DialogueEvent>,
+) {
+    if !runner.is_settled() {
+        runner.settle(&mut flags);
+        events.send_batch(runner.events.drain(..));
+    }
+}
+
+// Hold the player still while a conversation is going, and say when one
+// starts or ends
+fn lock_during_dialogue(
+    runner: Res<DialogueRunner>,
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut was_active: Local<Option<Option<Entity>>>, // the speaker of the last conversation, while it lasted
+    mut started: EventWriter<DialogueStarted>,
+    mut ended: EventWriter<DialogueEnded>,
+) {
+    let active = runner.is_active().then(|| runner.speaker());
+    if *was_active == active {
+        return;
+    }
+    if let Some(speaker) = *was_active {
+        ended.send(DialogueEnded { speaker });
+    }
+    match active {
+        Some(speaker) => {
+            control_lock.lock(CONTROL_LOCK);
+            started.send(DialogueStarted { speaker });
+        },
+        None => control_lock.unlock(CONTROL_LOCK),
+    }
+    *was_active = active;
+}
@@ -0,0 +1,157 @@
+// :: Dialogue nodes ::
+// What a conversation is made of. A Dialogue is a list of named nodes,
+// starting with the first; each is a list of steps run in order: lines to
+// show, menus of choices, flags to set, and jumps to other nodes. When a
+// node runs out of steps, the conversation is over.
+//
+//     Dialogue::new()
+//         .line("Mira", "Have you seen my cat?")
+//         .choice(vec![
+//             DialogueChoice::new("I found him by the pond.", "found")
+//                 .with_condition(FlagCondition::is_set("found_cat")),
+//             DialogueChoice::new("Sorry, no.", "not_found"),
+//         ])
+//         .node("found")
+//         .set_flag("cat_returned", true)
+//         .line("Mira", "Oh, thank you!")
+//         .node("not_found")
+//         .line("Mira", "If you see him, let me know.")
+//
+// Builder methods add steps to the node added last.
+use bevy::{prelude::*, reflect::TypeUuid};
+
+use crate::flags::{FlagCondition, FlagValue};
+
+const START_NODE: &str = "start";
+
+#[derive(Clone, Debug, Default)]
+pub struct DialogueLine {
+    pub speaker: Option<String>, // the name shown over the text
+    pub portrait: Option<Handle<Image>>, // the picture shown beside it
+    pub text: String,
+}
+impl DialogueLine {
+    pub fn new(text: &str) -> Self {
+        Self { text: text.to_string(), ..default() }
+    }
+    pub fn with_speaker(mut self, speaker: &str) -> Self {
+        self.speaker = Some(speaker.to_string());
+        self
+    }
+    pub fn with_portrait(mut self, portrait: Handle<Image>) -> Self {
+        self.portrait = Some(portrait);
+        self
+    }
+}
+
+// One answer in a menu of choices
+#[derive(Clone, Debug)]
+pub struct DialogueChoice {
+    pub text: String,
+    pub next: Option<String>, // the node to go to, or None to carry on after the menu
+    pub condition: Option<FlagCondition>, // only offered when this holds
+}
+impl DialogueChoice {
+    pub fn new(text: &str, next: &str) -> Self {
+        Self { text: text.to_string(), next: Some(next.to_string()), condition: None }
+    }
+    // A choice that carries on with the rest of the node
+    pub fn carry_on(text: &str) -> Self {
+        Self { text: text.to_string(), next: None, condition: None }
+    }
+    pub fn with_condition(mut self, condition: FlagCondition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum DialogueStep {
+    Line(DialogueLine),
+    // Ask the player to pick one of these. If none are offered, it's skipped.
+    Choice(Vec<DialogueChoice>),
+    SetFlag(String, FlagValue),
+    // Tell the rest of the game something, with a DialogueEvent (e.g., for
+    // a shopkeeper to open their shop)
+    Event(String),
+    Jump(String),
+    // Jump to `then` if the condition holds, otherwise to `otherwise`
+    // (or carry on, if there isn't one)
+    Branch {
+        condition: FlagCondition,
+        then: String,
+        otherwise: Option<String>,
+    },
+    End,
+}
+
+#[derive(Clone, Debug)]
+pub struct DialogueNode {
+    pub name: String,
+    pub steps: Vec<DialogueStep>,
+}
+
+// A conversation. Put one on an Interactable to have it said when used.
+// Also an asset, loaded from `.dialogue` files (see script.rs).
+#[derive(Component, Clone, Debug, TypeUuid)]
+#[uuid = "439e4634-8f9c-4b28-982e-6ba91825ab0b"]
+pub struct Dialogue {
+    pub nodes: Vec<DialogueNode>, // starts from the first
+}
+impl Default for Dialogue {
+    fn default() -> Self {
+        Self { nodes: vec![DialogueNode { name: START_NODE.to_string(), steps: Vec::new() }] }
+    }
+}
+impl Dialogue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // Where the node called `name` is in `nodes`
+    pub fn find_node(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    // Start a new node; the steps added after this go in it
+    pub fn node(mut self, name: &str) -> Self {
+        self.nodes.push(DialogueNode { name: name.to_string(), steps: Vec::new() });
+        self
+    }
+    pub fn step(mut self, step: DialogueStep) -> Self {
+        match self.nodes.last_mut() {
+            Some(node) => node.steps.push(step),
+            None => self.nodes.push(DialogueNode { name: START_NODE.to_string(), steps: vec![step] }),
+        }
+        self
+    }
+    // Add a line said by `speaker` (or by nobody, if it's empty)
+    pub fn line(self, speaker: &str, text: &str) -> Self {
+        let line = DialogueLine::new(text);
+        self.with_line(if speaker.is_empty() { line } else { line.with_speaker(speaker) })
+    }
+    pub fn with_line(self, line: DialogueLine) -> Self {
+        self.step(DialogueStep::Line(line))
+    }
+    pub fn choice(self, choices: Vec<DialogueChoice>) -> Self {
+        self.step(DialogueStep::Choice(choices))
+    }
+    pub fn set_flag(self, name: &str, value: impl Into<FlagValue>) -> Self {
+        self.step(DialogueStep::SetFlag(name.to_string(), value.into()))
+    }
+    pub fn event(self, name: &str) -> Self {
+        self.step(DialogueStep::Event(name.to_string()))
+    }
+    pub fn jump(self, node: &str) -> Self {
+        self.step(DialogueStep::Jump(node.to_string()))
+    }
+    pub fn branch(self, condition: FlagCondition, then: &str, otherwise: Option<&str>) -> Self {
+        self.step(DialogueStep::Branch {
+            condition,
+            then: then.to_string(),
+            otherwise: otherwise.map(str::to_string),
+        })
+    }
+    pub fn end(self) -> Self {
+        self.step(DialogueStep::End)
+    }
+}
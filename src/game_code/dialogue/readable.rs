@@ -0,0 +1,36 @@
+// :: Readables ::
+// Signs, bookshelves and notes: an Interactable with a Readable shows its
+// pages one after another in the dialogue box, with nobody speaking:
+//
+//     commands.spawn((
+//         Interactable::new(12.0, "Read"),
+//         Readable::new(vec!["North: the old mill.", "South: the meadow."]),
+//         ..
+//     ));
+use bevy::prelude::*;
+
+use super::Dialogue;
+
+#[derive(Component, Clone, Debug, Default)]
+pub struct Readable {
+    pub pages: Vec<String>,
+}
+impl Readable {
+    pub fn new(pages: Vec<&str>) -> Self {
+        Self { pages: pages.into_iter().map(str::to_string).collect() }
+    }
+    // Pages from one piece of text, split at blank lines (e.g., a property
+    // set in a map editor)
+    pub fn from_text(text: &str) -> Self {
+        let pages = text.replace("\r\n", "\n").split("\n\n")
+            .map(str::trim)
+            .filter(|page| !page.is_empty())
+            .map(str::to_string)
+            .collect();
+        Self { pages }
+    }
+
+    pub fn to_dialogue(&self) -> Dialogue {
+        self.pages.iter().fold(Dialogue::new(), |dialogue, page| dialogue.line("", page))
+    }
+}
@@ -0,0 +1,241 @@
+// :: Dialogue scripts ::
+// Conversations written as text, in `.dialogue` files under
+// `assets/dialogue/`, so writers don't have to touch Rust. The format is
+// a small cousin of Yarn Spinner's:
+//
+//     # Comments start with a hash
+//     <<portrait Mira portraits/mira.png>>
+//
+//     === mira
+//     <<if met_mira jump mira_again>>
+//     <<set met_mira true>>
+//     Mira: Have you seen my cat?
+//     -> I found him by the pond. -> found <<if found_cat>>
+//     -> Sorry, no.
+//     Mira: If you see him, let me know.
+//
+//     === found
+//     <<set cat_returned true>>
+//     Mira: Oh, thank you!
+//
+//     === mira_again
+//     Mira: Lovely day for it.
+//
+// - `=== name` starts a node. Lines before the first one go in a node
+//   called "start".
+// - `Speaker: text` is a line said by Speaker. Lines without a colon are
+//   said by nobody (e.g., for signs).
+// - `-> text` is a choice; choices in a row make one menu. `-> text -> node`
+//   jumps to that node when chosen, otherwise the node carries on after the
+//   menu. Ending it with `<<if condition>>` only offers it when that holds.
+// - `<<set flag value>>` sets a GameFlag, to `true`, `false`, a whole
+//   number or some text (in quotes, if it has spaces).
+// - `<<event name>>` sends a DialogueEvent, for the rest of the game to
+//   act on (e.g., `<<event shop>>` opens a shopkeeper's shop).
+// - `<<jump node>>` goes to another node; `<<end>>` ends the conversation.
+// - `<<if condition jump node>>` jumps only when the condition holds, and
+//   `<<if condition jump node else other>>` goes to `other` when it doesn't.
+// - `<<portrait Speaker path>>` shows an image (a path under `assets/`)
+//   beside every line Speaker says in the file.
+//
+// Conditions are `flag` (it's set), `flag == value`, `flag >= number`, or
+// those with `not` in front, joined with `and` or `or` (but not both).
+use bevy::utils::HashMap;
+
+use super::{Dialogue, DialogueChoice, DialogueStep};
+use crate::flags::{FlagCondition, FlagValue};
+
+#[derive(Debug)]
+pub struct DialogueScriptError {
+    pub line: usize, // counting from 1
+    pub message: String,
+}
+impl std::fmt::Display for DialogueScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Couldn't read dialogue script, line {}: {}", self.line, self.message)
+    }
+}
+impl std::error::Error for DialogueScriptError {}
+
+// A parsed script: the conversation, plus the portrait (an asset path) for
+// each speaker that has one
+pub struct DialogueScript {
+    pub dialogue: Dialogue,
+    pub portraits: HashMap<String, String>,
+}
+
+pub fn parse_script(source: &str) -> Result<DialogueScript, DialogueScriptError> {
+    let mut dialogue = Dialogue::new();
+    let mut portraits = HashMap::new();
+    let mut started_node = false; // whether "start" has been replaced by a named node
+    let mut jumps: Vec<(usize, String)> = Vec::new(); // every node jumped to, and from which line
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: String| DialogueScriptError { line: line_number, message };
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix("===") {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(error("a node needs a name".to_string()));
+            }
+            // The first named node replaces "start", if nothing was put in it
+            if !started_node && dialogue.nodes[0].steps.is_empty() {
+                dialogue.nodes[0].name = name.to_string();
+            } else if dialogue.find_node(name).is_some() {
+                return Err(error(format!("there's already a node called \"{}\"", name)));
+            } else {
+                dialogue = dialogue.node(name);
+            }
+            started_node = true;
+        } else if let Some(choice) = line.strip_prefix("->") {
+            let (choice, condition) = split_condition(choice).map_err(error)?;
+            let mut choice = match choice.split_once("->") {
+                Some((text, next)) => {
+                    jumps.push((line_number, next.trim().to_string()));
+                    DialogueChoice::new(text.trim(), next.trim())
+                },
+                None => DialogueChoice::carry_on(choice.trim()),
+            };
+            choice.condition = condition;
+            // Choices in a row make one menu
+            let steps = &mut dialogue.nodes.last_mut().expect("dialogue always has a node").steps;
+            match steps.last_mut() {
+                Some(DialogueStep::Choice(choices)) => choices.push(choice),
+                _ => steps.push(DialogueStep::Choice(vec![choice])),
+            }
+        } else if let Some(command) = line.strip_prefix("<<") {
+            let command = command.strip_suffix(">>")
+                .ok_or_else(|| error("commands need to end with \">>\"".to_string()))?;
+            let step = parse_command(command, &mut portraits, &mut jumps, line_number).map_err(error)?;
+            if let Some(step) = step {
+                dialogue = dialogue.step(step);
+            }
+        } else {
+            dialogue = match line.split_once(':') {
+                Some((speaker, text)) => dialogue.line(speaker.trim(), text.trim()),
+                None => dialogue.line("", line),
+            };
+        }
+    }
+
+    for (line, node) in jumps {
+        if dialogue.find_node(&node).is_none() {
+            return Err(DialogueScriptError { line, message: format!("there's no node called \"{}\"", node) });
+        }
+    }
+    Ok(DialogueScript { dialogue, portraits })
+}
+
+// Split `<<if condition>>` off the end of a choice
+fn split_condition(choice: &str) -> Result<(&str, Option<FlagCondition>), String> {
+    let choice = choice.trim();
+    match choice.strip_suffix(">>").and_then(|rest| rest.rsplit_once("<<")) {
+        Some((text, command)) => {
+            let condition = command.trim().strip_prefix("if ")
+                .ok_or_else(|| format!("choices can only end with <<if ..>>, not <<{}>>", command))?;
+            Ok((text, Some(parse_condition(condition)?)))
+        },
+        None => Ok((choice, None)),
+    }
+}
+
+// A command, without its << >>. Portraits don't make a step.
+fn parse_command(
+    command: &str,
+    portraits: &mut HashMap<String, String>,
+    jumps: &mut Vec<(usize, String)>,
+    line_number: usize,
+) -> Result<Option<DialogueStep>, String> {
+    let words = split_words(command)?;
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let mut jump = |node: &str| {
+        jumps.push((line_number, node.to_string()));
+        node.to_string()
+    };
+    let step = match words.as_slice() {
+        ["set", flag, value] => DialogueStep::SetFlag(flag.to_string(), parse_value(value)),
+        ["event", name] => DialogueStep::Event(name.to_string()),
+        ["jump", node] => DialogueStep::Jump(jump(*node)),
+        ["end"] => DialogueStep::End,
+        ["portrait", speaker @ .., path] if !speaker.is_empty() => {
+            portraits.insert(speaker.join(" "), path.to_string());
+            return Ok(None);
+        },
+        ["if", rest @ ..] => {
+            let jump_at = rest.iter().position(|word| *word == "jump")
+                .ok_or_else(|| "<<if ..>> needs a \"jump node\"".to_string())?;
+            let condition = parse_condition_words(&rest[..jump_at])?;
+            let (then, otherwise) = match &rest[jump_at + 1..] {
+                [then] => (jump(*then), None),
+                [then, "else", otherwise] => (jump(*then), Some(jump(*otherwise))),
+                _ => return Err("expected <<if condition jump node>> or <<if condition jump node else node>>".to_string()),
+            };
+            DialogueStep::Branch { condition, then, otherwise }
+        },
+        _ => return Err(format!("unknown command <<{}>>", command)),
+    };
+    Ok(Some(step))
+}
+
+fn parse_condition(condition: &str) -> Result<FlagCondition, String> {
+    let words = split_words(condition)?;
+    parse_condition_words(&words.iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+fn parse_condition_words(words: &[&str]) -> Result<FlagCondition, String> {
+    if words.is_empty() {
+        return Err("missing a condition".to_string());
+    }
+    let joiners: [(&str, fn(Vec<FlagCondition>) -> FlagCondition); 2] = [
+        ("and", FlagCondition::All),
+        ("or", FlagCondition::Any),
+    ];
+    for (joiner, join) in joiners {
+        if words.contains(&joiner) {
+            let parts = words.split(|word| *word == joiner)
+                .map(parse_condition_words)
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(join(parts));
+        }
+    }
+    match words {
+        ["not", rest @ ..] => Ok(FlagCondition::not(parse_condition_words(rest)?)),
+        [flag] => Ok(FlagCondition::is_set(flag)),
+        [flag, "==", value] => Ok(FlagCondition::Equals(flag.to_string(), parse_value(value))),
+        [flag, ">=", value] => value.parse()
+            .map(|value| FlagCondition::at_least(flag, value))
+            .map_err(|_| format!("\"{}\" isn't a whole number", value)),
+        _ => Err(format!("can't understand the condition \"{}\"", words.join(" "))),
+    }
+}
+
+fn parse_value(value: &str) -> FlagValue {
+    match value {
+        "true" => FlagValue::Bool(true),
+        "false" => FlagValue::Bool(false),
+        _ => value.parse().map_or_else(|_| FlagValue::Text(value.to_string()), FlagValue::Number),
+    }
+}
+
+// Split on spaces, except inside "quotes" (which are dropped)
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').ok_or_else(|| "missing a closing quote".to_string())?;
+            words.push(quoted[..end].to_string());
+            rest = quoted[end + 1..].trim_start();
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            words.push(rest[..end].to_string());
+            rest = rest[end..].trim_start();
+        }
+    }
+    Ok(words)
+}
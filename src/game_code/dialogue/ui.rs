@@ -0,0 +1,128 @@
+// :: Dialogue box ::
+// The text box along the bottom of the screen: the speaker's portrait on
+// the left, and their name over the line on the right, with any choices
+// listed under it. It's spawned once, hidden, and shown whenever the
+// DialogueRunner has something to show.
+use bevy::prelude::*;
+
+use super::DialogueRunner;
+use crate::ui::UI_FONT;
+
+const FONT_SIZE: f32 = 20.0;
+const MARGIN: f32 = 16.0; // between the box and the edges of the window
+const PADDING: f32 = 12.0; // between the box's edges and what's in it
+const HEIGHT: f32 = 128.0;
+const PORTRAIT_SIZE: f32 = HEIGHT - PADDING * 2.0;
+const BOX_COLOR: Color = Color::rgba(0.05, 0.05, 0.1, 0.85);
+const SPEAKER_COLOR: Color = Color::rgb(1.0, 0.85, 0.4);
+const CHOICE_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+const SELECTED_COLOR: Color = Color::WHITE;
+
+#[derive(Component)]
+pub(super) struct DialogueBox;
+
+#[derive(Component)]
+pub(super) struct DialoguePortrait;
+
+// The speaker's name, then the line, then a section for each choice
+#[derive(Component)]
+pub(super) struct DialogueText;
+
+pub(super) fn spawn_dialogue_box(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load(UI_FONT);
+    commands.spawn((
+        DialogueBox,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(MARGIN),
+                    right: Val::Px(MARGIN),
+                    bottom: Val::Px(MARGIN),
+                    ..default()
+                },
+                min_size: Size::new(Val::Auto, Val::Px(HEIGHT)),
+                padding: UiRect::all(Val::Px(PADDING)),
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            background_color: BOX_COLOR.into(),
+            visibility: Visibility { is_visible: false },
+            z_index: ZIndex::Global(i32::MAX - 1), // over other UI, screen fades included, so it can be read on black
+            ..default()
+        },
+    )).with_children(|node| {
+        node.spawn((
+            DialoguePortrait,
+            ImageBundle {
+                style: Style {
+                    size: Size::new(Val::Px(PORTRAIT_SIZE), Val::Px(PORTRAIT_SIZE)),
+                    margin: UiRect { right: Val::Px(PADDING), ..default() },
+                    flex_shrink: 0.0,
+                    display: Display::None,
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+        node.spawn((
+            DialogueText,
+            TextBundle::from_sections([
+                TextSection::new("", TextStyle { font: font.clone(), font_size: FONT_SIZE, color: SPEAKER_COLOR }),
+                TextSection::new("", TextStyle { font, font_size: FONT_SIZE, color: Color::WHITE }),
+            ]).with_style(Style {
+                // Wrap at the edge of the box
+                flex_shrink: 1.0,
+                max_size: Size::new(Val::Percent(100.0), Val::Undefined),
+                ..default()
+            }),
+        ));
+    });
+}
+
+pub(super) fn update_dialogue_box(
+    runner: Res<DialogueRunner>,
+    mut boxes: Query<&mut Visibility, With<DialogueBox>>,
+    mut portraits: Query<(&mut Style, &mut UiImage), With<DialoguePortrait>>,
+    mut texts: Query<&mut Text, With<DialogueText>>,
+) {
+    if !runner.is_changed() {
+        return;
+    }
+    let line = runner.line();
+    let choices = runner.choices();
+    for mut visibility in &mut boxes {
+        visibility.is_visible = line.is_some() || !choices.is_empty();
+    }
+    for (mut style, mut image) in &mut portraits {
+        match line.and_then(|line| line.portrait.as_ref()) {
+            Some(portrait) => {
+                style.display = Display::Flex;
+                image.0 = portrait.clone();
+            },
+            None => style.display = Display::None,
+        }
+    }
+    for mut text in &mut texts {
+        let speaker = line.and_then(|line| line.speaker.as_ref());
+        text.sections[0].value = speaker.map_or(String::new(), |speaker| format!("{}\n", speaker));
+        text.sections[1].value = runner.visible_text().to_string();
+
+        // Choices go under the line, with an arrow by the selected one
+        let style = text.sections[1].style.clone();
+        text.sections.truncate(2);
+        for (index, choice) in choices.iter().enumerate() {
+            let selected = index == runner.selected();
+            let gap = match (index, line) {
+                (0, Some(_)) => "\n\n",
+                (0, None) => "",
+                _ => "\n",
+            };
+            let arrow = if selected { "> " } else { "  " };
+            text.sections.push(TextSection::new(format!("{}{}{}", gap, arrow, choice.text), TextStyle {
+                color: if selected { SELECTED_COLOR } else { CHOICE_COLOR },
+                ..style.clone()
+            }));
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// :: Facing direction ::
+// The eight directions a character can face, from Part 1.
+use bevy::prelude::*;
+use serde::Deserialize;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+pub enum Direction {
+    N, NE, E, SE, S, SW, W, NW,
+}
+impl Direction {
+    // Every direction, clockwise starting from north
+    pub const ALL: [Direction; 8] = [
+        Direction::N, Direction::NE, Direction::E, Direction::SE,
+        Direction::S, Direction::SW, Direction::W, Direction::NW,
+    ];
+
+    // The closest of the eight directions to a (nonzero) vector
+    pub fn from_vec2(v: Vec2) -> Option<Self> {
+        if v.length_squared() < f32::EPSILON {
+            return None;
+        }
+        // Angle clockwise from north, split into eight 45 degree slices
+        let angle = v.x.atan2(v.y).to_degrees().rem_euclid(360.0);
+        let slice = ((angle + 22.5) / 45.0) as usize % 8;
+        Some(Self::ALL[slice])
+    }
+
+    // A unit vector pointing in this direction
+    pub fn to_vec2(&self) -> Vec2 {
+        const DIAG: f32 = std::f32::consts::FRAC_1_SQRT_2;
+        match self {
+            Direction::N => Vec2::new(0.0, 1.0),
+            Direction::NE => Vec2::new(DIAG, DIAG),
+            Direction::E => Vec2::new(1.0, 0.0),
+            Direction::SE => Vec2::new(DIAG, -DIAG),
+            Direction::S => Vec2::new(0.0, -1.0),
+            Direction::SW => Vec2::new(-DIAG, -DIAG),
+            Direction::W => Vec2::new(-1.0, 0.0),
+            Direction::NW => Vec2::new(-DIAG, DIAG),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// :: Enemy deaths ::
+// When an Enemy's hp reaches 0 (see combat/mod.rs) it becomes Dying: it
+// stops thinking and moving, can't be bumped into or hit any more, drops
+// its loot, and is despawned once its death animation has played:
+//
+//     commands.spawn((
+//         Enemy, Stats::new(3, 1, 0),
+//         LootDrop(asset_server.load("loot/slime.loot.ron")),
+//         DeathAnimation(SlimeAnim::Die),
+//         ..
+//     ));
+//
+// The animation is played Once, and should have a high enough priority
+// that nothing else (e.g. a DirectionalAnimator) cuts it short. Add
+// `EnemyDeathPlugin::<State>::default()` for each animation state type
+// that death animations are played with. Enemies without a
+// DeathAnimation are despawned straight away.
+//
+// Items are scattered around where the enemy died as Pickups (on the
+// CurrentMap, so they're gone once the player warps away), and money goes
+// straight to whoever killed it, if they have an Inventory.
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use super::{Enemy, EnemyAlert};
+use crate::{
+    ai::BehaviorTree,
+    animation::{AnimState, AnimationFinished, AnimationSystem, SpritesheetAnimator},
+    chest::LootTable,
+    collision::{Collider, Separation, TriggerSensor},
+    combat::{Died, Hurtbox},
+    inventory::Inventory,
+    movement::{MoveIntent, MovePath},
+    npc::{Follower, Patrol, Schedule, Wander},
+    perception::{Hearing, PerceivedTarget, Vision},
+    pickup::Pickup,
+    warp::{map_local, CurrentMap},
+};
+
+const DROP_SCATTER: f32 = 10.0; // how far from the body drops can land, in pixels
+const DEATH_TIMEOUT: f32 = 5.0; // seconds, in case a death animation never finishes
+
+// The loot table rolled when this enemy dies
+#[derive(Component, Clone, Debug)]
+pub struct LootDrop(pub Handle<LootTable>);
+
+// The state to play when this enemy dies
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DeathAnimation<S: AnimState>(pub S);
+
+// On its way out. It's despawned when its timer runs out, which is
+// straight away unless a death animation is playing.
+#[derive(Component, Deref, DerefMut)]
+pub struct Dying(pub Timer);
+impl Dying {
+    pub fn new(seconds: f32) -> Self {
+        Self(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+}
+
+pub struct EnemyDeathPlugin<S: AnimState>(PhantomData<S>);
+impl<S: AnimState> Default for EnemyDeathPlugin<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: AnimState> Plugin for EnemyDeathPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.add_system(play_death_animations::<S>.before(despawn_dying).before(AnimationSystem))
+            .add_system(finish_death_animations::<S>.after(AnimationSystem));
+    }
+}
+
+pub(super) fn start_dying(
+    mut commands: Commands,
+    mut died: EventReader<Died>,
+    loot_tables: Res<Assets<LootTable>>,
+    mut enemies: Query<(&GlobalTransform, Option<&LootDrop>, Option<&mut MoveIntent>), (With<Enemy>, Without<Dying>)>,
+    mut inventories: Query<&mut Inventory>,
+    maps: Query<(Entity, &GlobalTransform), With<CurrentMap>>,
+) {
+    let mut rng = rand::thread_rng();
+    let map = maps.get_single().ok();
+    for event in died.iter() {
+        let (transform, loot_drop, intent) = match enemies.get_mut(event.entity) {
+            Ok(enemy) => enemy,
+            Err(_) => continue,
+        };
+        commands.entity(event.entity)
+            .insert(Dying::new(0.0))
+            .remove::<(Collider, Hurtbox, Separation, TriggerSensor)>()
+            .remove::<(BehaviorTree, EnemyAlert, MovePath, Vision, Hearing, PerceivedTarget)>()
+            .remove::<(Wander, Patrol, Schedule, Follower)>();
+        if let Some(mut intent) = intent {
+            intent.0 = Vec2::ZERO;
+        }
+
+        let table = match loot_drop.map(|drop| loot_tables.get(&drop.0)) {
+            Some(Some(table)) => table,
+            Some(None) => {
+                warn!("An enemy died before its loot table loaded, so it dropped nothing");
+                continue;
+            },
+            None => continue,
+        };
+        let loot = table.roll(&mut rng);
+        if let Some(mut inventory) = event.killer.and_then(|killer| inventories.get_mut(killer).ok()) {
+            inventory.add_currency(loot.money);
+        }
+        let position = transform.translation();
+        let position = map.map_or(position, |(_, map)| map_local(map, position));
+        for stack in &loot.items {
+            let scatter = Vec2::new(
+                rng.gen_range(-DROP_SCATTER..=DROP_SCATTER),
+                rng.gen_range(-DROP_SCATTER..=DROP_SCATTER),
+            );
+            let pickup = commands.spawn((
+                Pickup::new(&stack.item, stack.count),
+                SpatialBundle::from_transform(Transform::from_translation(position + scatter.extend(0.0))),
+            )).id();
+            if let Some((map, _)) = map {
+                commands.entity(map).add_child(pickup);
+            }
+        }
+    }
+}
+
+fn play_death_animations<S: AnimState>(
+    mut dying: Query<(Entity, &mut Dying, &DeathAnimation<S>, &mut SpritesheetAnimator<S>), Added<Dying>>,
+) {
+    for (entity, mut timer, death, mut animator) in &mut dying {
+        match animator.force_state(death.0.clone(), None) {
+            Ok(()) => *timer = Dying::new(DEATH_TIMEOUT),
+            Err(error) => warn!("Couldn't play the death animation of {:?}: {}", entity, error),
+        }
+    }
+}
+
+fn finish_death_animations<S: AnimState>(
+    mut commands: Commands,
+    mut finished: EventReader<AnimationFinished<S>>,
+    dying: Query<&DeathAnimation<S>, With<Dying>>,
+) {
+    for event in finished.iter() {
+        if matches!(dying.get(event.entity), Ok(death) if death.0 == event.state) {
+            commands.entity(event.entity).despawn_recursive();
+        }
+    }
+}
+
+pub(super) fn despawn_dying(mut commands: Commands, time: Res<Time>, mut dying: Query<(Entity, &mut Dying)>) {
+    for (entity, mut timer) in &mut dying {
+        if timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
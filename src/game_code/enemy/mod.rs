@@ -0,0 +1,131 @@
+// :: Enemies ::
+// How alert an enemy is, from what it perceives (see perception.rs):
+//
+//   - Idle: hasn't noticed anything, and goes about its business (e.g.,
+//     a Wander)
+//   - Suspicious: heard something, or caught a glimpse; it goes to look
+//     where that was, and gives up after a while if nothing turns up.
+//     Keeping a target in sight for long enough turns this into a chase.
+//   - Chasing: heads for where the target was last seen, until it's been
+//     out of sight for a while, then goes back to being suspicious
+//
+//     commands.spawn((Enemy, EnemyAlert::default(), Vision::new(96.0, 90.0), Hearing::default(), ..));
+//
+// An EnemyAlertChanged event is sent on every change, e.g. to pop up a
+// "?" or "!" over the enemy's head.
+//
+// Enemies with Aggro pick who to go after (see targeting.rs). Enemies
+// that are killed die, drop their loot and go away (see death.rs).
+use bevy::prelude::*;
+
+use crate::{
+    ai::BehaviorTreeSystem,
+    combat::DamageSystem,
+    movement::{follow_move_paths, MovePath},
+    perception::{PerceivedTarget, PerceptionSystem},
+};
+
+mod death;
+mod targeting;
+
+pub use death::{DeathAnimation, Dying, EnemyDeathPlugin, LootDrop};
+pub use targeting::{Aggro, CurrentTarget, Taunt, TargetChanged, TargetingSystem};
+
+const NOTICE_TIME: f32 = 0.75; // seconds a target must stay in sight to start a chase
+const SEARCH_TIME: f32 = 4.0; // seconds to look around before giving up
+const LOSE_TIME: f32 = 2.0; // seconds out of sight before a chase is given up
+
+#[derive(Component)]
+pub struct Enemy;
+
+#[derive(Component, Clone, Copy, PartialEq, Debug, Default)]
+pub enum EnemyAlert {
+    #[default]
+    Idle,
+    Suspicious {
+        remaining: f32, // seconds left before giving up
+        noticing: f32, // seconds the target's been in sight for
+    },
+    Chasing,
+}
+impl EnemyAlert {
+    fn suspicious() -> Self {
+        EnemyAlert::Suspicious { remaining: SEARCH_TIME, noticing: 0.0 }
+    }
+    // Whether it's the same kind of alertness, ignoring timers
+    fn same_kind(&self, other: &EnemyAlert) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+pub struct EnemyAlertChanged {
+    pub entity: Entity,
+    pub from: EnemyAlert,
+    pub to: EnemyAlert,
+}
+
+pub struct EnemyPlugin;
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnemyAlertChanged>()
+            .add_event::<TargetChanged>()
+            .add_system(update_alertness.after(PerceptionSystem).before(follow_move_paths))
+            .add_system(death::start_dying.after(DamageSystem))
+            .add_system(death::despawn_dying)
+            .add_system(targeting::add_current_targets)
+            .add_system(targeting::build_threat.after(DamageSystem))
+            .add_system(targeting::decay_threat.after(targeting::build_threat))
+            .add_system(targeting::select_targets
+                .label(TargetingSystem)
+                .after(targeting::decay_threat)
+                .before(BehaviorTreeSystem));
+    }
+}
+
+fn update_alertness(
+    time: Res<Time>,
+    mut events: EventWriter<EnemyAlertChanged>,
+    mut query: Query<(Entity, &mut EnemyAlert, &mut PerceivedTarget, Option<&mut MovePath>), With<Enemy>>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut alert, mut perceived, path) in &mut query {
+        let noticed = perceived.sense.is_some();
+        let next = match *alert {
+            EnemyAlert::Idle if noticed => EnemyAlert::suspicious(),
+            EnemyAlert::Idle => EnemyAlert::Idle,
+            EnemyAlert::Suspicious { noticing, .. } if perceived.is_seen() && noticing + dt >= NOTICE_TIME => {
+                EnemyAlert::Chasing
+            },
+            EnemyAlert::Suspicious { noticing, .. } if noticed => EnemyAlert::Suspicious {
+                remaining: SEARCH_TIME,
+                noticing: if perceived.is_seen() { noticing + dt } else { 0.0 },
+            },
+            EnemyAlert::Suspicious { remaining, .. } if remaining > dt => {
+                EnemyAlert::Suspicious { remaining: remaining - dt, noticing: 0.0 }
+            },
+            EnemyAlert::Suspicious { .. } => EnemyAlert::Idle,
+            EnemyAlert::Chasing if perceived.time_since > LOSE_TIME => EnemyAlert::suspicious(),
+            EnemyAlert::Chasing => EnemyAlert::Chasing,
+        };
+        if next != *alert {
+            if !next.same_kind(&alert) {
+                events.send(EnemyAlertChanged { entity, from: *alert, to: next });
+            }
+            *alert = next;
+        }
+
+        // Go and look, or give chase
+        if let Some(mut path) = path {
+            match (next, perceived.last_known) {
+                (EnemyAlert::Idle, _) => {
+                    if perceived.last_known.is_some() {
+                        perceived.forget();
+                        path.clear();
+                    }
+                },
+                (_, Some(target)) if noticed && path.waypoints.back() != Some(&target) => path.go_to(target),
+                _ => {},
+            }
+        }
+    }
+}
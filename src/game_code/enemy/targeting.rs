@@ -0,0 +1,133 @@
+// :: Targeting ::
+// Who an enemy goes after, when there's more than one to choose from (the
+// player, a follower, a decoy). Enemies with Aggro score everything hostile
+// to them (see combat/hitbox.rs) that has Stats and is within `range`, and
+// keep the best one in their CurrentTarget:
+//
+//   - closer is better
+//   - whoever's been hurting it builds up threat, which dies away over time
+//   - a Taunt adds its strength, to draw enemies away from everyone else
+//
+//     commands.spawn((Enemy, Aggro::new(128.0), ..));
+//     commands.spawn((Decoy, Stats::new(5, 0, 0), Taunt(50.0), ..));
+//
+// An enemy only switches when the new target beats the current one by a
+// margin, so it doesn't dither between two who are about as good. Behavior
+// trees chase and face it with `MoveTo::current()` and
+// `FaceTarget::current()` (see ai/leaves.rs), and a TargetChanged event is
+// sent whenever it changes.
+use bevy::{prelude::*, utils::HashMap};
+
+use super::Enemy;
+use crate::combat::{are_hostile, DamageTaken, Stats};
+
+const DISTANCE_SCORE: f32 = 10.0; // for a target right on top of it, down to 0.0 at `range`
+const THREAT_PER_DAMAGE: f32 = 5.0;
+const THREAT_DECAY: f32 = 2.0; // per second
+const SWITCH_MARGIN: f32 = 5.0; // how much better a new target has to be
+
+#[derive(Component, Clone, Debug)]
+pub struct Aggro {
+    pub range: f32, // in pixels
+    threat: HashMap<Entity, f32>, // from who's been hurting it
+}
+impl Aggro {
+    pub fn new(range: f32) -> Self {
+        Self { range, threat: HashMap::default() }
+    }
+    pub fn threat(&self, entity: Entity) -> f32 {
+        self.threat.get(&entity).copied().unwrap_or(0.0)
+    }
+    pub fn add_threat(&mut self, entity: Entity, amount: f32) {
+        *self.threat.entry(entity).or_insert(0.0) += amount;
+    }
+}
+
+// Makes enemies more likely to pick this as their target
+#[derive(Component, Clone, Copy, Debug, Deref, DerefMut)]
+pub struct Taunt(pub f32);
+
+// Who an enemy's going after. Added automatically to entities with Aggro.
+#[derive(Component, Clone, Copy, Default, Debug, Deref, DerefMut)]
+pub struct CurrentTarget(pub Option<Entity>);
+
+pub struct TargetChanged {
+    pub entity: Entity,
+    pub from: Option<Entity>,
+    pub to: Option<Entity>,
+}
+
+// Systems that read CurrentTarget can run `.after(TargetingSystem)`
+#[derive(SystemLabel)]
+pub struct TargetingSystem;
+
+pub(super) fn add_current_targets(
+    mut commands: Commands,
+    query: Query<Entity, (With<Aggro>, Without<CurrentTarget>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(CurrentTarget::default());
+    }
+}
+
+// Whoever hurts an enemy gets its attention
+pub(super) fn build_threat(mut taken: EventReader<DamageTaken>, mut aggro: Query<&mut Aggro>) {
+    for event in taken.iter() {
+        if let (Ok(mut aggro), Some(source)) = (aggro.get_mut(event.target), event.source) {
+            aggro.add_threat(source, event.amount as f32 * THREAT_PER_DAMAGE);
+        }
+    }
+}
+
+pub(super) fn decay_threat(time: Res<Time>, mut aggro: Query<&mut Aggro>) {
+    let decay = THREAT_DECAY * time.delta_seconds();
+    for mut aggro in &mut aggro {
+        if aggro.threat.is_empty() {
+            continue;
+        }
+        for threat in aggro.threat.values_mut() {
+            *threat -= decay;
+        }
+        aggro.threat.retain(|_, threat| *threat > 0.0);
+    }
+}
+
+pub(super) fn select_targets(
+    mut changes: EventWriter<TargetChanged>,
+    mut enemies: Query<(Entity, &Aggro, &mut CurrentTarget, &GlobalTransform, Option<&Enemy>)>,
+    candidates: Query<(Entity, &Stats, &GlobalTransform, Option<&Taunt>, Option<&Enemy>)>,
+) {
+    for (entity, aggro, mut current, transform, is_enemy) in &mut enemies {
+        let position = transform.translation().truncate();
+        let score = |(target, stats, target_transform, taunt, target_is_enemy): (
+            Entity,
+            &Stats,
+            &GlobalTransform,
+            Option<&Taunt>,
+            Option<&Enemy>,
+        )| {
+            if target == entity || stats.is_dead() || !are_hostile(is_enemy.is_some(), target_is_enemy.is_some()) {
+                return None;
+            }
+            let distance = position.distance(target_transform.translation().truncate());
+            if distance > aggro.range {
+                return None;
+            }
+            let closeness = DISTANCE_SCORE * (1.0 - distance / aggro.range);
+            Some(closeness + aggro.threat(target) + taunt.map_or(0.0, |taunt| taunt.0))
+        };
+
+        let current_score = current.0.and_then(|target| candidates.get(target).ok()).and_then(score);
+        let best = candidates.iter()
+            .filter_map(|candidate| score(candidate).map(|score| (candidate.0, score)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        let next = match (current_score, best) {
+            (Some(current_score), Some((_, best_score))) if best_score < current_score + SWITCH_MARGIN => current.0,
+            (_, best) => best.map(|(target, _)| target),
+        };
+        if next != current.0 {
+            changes.send(TargetChanged { entity, from: current.0, to: next });
+            current.0 = next;
+        }
+    }
+}
@@ -0,0 +1,305 @@
+// :: Screen fades ::
+// A full-screen overlay, for covering the screen while things change
+// behind it: a warp to another map, a cutscene's change of scene, a game
+// over. Play a Fade on the ScreenFade; fades chained together with `then`
+// (or played while another's still going) run one after another:
+//
+//     screen_fade.play(fade_out(0.5).then(fade_in(0.5)));
+//     screen_fade.play(fade_out(1.0).with_style(FadeStyle::CircleWipe).with_color(Color::WHITE));
+//     screen_fade.play(fade_out(0.8).with_style(FadeStyle::Pixelate).then(fade_in(0.8).with_style(FadeStyle::Pixelate)));
+//
+// Each fade goes from however covered the screen is when it starts, over
+// its whole duration. A ScreenCovered event is sent whenever a fade out
+// finishes (the moment to swap things behind it), and FadeFinished once
+// there's nothing left to play.
+//
+// The overlay is a small image stretched over the screen, redrawn as it
+// fades; it's under dialogue boxes, so they can be read on black.
+// Pixelate also draws the world in bigger and bigger blocks (through the
+// PixelPerfect camera; see camera/pixel_perfect.rs).
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+
+use crate::camera::PixelPerfect;
+
+const MASK_WIDTH: u32 = 160; // the overlay's pixels across; how many down follows the window's shape
+const BLOCK_SIZE: u32 = 8; // for Dissolve, in overlay pixels
+const MAX_PIXELATION: u32 = 16; // for Pixelate: the world pixels across a block, once the screen's covered
+// The order Dissolve's blocks fill in (an ordered dither), so they're spread out evenly
+const DITHER: [[u32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FadeStyle {
+    #[default]
+    Fade, // the whole screen at once
+    CircleWipe, // a circle closing in on the middle of the screen (or opening out from it)
+    Dissolve, // block by block, scattered over the screen
+    Pixelate, // the world breaking up into bigger and bigger blocks, darkening as they grow
+}
+
+#[derive(Clone, Copy, Debug)]
+struct FadeStep {
+    covered: bool, // whether it ends with the screen covered, or clear
+    seconds: f32,
+    style: FadeStyle,
+    color: Color,
+}
+
+// One or more fades, played one after another
+#[derive(Clone, Debug)]
+pub struct Fade {
+    steps: Vec<FadeStep>,
+}
+impl Fade {
+    fn new(covered: bool, seconds: f32) -> Self {
+        let step = FadeStep { covered, seconds, style: FadeStyle::default(), color: Color::BLACK };
+        Self { steps: vec![step] }
+    }
+    pub fn then(mut self, next: Fade) -> Self {
+        self.steps.extend(next.steps);
+        self
+    }
+    // For the fades so far, not ones chained on after
+    pub fn with_style(mut self, style: FadeStyle) -> Self {
+        for step in &mut self.steps {
+            step.style = style;
+        }
+        self
+    }
+    pub fn with_color(mut self, color: Color) -> Self {
+        for step in &mut self.steps {
+            step.color = color;
+        }
+        self
+    }
+}
+
+// Cover the screen (to black, unless it says otherwise)
+pub fn fade_out(seconds: f32) -> Fade {
+    Fade::new(true, seconds)
+}
+
+// Uncover it again
+pub fn fade_in(seconds: f32) -> Fade {
+    Fade::new(false, seconds)
+}
+
+#[derive(Resource)]
+pub struct ScreenFade {
+    queue: VecDeque<FadeStep>,
+    playing: Option<PlayingFade>,
+    amount: f32, // how covered the screen is, from 0.0 (not at all) to 1.0
+    style: FadeStyle, // of the fade on the screen
+    color: Color,
+}
+struct PlayingFade {
+    step: FadeStep,
+    from: f32, // how covered the screen was when it started
+    elapsed: f32,
+}
+impl Default for ScreenFade {
+    fn default() -> Self {
+        Self {
+            queue: VecDeque::new(),
+            playing: None,
+            amount: 0.0,
+            style: FadeStyle::default(),
+            color: Color::BLACK,
+        }
+    }
+}
+impl ScreenFade {
+    // After any already playing
+    pub fn play(&mut self, fade: Fade) {
+        self.queue.extend(fade.steps);
+    }
+    pub fn is_fading(&self) -> bool {
+        self.playing.is_some() || !self.queue.is_empty()
+    }
+    // Whether the screen's covered, and staying that way
+    pub fn is_covered(&self) -> bool {
+        !self.is_fading() && self.amount >= 1.0
+    }
+    pub fn amount(&self) -> f32 {
+        self.amount
+    }
+    // Stop fading, and uncover the screen at once
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.playing = None;
+        self.amount = 0.0;
+    }
+}
+
+// Sent when a fade out finishes
+pub struct ScreenCovered;
+
+// Sent when the last fade playing finishes
+pub struct FadeFinished;
+
+// Systems that check the ScreenFade can run `.after(FadeSystem)`
+#[derive(SystemLabel)]
+pub struct FadeSystem;
+
+#[derive(Component)]
+struct FadeOverlay;
+
+pub struct FadePlugin;
+impl Plugin for FadePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenFade>()
+            .add_event::<ScreenCovered>()
+            .add_event::<FadeFinished>()
+            .add_startup_system(spawn_overlay)
+            .add_system(update_screen_fade.label(FadeSystem))
+            .add_system(draw_overlay.after(FadeSystem));
+    }
+}
+
+fn spawn_overlay(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut mask = Image::new_fill(
+        Extent3d { width: MASK_WIDTH, height: MASK_WIDTH, ..default() }, // resized to the window when drawn
+        TextureDimension::D2,
+        &[255, 255, 255, 0],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    mask.sampler_descriptor = ImageSampler::nearest();
+    commands.spawn((
+        FadeOverlay,
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..default()
+            },
+            image: images.add(mask).into(),
+            background_color: Color::BLACK.into(),
+            visibility: Visibility { is_visible: false },
+            z_index: ZIndex::Global(i32::MAX - 2), // under dialogue boxes, so they can be read on black
+            ..default()
+        },
+    ));
+}
+
+fn update_screen_fade(
+    time: Res<Time>,
+    mut screen_fade: ResMut<ScreenFade>,
+    mut covered: EventWriter<ScreenCovered>,
+    mut finished: EventWriter<FadeFinished>,
+) {
+    if !screen_fade.is_fading() {
+        return;
+    }
+    let screen_fade = &mut *screen_fade;
+    if screen_fade.playing.is_none() {
+        if let Some(step) = screen_fade.queue.pop_front() {
+            screen_fade.style = step.style;
+            screen_fade.color = step.color;
+            screen_fade.playing = Some(PlayingFade { step, from: screen_fade.amount, elapsed: 0.0 });
+        }
+    }
+    let done = match &mut screen_fade.playing {
+        Some(playing) => {
+            playing.elapsed += time.delta_seconds();
+            let to = if playing.step.covered { 1.0 } else { 0.0 };
+            let t = if playing.step.seconds > 0.0 { (playing.elapsed / playing.step.seconds).min(1.0) } else { 1.0 };
+            screen_fade.amount = playing.from + (to - playing.from) * t;
+            t >= 1.0
+        },
+        None => return,
+    };
+    if !done {
+        return;
+    }
+    if let Some(playing) = screen_fade.playing.take() {
+        if playing.step.covered {
+            covered.send(ScreenCovered);
+        }
+    }
+    if screen_fade.queue.is_empty() {
+        finished.send(FadeFinished);
+    }
+}
+
+fn draw_overlay(
+    screen_fade: Res<ScreenFade>,
+    windows: Res<Windows>,
+    mut images: ResMut<Assets<Image>>,
+    pixel_perfect: Option<ResMut<PixelPerfect>>,
+    mut overlays: Query<(&UiImage, &mut BackgroundColor, &mut Visibility), With<FadeOverlay>>,
+) {
+    if let Some(mut pixel_perfect) = pixel_perfect {
+        let block = match screen_fade.style {
+            FadeStyle::Pixelate => 1 + (screen_fade.amount * (MAX_PIXELATION - 1) as f32).round() as u32,
+            _ => 1,
+        };
+        if pixel_perfect.pixelation() != block {
+            pixel_perfect.set_pixelation(block);
+        }
+    }
+    let aspect = windows.get_primary().map_or(1.0, |window| window.width() / window.height().max(1.0));
+    let height = ((MASK_WIDTH as f32 / aspect).round() as u32).max(1);
+    for (image, mut color, mut visibility) in &mut overlays {
+        let visible = screen_fade.amount > 0.0;
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+        if !visible {
+            continue;
+        }
+        let resized = match images.get(&image.0) {
+            Some(mask) => mask.size() != Vec2::new(MASK_WIDTH as f32, height as f32),
+            None => continue,
+        };
+        if !resized && !screen_fade.is_changed() {
+            continue;
+        }
+        if let Some(mask) = images.get_mut(&image.0) {
+            if resized {
+                mask.resize(Extent3d { width: MASK_WIDTH, height, ..default() });
+            }
+            draw_mask(mask, screen_fade.style, screen_fade.amount, aspect);
+        }
+        if color.0 != screen_fade.color {
+            color.0 = screen_fade.color;
+        }
+    }
+}
+
+// Set each pixel's alpha to how covered that part of the screen is
+fn draw_mask(mask: &mut Image, style: FadeStyle, amount: f32, aspect: f32) {
+    let size = mask.size();
+    let (width, height) = (size.x as u32, size.y as u32);
+    // For CircleWipe: from just reaching the corners, down to nothing.
+    // Measured in screen heights, so it's round whatever the window's shape.
+    let radius = Vec2::new(aspect, 1.0).length() / 2.0 * (1.0 - amount);
+    for y in 0..height {
+        for x in 0..width {
+            let covered = match style {
+                FadeStyle::Fade => amount,
+                FadeStyle::CircleWipe => {
+                    let from_middle = Vec2::new(
+                        ((x as f32 + 0.5) / width as f32 - 0.5) * aspect,
+                        (y as f32 + 0.5) / height as f32 - 0.5,
+                    );
+                    if from_middle.length() >= radius { 1.0 } else { 0.0 }
+                },
+                FadeStyle::Dissolve => {
+                    let (block_x, block_y) = ((x / BLOCK_SIZE) % 4, (y / BLOCK_SIZE) % 4);
+                    let threshold = (DITHER[block_y as usize][block_x as usize] as f32 + 0.5) / 16.0;
+                    if amount >= threshold { 1.0 } else { 0.0 }
+                },
+                // Mostly once the blocks are big, so they can be seen
+                FadeStyle::Pixelate => amount * amount,
+            };
+            mask.data[((y * width + x) * 4 + 3) as usize] = (covered * 255.0).round() as u8;
+        }
+    }
+}
@@ -0,0 +1,179 @@
+// :: Game flags ::
+// Named values that remember what's happened in the game: whether a door
+// has been unlocked, how many times the player has talked to someone,
+// which answer they gave. Anything can set or check them, e.g. dialogue,
+// to say different things once something has happened:
+//
+//     flags.set("met_mira", true);
+//     flags.set("apples", flags.number("apples") + 1);
+//     if FlagCondition::at_least("apples", 3).check(&flags) { .. }
+//
+// Flags that have never been set are false (or 0). A TriggerZone with a
+// FlagTrigger sets one when the player walks into it.
+//
+// The flags are part of what gets saved: GameFlags is (de)serializable,
+// written as a map from names to plain values, e.g. in RON
+//
+//     {"met_mira": true, "apples": 3, "answer": "the pond"}
+//
+// and can be saved to and loaded from a file on its own with save/load.
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{collision::TriggerEnter, player::Player};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FlagValue {
+    Bool(bool),
+    Number(i32),
+    Text(String),
+}
+impl FlagValue {
+    // Whether the value counts as set: true, non-zero or non-empty
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            FlagValue::Bool(value) => *value,
+            FlagValue::Number(value) => *value != 0,
+            FlagValue::Text(value) => !value.is_empty(),
+        }
+    }
+}
+impl From<bool> for FlagValue {
+    fn from(value: bool) -> Self {
+        FlagValue::Bool(value)
+    }
+}
+impl From<i32> for FlagValue {
+    fn from(value: i32) -> Self {
+        FlagValue::Number(value)
+    }
+}
+impl From<&str> for FlagValue {
+    fn from(value: &str) -> Self {
+        FlagValue::Text(value.to_string())
+    }
+}
+
+#[derive(Resource, Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GameFlags {
+    values: BTreeMap<String, FlagValue>, // sorted, so saves don't shuffle about
+}
+impl GameFlags {
+    pub fn get(&self, name: &str) -> Option<&FlagValue> {
+        self.values.get(name)
+    }
+    pub fn set(&mut self, name: &str, value: impl Into<FlagValue>) {
+        self.values.insert(name.to_string(), value.into());
+    }
+    pub fn clear(&mut self, name: &str) {
+        self.values.remove(name);
+    }
+    // Whether the flag is set to something truthy
+    pub fn is_set(&self, name: &str) -> bool {
+        self.get(name).map_or(false, FlagValue::is_truthy)
+    }
+    // The flag's value as a number, or 0 if it isn't one
+    pub fn number(&self, name: &str) -> i32 {
+        match self.get(name) {
+            Some(FlagValue::Number(value)) => *value,
+            Some(FlagValue::Bool(value)) => *value as i32,
+            _ => 0,
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FlagValue)> {
+        self.values.iter().map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let text = fs::read_to_string(path)?;
+        ron::from_str(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let text = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+}
+
+// A check against the GameFlags, e.g. for whether a line of dialogue
+// should be said
+#[derive(Clone, PartialEq, Debug)]
+pub enum FlagCondition {
+    IsSet(String),
+    Equals(String, FlagValue),
+    AtLeast(String, i32),
+    Not(Box<FlagCondition>),
+    All(Vec<FlagCondition>),
+    Any(Vec<FlagCondition>),
+}
+impl FlagCondition {
+    pub fn is_set(name: &str) -> Self {
+        FlagCondition::IsSet(name.to_string())
+    }
+    pub fn equals(name: &str, value: impl Into<FlagValue>) -> Self {
+        FlagCondition::Equals(name.to_string(), value.into())
+    }
+    pub fn at_least(name: &str, value: i32) -> Self {
+        FlagCondition::AtLeast(name.to_string(), value)
+    }
+    pub fn not(condition: FlagCondition) -> Self {
+        FlagCondition::Not(Box::new(condition))
+    }
+
+    pub fn check(&self, flags: &GameFlags) -> bool {
+        match self {
+            FlagCondition::IsSet(name) => flags.is_set(name),
+            FlagCondition::Equals(name, value) => match flags.get(name) {
+                Some(flag) => flag == value,
+                None => !value.is_truthy(), // unset flags are false (or 0)
+            },
+            FlagCondition::AtLeast(name, value) => flags.number(name) >= *value,
+            FlagCondition::Not(condition) => !condition.check(flags),
+            FlagCondition::All(conditions) => conditions.iter().all(|condition| condition.check(flags)),
+            FlagCondition::Any(conditions) => conditions.iter().any(|condition| condition.check(flags)),
+        }
+    }
+}
+
+// Sets a flag when the player walks into this entity's TriggerZone, e.g.
+// to remember that they've found somewhere
+#[derive(Component, Clone, Debug)]
+pub struct FlagTrigger {
+    pub name: String,
+    pub value: FlagValue,
+}
+impl FlagTrigger {
+    pub fn new(name: &str, value: impl Into<FlagValue>) -> Self {
+        Self { name: name.to_string(), value: value.into() }
+    }
+}
+
+pub struct FlagsPlugin;
+impl Plugin for FlagsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameFlags>()
+            .add_system(set_triggered_flags);
+    }
+}
+
+fn set_triggered_flags(
+    mut flags: ResMut<GameFlags>,
+    mut trigger_enters: EventReader<TriggerEnter>,
+    triggers: Query<&FlagTrigger>,
+    players: Query<(), With<Player>>,
+) {
+    for enter in trigger_enters.iter() {
+        if !players.contains(enter.sensor) {
+            continue;
+        }
+        if let Ok(trigger) = triggers.get(enter.zone) {
+            if flags.get(&trigger.name) != Some(&trigger.value) {
+                flags.set(&trigger.name, trigger.value.clone());
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// :: Footsteps ::
+// Works out what each moving entity is standing on (its GroundTerrain),
+// from the Terrain of the map tiles under its feet. Whenever an animation
+// frame tagged "footstep" is shown, a Footstep event is sent with that
+// terrain, for sounds, dust puffs, splashes and so on:
+//
+//     for step in footsteps.iter() {
+//         if step.terrain == Some(Terrain::Water) { spawn_splash(step.position); }
+//     }
+//
+// Sounds for each terrain can be set in FootstepSounds, which plays them.
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    animation::{AnimationFrameEvent, AnimationSystem},
+    collision::{feet, Collider},
+    movement::{MovementSystem, Position},
+    settings::Settings,
+    tilemap::{Terrain, Tilemap},
+};
+
+const FOOTSTEP_TAG: &str = "footstep";
+
+// What an entity is standing on, if the map says. Added automatically to
+// anything with a Position.
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GroundTerrain(pub Option<Terrain>);
+
+pub struct Footstep {
+    pub entity: Entity,
+    pub terrain: Option<Terrain>,
+    pub position: Vec2, // where the foot came down
+}
+
+// The sound to play for footsteps on each terrain, and for footsteps on
+// tiles without one
+#[derive(Resource, Default)]
+pub struct FootstepSounds {
+    pub sounds: HashMap<Terrain, Handle<AudioSource>>,
+    pub default: Option<Handle<AudioSource>>,
+    pub volume: f32,
+}
+impl FootstepSounds {
+    pub fn with_sound(mut self, terrain: Terrain, sound: Handle<AudioSource>) -> Self {
+        self.sounds.insert(terrain, sound);
+        self
+    }
+    pub fn with_default(mut self, sound: Handle<AudioSource>) -> Self {
+        self.default = Some(sound);
+        self
+    }
+}
+
+pub struct FootstepPlugin;
+impl Plugin for FootstepPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Footstep>()
+            .insert_resource(FootstepSounds { volume: 0.5, ..default() })
+            .add_system(add_ground_terrain)
+            .add_system(find_ground_terrain.after(MovementSystem))
+            .add_system(send_footsteps.after(find_ground_terrain).after(AnimationSystem))
+            .add_system(play_footstep_sounds.after(send_footsteps));
+    }
+}
+
+fn add_ground_terrain(
+    mut commands: Commands,
+    query: Query<Entity, (With<Position>, Without<GroundTerrain>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(GroundTerrain::default());
+    }
+}
+
+pub(crate) fn find_ground_terrain(
+    tilemaps: Query<(&Tilemap, &GlobalTransform)>,
+    mut query: Query<(&GlobalTransform, Option<&Collider>, &mut GroundTerrain)>,
+) {
+    for (transform, collider, mut ground) in &mut query {
+        let feet = feet(collider, transform.translation().truncate());
+        let terrain = tilemaps.iter()
+            .find_map(|(tilemap, map_transform)| tilemap.terrain_at(feet - map_transform.translation().truncate()));
+        if ground.0 != terrain {
+            ground.0 = terrain;
+        }
+    }
+}
+
+fn send_footsteps(
+    mut frame_events: EventReader<AnimationFrameEvent>,
+    mut footsteps: EventWriter<Footstep>,
+    query: Query<(&GlobalTransform, Option<&Collider>, Option<&GroundTerrain>)>,
+) {
+    for event in frame_events.iter().filter(|event| event.tag == FOOTSTEP_TAG) {
+        if let Ok((transform, collider, ground)) = query.get(event.entity) {
+            footsteps.send(Footstep {
+                entity: event.entity,
+                terrain: ground.and_then(|ground| ground.0),
+                position: feet(collider, transform.translation().truncate()),
+            });
+        }
+    }
+}
+
+fn play_footstep_sounds(
+    audio: Res<Audio>,
+    sounds: Res<FootstepSounds>,
+    settings: Res<Settings>,
+    mut footsteps: EventReader<Footstep>,
+) {
+    for step in footsteps.iter() {
+        let sound = step.terrain.and_then(|terrain| sounds.sounds.get(&terrain)).or(sounds.default.as_ref());
+        if let Some(sound) = sound {
+            audio.play_with_settings(sound.clone(), PlaybackSettings::ONCE.with_volume(sounds.volume * settings.effects_volume()));
+        }
+    }
+}
@@ -0,0 +1,438 @@
+// :: Game over ::
+// What happens when the player's hp reaches 0 (see combat/mod.rs): they
+// lose control and become PlayerState::Dead, their death animation plays,
+// the screen fades out (see fade.rs), and the Game Over screen (see ui.rs)
+// offers Retry, Load and Title.
+//
+// Each brings the player back at the last Checkpoint they walked into,
+// at full health, and fades back in. Walking through a Warp counts as a
+// checkpoint too, where the player arrives; before either, it's where the
+// player started.
+//
+//     commands.spawn((
+//         Checkpoint,
+//         TriggerZone::new(Vec2::new(16.0, 16.0)),
+//         SpatialBundle::from_transform(Transform::from_xyz(80.0, 40.0, 0.0)),
+//     ));
+//
+// Each checkpoint keeps a copy of the game as it is then: the GameFlags,
+// the QuestLog, and the player's Inventory, Equipment and Stats. Retry
+// carries on with everything as it is, and Load puts it all back the way
+// it was at the checkpoint, undoing whatever happened since (so a chest
+// opened after it is closed again, and its loot is gone from the
+// inventory). Every comeback sends a PlayerRespawned (bosses reset their
+// fights on it).
+//
+// Title also sends ReturnToTitle, for whatever shows the title screen; the
+// player's brought back at the checkpoint first (as with Retry), so the
+// world's never left with a dead player in it.
+//
+// The death animation is a state played Once on the player, then held on
+// its last frame until they're back. Add
+// `GameOverAnimationPlugin::<State>::default()` for the player's state type:
+//
+//     commands.spawn((Player, PlayerDeathAnimation(PlayerAnim::Die), ..));
+//
+// Without one, the screen fades out after a short pause.
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use crate::{
+    animation::{AnimState, AnimationFinished, AnimationSystem, DirectionalAnimationSystem, SpritesheetAnimator},
+    camera::CameraFollow,
+    collision::TriggerEnter,
+    combat::{DamageSystem, Died, Stats, StatusEffects},
+    fade::{fade_in, fade_out, FadeSystem, ScreenFade},
+    flags::GameFlags,
+    input::{Action, Actions, InputSystem},
+    inventory::{Equipment, Inventory},
+    movement::{MoveIntent, MovePath, Position},
+    player::{Player, PlayerControlLock, PlayerState},
+    quest::QuestLog,
+    warp::WarpFinished,
+};
+
+mod ui;
+
+const CONTROL_LOCK: &str = "game over";
+const DEATH_PAUSE: f32 = 1.0; // seconds before the fade, for players without a death animation
+const DEATH_TIMEOUT: f32 = 5.0; // seconds, in case a death animation never finishes
+const FADE_OUT_TIME: f32 = 1.0;
+const FADE_IN_TIME: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameOverOption {
+    Retry,
+    Load,
+    Title,
+}
+impl GameOverOption {
+    pub const ALL: [GameOverOption; 3] = [GameOverOption::Retry, GameOverOption::Load, GameOverOption::Title];
+
+    fn label(&self) -> &'static str {
+        match self {
+            GameOverOption::Retry => "Retry",
+            GameOverOption::Load => "Load",
+            GameOverOption::Title => "Title",
+        }
+    }
+}
+
+// Where the player comes back when they walk into the entity's TriggerZone
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Checkpoint;
+
+// Where the player will come back, if they die
+#[derive(Resource, Default, Debug)]
+pub struct LastCheckpoint(pub Option<Vec2>);
+
+// The game as it was at the last checkpoint, for Load
+#[derive(Resource, Default)]
+struct CheckpointSave {
+    flags: GameFlags,
+    quests: QuestLog,
+    inventory: Option<Inventory>,
+    equipment: Option<Equipment>,
+    stats: Option<Stats>,
+}
+
+// The state to play when the player dies
+#[derive(Component, Clone, Copy, Debug)]
+pub struct PlayerDeathAnimation<S: AnimState>(pub S);
+
+// How far along the game over is
+#[derive(Resource, Default)]
+pub struct GameOver {
+    stage: GameOverStage,
+    cursor: usize,
+}
+#[derive(Default)]
+enum GameOverStage {
+    #[default]
+    None,
+    Dying { player: Entity, timer: Timer },
+    FadingOut { player: Entity },
+    Choosing { player: Entity }, // on the Game Over screen
+    FadingIn,
+}
+impl GameOver {
+    pub fn is_over(&self) -> bool {
+        !matches!(self.stage, GameOverStage::None)
+    }
+    // Whether the Game Over screen is up
+    pub fn is_choosing(&self) -> bool {
+        matches!(self.stage, GameOverStage::Choosing { .. })
+    }
+    // Cut the wait before the fade short, e.g. once the death animation's done
+    fn finish_dying(&mut self, player: Entity) {
+        if let GameOverStage::Dying { player: dying, timer } = &mut self.stage {
+            if *dying == player {
+                *timer = Timer::from_seconds(0.0, TimerMode::Once);
+            }
+        }
+    }
+}
+
+// Sent when the player chooses Title on the Game Over screen
+pub struct ReturnToTitle;
+
+pub struct PlayerRespawned {
+    pub player: Entity,
+    pub position: Vec2,
+}
+
+pub struct GameOverPlugin;
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameOver>()
+            .init_resource::<LastCheckpoint>()
+            .init_resource::<CheckpointSave>()
+            .add_event::<ReturnToTitle>()
+            .add_event::<PlayerRespawned>()
+            .add_startup_system(ui::spawn_game_over_screen)
+            .add_system(set_first_checkpoint)
+            .add_system(reach_checkpoints)
+            .add_system(start_game_over.after(DamageSystem))
+            .add_system(update_game_over.after(start_game_over).after(FadeSystem))
+            .add_system(choose_option.after(update_game_over).after(InputSystem))
+            .add_system(ui::show_game_over_screen.after(choose_option))
+            .add_system(ui::update_game_over_screen.after(choose_option));
+    }
+}
+
+pub struct GameOverAnimationPlugin<S: AnimState>(PhantomData<S>);
+impl<S: AnimState> Default for GameOverAnimationPlugin<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: AnimState> Plugin for GameOverAnimationPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.add_system(play_death_animations::<S>.after(start_game_over).before(AnimationSystem))
+            .add_system(hold_death_animations::<S>
+                .after(play_death_animations::<S>)
+                .after(DirectionalAnimationSystem)
+                .before(AnimationSystem))
+            .add_system(finish_death_animations::<S>.after(AnimationSystem).before(update_game_over));
+    }
+}
+
+// Where the player starts, until they reach a checkpoint
+fn set_first_checkpoint(
+    flags: Res<GameFlags>,
+    quests: Res<QuestLog>,
+    mut checkpoint: ResMut<LastCheckpoint>,
+    mut save: ResMut<CheckpointSave>,
+    players: Query<(&Transform, Option<&Inventory>, Option<&Equipment>, Option<&Stats>), Added<Player>>,
+) {
+    if checkpoint.0.is_some() {
+        return;
+    }
+    if let Some((transform, inventory, equipment, stats)) = players.iter().next() {
+        checkpoint.0 = Some(transform.translation.truncate());
+        *save = CheckpointSave::new(&flags, &quests, inventory, equipment, stats);
+    }
+}
+
+fn reach_checkpoints(
+    mut enters: EventReader<TriggerEnter>,
+    mut warps: EventReader<WarpFinished>,
+    flags: Res<GameFlags>,
+    quests: Res<QuestLog>,
+    mut checkpoint: ResMut<LastCheckpoint>,
+    mut save: ResMut<CheckpointSave>,
+    checkpoints: Query<&GlobalTransform, With<Checkpoint>>,
+    players: Query<(&Transform, Option<&Inventory>, Option<&Equipment>, Option<&Stats>), With<Player>>,
+) {
+    let mut reached = None;
+    for enter in enters.iter() {
+        if let (Ok(transform), Ok(player)) = (checkpoints.get(enter.zone), players.get(enter.sensor)) {
+            checkpoint.0 = Some(transform.translation().truncate());
+            reached = Some(player);
+        }
+    }
+    for warp in warps.iter() {
+        if let Ok(player) = players.get(warp.player) {
+            checkpoint.0 = Some(player.0.translation.truncate());
+            reached = Some(player);
+        }
+    }
+    if let Some((_, inventory, equipment, stats)) = reached {
+        *save = CheckpointSave::new(&flags, &quests, inventory, equipment, stats);
+    }
+}
+
+impl CheckpointSave {
+    fn new(
+        flags: &GameFlags,
+        quests: &QuestLog,
+        inventory: Option<&Inventory>,
+        equipment: Option<&Equipment>,
+        stats: Option<&Stats>,
+    ) -> Self {
+        Self {
+            flags: flags.clone(),
+            quests: quests.clone(),
+            inventory: inventory.cloned(),
+            equipment: equipment.cloned(),
+            stats: stats.copied(),
+        }
+    }
+}
+
+fn start_game_over(
+    mut died: EventReader<Died>,
+    mut game_over: ResMut<GameOver>,
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut players: Query<(&mut PlayerState, Option<&mut MoveIntent>, Option<&mut MovePath>), With<Player>>,
+) {
+    for event in died.iter() {
+        if game_over.is_over() {
+            continue;
+        }
+        let (mut state, intent, path) = match players.get_mut(event.entity) {
+            Ok(player) => player,
+            Err(_) => continue,
+        };
+        if let Err(err) = state.transition(PlayerState::Dead) {
+            warn!("{}", err);
+        }
+        if let Some(mut intent) = intent {
+            intent.0 = Vec2::ZERO;
+        }
+        if let Some(mut path) = path {
+            path.clear();
+        }
+        control_lock.lock(CONTROL_LOCK);
+        game_over.stage = GameOverStage::Dying {
+            player: event.entity,
+            timer: Timer::from_seconds(DEATH_PAUSE, TimerMode::Once),
+        };
+    }
+}
+
+fn update_game_over(
+    time: Res<Time>,
+    mut game_over: ResMut<GameOver>,
+    mut screen_fade: ResMut<ScreenFade>,
+    mut control_lock: ResMut<PlayerControlLock>,
+) {
+    // Only a change of stage counts as a change
+    let next = match &mut game_over.bypass_change_detection().stage {
+        GameOverStage::Dying { player, timer } => {
+            if !timer.tick(time.delta()).finished() {
+                return;
+            }
+            screen_fade.play(fade_out(FADE_OUT_TIME));
+            GameOverStage::FadingOut { player: *player }
+        },
+        GameOverStage::FadingOut { player } if screen_fade.is_covered() => GameOverStage::Choosing { player: *player },
+        GameOverStage::FadingIn if !screen_fade.is_fading() => {
+            control_lock.unlock(CONTROL_LOCK);
+            GameOverStage::None
+        },
+        _ => return,
+    };
+    game_over.stage = next;
+    game_over.cursor = 0;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn choose_option(
+    actions: Res<Actions>,
+    checkpoint: Res<LastCheckpoint>,
+    mut game_over: ResMut<GameOver>,
+    mut screen_fade: ResMut<ScreenFade>,
+    save: Res<CheckpointSave>,
+    mut flags: ResMut<GameFlags>,
+    mut quests: ResMut<QuestLog>,
+    mut respawned: EventWriter<PlayerRespawned>,
+    mut to_title: EventWriter<ReturnToTitle>,
+    mut players: Query<
+        (
+            &mut PlayerState,
+            &mut Stats,
+            &mut Transform,
+            Option<&mut Position>,
+            Option<&mut StatusEffects>,
+            Option<&mut Inventory>,
+            Option<&mut Equipment>,
+        ),
+        With<Player>,
+    >,
+    mut cameras: Query<(&CameraFollow, &mut Transform), Without<Player>>,
+) {
+    let player = match game_over.stage {
+        GameOverStage::Choosing { player } => player,
+        _ => return,
+    };
+    let options = GameOverOption::ALL.len();
+    if actions.just_pressed(Action::MoveUp) {
+        game_over.cursor = (game_over.cursor + options - 1) % options;
+        return;
+    } else if actions.just_pressed(Action::MoveDown) {
+        game_over.cursor = (game_over.cursor + 1) % options;
+        return;
+    } else if !actions.just_pressed(Action::Interact) {
+        return;
+    }
+    let option = GameOverOption::ALL[game_over.cursor];
+    let load = option == GameOverOption::Load;
+    if load {
+        *flags = save.flags.clone();
+        *quests = save.quests.clone();
+    }
+
+    if let Ok((mut state, mut stats, mut transform, position, status_effects, inventory, equipment)) =
+        players.get_mut(player)
+    {
+        if load {
+            if let (Some(mut inventory), Some(saved)) = (inventory, &save.inventory) {
+                inventory.restore(saved);
+            }
+            if let (Some(mut equipment), Some(saved)) = (equipment, &save.equipment) {
+                *equipment = saved.clone();
+            }
+            if let Some(saved) = save.stats {
+                *stats = saved;
+            }
+        }
+        let spawn = checkpoint.0.unwrap_or_else(|| transform.translation.truncate());
+        if let Some(mut position) = position {
+            position.teleport(spawn);
+        }
+        transform.translation.x = spawn.x;
+        transform.translation.y = spawn.y;
+        for (follow, mut transform) in &mut cameras {
+            if follow.target == player {
+                transform.translation.x = spawn.x;
+                transform.translation.y = spawn.y;
+            }
+        }
+        stats.hp = stats.max_hp();
+        if let Some(mut status_effects) = status_effects {
+            status_effects.clear();
+        }
+        if let Err(err) = state.transition(PlayerState::Idle) {
+            warn!("{}", err);
+        }
+        respawned.send(PlayerRespawned { player, position: spawn });
+    }
+    if option == GameOverOption::Title {
+        to_title.send(ReturnToTitle);
+    }
+    screen_fade.play(fade_in(FADE_IN_TIME));
+    game_over.stage = GameOverStage::FadingIn;
+}
+
+fn play_death_animations<S: AnimState>(
+    mut game_over: ResMut<GameOver>,
+    mut players: Query<
+        (Entity, &PlayerState, &PlayerDeathAnimation<S>, &mut SpritesheetAnimator<S>),
+        Changed<PlayerState>,
+    >,
+) {
+    for (entity, state, death, mut animator) in &mut players {
+        if *state != PlayerState::Dead {
+            continue;
+        }
+        match animator.force_state(death.0.clone(), None) {
+            Ok(()) => {
+                if let GameOverStage::Dying { player, timer } = &mut game_over.stage {
+                    if *player == entity {
+                        *timer = Timer::from_seconds(DEATH_TIMEOUT, TimerMode::Once);
+                    }
+                }
+            },
+            Err(error) => warn!("Couldn't play the player's death animation: {}", error),
+        }
+    }
+}
+
+// Once it's finished, anything may interrupt an animation (e.g. the
+// DirectionalAnimator going back to standing), so put the last frame back
+fn hold_death_animations<S: AnimState>(
+    mut players: Query<(&PlayerState, &PlayerDeathAnimation<S>, &mut SpritesheetAnimator<S>)>,
+) {
+    for (state, death, mut animator) in &mut players {
+        if *state != PlayerState::Dead || animator.cur_state == death.0 {
+            continue;
+        }
+        if animator.force_state(death.0.clone(), None).is_ok() {
+            animator.skip_to_end();
+        }
+    }
+}
+
+fn finish_death_animations<S: AnimState>(
+    mut finished: EventReader<AnimationFinished<S>>,
+    mut game_over: ResMut<GameOver>,
+    players: Query<&PlayerDeathAnimation<S>, With<Player>>,
+) {
+    for event in finished.iter() {
+        if matches!(players.get(event.entity), Ok(death) if death.0 == event.state) {
+            game_over.finish_dying(event.entity);
+        }
+    }
+}
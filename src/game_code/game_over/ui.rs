@@ -0,0 +1,65 @@
+// :: Game Over screen ::
+// "Game Over" over the faded-out screen, and the options under it. MoveUp
+// and MoveDown pick one, and Interact chooses it (see `choose_option`).
+use bevy::prelude::*;
+
+use super::{GameOver, GameOverOption};
+use crate::ui::{set_visible, FONT_SIZE, UI_FONT, UNSELECTED_COLOR};
+
+const TITLE_SIZE: f32 = 36.0;
+const TITLE_COLOR: Color = Color::rgb(0.85, 0.15, 0.2);
+
+#[derive(Component)]
+pub(super) struct GameOverRoot;
+
+#[derive(Component)]
+pub(super) struct GameOverText;
+
+pub(super) fn spawn_game_over_screen(mut commands: Commands) {
+    // A full-screen node, to center the text in
+    commands.spawn((
+        GameOverRoot,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            z_index: ZIndex::Global(i32::MAX - 1), // over the screen fade it's shown on
+            ..default()
+        },
+    )).with_children(|root| {
+        root.spawn((GameOverText, TextBundle::from_sections([])));
+    });
+}
+
+pub(super) fn show_game_over_screen(game_over: Res<GameOver>, mut roots: Query<&mut Visibility, With<GameOverRoot>>) {
+    for mut visibility in &mut roots {
+        set_visible(&mut visibility, game_over.is_choosing());
+    }
+}
+
+pub(super) fn update_game_over_screen(
+    asset_server: Res<AssetServer>,
+    game_over: Res<GameOver>,
+    mut texts: Query<&mut Text, With<GameOverText>>,
+) {
+    if !game_over.is_changed() || !game_over.is_choosing() {
+        return;
+    }
+    let font = asset_server.load(UI_FONT);
+    let style = |size: f32, color: Color| TextStyle { font: font.clone(), font_size: size, color };
+    let mut sections = vec![TextSection::new("Game Over\n\n", style(TITLE_SIZE, TITLE_COLOR))];
+    for (index, option) in GameOverOption::ALL.iter().enumerate() {
+        let selected = index == game_over.cursor;
+        let arrow = if selected { "> " } else { "  " };
+        let color = if selected { Color::WHITE } else { UNSELECTED_COLOR };
+        sections.push(TextSection::new(format!("{}{}\n", arrow, option.label()), style(FONT_SIZE, color)));
+    }
+    for mut text in &mut texts {
+        text.sections = sections.clone();
+    }
+}
@@ -0,0 +1,218 @@
+// :: HUD ::
+// What the player always needs to see, in the top-left corner: their
+// health, their money, and the item in their hand (their equipped
+// weapon). Health is drawn as hearts or as a bar, whichever Hud says:
+//
+//     hud.health = HealthDisplay::Hearts { hp_per_heart: 2 };
+//
+// Each part is only redrawn when what it shows changes: health when the
+// player's Stats do, money on InventoryChanged events (a frame late; see
+// inventory/mod.rs), and the item when their Equipment does (or its icon
+// finishes loading).
+use bevy::prelude::*;
+
+use crate::{
+    combat::Stats,
+    inventory::{icon_image, EquipSlot, Equipment, Inventory, InventoryChange, InventoryChanged, ItemRegistry},
+    player::Player,
+    ui::UI_FONT,
+};
+
+const FONT_SIZE: f32 = 16.0;
+const MARGIN: f32 = 12.0;
+const GAP: f32 = 6.0; // between the parts
+const HEART_SIZE: f32 = 12.0;
+const HEART_GAP: f32 = 3.0;
+const BAR_WIDTH: f32 = 120.0;
+const BAR_HEIGHT: f32 = 10.0;
+const ITEM_SLOT_SIZE: f32 = 32.0;
+const HEALTH_COLOR: Color = Color::rgb(0.9, 0.2, 0.25);
+const EMPTY_COLOR: Color = Color::rgba(0.05, 0.05, 0.1, 0.7);
+const SLOT_COLOR: Color = Color::rgba(0.05, 0.05, 0.1, 0.7);
+const MONEY_COLOR: Color = Color::rgb(1.0, 0.85, 0.4);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HealthDisplay {
+    Hearts { hp_per_heart: i32 },
+    Bar,
+}
+
+#[derive(Resource)]
+pub struct Hud {
+    pub health: HealthDisplay,
+    pub visible: bool,
+}
+impl Default for Hud {
+    fn default() -> Self {
+        Self { health: HealthDisplay::Hearts { hp_per_heart: 2 }, visible: true }
+    }
+}
+
+#[derive(Component)]
+struct HudRoot;
+
+#[derive(Component)]
+struct HudHealth;
+
+#[derive(Component)]
+struct HudMoney;
+
+#[derive(Component)]
+struct HudItemSlot;
+
+pub struct HudPlugin;
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hud>()
+            .add_startup_system(spawn_hud)
+            .add_system(show_hud)
+            .add_system(update_health)
+            .add_system(update_money)
+            .add_system(update_active_item);
+    }
+}
+
+fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        HudRoot,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect { left: Val::Px(MARGIN), top: Val::Px(MARGIN), ..default() },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            z_index: ZIndex::Global(i32::MAX - 6), // over the world, under the boss bar, toasts and menus
+            ..default()
+        },
+    )).with_children(|hud| {
+        hud.spawn((
+            HudHealth,
+            NodeBundle {
+                style: Style { margin: UiRect::bottom(Val::Px(GAP)), ..default() },
+                ..default()
+            },
+        ));
+        hud.spawn((
+            HudMoney,
+            TextBundle::from_section("", TextStyle {
+                font: asset_server.load(UI_FONT),
+                font_size: FONT_SIZE,
+                color: MONEY_COLOR,
+            }).with_style(Style { margin: UiRect::bottom(Val::Px(GAP)), ..default() }),
+        ));
+        hud.spawn((
+            HudItemSlot,
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(ITEM_SLOT_SIZE), Val::Px(ITEM_SLOT_SIZE)),
+                    overflow: Overflow::Hidden,
+                    ..default()
+                },
+                background_color: SLOT_COLOR.into(),
+                ..default()
+            },
+        ));
+    });
+}
+
+fn show_hud(hud: Res<Hud>, mut roots: Query<&mut Visibility, With<HudRoot>>) {
+    if !hud.is_changed() {
+        return;
+    }
+    for mut visibility in &mut roots {
+        visibility.is_visible = hud.visible;
+    }
+}
+
+fn update_health(
+    mut commands: Commands,
+    hud: Res<Hud>,
+    players: Query<(&Stats, ChangeTrackers<Stats>), With<Player>>,
+    health: Query<Entity, With<HudHealth>>,
+) {
+    let stats = match players.get_single() {
+        Ok((stats, tracker)) if tracker.is_changed() || hud.is_changed() => stats,
+        _ => return,
+    };
+    for entity in &health {
+        commands.entity(entity).despawn_descendants();
+        commands.entity(entity).with_children(|health| match hud.health {
+            HealthDisplay::Hearts { hp_per_heart } => {
+                let hp_per_heart = hp_per_heart.max(1);
+                let hearts = (stats.max_hp() + hp_per_heart - 1) / hp_per_heart;
+                for heart in 0..hearts {
+                    let filled = (stats.hp - heart * hp_per_heart).clamp(0, hp_per_heart);
+                    spawn_meter(
+                        health,
+                        Vec2::splat(HEART_SIZE),
+                        filled as f32 / hp_per_heart as f32,
+                        UiRect::right(Val::Px(HEART_GAP)),
+                    );
+                }
+            },
+            HealthDisplay::Bar => {
+                let fraction = stats.hp.max(0) as f32 / stats.max_hp() as f32;
+                spawn_meter(health, Vec2::new(BAR_WIDTH, BAR_HEIGHT), fraction, UiRect::default());
+            },
+        });
+    }
+}
+
+// A box filled from the left, e.g. one heart or the whole bar
+fn spawn_meter(parent: &mut ChildBuilder, size: Vec2, fraction: f32, margin: UiRect) {
+    parent.spawn(NodeBundle {
+        style: Style { size: Size::new(Val::Px(size.x), Val::Px(size.y)), margin, ..default() },
+        background_color: EMPTY_COLOR.into(),
+        ..default()
+    }).with_children(|meter| {
+        meter.spawn(NodeBundle {
+            style: Style { size: Size::new(Val::Percent(100.0 * fraction), Val::Percent(100.0)), ..default() },
+            background_color: HEALTH_COLOR.into(),
+            ..default()
+        });
+    });
+}
+
+fn update_money(
+    mut changes: EventReader<InventoryChanged>,
+    players: Query<(Entity, &Inventory, ChangeTrackers<Inventory>), With<Player>>,
+    mut texts: Query<&mut Text, With<HudMoney>>,
+) {
+    let spent_or_earned: Vec<Entity> = changes.iter()
+        .filter(|event| matches!(event.change, InventoryChange::Currency(_)))
+        .map(|event| event.entity)
+        .collect();
+    for (entity, inventory, tracker) in &players {
+        if !tracker.is_added() && !spent_or_earned.contains(&entity) {
+            continue;
+        }
+        for mut text in &mut texts {
+            text.sections[0].value = format!("${}", inventory.currency());
+        }
+    }
+}
+
+fn update_active_item(
+    mut commands: Commands,
+    items: Res<ItemRegistry>,
+    atlases: Res<Assets<TextureAtlas>>,
+    players: Query<(&Equipment, ChangeTrackers<Equipment>), With<Player>>,
+    slots: Query<Entity, With<HudItemSlot>>,
+) {
+    let equipment = match players.get_single() {
+        Ok((equipment, tracker)) if tracker.is_changed() || items.is_changed() || atlases.is_changed() => equipment,
+        _ => return,
+    };
+    let weapon = equipment.get(EquipSlot::Weapon).and_then(|item| items.get(item));
+    for entity in &slots {
+        commands.entity(entity).despawn_descendants();
+        let icon = weapon.and_then(|item| icon_image(&atlases, &item.atlas, item.icon, ITEM_SLOT_SIZE));
+        if let Some(icon) = icon {
+            commands.entity(entity).with_children(|slot| {
+                slot.spawn(icon);
+            });
+        }
+    }
+}
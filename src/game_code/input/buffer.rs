@@ -0,0 +1,56 @@
+// :: Input buffering ::
+// A press is remembered for a short while, so an attack pressed a few frames
+// before the previous attack finishes still happens, instead of being lost.
+// Systems that act on a press should `consume` it from the InputBuffer
+// rather than checking `Actions::just_pressed`, which only lasts one frame:
+//
+//     if can_attack && input_buffer.consume(Action::Attack) { ... }
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{Action, Actions};
+
+// How long a press is remembered, by default
+pub const INPUT_BUFFER_SECONDS: f32 = 0.15;
+
+#[derive(Resource)]
+pub struct InputBuffer {
+    pub window: f32, // how long a press is remembered, in seconds
+    presses: HashMap<Action, f32>, // seconds left until each buffered press is forgotten
+}
+impl Default for InputBuffer {
+    fn default() -> Self {
+        Self { window: INPUT_BUFFER_SECONDS, presses: HashMap::default() }
+    }
+}
+impl InputBuffer {
+    // Whether the action was pressed recently, and hasn't been consumed yet
+    pub fn buffered(&self, action: Action) -> bool {
+        self.presses.contains_key(&action)
+    }
+    // Use up a recent press, so it only triggers one thing.
+    // Returns false if there was no recent press.
+    pub fn consume(&mut self, action: Action) -> bool {
+        self.presses.remove(&action).is_some()
+    }
+    pub fn clear(&mut self) {
+        self.presses.clear();
+    }
+}
+
+pub(super) fn buffer_actions(
+    time: Res<Time>,
+    actions: Res<Actions>,
+    mut input_buffer: ResMut<InputBuffer>,
+) {
+    let delta = time.delta_seconds();
+    input_buffer.presses.retain(|_, time_left| {
+        *time_left -= delta;
+        *time_left > 0.0
+    });
+    let window = input_buffer.window;
+    for action in Action::ALL {
+        if actions.just_pressed(action) {
+            input_buffer.presses.insert(action, window);
+        }
+    }
+}
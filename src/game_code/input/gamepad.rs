@@ -0,0 +1,69 @@
+// :: Gamepads ::
+// The player is controlled by one gamepad at a time: the first one
+// connected. If it's unplugged, another connected pad takes over, and
+// a pad plugged in mid-game is picked up right away.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// The gamepad controlling the player, if any
+#[derive(Resource, Default)]
+pub struct ActiveGamepad(pub Option<Gamepad>);
+
+pub(super) fn track_gamepads(
+    mut gamepad_events: EventReader<GamepadEvent>,
+    gamepads: Res<Gamepads>,
+    mut active: ResMut<ActiveGamepad>,
+) {
+    for event in gamepad_events.iter() {
+        match event.event_type {
+            GamepadEventType::Connected(_) => {
+                if active.0.is_none() {
+                    info!("Using gamepad {:?}", event.gamepad);
+                    active.0 = Some(event.gamepad);
+                }
+            },
+            GamepadEventType::Disconnected => {
+                if active.0 == Some(event.gamepad) {
+                    active.0 = gamepads.iter().find(|pad| *pad != event.gamepad);
+                    info!("Gamepad {:?} disconnected, now using {:?}", event.gamepad, active.0);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+// How the stick's raw position becomes a movement vector. The dead zones
+// are radial (measured on the stick's distance from center, not each axis
+// on its own), so diagonals feel the same as straight lines.
+#[derive(Resource, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AnalogSettings {
+    pub dead_zone: f32, // stick positions closer to the center than this are ignored
+    pub outer_dead_zone: f32, // stick positions farther out than this count as full tilt
+    pub response_curve: f32, // 1.0 is linear; higher values give finer control at low tilt
+    pub sensitivity: f32, // multiplies the result (capped at full speed)
+}
+impl Default for AnalogSettings {
+    fn default() -> Self {
+        Self { dead_zone: 0.15, outer_dead_zone: 0.95, response_curve: 1.0, sensitivity: 1.0 }
+    }
+}
+impl AnalogSettings {
+    pub fn apply(&self, raw: Vec2) -> Vec2 {
+        let tilt = raw.length();
+        if tilt <= self.dead_zone || tilt == 0.0 {
+            return Vec2::ZERO;
+        }
+        // Rescale so the edge of the dead zone is 0.0 and the outer dead zone is 1.0
+        let range = (self.outer_dead_zone - self.dead_zone).max(f32::EPSILON);
+        let scaled = ((tilt - self.dead_zone) / range).clamp(0.0, 1.0);
+        let curved = (scaled.powf(self.response_curve.max(f32::EPSILON)) * self.sensitivity).min(1.0);
+        raw / tilt * curved
+    }
+}
+
+// The left stick. (The D-pad is bound to the Move* actions in the InputMap.)
+pub fn gamepad_move_vector(gamepad: Gamepad, axes: &Axis<GamepadAxis>, settings: &AnalogSettings) -> Vec2 {
+    let axis = |axis_type| axes.get(GamepadAxis::new(gamepad, axis_type)).unwrap_or(0.0);
+    settings.apply(Vec2::new(axis(GamepadAxisType::LeftStickX), axis(GamepadAxisType::LeftStickY)))
+}
@@ -0,0 +1,156 @@
+// :: Input bindings ::
+// Every Action can be triggered by any number of keys and gamepad buttons.
+// Bindings can be changed while the game runs (e.g., from a controls menu)
+// and are saved to INPUT_MAP_PATH whenever they change, then loaded again
+// the next time the game starts. Actions missing from the saved bindings
+// (e.g. ones added to the game since) get their default bindings.
+use std::{fs, io, path::Path};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::Action;
+
+pub const INPUT_MAP_PATH: &str = "input_map.ron";
+
+// One physical input
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    GamepadButton(GamepadButtonType),
+}
+
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<InputBinding>>,
+}
+impl Default for InputMap {
+    fn default() -> Self {
+        use GamepadButtonType::*;
+        use InputBinding::{GamepadButton as Pad, Key};
+        let bindings = [
+            (Action::MoveUp, vec![Key(KeyCode::Up), Key(KeyCode::W), Pad(DPadUp)]),
+            (Action::MoveDown, vec![Key(KeyCode::Down), Key(KeyCode::S), Pad(DPadDown)]),
+            (Action::MoveLeft, vec![Key(KeyCode::Left), Key(KeyCode::A), Pad(DPadLeft)]),
+            (Action::MoveRight, vec![Key(KeyCode::Right), Key(KeyCode::D), Pad(DPadRight)]),
+            (Action::Interact, vec![Key(KeyCode::E), Key(KeyCode::Return), Pad(South)]),
+            (Action::Attack, vec![Key(KeyCode::Space), Pad(West)]),
+            (Action::Menu, vec![Key(KeyCode::Escape), Pad(Start)]),
+            (Action::Sprint, vec![Key(KeyCode::LShift), Key(KeyCode::RShift), Pad(RightTrigger2)]),
+            (Action::Journal, vec![Key(KeyCode::J), Pad(Select)]),
+            (Action::Inventory, vec![Key(KeyCode::I), Key(KeyCode::Tab), Pad(North)]),
+        ];
+        Self { bindings: bindings.into_iter().collect() }
+    }
+}
+impl InputMap {
+    // Bindings for one half of the keyboard each, for two players
+    // sharing a keyboard (see `InputDevice::Keyboard`)
+    pub fn wasd() -> Self {
+        use InputBinding::Key;
+        let bindings = [
+            (Action::MoveUp, vec![Key(KeyCode::W)]),
+            (Action::MoveDown, vec![Key(KeyCode::S)]),
+            (Action::MoveLeft, vec![Key(KeyCode::A)]),
+            (Action::MoveRight, vec![Key(KeyCode::D)]),
+            (Action::Interact, vec![Key(KeyCode::E)]),
+            (Action::Attack, vec![Key(KeyCode::Space)]),
+            (Action::Menu, vec![Key(KeyCode::Escape)]),
+            (Action::Sprint, vec![Key(KeyCode::LShift)]),
+            (Action::Journal, vec![Key(KeyCode::Q)]),
+            (Action::Inventory, vec![Key(KeyCode::Tab)]),
+        ];
+        Self { bindings: bindings.into_iter().collect() }
+    }
+    pub fn arrows() -> Self {
+        use InputBinding::Key;
+        let bindings = [
+            (Action::MoveUp, vec![Key(KeyCode::Up)]),
+            (Action::MoveDown, vec![Key(KeyCode::Down)]),
+            (Action::MoveLeft, vec![Key(KeyCode::Left)]),
+            (Action::MoveRight, vec![Key(KeyCode::Right)]),
+            (Action::Interact, vec![Key(KeyCode::Return)]),
+            (Action::Attack, vec![Key(KeyCode::RControl)]),
+            (Action::Menu, vec![Key(KeyCode::Back)]),
+            (Action::Sprint, vec![Key(KeyCode::RShift)]),
+            (Action::Journal, vec![Key(KeyCode::RAlt)]),
+            (Action::Inventory, vec![Key(KeyCode::Slash)]),
+        ];
+        Self { bindings: bindings.into_iter().collect() }
+    }
+
+    pub fn bindings(&self, action: Action) -> &[InputBinding] {
+        self.bindings.get(&action).map_or(&[], |bindings| bindings.as_slice())
+    }
+    // Add another input for an action
+    pub fn bind(&mut self, action: Action, binding: InputBinding) {
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+    pub fn unbind(&mut self, action: Action, binding: InputBinding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+    // Replace all of an action's inputs
+    pub fn rebind(&mut self, action: Action, bindings: Vec<InputBinding>) {
+        self.bindings.insert(action, bindings);
+    }
+    pub fn reset_to_defaults(&mut self) {
+        *self = Self::default();
+    }
+
+    // Whether any of an action's inputs is held down. Keys are only
+    // checked if a keyboard is given, and buttons only if a gamepad is.
+    pub fn pressed(&self,
+                   action: Action,
+                   keyboard_input: Option<&Input<KeyCode>>,
+                   gamepad: Option<Gamepad>,
+                   gamepad_buttons: &Input<GamepadButton>) -> bool {
+        self.bindings(action).iter().any(|binding| match binding {
+            InputBinding::Key(key) => keyboard_input.map_or(false, |keyboard| keyboard.pressed(*key)),
+            InputBinding::GamepadButton(button_type) => gamepad.map_or(false, |gamepad| {
+                gamepad_buttons.pressed(GamepadButton::new(gamepad, *button_type))
+            }),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let text = fs::read_to_string(path)?;
+        ron::from_str(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let text = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+}
+
+// The saved bindings if there are any, otherwise the defaults
+pub(super) fn load_input_map() -> InputMap {
+    match InputMap::load(INPUT_MAP_PATH) {
+        Ok(mut input_map) => {
+            // Actions added since the bindings were saved get their defaults
+            for (action, bindings) in InputMap::default().bindings {
+                input_map.bindings.entry(action).or_insert(bindings);
+            }
+            input_map
+        },
+        Err(err) => {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!("Couldn't load {}, using default controls: {}", INPUT_MAP_PATH, err);
+            }
+            InputMap::default()
+        },
+    }
+}
+
+pub(super) fn save_input_map(input_map: Res<InputMap>) {
+    if input_map.is_changed() && !input_map.is_added() {
+        if let Err(err) = input_map.save(INPUT_MAP_PATH) {
+            warn!("Couldn't save {}: {}", INPUT_MAP_PATH, err);
+        }
+    }
+}
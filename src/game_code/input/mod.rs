@@ -0,0 +1,249 @@
+// :: Player input ::
+// Turns what the player presses into a MoveIntent (see movement.rs) and a
+// set of Actions. Nothing else in the game reads the keyboard or gamepad
+// directly, so gameplay code only ever asks "was Interact just pressed?",
+// and which keys and buttons mean what is up to the InputMap (see map.rs).
+//
+// Each Player reads from the device in its PlayerInput, so several players
+// can share a screen: e.g. one on WASD, one on the arrow keys and one on a
+// gamepad. The Actions resource collects presses from every device, for
+// menus that anyone may use; each player also gets its own Actions component.
+use bevy::{prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    movement::{self, MoveIntent, MovePath, MovementSystem, Sprint},
+    player::{player_has_control, Player, PlayerControlLock, PlayerState},
+};
+
+mod buffer;
+mod gamepad;
+mod map;
+mod mouse;
+mod touch;
+
+pub use buffer::InputBuffer;
+pub use gamepad::{ActiveGamepad, AnalogSettings};
+pub use map::{InputBinding, InputMap};
+pub use touch::{TouchControls, TouchState};
+
+// Things the player can do. In the saved bindings file, these are
+// written in snake_case, e.g. "move_up".
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Interact,
+    Attack,
+    Menu,
+    Sprint,
+    Journal, // the quest log
+    Inventory,
+}
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::MoveUp, Action::MoveDown, Action::MoveLeft, Action::MoveRight,
+        Action::Interact, Action::Attack, Action::Menu, Action::Sprint, Action::Journal,
+        Action::Inventory,
+    ];
+}
+
+// Which actions are held down this frame. There's one Actions resource for
+// every device at once, plus an Actions component on each Player for theirs.
+#[derive(Resource, Component, Default)]
+pub struct Actions {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+}
+impl Actions {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+    // Pressed this frame, but not last frame
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+    fn update(&mut self, pressed: HashSet<Action>) {
+        self.just_pressed = pressed.difference(&self.pressed).copied().collect();
+        self.pressed = pressed;
+    }
+    // Which way the Move* actions point, normalized so diagonals aren't faster
+    fn move_dir(&self) -> Vec2 {
+        let axis = |negative: Action, positive: Action| {
+            self.pressed(positive) as i32 as f32 - self.pressed(negative) as i32 as f32
+        };
+        Vec2::new(axis(Action::MoveLeft, Action::MoveRight),
+                  axis(Action::MoveDown, Action::MoveUp)).normalize_or_zero()
+    }
+}
+
+// Where a player's input comes from
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum InputDevice {
+    #[default]
+    Any,              // every key binding, the active gamepad, touch and mouse
+    Keyboard,         // only key bindings
+    Gamepad(Gamepad), // only this gamepad
+}
+
+// Which device controls a player, and with which bindings
+#[derive(Component, Default)]
+pub struct PlayerInput {
+    pub device: InputDevice,
+    pub input_map: Option<InputMap>, // optional. bindings to use instead of the InputMap resource
+}
+impl PlayerInput {
+    pub fn new(device: InputDevice) -> Self {
+        Self { device, input_map: None }
+    }
+    pub fn with_input_map(mut self, input_map: InputMap) -> Self {
+        self.input_map = Some(input_map);
+        self
+    }
+    // The gamepad this player listens to, if any
+    fn gamepad(&self, active_gamepad: &ActiveGamepad) -> Option<Gamepad> {
+        match self.device {
+            InputDevice::Any => active_gamepad.0,
+            InputDevice::Keyboard => None,
+            InputDevice::Gamepad(gamepad) => Some(gamepad),
+        }
+    }
+}
+
+// Systems that read the player's MoveIntent, Actions or InputBuffer
+// can run `.after(InputSystem)`
+#[derive(SystemLabel)]
+pub struct InputSystem;
+
+pub struct PlayerInputPlugin;
+impl Plugin for PlayerInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(map::load_input_map())
+            .init_resource::<Actions>()
+            .init_resource::<ActiveGamepad>()
+            .init_resource::<AnalogSettings>()
+            .init_resource::<InputBuffer>()
+            .init_resource::<TouchControls>()
+            .init_resource::<TouchState>()
+            .add_startup_system(touch::spawn_touch_ui)
+            .add_system(gamepad::track_gamepads.before(InputSystem))
+            .add_system(touch::detect_touch_device.before(touch::read_touch_controls))
+            .add_system(touch::read_touch_controls.before(InputSystem))
+            .add_system(touch::update_touch_ui.after(touch::read_touch_controls))
+            .add_system(add_player_actions.before(InputSystem))
+            .add_system(update_actions.label(InputSystem))
+            .add_system(update_player_actions.label(InputSystem))
+            .add_system(buffer::buffer_actions.label(InputSystem).after(update_actions))
+            .add_system(player_move_intent
+                .label(InputSystem)
+                .after(update_player_actions)
+                .before(movement::follow_move_paths)
+                .before(MovementSystem))
+            .add_system(mouse::click_to_move
+                .with_run_criteria(player_has_control)
+                .label(InputSystem)
+                .before(movement::follow_move_paths))
+            .add_system(map::save_input_map);
+    }
+}
+
+fn add_player_actions(
+    mut commands: Commands,
+    query: Query<Entity, (With<PlayerInput>, Without<Actions>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(Actions::default());
+    }
+}
+
+// Presses from every device
+fn update_actions(
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    touch_state: Res<TouchState>,
+    mut actions: ResMut<Actions>,
+) {
+    let pressed = Action::ALL.into_iter()
+        .filter(|action| input_map.pressed(*action, Some(&keyboard_input), None, &gamepad_buttons)
+            || gamepads.iter().any(|gamepad| input_map.pressed(*action, None, Some(gamepad), &gamepad_buttons))
+            || touch_state.pressed.contains(action))
+        .collect();
+    actions.update(pressed);
+}
+
+// Presses from each player's own device
+fn update_player_actions(
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    active_gamepad: Res<ActiveGamepad>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    touch_state: Res<TouchState>,
+    mut query: Query<(&PlayerInput, &mut Actions)>,
+) {
+    for (player_input, mut actions) in &mut query {
+        let input_map = player_input.input_map.as_ref().unwrap_or(&input_map);
+        let keyboard = match player_input.device {
+            InputDevice::Any | InputDevice::Keyboard => Some(&*keyboard_input),
+            InputDevice::Gamepad(_) => None,
+        };
+        let gamepad = player_input.gamepad(&active_gamepad);
+        let uses_touch = player_input.device == InputDevice::Any;
+        let pressed = Action::ALL.into_iter()
+            .filter(|action| input_map.pressed(*action, keyboard, gamepad, &gamepad_buttons)
+                || (uses_touch && touch_state.pressed.contains(action)))
+            .collect();
+        actions.update(pressed);
+    }
+}
+
+// Each player's Move* actions, gamepad stick and (for InputDevice::Any)
+// the touch joystick are added together, so any of them works
+fn player_move_intent(
+    active_gamepad: Res<ActiveGamepad>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    analog_settings: Res<AnalogSettings>,
+    touch_state: Res<TouchState>,
+    control_lock: Res<PlayerControlLock>,
+    mut query: Query<(
+        &PlayerInput,
+        &Actions,
+        &mut MoveIntent,
+        &PlayerState,
+        Option<&mut Sprint>,
+        Option<&mut MovePath>,
+    ), With<Player>>,
+) {
+    for (player_input, actions, mut intent, state, sprint, path) in &mut query {
+        let mut move_dir = actions.move_dir();
+        if let Some(gamepad) = player_input.gamepad(&active_gamepad) {
+            move_dir += gamepad::gamepad_move_vector(gamepad, &gamepad_axes, &analog_settings);
+        }
+        if player_input.device == InputDevice::Any {
+            move_dir += touch_state.move_dir;
+        }
+
+        // Ignore input while talking, in cutscenes and in menus
+        let has_control = state.can_move() && !control_lock.is_locked();
+        let move_dir = if has_control { move_dir.clamp_length_max(1.0) } else { Vec2::ZERO };
+        if intent.0 != move_dir {
+            intent.0 = move_dir;
+        }
+        // Steering by hand (or losing control) cancels any click-to-move walk
+        if let Some(mut path) = path {
+            if (move_dir != Vec2::ZERO || !has_control) && !path.is_empty() {
+                path.clear();
+            }
+        }
+        if let Some(mut sprint) = sprint {
+            let sprinting = actions.pressed(Action::Sprint);
+            if sprint.active != sprinting {
+                sprint.active = sprinting;
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// :: Click to move ::
+// Clicking somewhere in the world sends the player walking there, around
+// anything in the way (see FindPath). The walk goes through the player's
+// MovePath, so it moves and animates exactly like walking with the
+// keyboard. Pressing a movement key cancels it.
+// Only players controlled by InputDevice::Any follow the mouse.
+use bevy::prelude::*;
+
+use super::{InputDevice, PlayerInput};
+use crate::{
+    camera::{PixelPerfect, PixelPerfectCamera},
+    movement::MovePath,
+    pathfinding::FindPath,
+    player::{Player, PlayerState},
+};
+
+const CLICK_TO_MOVE_BUTTON: MouseButton = MouseButton::Left;
+
+// Where a point in a camera's view (in pixels, from the bottom-left corner
+// of a view `viewport_size` big) is in the world
+pub fn viewport_to_world(
+    viewport_size: Vec2,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    viewport_pos: Vec2,
+) -> Vec2 {
+    let ndc = (viewport_pos / viewport_size) * 2.0 - Vec2::ONE;
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
+    ndc_to_world.project_point3(ndc.extend(-1.0)).truncate()
+}
+
+pub(super) fn click_to_move(
+    mut commands: Commands,
+    mouse_buttons: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    pixel_perfect: Res<PixelPerfect>,
+    cameras: Query<(&Camera, &GlobalTransform), With<PixelPerfectCamera>>,
+    players: Query<(Entity, &PlayerState, &PlayerInput), (With<Player>, With<MovePath>)>,
+) {
+    if !mouse_buttons.just_pressed(CLICK_TO_MOVE_BUTTON) {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    // Clicks on the letterbox bars don't count
+    let viewport_pos = match window.cursor_position()
+        .and_then(|pos| pixel_perfect.window_to_viewport(window, pos))
+    {
+        Some(pos) => pos,
+        None => return,
+    };
+    // With a split screen, use whichever half was clicked
+    let resolution = pixel_perfect.resolution.as_vec2();
+    let target = cameras.iter()
+        .filter(|(camera, _)| camera.is_active)
+        .find_map(|(camera, camera_transform)| {
+            let (min, size) = match &camera.viewport {
+                // Viewports are measured from the top-left, not the bottom-left
+                Some(viewport) => {
+                    let size = viewport.physical_size.as_vec2();
+                    let top_left = viewport.physical_position.as_vec2();
+                    (Vec2::new(top_left.x, resolution.y - top_left.y - size.y), size)
+                }
+                None => (Vec2::ZERO, resolution),
+            };
+            let pos = viewport_pos - min;
+            let inside = pos.cmpge(Vec2::ZERO).all() && pos.cmple(size).all();
+            inside.then(|| viewport_to_world(size, camera, camera_transform, pos))
+        });
+    let target = match target {
+        Some(target) => target,
+        None => return,
+    };
+
+    for (player, state, player_input) in &players {
+        if state.can_move() && player_input.device == InputDevice::Any {
+            commands.entity(player).insert(FindPath::new(target));
+        }
+    }
+}
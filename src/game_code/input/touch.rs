@@ -0,0 +1,211 @@
+// :: Touch controls ::
+// On touchscreens, an on-screen joystick in the bottom-left corner moves
+// the player, and virtual buttons in the bottom-right corner press Actions.
+// They drive the same MoveIntent and Actions as keyboards and gamepads.
+// The controls appear as soon as the screen is touched, or can be turned
+// on (or off) with `TouchControls::enabled`, e.g. from a settings menu.
+use bevy::{prelude::*, utils::HashSet};
+
+use super::Action;
+use crate::ui::UI_FONT;
+
+const JOYSTICK_MARGIN: f32 = 24.0; // from the bottom-left corner of the window
+const KNOB_SIZE: f32 = 32.0;
+const BUTTON_SIZE: f32 = 56.0;
+const CONTROL_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.25);
+
+// An on-screen button
+#[derive(Clone)]
+pub struct VirtualButton {
+    pub action: Action,
+    pub label: String,
+    pub offset: Vec2, // the button's center, in pixels from the bottom-right corner
+}
+
+#[derive(Resource)]
+pub struct TouchControls {
+    pub enabled: bool, // whether the controls are shown and used
+    pub detect_touch: bool, // turn the controls on when the screen is first touched
+    pub joystick_radius: f32, // how far the knob can move from the center, in pixels
+    pub buttons: Vec<VirtualButton>,
+}
+impl Default for TouchControls {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            detect_touch: true,
+            joystick_radius: 48.0,
+            buttons: vec![
+                VirtualButton { action: Action::Interact, label: "E".to_string(), offset: Vec2::new(56.0, 104.0) },
+                VirtualButton { action: Action::Attack, label: "A".to_string(), offset: Vec2::new(120.0, 48.0) },
+                VirtualButton { action: Action::Menu, label: "=".to_string(), offset: Vec2::new(40.0, 200.0) },
+            ],
+        }
+    }
+}
+impl TouchControls {
+    // The joystick's center, in window coordinates (origin at the bottom-left)
+    fn joystick_center(&self) -> Vec2 {
+        Vec2::splat(JOYSTICK_MARGIN + self.joystick_radius)
+    }
+}
+
+// What the touch controls are doing this frame
+#[derive(Resource, Default)]
+pub struct TouchState {
+    pub move_dir: Vec2,
+    pub pressed: HashSet<Action>,
+    joystick_touch: Option<u64>, // the finger on the joystick
+}
+
+#[derive(Component)]
+pub struct TouchJoystickBase;
+
+#[derive(Component)]
+pub struct TouchJoystickKnob;
+
+#[derive(Component)]
+pub struct TouchButton(pub Action);
+
+pub(super) fn detect_touch_device(touches: Res<Touches>, mut controls: ResMut<TouchControls>) {
+    if controls.detect_touch && !controls.enabled && touches.iter_just_pressed().next().is_some() {
+        info!("Touchscreen detected, showing touch controls");
+        controls.enabled = true;
+    }
+}
+
+pub(super) fn read_touch_controls(
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    controls: Res<TouchControls>,
+    mut state: ResMut<TouchState>,
+) {
+    state.move_dir = Vec2::ZERO;
+    state.pressed.clear();
+    if !controls.enabled {
+        state.joystick_touch = None;
+        return;
+    }
+
+    // :: Joystick ::
+    // A finger that lands on (or near) the joystick keeps steering
+    // until it's lifted, even if it slides off
+    let center = controls.joystick_center();
+    let radius = controls.joystick_radius;
+    if let Some(id) = state.joystick_touch {
+        if touches.get_pressed(id).is_none() {
+            state.joystick_touch = None;
+        }
+    }
+    if state.joystick_touch.is_none() {
+        state.joystick_touch = touches.iter_just_pressed()
+            .find(|touch| touch.position().distance(center) <= radius * 2.0)
+            .map(|touch| touch.id());
+    }
+    if let Some(touch) = state.joystick_touch.and_then(|id| touches.get_pressed(id)) {
+        state.move_dir = ((touch.position() - center) / radius).clamp_length_max(1.0);
+    }
+
+    // :: Buttons ::
+    let window_width = windows.get_primary().map_or(0.0, |window| window.width());
+    for touch in touches.iter() {
+        if Some(touch.id()) == state.joystick_touch {
+            continue;
+        }
+        for button in controls.buttons.iter() {
+            let button_center = Vec2::new(window_width - button.offset.x, button.offset.y);
+            if touch.position().distance(button_center) <= BUTTON_SIZE / 2.0 {
+                state.pressed.insert(button.action);
+            }
+        }
+    }
+}
+
+pub(super) fn spawn_touch_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    controls: Res<TouchControls>,
+) {
+    let hidden = Visibility { is_visible: false };
+    let base_size = controls.joystick_radius * 2.0;
+    commands.spawn((
+        TouchJoystickBase,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(JOYSTICK_MARGIN),
+                    bottom: Val::Px(JOYSTICK_MARGIN),
+                    ..default()
+                },
+                size: Size::new(Val::Px(base_size), Val::Px(base_size)),
+                ..default()
+            },
+            background_color: CONTROL_COLOR.into(),
+            visibility: hidden.clone(),
+            ..default()
+        },
+    )).with_children(|base| {
+        base.spawn((
+            TouchJoystickKnob,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    size: Size::new(Val::Px(KNOB_SIZE), Val::Px(KNOB_SIZE)),
+                    ..default()
+                },
+                background_color: CONTROL_COLOR.into(),
+                ..default()
+            },
+        ));
+    });
+
+    for button in controls.buttons.iter() {
+        commands.spawn((
+            TouchButton(button.action),
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        right: Val::Px(button.offset.x - BUTTON_SIZE / 2.0),
+                        bottom: Val::Px(button.offset.y - BUTTON_SIZE / 2.0),
+                        ..default()
+                    },
+                    size: Size::new(Val::Px(BUTTON_SIZE), Val::Px(BUTTON_SIZE)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: CONTROL_COLOR.into(),
+                visibility: hidden.clone(),
+                ..default()
+            },
+        )).with_children(|node| {
+            node.spawn(TextBundle::from_section(button.label.clone(), TextStyle {
+                font: asset_server.load(UI_FONT),
+                font_size: 24.0,
+                color: Color::WHITE,
+            }));
+        });
+    }
+}
+
+pub(super) fn update_touch_ui(
+    controls: Res<TouchControls>,
+    state: Res<TouchState>,
+    mut visibilities: Query<&mut Visibility, Or<(With<TouchJoystickBase>, With<TouchButton>)>>,
+    mut knobs: Query<&mut Style, With<TouchJoystickKnob>>,
+) {
+    for mut visibility in &mut visibilities {
+        if visibility.is_visible != controls.enabled {
+            visibility.is_visible = controls.enabled;
+        }
+    }
+    // Center the knob on the joystick, then push it the way it's held
+    let radius = controls.joystick_radius;
+    let knob_pos = Vec2::splat(radius - KNOB_SIZE / 2.0) + state.move_dir * radius;
+    for mut style in &mut knobs {
+        style.position.left = Val::Px(knob_pos.x);
+        style.position.bottom = Val::Px(knob_pos.y);
+    }
+}
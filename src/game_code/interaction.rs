@@ -0,0 +1,160 @@
+// :: Interaction ::
+// Anything the player can walk up to and use (doors, signs, NPCs, chests)
+// gets an Interactable. When a player is within its radius, a prompt floats
+// above it, and pressing Interact sends an InteractionEvent. What happens
+// next is up to whichever system reads the event:
+//
+//     commands.spawn((Interactable::new(24.0, "Read"), Sign { .. }, SpriteBundle { .. }));
+//
+//     for event in interactions.iter() {
+//         if let Ok(sign) = signs.get(event.target) { ... }
+//     }
+use bevy::prelude::*;
+
+use crate::{
+    input::{Action, Actions, InputSystem},
+    movement::MovementSystem,
+    player::{Player, PlayerControlLock, PlayerState},
+    spatial::SpatialHash,
+    ui::UI_FONT,
+};
+
+const PROMPT_FONT_SIZE: f32 = 8.0;
+const PROMPT_OFFSET: Vec3 = Vec3::new(0.0, 20.0, 10.0); // above the object, drawn on top
+
+#[derive(Component)]
+pub struct Interactable {
+    pub radius: f32, // how close a player has to be, in pixels
+    pub prompt: String, // what the floating prompt says, e.g. "Open"
+}
+impl Interactable {
+    pub fn new(radius: f32, prompt: &str) -> Self {
+        Self { radius, prompt: prompt.to_string() }
+    }
+}
+
+// The Interactable a player would use if they pressed Interact now
+#[derive(Component, Default)]
+pub struct InteractionFocus(pub Option<Entity>);
+
+// The floating prompt above an Interactable
+#[derive(Component)]
+pub struct InteractionPrompt;
+
+// Sent when a player presses Interact near an Interactable
+pub struct InteractionEvent {
+    pub player: Entity,
+    pub target: Entity,
+}
+
+// Systems reading InteractionEvents should run `.after(InteractionSystem)`
+#[derive(SystemLabel)]
+pub struct InteractionSystem;
+
+pub struct InteractionPlugin;
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<InteractionEvent>()
+            .add_system(add_interaction_focus)
+            .add_system(spawn_prompts)
+            .add_system(find_nearby_interactables.after(MovementSystem))
+            .add_system(send_interactions
+                .label(InteractionSystem)
+                .after(find_nearby_interactables)
+                .after(InputSystem))
+            .add_system(show_prompts.after(find_nearby_interactables));
+    }
+}
+
+fn add_interaction_focus(
+    mut commands: Commands,
+    query: Query<Entity, (With<Player>, Without<InteractionFocus>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(InteractionFocus::default());
+    }
+}
+
+fn spawn_prompts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    query: Query<(Entity, &Interactable), Added<Interactable>>,
+) {
+    for (entity, interactable) in &query {
+        let prompt = commands.spawn((
+            InteractionPrompt,
+            Text2dBundle {
+                text: Text::from_section(interactable.prompt.clone(), TextStyle {
+                    font: asset_server.load(UI_FONT),
+                    font_size: PROMPT_FONT_SIZE,
+                    color: Color::WHITE,
+                }).with_alignment(TextAlignment::BOTTOM_CENTER),
+                transform: Transform::from_translation(PROMPT_OFFSET),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+        )).id();
+        commands.entity(entity).add_child(prompt);
+    }
+}
+
+// Each player focuses on the closest Interactable in range
+fn find_nearby_interactables(
+    spatial_hash: Res<SpatialHash>,
+    mut players: Query<(&GlobalTransform, &mut InteractionFocus), With<Player>>,
+    interactables: Query<(Entity, &GlobalTransform, &Interactable)>,
+) {
+    for (player_transform, mut focus) in &mut players {
+        let player_pos = player_transform.translation().truncate();
+        // The hash files each Interactable under its whole radius
+        let closest = spatial_hash.query_point(player_pos).into_iter()
+            .filter_map(|entity| interactables.get(entity).ok())
+            .map(|(entity, transform, interactable)| {
+                (entity, transform.translation().truncate().distance(player_pos), interactable.radius)
+            })
+            .filter(|(_, distance, radius)| distance <= radius)
+            .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b))
+            .map(|(entity, _, _)| entity);
+        if focus.0 != closest {
+            focus.0 = closest;
+        }
+    }
+}
+
+fn send_interactions(
+    control_lock: Res<PlayerControlLock>,
+    mut events: EventWriter<InteractionEvent>,
+    players: Query<(Entity, &Actions, &InteractionFocus, &PlayerState), With<Player>>,
+) {
+    if control_lock.is_locked() {
+        return;
+    }
+    for (player, actions, focus, state) in &players {
+        if let Some(target) = focus.0 {
+            if state.can_move() && actions.just_pressed(Action::Interact) {
+                events.send(InteractionEvent { player, target });
+            }
+        }
+    }
+}
+
+// Show the prompts of Interactables that a player is focused on
+fn show_prompts(
+    players: Query<&InteractionFocus, With<Player>>,
+    interactables: Query<(Entity, &Interactable, &Children)>,
+    mut prompts: Query<(&mut Visibility, &mut Text), With<InteractionPrompt>>,
+) {
+    for (entity, interactable, children) in &interactables {
+        let focused = players.iter().any(|focus| focus.0 == Some(entity));
+        for child in children.iter() {
+            if let Ok((mut visibility, mut text)) = prompts.get_mut(*child) {
+                if visibility.is_visible != focused {
+                    visibility.is_visible = focused;
+                }
+                if text.sections[0].value != interactable.prompt {
+                    text.sections[0].value = interactable.prompt.clone();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+// :: Equipment ::
+// What an entity is wearing and wielding: one item in each EquipSlot.
+// Items that can be equipped say which slot they go in, what they add to
+// the wearer's stats and, optionally, an atlas to draw over the wearer
+// (a layer that mirrors its sprite; see animation/layers.rs):
+//
+//     "stick": (
+//         name: "Stick",
+//         icon: 7,
+//         category: Equipment,
+//         equip: Some((slot: Weapon, bonuses: (attack: 1), overlay: Some("images/stick_overlay.atlas.ron"))),
+//     ),
+//
+// Speed bonuses speed up the wearer (through its SpeedModifiers), whether
+// or not it has Stats for the rest to go to (see combat/mod.rs).
+//
+// Equipped items stay in the inventory; they're equipped and unequipped
+// from the inventory screen, and taken off if they leave the inventory.
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{Inventory, ItemRegistry};
+use crate::{animation::LinkedAnimator, movement::SpeedModifiers};
+
+const SPEED_REASON: &str = "equipment";
+const OVERLAY_Z: f32 = 0.01; // how far in front of the wearer (and each other) overlays are
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum EquipSlot {
+    Weapon,
+    Armor,
+    Accessory,
+}
+impl EquipSlot {
+    pub const ALL: [EquipSlot; 3] = [EquipSlot::Weapon, EquipSlot::Armor, EquipSlot::Accessory];
+}
+
+// What equipping something adds to its wearer's stats
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct StatBonuses {
+    pub max_hp: i32,
+    pub attack: i32,
+    pub defense: i32,
+    pub speed: f32, // a fraction of the wearer's speed, e.g. 0.1 for 10% faster
+}
+impl std::ops::Add for StatBonuses {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self {
+            max_hp: self.max_hp + other.max_hp,
+            attack: self.attack + other.attack,
+            defense: self.defense + other.defense,
+            speed: self.speed + other.speed,
+        }
+    }
+}
+
+// How an item is equipped; part of its ItemDef
+#[derive(Clone, Debug, Deserialize)]
+pub struct EquipDef {
+    pub slot: EquipSlot,
+    #[serde(default)]
+    pub bonuses: StatBonuses,
+    #[serde(default)]
+    pub overlay: Option<String>, // the path of an atlas to draw over the wearer
+    #[serde(skip)]
+    pub overlay_atlas: Option<Handle<TextureAtlas>>,
+}
+
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Equipment {
+    slots: BTreeMap<EquipSlot, String>, // item ids
+}
+impl Equipment {
+    pub fn get(&self, slot: EquipSlot) -> Option<&str> {
+        self.slots.get(&slot).map(String::as_str)
+    }
+    pub fn is_equipped(&self, item: &str) -> bool {
+        self.slots.values().any(|equipped| equipped == item)
+    }
+    // Put an item on, in the slot it goes in, giving back whatever was
+    // there. Err if the item can't be equipped.
+    pub fn equip(&mut self, items: &ItemRegistry, item: &str) -> Result<Option<String>, EquipError> {
+        let slot = items.get(item)
+            .and_then(|def| def.equip.as_ref())
+            .map(|equip| equip.slot)
+            .ok_or_else(|| EquipError(item.to_string()))?;
+        Ok(self.slots.insert(slot, item.to_string()))
+    }
+    pub fn unequip(&mut self, slot: EquipSlot) -> Option<String> {
+        self.slots.remove(&slot)
+    }
+    // Everything the equipped items add up to
+    pub fn bonuses(&self, items: &ItemRegistry) -> StatBonuses {
+        self.slots.values()
+            .filter_map(|item| items.get(item)?.equip.as_ref())
+            .fold(StatBonuses::default(), |total, equip| total + equip.bonuses)
+    }
+}
+
+#[derive(Debug)]
+pub struct EquipError(pub String);
+impl std::fmt::Display for EquipError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\" can't be equipped", self.0)
+    }
+}
+impl std::error::Error for EquipError {}
+
+// A child of an Equipment's entity, drawing an equipped item over it
+#[derive(Component)]
+pub(super) struct EquipmentOverlay;
+
+// Take off anything that's no longer in the inventory (e.g., dropped)
+pub(super) fn unequip_missing(mut wearers: Query<(&mut Equipment, &Inventory), Changed<Inventory>>) {
+    for (mut equipment, inventory) in &mut wearers {
+        let missing: Vec<EquipSlot> = equipment.slots.iter()
+            .filter(|(_, item)| !inventory.has(item, 1))
+            .map(|(slot, _)| *slot)
+            .collect();
+        for slot in missing {
+            equipment.unequip(slot);
+        }
+    }
+}
+
+// Keep the speed of anything wearing Equipment up to date with it
+pub(super) fn apply_equipment_speed(
+    items: Res<ItemRegistry>,
+    mut wearers: Query<(&Equipment, ChangeTrackers<Equipment>, &mut SpeedModifiers)>,
+) {
+    for (equipment, equipment_tracker, mut modifiers) in &mut wearers {
+        // Everyone, if an item's definition might have changed
+        if !items.is_changed() && !equipment_tracker.is_changed() && !modifiers.is_added() {
+            continue;
+        }
+        let speed = equipment.bonuses(&items).speed;
+        let multiplier = (speed != 0.0).then_some(1.0 + speed);
+        if modifiers.get(SPEED_REASON) != multiplier {
+            match multiplier {
+                Some(multiplier) => modifiers.set(SPEED_REASON, multiplier),
+                None => modifiers.remove(SPEED_REASON),
+            }
+        }
+    }
+}
+
+// Respawn the overlays of anything whose equipment changed
+pub(super) fn update_equipment_overlays(
+    mut commands: Commands,
+    items: Res<ItemRegistry>,
+    wearers: Query<(Entity, &Equipment, Option<&Children>), Changed<Equipment>>,
+    overlays: Query<(), With<EquipmentOverlay>>,
+) {
+    for (entity, equipment, children) in &wearers {
+        for child in children.iter().flat_map(|children| children.iter()) {
+            if overlays.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        for (order, slot) in EquipSlot::ALL.into_iter().enumerate() {
+            let atlas = equipment.get(slot)
+                .and_then(|item| items.get(item)?.equip.as_ref()?.overlay_atlas.clone());
+            if let Some(atlas) = atlas {
+                commands.entity(entity).with_children(|wearer| {
+                    wearer.spawn((
+                        EquipmentOverlay,
+                        LinkedAnimator,
+                        SpriteSheetBundle {
+                            texture_atlas: atlas,
+                            transform: Transform::from_xyz(0.0, 0.0, OVERLAY_Z * (order + 1) as f32),
+                            ..default()
+                        },
+                    ));
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+// :: Item definitions ::
+// What each kind of item is: its name, icon, how many fit in one slot.
+// Items are defined in `.items.ron` (or `.items.json`) catalogs under
+// `assets/items/`, each with the atlas its icons come from:
+//
+//     (
+//         atlas: "images/items.atlas.ron",
+//         items: {
+//             "apple": (name: "Apple", icon: 3, category: Consumable),
+//             "old_key": (name: "Old Key", icon: 12, stack_size: 1, category: Key),
+//         },
+//     )
+//
+// Catalogs are added to the ItemRegistry, which every item id is looked up
+// in. Edits to a catalog are picked up while the game runs.
+use bevy::{
+    asset::{AssetPath, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+use serde::Deserialize;
+
+use super::EquipDef;
+
+pub const DEFAULT_STACK_SIZE: u32 = 99;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Deserialize)]
+pub enum ItemCategory {
+    Consumable,
+    Material,
+    Equipment,
+    Key, // quest items, which can't be dropped
+    #[default]
+    Misc,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ItemDef {
+    #[serde(skip)]
+    pub id: String, // its key in the catalog
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub icon: usize, // index into the catalog's atlas
+    #[serde(skip)]
+    pub atlas: Handle<TextureAtlas>,
+    #[serde(default = "default_stack_size")]
+    pub stack_size: u32, // how many fit in one inventory slot
+    #[serde(default)]
+    pub category: ItemCategory,
+    #[serde(default)]
+    pub price: u32, // what it's worth in a shop; 0 if it can't be bought or sold
+    #[serde(default)]
+    pub equip: Option<EquipDef>, // for things that can be equipped (see equipment.rs)
+}
+
+fn default_stack_size() -> u32 {
+    DEFAULT_STACK_SIZE
+}
+
+#[derive(Clone, Debug, Deserialize, TypeUuid)]
+#[uuid = "c1f8e0d4-6a0e-4d8c-9f59-2a3b7d41e6c2"]
+pub struct ItemCatalog {
+    pub atlas: String,
+    pub items: HashMap<String, ItemDef>,
+}
+
+// Fill in each item's id and atlas, and load the atlases it needs
+pub(super) fn finish_catalog(mut catalog: ItemCatalog, load_context: &mut LoadContext) -> LoadedAsset<ItemCatalog> {
+    let atlas_path = AssetPath::new(catalog.atlas.clone().into(), None);
+    let atlas: Handle<TextureAtlas> = load_context.get_handle(atlas_path.clone());
+    let mut dependencies = vec![atlas_path];
+    for (id, item) in catalog.items.iter_mut() {
+        item.id = id.clone();
+        item.atlas = atlas.clone();
+        if item.stack_size == 0 {
+            warn!("Item \"{}\" has a stack size of 0; using 1", id);
+            item.stack_size = 1;
+        }
+        if let Some(equip) = item.equip.as_mut() {
+            if let Some(overlay) = &equip.overlay {
+                let overlay_path = AssetPath::new(overlay.clone().into(), None);
+                equip.overlay_atlas = Some(load_context.get_handle(overlay_path.clone()));
+                dependencies.push(overlay_path);
+            }
+        }
+    }
+    let mut asset = LoadedAsset::new(catalog);
+    for path in dependencies {
+        asset = asset.with_dependency(path);
+    }
+    asset
+}
+
+// Every item there is, from all the catalogs added to it
+#[derive(Resource, Default)]
+pub struct ItemRegistry {
+    catalogs: Vec<Handle<ItemCatalog>>,
+    items: HashMap<String, ItemDef>,
+}
+impl ItemRegistry {
+    // Items in later catalogs replace ones with the same id in earlier ones
+    pub fn add_catalog(&mut self, catalog: Handle<ItemCatalog>) {
+        self.catalogs.push(catalog);
+    }
+    pub fn get(&self, id: &str) -> Option<&ItemDef> {
+        self.items.get(id)
+    }
+    // How many of an item fit in one slot. Items that aren't defined (or
+    // whose catalog hasn't loaded yet) get the default.
+    pub fn stack_size(&self, id: &str) -> u32 {
+        self.get(id).map_or(DEFAULT_STACK_SIZE, |item| item.stack_size)
+    }
+    // An item's name, or its id if it isn't defined
+    pub fn name<'a>(&'a self, id: &'a str) -> &'a str {
+        self.get(id).map_or(id, |item| item.name.as_str())
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &ItemDef> {
+        self.items.values()
+    }
+}
+
+// Rebuilt whenever one of its catalogs (re)loads
+pub(super) fn update_item_registry(
+    mut registry: ResMut<ItemRegistry>,
+    catalogs: Res<Assets<ItemCatalog>>,
+    mut catalog_events: EventReader<AssetEvent<ItemCatalog>>,
+) {
+    let reloaded = catalog_events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => registry.catalogs.contains(handle),
+        AssetEvent::Removed { .. } => false,
+    });
+    if !reloaded {
+        return;
+    }
+    let items = registry.catalogs.iter()
+        .filter_map(|handle| catalogs.get(handle))
+        .flat_map(|catalog| catalog.items.iter())
+        .map(|(id, item)| (id.clone(), item.clone()))
+        .collect();
+    registry.items = items;
+}
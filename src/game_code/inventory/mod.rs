@@ -0,0 +1,249 @@
+// :: Inventory ::
+// The items an entity is carrying, in a fixed number of slots. Each slot
+// holds a stack of one kind of item, up to its stack size (see items.rs
+// for where items are defined):
+//
+//     let left_over = inventory.add(&item_registry, "apple", 3);
+//     if inventory.has("old_key", 1) { .. }
+//     inventory.remove("apple", 1);
+//
+// Adding fills up stacks of the same item before starting new ones, and
+// gives back however many didn't fit. An inventory also holds money (its
+// currency), for shops. Every change is announced with an InventoryChanged
+// event, e.g. for the inventory screen to redraw.
+//
+// The player's inventory can be looked through on the inventory screen
+// (see ui.rs), where items are used and dropped. What using an item does
+// is up to whatever reads the ItemUsed events; things that can be
+// equipped are put on and taken off (see equipment.rs).
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{asset_loader::RonOrJsonLoader, input::InputSystem};
+
+mod equipment;
+mod items;
+mod ui;
+
+pub use equipment::{EquipDef, EquipError, EquipSlot, Equipment, StatBonuses};
+pub use items::{ItemCatalog, ItemCategory, ItemDef, ItemRegistry, DEFAULT_STACK_SIZE};
+pub(crate) use ui::icon_image;
+pub use ui::InventoryScreen;
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: String, // the item's id
+    pub count: u32,
+}
+
+#[derive(Component, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+    #[serde(default)]
+    currency: u32,
+    #[serde(skip)]
+    changes: Vec<InventoryChange>, // not announced yet
+}
+impl Inventory {
+    pub fn new(size: usize) -> Self {
+        Self { slots: vec![None; size], currency: 0, changes: Vec::new() }
+    }
+    pub fn with_currency(mut self, currency: u32) -> Self {
+        self.currency = currency;
+        self
+    }
+    pub fn size(&self) -> usize {
+        self.slots.len()
+    }
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+    pub fn slot(&self, index: usize) -> Option<&ItemStack> {
+        self.slots.get(index)?.as_ref()
+    }
+    // How many of an item there are, in all slots
+    pub fn count(&self, item: &str) -> u32 {
+        self.stacks_of(item).map(|stack| stack.count).sum()
+    }
+    pub fn has(&self, item: &str, count: u32) -> bool {
+        self.count(item) >= count
+    }
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+    // How many more of an item would fit
+    pub fn space_for(&self, items: &ItemRegistry, item: &str) -> u32 {
+        let stack_size = items.stack_size(item);
+        self.slots.iter().map(|slot| match slot {
+            Some(stack) if stack.item == item => stack_size.saturating_sub(stack.count),
+            Some(_) => 0,
+            None => stack_size,
+        }).sum()
+    }
+
+    // Add some of an item, topping up its stacks first and then filling empty
+    // slots. Returns how many didn't fit.
+    pub fn add(&mut self, items: &ItemRegistry, item: &str, count: u32) -> u32 {
+        let stack_size = items.stack_size(item);
+        let mut left = count;
+        for stack in self.slots.iter_mut().flatten().filter(|stack| stack.item == item) {
+            let added = left.min(stack_size.saturating_sub(stack.count));
+            stack.count += added;
+            left -= added;
+        }
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if left == 0 {
+                break;
+            }
+            let added = left.min(stack_size);
+            *slot = Some(ItemStack { item: item.to_string(), count: added });
+            left -= added;
+        }
+        if left < count {
+            self.changes.push(InventoryChange::Added { item: item.to_string(), count: count - left });
+        }
+        left
+    }
+    // Take away up to `count` of an item, from its last stacks first.
+    // Returns how many were taken.
+    pub fn remove(&mut self, item: &str, count: u32) -> u32 {
+        let mut left = count;
+        for slot in self.slots.iter_mut().rev() {
+            if left == 0 {
+                break;
+            }
+            if let Some(stack) = slot.as_mut().filter(|stack| stack.item == item) {
+                let removed = left.min(stack.count);
+                stack.count -= removed;
+                left -= removed;
+                if stack.count == 0 {
+                    *slot = None;
+                }
+            }
+        }
+        if left < count {
+            self.changes.push(InventoryChange::Removed { item: item.to_string(), count: count - left });
+        }
+        count - left
+    }
+    // Empty a slot, giving back what was in it
+    pub fn take_slot(&mut self, index: usize) -> Option<ItemStack> {
+        let stack = self.slots.get_mut(index)?.take()?;
+        self.changes.push(InventoryChange::Removed { item: stack.item.clone(), count: stack.count });
+        Some(stack)
+    }
+    // Swap what's in two slots, e.g. to rearrange things
+    pub fn swap(&mut self, a: usize, b: usize) {
+        if a != b && a < self.slots.len() && b < self.slots.len() {
+            self.slots.swap(a, b);
+            self.changes.push(InventoryChange::Moved);
+        }
+    }
+
+    // Put everything back the way it is in `saved` (e.g. a copy kept at a
+    // checkpoint), announcing what that takes away and gives back
+    pub fn restore(&mut self, saved: &Inventory) {
+        for stack in self.slots.iter().flatten() {
+            self.changes.push(InventoryChange::Removed { item: stack.item.clone(), count: stack.count });
+        }
+        self.slots = saved.slots.clone();
+        for stack in self.slots.iter().flatten() {
+            self.changes.push(InventoryChange::Added { item: stack.item.clone(), count: stack.count });
+        }
+        if saved.currency != self.currency {
+            self.changes.push(InventoryChange::Currency(saved.currency as i64 - self.currency as i64));
+            self.currency = saved.currency;
+        }
+    }
+    pub fn currency(&self) -> u32 {
+        self.currency
+    }
+    pub fn add_currency(&mut self, amount: u32) {
+        self.currency = self.currency.saturating_add(amount);
+        self.changes.push(InventoryChange::Currency(amount as i64));
+    }
+    // Pay `amount`, if there's enough. False (and nothing's spent) if not.
+    pub fn spend_currency(&mut self, amount: u32) -> bool {
+        if amount > self.currency {
+            return false;
+        }
+        self.currency -= amount;
+        self.changes.push(InventoryChange::Currency(-(amount as i64)));
+        true
+    }
+
+    fn stacks_of<'a>(&'a self, item: &'a str) -> impl Iterator<Item = &'a ItemStack> {
+        self.slots.iter().flatten().filter(move |stack| stack.item == item)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum InventoryChange {
+    Added { item: String, count: u32 },
+    Removed { item: String, count: u32 },
+    Moved, // slots were rearranged, but nothing came or went
+    Currency(i64), // how much was gained (or, if negative, spent)
+}
+
+pub struct InventoryChanged {
+    pub entity: Entity,
+    pub change: InventoryChange,
+}
+
+// An item was used from an inventory. Consumables have already been taken
+// out of it.
+pub struct ItemUsed {
+    pub user: Entity,
+    pub item: String,
+}
+
+// Items were dropped from an inventory (and are gone from it)
+pub struct ItemDropped {
+    pub owner: Entity,
+    pub item: String,
+    pub count: u32,
+}
+
+// The InventoryChanged events for a frame are sent after Update, so
+// systems reading them run in the next frame's Update
+#[derive(SystemLabel)]
+pub struct InventorySystem;
+
+pub struct InventoryPlugin;
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ItemCatalog>()
+            .add_asset_loader(RonOrJsonLoader::<ItemCatalog>::new(&["items.ron", "items.json"])
+                .with_process(items::finish_catalog))
+            .init_resource::<ItemRegistry>()
+            .init_resource::<InventoryScreen>()
+            .add_event::<InventoryChanged>()
+            .add_event::<ItemUsed>()
+            .add_event::<ItemDropped>()
+            .add_startup_system(ui::spawn_inventory_screen)
+            .add_system(items::update_item_registry)
+            .add_system(ui::toggle_inventory_screen.after(InputSystem))
+            .add_system(ui::navigate_inventory_screen.after(ui::toggle_inventory_screen))
+            .add_system(equipment::unequip_missing.after(ui::navigate_inventory_screen))
+            .add_system(equipment::update_equipment_overlays.after(equipment::unequip_missing))
+            .add_system(equipment::apply_equipment_speed
+                .after(equipment::unequip_missing)
+                .after(items::update_item_registry))
+            .add_system(ui::update_inventory_screen
+                .after(equipment::unequip_missing)
+                .after(items::update_item_registry))
+            .add_system_to_stage(CoreStage::PostUpdate, send_inventory_events.label(InventorySystem));
+    }
+}
+
+fn send_inventory_events(
+    mut inventories: Query<(Entity, &mut Inventory), Changed<Inventory>>,
+    mut events: EventWriter<InventoryChanged>,
+) {
+    for (entity, mut inventory) in &mut inventories {
+        // Without marking it changed again
+        for change in inventory.bypass_change_detection().changes.drain(..) {
+            events.send(InventoryChanged { entity, change });
+        }
+    }
+}
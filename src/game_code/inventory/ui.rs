@@ -0,0 +1,347 @@
+// :: Inventory screen ::
+// The player's inventory as a grid of slots, with the selected item's
+// name and description beside it. Inventory opens and closes it (Menu
+// closes it too); the move actions pick a slot, Interact uses the item in
+// it (or puts it on or takes it off, for equipment) and Attack drops one.
+// Equipped items are marked with an "E". The player can't move while it's
+// open.
+//
+// Bevy's UI can't show one icon out of a texture atlas, so each slot
+// shows the atlas's whole image, shifted so the icon lines up with the
+// slot, and clips off the rest.
+use bevy::prelude::*;
+
+use super::{Equipment, Inventory, ItemCategory, ItemDropped, ItemRegistry, ItemUsed, StatBonuses};
+use crate::{
+    input::{Action, Actions},
+    player::{Player, PlayerControlLock},
+    ui::{FONT_SIZE, MENU_LAYER, SCREEN_COLOR, UI_FONT},
+};
+
+const COUNT_FONT_SIZE: f32 = 12.0;
+const COLUMNS: usize = 6;
+const ICON_SCALE: f32 = 2.0; // icons are drawn at this many screen pixels per pixel
+const SLOT_SIZE: f32 = 40.0;
+const SLOT_GAP: f32 = 4.0;
+const PADDING: f32 = 16.0;
+const DETAILS_WIDTH: f32 = 240.0;
+const CONTROL_LOCK: &str = "inventory";
+const SLOT_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.08);
+const SELECTED_COLOR: Color = Color::rgba(1.0, 0.85, 0.4, 0.5);
+const NAME_COLOR: Color = Color::rgb(1.0, 0.85, 0.4);
+const HINT_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+const EQUIPPED_COLOR: Color = Color::rgb(0.5, 0.9, 0.5);
+
+// Whether the screen's open, and which slot is selected
+#[derive(Resource, Default)]
+pub struct InventoryScreen {
+    open: bool,
+    cursor: usize,
+}
+impl InventoryScreen {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+}
+
+#[derive(Component)]
+pub(super) struct InventoryRoot;
+
+// Where the slots go; they're rebuilt whenever anything changes
+#[derive(Component)]
+pub(super) struct InventoryGrid;
+
+#[derive(Component)]
+pub(super) struct InventoryDetails;
+
+pub(super) fn spawn_inventory_screen(mut commands: Commands) {
+    // A full-screen node, to center the panel in
+    commands.spawn((
+        InventoryRoot,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            z_index: MENU_LAYER,
+            ..default()
+        },
+    )).with_children(|root| {
+        root.spawn(NodeBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(PADDING)),
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            background_color: SCREEN_COLOR.into(),
+            ..default()
+        }).with_children(|panel| {
+            panel.spawn((InventoryGrid, NodeBundle::default()));
+            panel.spawn((
+                InventoryDetails,
+                TextBundle::from_sections([]).with_style(Style {
+                    size: Size::new(Val::Px(DETAILS_WIDTH), Val::Undefined),
+                    max_size: Size::new(Val::Px(DETAILS_WIDTH), Val::Undefined),
+                    margin: UiRect { left: Val::Px(PADDING), ..default() },
+                    ..default()
+                }),
+            ));
+        });
+    });
+}
+
+pub(super) fn toggle_inventory_screen(
+    actions: Res<Actions>,
+    mut screen: ResMut<InventoryScreen>,
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut roots: Query<&mut Visibility, With<InventoryRoot>>,
+) {
+    let open = if screen.open {
+        !(actions.just_pressed(Action::Inventory) || actions.just_pressed(Action::Menu))
+    } else {
+        actions.just_pressed(Action::Inventory) && !control_lock.is_locked()
+    };
+    if open == screen.open {
+        return;
+    }
+    screen.open = open;
+    if open {
+        control_lock.lock(CONTROL_LOCK);
+    } else {
+        control_lock.unlock(CONTROL_LOCK);
+    }
+    for mut visibility in &mut roots {
+        visibility.is_visible = open;
+    }
+}
+
+// Move the cursor, and use or drop what it's on
+pub(super) fn navigate_inventory_screen(
+    actions: Res<Actions>,
+    items: Res<ItemRegistry>,
+    mut screen: ResMut<InventoryScreen>,
+    mut players: Query<(Entity, &mut Inventory, Option<&mut Equipment>), With<Player>>,
+    mut used: EventWriter<ItemUsed>,
+    mut dropped: EventWriter<ItemDropped>,
+) {
+    if !screen.open {
+        return;
+    }
+    let (player, mut inventory, equipment) = match players.iter_mut().next() {
+        Some(player) => player,
+        None => return,
+    };
+    let size = inventory.size();
+    if size == 0 {
+        return;
+    }
+
+    // Wrapping around at the edges
+    let cursor = screen.cursor.min(size - 1);
+    let (column, row) = (cursor % COLUMNS, cursor / COLUMNS);
+    let rows = (size + COLUMNS - 1) / COLUMNS;
+    let moved = if actions.just_pressed(Action::MoveLeft) {
+        Some(row * COLUMNS + (column + COLUMNS - 1) % COLUMNS)
+    } else if actions.just_pressed(Action::MoveRight) {
+        Some(row * COLUMNS + (column + 1) % COLUMNS)
+    } else if actions.just_pressed(Action::MoveUp) {
+        Some((row + rows - 1) % rows * COLUMNS + column)
+    } else if actions.just_pressed(Action::MoveDown) {
+        Some((row + 1) % rows * COLUMNS + column)
+    } else {
+        None
+    };
+    if let Some(moved) = moved {
+        screen.cursor = moved.min(size - 1);
+        return;
+    }
+
+    let stack = match inventory.slot(cursor) {
+        Some(stack) => stack.clone(),
+        None => return,
+    };
+    let category = items.get(&stack.item).map_or(ItemCategory::Misc, |item| item.category);
+    let equip_slot = items.get(&stack.item).and_then(|item| item.equip.as_ref()).map(|equip| equip.slot);
+    if let (true, Some(slot), Some(mut equipment)) = (actions.just_pressed(Action::Interact), equip_slot, equipment) {
+        if equipment.get(slot) == Some(stack.item.as_str()) {
+            equipment.unequip(slot);
+        } else if let Err(err) = equipment.equip(&items, &stack.item) {
+            warn!("{}", err);
+        }
+    } else if actions.just_pressed(Action::Interact) {
+        // Consumables are used up; anything else is up to whatever reads ItemUsed
+        if category == ItemCategory::Consumable {
+            inventory.remove(&stack.item, 1);
+        }
+        used.send(ItemUsed { user: player, item: stack.item });
+    } else if actions.just_pressed(Action::Attack) && category != ItemCategory::Key {
+        inventory.remove(&stack.item, 1);
+        dropped.send(ItemDropped { owner: player, item: stack.item, count: 1 });
+    }
+}
+
+pub(super) fn update_inventory_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    screen: Res<InventoryScreen>,
+    items: Res<ItemRegistry>,
+    atlases: Res<Assets<TextureAtlas>>,
+    players: Query<(
+        &Inventory,
+        ChangeTrackers<Inventory>,
+        Option<&Equipment>,
+        Option<ChangeTrackers<Equipment>>,
+    ), With<Player>>,
+    grids: Query<Entity, With<InventoryGrid>>,
+    mut details: Query<&mut Text, With<InventoryDetails>>,
+) {
+    let (inventory, inventory_tracker, equipment, equipment_tracker) = match players.iter().next() {
+        Some(player) => player,
+        None => return,
+    };
+    let equipment_changed = equipment_tracker.map_or(false, |tracker| tracker.is_changed());
+    let changed = screen.is_changed() || items.is_changed() || inventory_tracker.is_changed() || equipment_changed;
+    if !screen.open || !changed {
+        return;
+    }
+    let font = asset_server.load(UI_FONT);
+
+    let rows = (inventory.size() + COLUMNS - 1) / COLUMNS;
+    for grid in &grids {
+        commands.entity(grid).despawn_descendants().insert(Style {
+            size: Size::new(
+                Val::Px(COLUMNS as f32 * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP),
+                Val::Px(rows as f32 * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP),
+            ),
+            ..default()
+        });
+        commands.entity(grid).with_children(|grid| {
+            for (index, slot) in inventory.slots().iter().enumerate() {
+                let (column, row) = (index % COLUMNS, index / COLUMNS);
+                let color = if index == screen.cursor { SELECTED_COLOR } else { SLOT_COLOR };
+                grid.spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            left: Val::Px(column as f32 * (SLOT_SIZE + SLOT_GAP)),
+                            bottom: Val::Px((rows - 1 - row) as f32 * (SLOT_SIZE + SLOT_GAP)),
+                            ..default()
+                        },
+                        size: Size::new(Val::Px(SLOT_SIZE), Val::Px(SLOT_SIZE)),
+                        overflow: Overflow::Hidden,
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                }).with_children(|slot_node| {
+                    let stack = match slot {
+                        Some(stack) => stack,
+                        None => return,
+                    };
+                    let icon = items.get(&stack.item)
+                        .and_then(|item| icon_image(&atlases, &item.atlas, item.icon, SLOT_SIZE));
+                    if let Some(icon) = icon {
+                        slot_node.spawn(icon);
+                    }
+                    if equipment.map_or(false, |equipment| equipment.is_equipped(&stack.item)) {
+                        slot_node.spawn(TextBundle::from_section("E", TextStyle {
+                            font: font.clone(),
+                            font_size: COUNT_FONT_SIZE,
+                            color: EQUIPPED_COLOR,
+                        }).with_style(Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect { left: Val::Px(2.0), top: Val::Px(1.0), ..default() },
+                            ..default()
+                        }));
+                    }
+                    if stack.count > 1 {
+                        slot_node.spawn(TextBundle::from_section(stack.count.to_string(), TextStyle {
+                            font: font.clone(),
+                            font_size: COUNT_FONT_SIZE,
+                            color: Color::WHITE,
+                        }).with_style(Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect { right: Val::Px(2.0), bottom: Val::Px(1.0), ..default() },
+                            ..default()
+                        }));
+                    }
+                });
+            }
+        });
+    }
+
+    let style = |color: Color| TextStyle { font: font.clone(), font_size: FONT_SIZE, color };
+    let sections = match inventory.slot(screen.cursor) {
+        Some(stack) => {
+            let item = items.get(&stack.item);
+            let mut sections = vec![
+                TextSection::new(format!("{}\n", items.name(&stack.item)), style(NAME_COLOR)),
+                TextSection::new(format!("{}\n\n", item.map_or("", |item| item.description.as_str())), style(Color::WHITE)),
+            ];
+            if let Some(equip) = item.and_then(|item| item.equip.as_ref()) {
+                sections.push(TextSection::new(format!("{:?}{}\n\n", equip.slot, describe_bonuses(&equip.bonuses)), style(EQUIPPED_COLOR)));
+            }
+            let use_hint = match item.and_then(|item| item.equip.as_ref()) {
+                Some(equip) if equipment.map_or(false, |equipment| equipment.get(equip.slot) == Some(stack.item.as_str())) => "Interact: unequip",
+                Some(_) => "Interact: equip",
+                None => "Interact: use",
+            };
+            let droppable = item.map_or(true, |item| item.category != ItemCategory::Key);
+            let hint = if droppable { format!("{}\nAttack: drop", use_hint) } else { use_hint.to_string() };
+            sections.push(TextSection::new(hint, style(HINT_COLOR)));
+            sections
+        },
+        None => vec![TextSection::new("Empty", style(HINT_COLOR))],
+    };
+    for mut text in &mut details {
+        text.sections = sections.clone();
+    }
+}
+
+// An item's icon: the atlas's image, scaled up and shifted so the icon
+// sits in a (clipping) slot `slot_size` pixels across, centered
+pub(crate) fn icon_image(
+    atlases: &Assets<TextureAtlas>,
+    atlas: &Handle<TextureAtlas>,
+    index: usize,
+    slot_size: f32,
+) -> Option<ImageBundle> {
+    let atlas = atlases.get(atlas)?;
+    let rect = *atlas.textures.get(index)?;
+    let inset = (Vec2::splat(slot_size) - rect.size() * ICON_SCALE) / 2.0;
+    Some(ImageBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                left: Val::Px(inset.x - rect.min.x * ICON_SCALE),
+                bottom: Val::Px(inset.y - (atlas.size.y - rect.max.y) * ICON_SCALE),
+                ..default()
+            },
+            size: Size::new(Val::Px(atlas.size.x * ICON_SCALE), Val::Px(atlas.size.y * ICON_SCALE)),
+            ..default()
+        },
+        image: atlas.texture.clone().into(),
+        ..default()
+    })
+}
+
+// e.g. ", Attack +1, Speed +10%"
+fn describe_bonuses(bonuses: &StatBonuses) -> String {
+    let mut text = String::new();
+    for (name, value) in [("Max HP", bonuses.max_hp), ("Attack", bonuses.attack), ("Defense", bonuses.defense)] {
+        if value != 0 {
+            text += &format!(", {} {:+}", name, value);
+        }
+    }
+    if bonuses.speed != 0.0 {
+        text += &format!(", Speed {:+}%", (bonuses.speed * 100.0).round() as i32);
+    }
+    text
+}
@@ -0,0 +1,299 @@
+// :: Movement ::
+// Anything that moves (the player, NPCs, enemies) says where it wants to go
+// with a MoveIntent, and this plugin does the moving. That way keyboards,
+// gamepads and AI can all steer a character the same way.
+//
+// Movement runs at a fixed rate (MOVEMENT_TIMESTEP), in its own stage after
+// Update, so it feels the same at any frame rate. Each entity's real position
+// is kept in its Position component, and its Transform is smoothly placed
+// between the last two movement steps every frame, so nothing jitters when
+// the frame rate and the movement rate don't line up.
+//
+// Things can also be pushed around, on top of (or instead of) steering
+// themselves: a Velocity keeps an entity coasting until Damping slows it
+// down, and an Impulse is a one-off push, like a hit's knockback:
+//
+//     impulses.get_mut(target)?.push(hit_direction * 120.0);
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, time::{FixedTimestep, FixedTimesteps}, utils::HashMap};
+
+use crate::direction::Direction;
+
+pub const MOVEMENT_TIMESTEP: f64 = 1.0 / 60.0;
+const ARRIVE_DISTANCE: f32 = 2.0; // how close to a waypoint counts as reaching it, in pixels
+pub(crate) const MOVEMENT_STAGE: &str = "fixed_movement";
+const MOVEMENT_TIMESTEP_LABEL: &str = "movement_timestep";
+
+// Which way an entity wants to move this frame. Its length is how fast,
+// from 0.0 (standing still) to 1.0 (full speed), so analog sticks can walk
+// slowly. Longer vectors are treated as 1.0.
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct MoveIntent(pub Vec2);
+
+// How fast an entity moves at full speed, in pixels per second
+#[derive(Component, Deref, DerefMut)]
+pub struct MoveSpeed(pub f32);
+
+// Lets an entity move faster while `active` (e.g., while Sprint is held)
+#[derive(Component)]
+pub struct Sprint {
+    pub multiplier: f32,
+    pub active: bool,
+}
+impl Sprint {
+    pub fn new(multiplier: f32) -> Self {
+        Self { multiplier, active: false }
+    }
+}
+
+// Things slowing an entity down or speeding it up (e.g., wading through
+// water), each multiplying its MoveSpeed, by the reason for it. Added
+// automatically to entities with a MoveSpeed.
+#[derive(Component, Default)]
+pub struct SpeedModifiers {
+    multipliers: HashMap<&'static str, f32>,
+}
+impl SpeedModifiers {
+    pub fn set(&mut self, reason: &'static str, multiplier: f32) {
+        self.multipliers.insert(reason, multiplier);
+    }
+    pub fn remove(&mut self, reason: &'static str) {
+        self.multipliers.remove(reason);
+    }
+    pub fn get(&self, reason: &'static str) -> Option<f32> {
+        self.multipliers.get(reason).copied()
+    }
+    pub fn total(&self) -> f32 {
+        self.multipliers.values().product()
+    }
+}
+
+// Extra movement that isn't steered, in pixels per second; e.g. knockback,
+// or a thrown object. Entities with a Velocity move even without a MoveIntent.
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct Velocity(pub Vec2);
+
+// How quickly an entity's Velocity dies away, per second; 0.0 coasts forever
+#[derive(Component, Deref, DerefMut)]
+pub struct Damping(pub f32);
+
+// The fastest an entity's Velocity can get, in pixels per second
+#[derive(Component, Deref, DerefMut)]
+pub struct MaxSpeed(pub f32);
+
+// Pushes to add to an entity's Velocity on the next movement step, in
+// pixels per second. Added automatically to entities with a Velocity.
+#[derive(Component, Default)]
+pub struct Impulse(Vec2);
+impl Impulse {
+    pub fn push(&mut self, change: Vec2) {
+        self.0 += change;
+    }
+}
+
+// Points for an entity to walk to, one after another. While it has
+// waypoints left, its MoveIntent is steered towards the next one.
+#[derive(Component, Default)]
+pub struct MovePath {
+    pub waypoints: VecDeque<Vec2>,
+}
+impl MovePath {
+    // Walk straight to `target`, replacing any current path
+    pub fn go_to(&mut self, target: Vec2) {
+        self.waypoints.clear();
+        self.waypoints.push_back(target);
+    }
+    pub fn clear(&mut self) {
+        self.waypoints.clear();
+    }
+    pub fn is_empty(&self) -> bool {
+        self.waypoints.is_empty()
+    }
+}
+
+// Where a moving entity really is, as of the last two movement steps.
+// Added automatically to entities with a MoveIntent. To move an entity
+// directly (e.g., through a door), use `teleport` rather than changing
+// its Transform, which is overwritten every frame.
+#[derive(Component)]
+pub struct Position {
+    pub current: Vec2,
+    pub previous: Vec2,
+}
+impl Position {
+    pub fn new(position: Vec2) -> Self {
+        Self { current: position, previous: position }
+    }
+    // Jump to a position, without sliding there
+    pub fn teleport(&mut self, position: Vec2) {
+        self.current = position;
+        self.previous = position;
+    }
+}
+
+// Systems that set MoveIntent should run `.before(MovementSystem)`, and
+// systems that read an entity's position or Direction `.after(MovementSystem)`
+#[derive(SystemLabel)]
+pub struct MovementSystem;
+
+// The fixed-rate step that moves each Position. Systems that adjust the
+// move (e.g., collision) go in MOVEMENT_STAGE, `.after(MovementStep)`
+#[derive(SystemLabel)]
+pub struct MovementStep;
+
+pub struct MovementPlugin;
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_stage_after(CoreStage::Update, MOVEMENT_STAGE,
+                SystemStage::parallel()
+                    .with_run_criteria(FixedTimestep::step(MOVEMENT_TIMESTEP)
+                        .with_label(MOVEMENT_TIMESTEP_LABEL))
+                    .with_system(apply_move_intents.label(MovementStep))
+                    .with_system(apply_velocities.label(MovementStep).after(apply_move_intents)))
+            .add_system(add_positions.before(MovementSystem))
+            .add_system(add_impulses)
+            .add_system(add_speed_modifiers)
+            .add_system(follow_move_paths.before(MovementSystem))
+            .add_system(interpolate_transforms.label(MovementSystem));
+    }
+}
+
+fn add_positions(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform), (Or<(With<MoveIntent>, With<Velocity>)>, Without<Position>)>,
+) {
+    for (entity, transform) in &query {
+        commands.entity(entity).insert(Position::new(transform.translation.truncate()));
+    }
+}
+
+fn add_impulses(
+    mut commands: Commands,
+    query: Query<Entity, (With<Velocity>, Without<Impulse>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(Impulse::default());
+    }
+}
+
+fn add_speed_modifiers(
+    mut commands: Commands,
+    query: Query<Entity, (With<MoveSpeed>, Without<SpeedModifiers>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(SpeedModifiers::default());
+    }
+}
+
+// Steer towards the next waypoint, and stop at the last one
+pub(crate) fn follow_move_paths(
+    mut query: Query<(&mut MovePath, &Position, &mut MoveIntent)>,
+) {
+    for (mut path, position, mut intent) in &mut query {
+        if path.is_empty() {
+            continue; // leave the MoveIntent to whatever else sets it
+        }
+        while let Some(waypoint) = path.waypoints.front() {
+            if waypoint.distance(position.current) > ARRIVE_DISTANCE {
+                break;
+            }
+            path.waypoints.pop_front();
+        }
+        intent.0 = match path.waypoints.front() {
+            Some(waypoint) => (*waypoint - position.current).normalize_or_zero(),
+            None => Vec2::ZERO,
+        };
+    }
+}
+
+fn apply_move_intents(
+    mut query: Query<(
+        &MoveIntent,
+        &MoveSpeed,
+        Option<&Sprint>,
+        Option<&SpeedModifiers>,
+        &mut Position,
+        Option<&mut Direction>,
+    )>,
+) {
+    for (intent, speed, sprint, modifiers, mut position, direction) in &mut query {
+        position.previous = position.current;
+        let mut multiplier = match sprint {
+            Some(sprint) if sprint.active => sprint.multiplier,
+            _ => 1.0,
+        };
+        if let Some(modifiers) = modifiers {
+            multiplier *= modifiers.total();
+        }
+        let velocity = intent.clamp_length_max(1.0) * speed.0 * multiplier;
+        if velocity == Vec2::ZERO {
+            continue;
+        }
+        position.current += velocity * MOVEMENT_TIMESTEP as f32;
+
+        // Face the way we're moving
+        if let (Some(mut direction), Some(new_direction)) = (direction, Direction::from_vec2(velocity)) {
+            if *direction != new_direction {
+                *direction = new_direction;
+            }
+        }
+    }
+}
+
+// Add on any pushes, slow down, and coast
+fn apply_velocities(
+    mut query: Query<(
+        &mut Velocity,
+        &mut Position,
+        Option<&mut Impulse>,
+        Option<&Damping>,
+        Option<&MaxSpeed>,
+        Option<&MoveIntent>,
+    )>,
+) {
+    let dt = MOVEMENT_TIMESTEP as f32;
+    for (mut velocity, mut position, impulse, damping, max_speed, intent) in &mut query {
+        // Entities steering themselves have already started this step
+        if intent.is_none() {
+            position.previous = position.current;
+        }
+        if let Some(mut impulse) = impulse {
+            if impulse.0 != Vec2::ZERO {
+                velocity.0 += impulse.0;
+                impulse.0 = Vec2::ZERO;
+            }
+        }
+        if velocity.0 == Vec2::ZERO {
+            continue;
+        }
+        if let Some(damping) = damping {
+            velocity.0 *= (-damping.0 * dt).exp();
+        }
+        if let Some(max_speed) = max_speed {
+            velocity.0 = velocity.0.clamp_length_max(max_speed.0);
+        }
+        // Come to a stop rather than creeping along forever
+        if velocity.0.length_squared() < 0.01 {
+            velocity.0 = Vec2::ZERO;
+        }
+        position.current += velocity.0 * dt;
+    }
+}
+
+// Place each Transform between the previous and current movement steps,
+// by how far we are into the next step
+fn interpolate_transforms(
+    fixed_timesteps: Res<FixedTimesteps>,
+    mut query: Query<(&Position, &mut Transform)>,
+) {
+    let alpha = fixed_timesteps.get(MOVEMENT_TIMESTEP_LABEL)
+        .map_or(1.0, |timestep| timestep.overstep_percentage() as f32);
+    for (position, mut transform) in &mut query {
+        let interpolated = position.previous.lerp(position.current, alpha);
+        if transform.translation.truncate() != interpolated {
+            transform.translation.x = interpolated.x;
+            transform.translation.y = interpolated.y;
+        }
+    }
+}
@@ -0,0 +1,125 @@
+// :: Followers ::
+// A companion that trails along behind a leader (usually the player),
+// like a JRPG party. The leader's path is dropped as a trail of
+// breadcrumbs, and the follower retraces it `distance` pixels behind, so
+// it goes around the same corners instead of cutting through walls. It
+// stops when the leader stops, and faces, walks and sprints as the leader
+// did:
+//
+//     commands.spawn((NpcBundle::new(32.0), Follower::new(player, 24.0), ..));
+//
+// Followers can follow followers, for a whole party in a line. When the
+// leader teleports (e.g., through a Warp), the follower jumps along too.
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    direction::Direction,
+    movement::{MoveIntent, Position, Sprint},
+};
+
+const BREADCRUMB_SPACING: f32 = 1.0; // how far the leader moves between breadcrumbs, in pixels
+const TELEPORT_DISTANCE: f32 = 64.0; // a leader moving this far in one step has teleported
+
+#[derive(Component, Clone, Debug)]
+pub struct Follower {
+    pub leader: Entity,
+    pub distance: f32, // how far behind the leader to walk, along its path, in pixels
+    trail: VecDeque<Vec2>, // where the leader's been, newest first
+    placed: Option<Vec2>, // where the follower was put last step
+}
+impl Follower {
+    pub fn new(leader: Entity, distance: f32) -> Self {
+        Self { leader, distance, trail: VecDeque::new(), placed: None }
+    }
+
+    // The point `distance` back along the trail from `from`, dropping the
+    // breadcrumbs past it. None if the trail isn't that long yet.
+    fn point_behind(&mut self, from: Vec2) -> Option<Vec2> {
+        let mut walked = 0.0;
+        let mut last = from;
+        for index in 0..self.trail.len() {
+            let crumb = self.trail[index];
+            let length = last.distance(crumb);
+            if walked + length >= self.distance {
+                self.trail.truncate(index + 1);
+                return Some(last.lerp(crumb, (self.distance - walked) / length.max(f32::EPSILON)));
+            }
+            walked += length;
+            last = crumb;
+        }
+        None
+    }
+}
+
+type LeaderQuery<'w, 's> = Query<'w, 's, (&'static Position, Option<&'static Sprint>)>;
+type FollowerQuery<'w, 's> = Query<'w, 's, (
+    Entity,
+    &'static mut Follower,
+    &'static mut Position,
+    &'static mut MoveIntent,
+    Option<&'static mut Direction>,
+    Option<&'static mut Sprint>,
+)>;
+
+// Runs each movement step, once the leaders have finished moving
+pub(super) fn follow_leaders(mut queries: ParamSet<(LeaderQuery, FollowerQuery)>) {
+    // Where every leader is (and whether it's sprinting), before any follower moves
+    let followers: Vec<(Entity, Entity)> = queries.p1().iter()
+        .map(|(entity, follower, ..)| (entity, follower.leader))
+        .collect();
+    let leaders: Vec<(Entity, Vec2, bool)> = followers.into_iter()
+        .filter_map(|(entity, leader)| {
+            let (position, sprint) = queries.p0().get(leader).ok()?;
+            Some((entity, position.current, sprint.map_or(false, |sprint| sprint.active)))
+        })
+        .collect();
+
+    let mut followers = queries.p1();
+    for (entity, leader_position, leader_sprinting) in leaders {
+        let (_, mut follower, mut position, mut intent, direction, sprint) = match followers.get_mut(entity) {
+            Ok(follower) => follower,
+            Err(_) => continue,
+        };
+        let placed = *follower.placed.get_or_insert(position.current);
+        if follower.trail.is_empty() {
+            follower.trail.push_back(placed); // walk over from wherever we started
+        }
+
+        // Jump along with a teleport
+        let newest = follower.trail.front().copied().unwrap_or(placed);
+        if leader_position.distance(newest) > TELEPORT_DISTANCE {
+            follower.trail.clear();
+            follower.trail.push_back(leader_position);
+            follower.placed = Some(leader_position);
+            position.teleport(leader_position);
+            continue;
+        }
+        // Or drop a breadcrumb, and retrace the trail
+        if leader_position.distance(newest) >= BREADCRUMB_SPACING {
+            follower.trail.push_front(leader_position);
+        }
+        let next = follower.point_behind(leader_position).unwrap_or(placed);
+        position.previous = placed;
+        position.current = next;
+        follower.placed = Some(next);
+
+        // Walk (or run) the way we're going, like the leader did
+        let step = next - placed;
+        let wanted = step.normalize_or_zero();
+        if intent.0 != wanted {
+            intent.0 = wanted;
+        }
+        if let (Some(mut direction), Some(facing)) = (direction, Direction::from_vec2(step)) {
+            if *direction != facing {
+                *direction = facing;
+            }
+        }
+        if let Some(mut sprint) = sprint {
+            if sprint.active != leader_sprinting {
+                sprint.active = leader_sprinting;
+            }
+        }
+    }
+}
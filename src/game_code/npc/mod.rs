@@ -0,0 +1,117 @@
+// :: NPCs ::
+// Villagers, shopkeepers and other characters that move on their own.
+// An NPC is steered the same way the player is, with a MoveIntent, so
+// collision, surfaces and y-sorting all work on it unchanged; only what
+// sets the MoveIntent is different. Give it a behavior, like Wander, a
+// PatrolPath, a Schedule or a Follower:
+//
+//     commands.spawn((
+//         NpcBundle::new(20.0),
+//         Wander::new(48.0),
+//         Collider::new(Vec2::new(10.0, 6.0)).with_offset(Vec2::new(0.0, -13.0)),
+//         YSort::new(-16.0),
+//         npc_animations(), // a DirectionalAnimator<NpcAction, VillagerAnim>
+//         AnimationSource::<VillagerAnim>::new(asset_server.load("animations/villager.anim.ron")),
+//         SpriteSheetBundle { .. },
+//     ));
+//
+// Animation follows the same conventions as the player's: a
+// DirectionalAnimator maps NpcActions and Directions to the NPC's states
+// ("stand-*" and "move-*"), and NpcAnimationPlugin picks the action from
+// whether the NPC is moving. Add one NpcAnimationPlugin per state type.
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use crate::{
+    animation::{AnimState, DirectionalAnimationSystem, DirectionalAnimator},
+    collision::{CollisionSystem, Separation},
+    direction::Direction,
+    movement::{follow_move_paths, MoveIntent, MovePath, MoveSpeed, MovementSystem, MOVEMENT_STAGE},
+};
+
+mod follow;
+mod patrol;
+mod schedule;
+mod wander;
+
+pub use follow::Follower;
+pub use patrol::{Patrol, PatrolMode, PatrolPath};
+pub use schedule::{Schedule, ScheduleEntry, ScheduledBehavior};
+pub use wander::Wander;
+
+#[derive(Component)]
+pub struct Npc;
+
+// What an NPC is doing; combined with its Direction, this picks its
+// animation state
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum NpcAction {
+    Stand,
+    Move,
+}
+
+#[derive(Bundle)]
+pub struct NpcBundle {
+    pub npc: Npc,
+    pub direction: Direction,
+    pub move_intent: MoveIntent,
+    pub move_speed: MoveSpeed,
+    pub move_path: MovePath,
+    pub separation: Separation, // so crowds of NPCs spread out
+}
+impl NpcBundle {
+    // An NPC facing down, that walks at `speed` pixels per second
+    pub fn new(speed: f32) -> Self {
+        Self {
+            npc: Npc,
+            direction: Direction::S,
+            move_intent: MoveIntent::default(),
+            move_speed: MoveSpeed(speed),
+            move_path: MovePath::default(),
+            separation: Separation::default(),
+        }
+    }
+}
+
+// Systems that steer NPCs (by setting their MoveIntent)
+#[derive(SystemLabel)]
+pub struct NpcAiSystem;
+
+pub struct NpcPlugin;
+impl Plugin for NpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(wander::wander.label(NpcAiSystem).before(MovementSystem))
+            .add_system(patrol::add_patrols)
+            .add_system(patrol::patrol.label(NpcAiSystem).before(follow_move_paths))
+            .add_system(schedule::follow_schedules.label(NpcAiSystem).before(follow_move_paths))
+            .add_system_to_stage(MOVEMENT_STAGE, follow::follow_leaders.after(CollisionSystem));
+    }
+}
+
+pub struct NpcAnimationPlugin<S: AnimState>(PhantomData<S>);
+impl<S: AnimState> Default for NpcAnimationPlugin<S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<S: AnimState> Plugin for NpcAnimationPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.add_system(npc_animation::<S>
+            .after(NpcAiSystem)
+            .after(MovementSystem)
+            .before(DirectionalAnimationSystem));
+    }
+}
+
+// Walk while moving, otherwise stand
+fn npc_animation<S: AnimState>(
+    mut query: Query<(&MoveIntent, &mut DirectionalAnimator<NpcAction, S>), With<Npc>>,
+) {
+    for (intent, mut directional) in &mut query {
+        let action = if intent.0 == Vec2::ZERO { NpcAction::Stand } else { NpcAction::Move };
+        if directional.action != action {
+            directional.action = action;
+        }
+    }
+}
@@ -0,0 +1,145 @@
+// :: Patrols ::
+// Walks from waypoint to waypoint along a PatrolPath, waiting a while at
+// each one, e.g. a guard pacing a wall or a farmer doing their rounds:
+//
+//     commands.spawn((
+//         NpcBundle::new(24.0),
+//         PatrolPath(vec![Vec2::new(0.0, 0.0), Vec2::new(96.0, 0.0), Vec2::new(96.0, 64.0)]),
+//         Patrol::new(PatrolMode::PingPong).with_wait_time(2.0).with_wait_at(2, 5.0),
+//         ..
+//     ));
+//
+// Each leg is walked with the entity's MovePath, so its MoveIntent (and
+// so its animation) follows along without anything else to do.
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::movement::MovePath;
+
+const DEFAULT_WAIT_TIME: f32 = 1.0; // in seconds
+
+// The points to walk between, in order
+#[derive(Component, Clone, Default, Debug, Deref, DerefMut)]
+pub struct PatrolPath(pub Vec<Vec2>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PatrolMode {
+    #[default]
+    Loop, // from the last waypoint back to the first
+    PingPong, // back and forth, turning around at either end
+}
+
+// How to walk a PatrolPath. Added automatically (looping, waiting a second
+// at each waypoint) to entities with a PatrolPath.
+#[derive(Component, Clone, Debug)]
+pub struct Patrol {
+    pub mode: PatrolMode,
+    pub wait_time: f32, // seconds to wait at each waypoint
+    pub waits: HashMap<usize, f32>, // wait times for particular waypoints, by index
+    target: usize, // the waypoint being walked to, or waited at
+    forward: bool, // whether a ping-pong patrol is going up the path
+    state: PatrolState,
+}
+impl Default for Patrol {
+    fn default() -> Self {
+        Self::new(PatrolMode::default())
+    }
+}
+impl Patrol {
+    pub fn new(mode: PatrolMode) -> Self {
+        Self {
+            mode,
+            wait_time: DEFAULT_WAIT_TIME,
+            waits: HashMap::new(),
+            target: 0,
+            forward: true,
+            state: PatrolState::Starting,
+        }
+    }
+    pub fn with_wait_time(mut self, seconds: f32) -> Self {
+        self.wait_time = seconds;
+        self
+    }
+    // Wait `seconds` at the waypoint at `index`, instead of `wait_time`
+    pub fn with_wait_at(mut self, index: usize, seconds: f32) -> Self {
+        self.waits.insert(index, seconds);
+        self
+    }
+    // The index of the waypoint being walked to, or waited at
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
+    fn wait_time_at(&self, index: usize) -> f32 {
+        self.waits.get(&index).copied().unwrap_or(self.wait_time)
+    }
+
+    // Pick the next waypoint
+    fn advance(&mut self, len: usize) {
+        if len < 2 {
+            self.target = 0;
+            return;
+        }
+        match self.mode {
+            PatrolMode::Loop => self.target = (self.target + 1) % len,
+            PatrolMode::PingPong => {
+                if self.forward && self.target + 1 >= len {
+                    self.forward = false;
+                } else if !self.forward && self.target == 0 {
+                    self.forward = true;
+                }
+                self.target = if self.forward { self.target + 1 } else { self.target - 1 };
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PatrolState {
+    Starting,
+    Walking,
+    Waiting { remaining: f32 },
+}
+
+pub(super) fn add_patrols(
+    mut commands: Commands,
+    query: Query<Entity, (With<PatrolPath>, Without<Patrol>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(Patrol::default());
+    }
+}
+
+pub(super) fn patrol(
+    time: Res<Time>,
+    mut query: Query<(&PatrolPath, &mut Patrol, &mut MovePath)>,
+) {
+    for (path, mut patrol, mut move_path) in &mut query {
+        if path.is_empty() {
+            continue;
+        }
+        // The path may have been changed to a shorter one
+        if patrol.target >= path.len() {
+            patrol.target = 0;
+        }
+        match patrol.state {
+            PatrolState::Starting => {},
+            PatrolState::Walking if move_path.is_empty() => {
+                // Arrived
+                let wait = patrol.wait_time_at(patrol.target);
+                patrol.state = PatrolState::Waiting { remaining: wait };
+                if wait > 0.0 {
+                    continue;
+                }
+                patrol.advance(path.len());
+            },
+            PatrolState::Walking => continue,
+            PatrolState::Waiting { remaining } if remaining > time.delta_seconds() => {
+                patrol.state = PatrolState::Waiting { remaining: remaining - time.delta_seconds() };
+                continue;
+            },
+            PatrolState::Waiting { .. } => patrol.advance(path.len()),
+        }
+        move_path.go_to(path[patrol.target]);
+        patrol.state = PatrolState::Walking;
+    }
+}
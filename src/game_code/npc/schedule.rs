@@ -0,0 +1,102 @@
+// :: Schedules ::
+// Where an NPC should be at each hour of the in-game day (see GameClock),
+// and what to do once there. When the hour moves into a new entry, the
+// NPC finds its way to the entry's location (see FindPath), then stands
+// or wanders there:
+//
+//     Schedule::new()
+//         .with(9.0, 17.0, stall, ScheduledBehavior::Wander(16.0)) // minding the shop
+//         .with(17.0, 9.0, home, ScheduledBehavior::Stand) // overnight
+//
+// Entries can wrap past midnight, as above. Hours no entry covers leave
+// the NPC doing whatever it was last doing. A scheduled NPC's Wander is
+// managed by its schedule, so don't give it one of its own.
+use bevy::prelude::*;
+
+use super::Wander;
+use crate::{
+    clock::GameClock,
+    movement::{MoveIntent, MovePath},
+    pathfinding::FindPath,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScheduledBehavior {
+    Stand,
+    Wander(f32), // within this radius of the location
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ScheduleEntry {
+    pub start: f32, // the hour it starts
+    pub end: f32, // and the hour it ends; before `start` to wrap past midnight
+    pub location: Vec2,
+    pub behavior: ScheduledBehavior,
+}
+impl ScheduleEntry {
+    pub fn covers(&self, hour: f32) -> bool {
+        if self.start <= self.end {
+            hour >= self.start && hour < self.end
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+#[derive(Component, Clone, Default, Debug)]
+pub struct Schedule {
+    pub entries: Vec<ScheduleEntry>,
+    current: Option<usize>, // the entry being followed
+    arrived: bool,
+}
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // Be at `location` from `start` until `end` (in hours), doing `behavior`
+    pub fn with(mut self, start: f32, end: f32, location: Vec2, behavior: ScheduledBehavior) -> Self {
+        self.entries.push(ScheduleEntry { start, end, location, behavior });
+        self
+    }
+    // The entry being followed, if any
+    pub fn current(&self) -> Option<&ScheduleEntry> {
+        self.current.and_then(|index| self.entries.get(index))
+    }
+    // Whether the NPC has got to where its current entry wants it
+    pub fn has_arrived(&self) -> bool {
+        self.arrived
+    }
+}
+
+pub(super) fn follow_schedules(
+    mut commands: Commands,
+    clock: Res<GameClock>,
+    mut query: Query<(Entity, &mut Schedule, &mut MovePath, &mut MoveIntent, Option<&FindPath>)>,
+) {
+    for (entity, mut schedule, mut path, mut intent, finding) in &mut query {
+        let due = schedule.entries.iter().position(|entry| entry.covers(clock.hour()));
+        if let Some(index) = due.filter(|index| schedule.current != Some(*index)) {
+            // Time to be somewhere else; stop, and work out how to get there
+            schedule.current = Some(index);
+            schedule.arrived = false;
+            path.clear();
+            intent.0 = Vec2::ZERO;
+            let location = schedule.entries[index].location;
+            commands.entity(entity).remove::<Wander>().insert(FindPath::new(location));
+            continue;
+        }
+        let entry = match schedule.current() {
+            Some(entry) if !schedule.arrived => *entry,
+            _ => continue,
+        };
+        // Still working out the way, or walking it (if there was no way,
+        // make the best of wherever we got to)
+        if finding.is_some() || !path.is_empty() {
+            continue;
+        }
+        schedule.arrived = true;
+        if let ScheduledBehavior::Wander(radius) = entry.behavior {
+            commands.entity(entity).insert(Wander::new(radius).with_home(entry.location));
+        }
+    }
+}
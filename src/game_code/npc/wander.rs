@@ -0,0 +1,114 @@
+// :: Wandering ::
+// Ambles around near home: stand for a while, walk a little way in a
+// random direction, stand again. NPCs that stray past `radius` (e.g.,
+// pushed by a conveyor) head back towards home on their next walk, and
+// NPCs that walk into something stop and wait rather than walking on
+// the spot.
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    direction::Direction,
+    movement::{MoveIntent, MovePath, Position},
+};
+
+const STUCK_TIME: f32 = 0.25; // seconds of walking without moving before giving up
+
+#[derive(Component, Clone, Debug)]
+pub struct Wander {
+    pub radius: f32, // how far from home to go, in pixels
+    pub pause_time: (f32, f32), // seconds to stand between walks, at least and at most
+    pub walk_time: (f32, f32), // seconds to walk for, at least and at most
+    home: Option<Vec2>, // where the NPC was first seen, unless set
+    state: WanderState,
+}
+impl Wander {
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            pause_time: (1.0, 3.0),
+            walk_time: (0.5, 2.0),
+            home: None,
+            state: WanderState::Paused { remaining: 0.0 },
+        }
+    }
+    pub fn with_pause_time(mut self, min: f32, max: f32) -> Self {
+        self.pause_time = (min, max);
+        self
+    }
+    pub fn with_walk_time(mut self, min: f32, max: f32) -> Self {
+        self.walk_time = (min, max);
+        self
+    }
+    // Wander around `home` rather than where the NPC starts
+    pub fn with_home(mut self, home: Vec2) -> Self {
+        self.home = Some(home);
+        self
+    }
+    pub fn home(&self) -> Option<Vec2> {
+        self.home
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum WanderState {
+    Paused { remaining: f32 },
+    Walking { heading: Vec2, remaining: f32, elapsed: f32 },
+}
+
+// A random time between `min` and `max`
+fn random_time(rng: &mut impl Rng, (min, max): (f32, f32)) -> f32 {
+    min + rng.gen::<f32>() * (max - min).max(0.0)
+}
+
+pub(super) fn wander(
+    time: Res<Time>,
+    mut query: Query<(&mut Wander, &Position, &mut MoveIntent, Option<&MovePath>)>,
+) {
+    let mut rng = rand::thread_rng();
+    let dt = time.delta_seconds();
+    for (mut wander, position, mut intent, path) in &mut query {
+        if path.map_or(false, |path| !path.is_empty()) {
+            continue; // something else is steering it for now
+        }
+        let home = *wander.home.get_or_insert(position.current);
+        let away = position.current - home;
+        let next = match wander.state {
+            WanderState::Paused { remaining } if remaining > dt => WanderState::Paused { remaining: remaining - dt },
+            WanderState::Paused { .. } => {
+                // Pick a direction, heading home if we've strayed too far
+                let direction = if away.length() > wander.radius {
+                    Direction::from_vec2(-away)
+                } else {
+                    Some(Direction::ALL[rng.gen_range(0..Direction::ALL.len())])
+                };
+                match direction {
+                    Some(direction) => WanderState::Walking {
+                        heading: direction.to_vec2(),
+                        remaining: random_time(&mut rng, wander.walk_time),
+                        elapsed: 0.0,
+                    },
+                    None => WanderState::Paused { remaining: random_time(&mut rng, wander.pause_time) },
+                }
+            },
+            WanderState::Walking { heading, remaining, elapsed } => {
+                let leaving = away.length() > wander.radius && heading.dot(away) > 0.0;
+                let stuck = elapsed > STUCK_TIME && position.current == position.previous;
+                if remaining <= dt || leaving || stuck {
+                    WanderState::Paused { remaining: random_time(&mut rng, wander.pause_time) }
+                } else {
+                    WanderState::Walking { heading, remaining: remaining - dt, elapsed: elapsed + dt }
+                }
+            },
+        };
+        wander.state = next;
+
+        let wanted = match next {
+            WanderState::Walking { heading, .. } => heading,
+            WanderState::Paused { .. } => Vec2::ZERO,
+        };
+        if intent.0 != wanted {
+            intent.0 = wanted;
+        }
+    }
+}
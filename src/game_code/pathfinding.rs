@@ -0,0 +1,161 @@
+// :: Pathfinding ::
+// Routes around walls, water and trees using a map's CollisionGrid. Any
+// tile that blocks at all (even half tiles and one-way edges) is walked
+// around, and diagonal steps never cut a blocked corner.
+//
+// `find_path` returns the tiles to walk through, for anything that wants
+// to plan by hand. To send an entity somewhere, give it a FindPath; the
+// route is worked out and put in its MovePath, so it walks (and animates)
+// there like any other walk:
+//
+//     commands.entity(npc).insert(FindPath::new(market_stall));
+//
+// Targets off the map (or on a map without a CollisionGrid) are walked to
+// in a straight line. If there's no way to a target, the walk is dropped.
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::prelude::*;
+
+use crate::{
+    collision::{feet, Collider, CollisionGrid, TileShape},
+    movement::{follow_move_paths, MovePath, Position},
+};
+
+// Steps cost 10, or 14 diagonally (about 10 * sqrt(2)), to keep the sums in integers
+const STEP_COST: u32 = 10;
+const DIAGONAL_STEP_COST: u32 = 14;
+const ORTHOGONAL_STEPS: [IVec2; 4] = [IVec2::new(0, -1), IVec2::new(1, 0), IVec2::new(0, 1), IVec2::new(-1, 0)];
+const DIAGONAL_STEPS: [IVec2; 4] = [IVec2::new(1, -1), IVec2::new(1, 1), IVec2::new(-1, 1), IVec2::new(-1, -1)];
+
+// Asks for a route to `target` (a position in the world), which replaces
+// the entity's MovePath once found. Removed once it's been handled.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FindPath {
+    pub target: Vec2,
+    pub diagonal: bool, // whether the route can step diagonally between tiles
+}
+impl FindPath {
+    pub fn new(target: Vec2) -> Self {
+        Self { target, diagonal: true }
+    }
+    pub fn with_diagonal(mut self, diagonal: bool) -> Self {
+        self.diagonal = diagonal;
+        self
+    }
+}
+
+pub struct PathfindingPlugin;
+impl Plugin for PathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(find_paths.before(follow_move_paths));
+    }
+}
+
+// The shortest way from `start` to `goal` with A*, as every tile along it
+// (including both ends), or None if `goal` can't be reached
+pub fn find_path(grid: &CollisionGrid, start: UVec2, goal: UVec2, diagonal: bool) -> Option<Vec<UVec2>> {
+    let size = grid.size().as_ivec2();
+    let walkable = |tile: IVec2| {
+        tile.cmpge(IVec2::ZERO).all() && tile.cmplt(size).all()
+            && grid.shape(tile.as_uvec2()) == TileShape::Empty
+    };
+    let (start, goal) = (start.as_ivec2(), goal.as_ivec2());
+    if !walkable(goal) || start.cmpge(size).any() {
+        return None;
+    }
+    let index = |tile: IVec2| (tile.y * size.x + tile.x) as usize;
+    let tile_at = |index: usize| IVec2::new(index as i32 % size.x, index as i32 / size.x);
+    // The octile distance: as many diagonal steps as fit, then straight ones
+    let estimate = |tile: IVec2| {
+        let distance = (goal - tile).abs();
+        let (short, long) = (distance.min_element() as u32, distance.max_element() as u32);
+        if diagonal {
+            DIAGONAL_STEP_COST * short + STEP_COST * (long - short)
+        } else {
+            STEP_COST * (short + long)
+        }
+    };
+
+    let mut costs = vec![u32::MAX; (size.x * size.y) as usize];
+    let mut came_from: Vec<Option<usize>> = vec![None; costs.len()];
+    let mut open = BinaryHeap::new();
+    costs[index(start)] = 0;
+    open.push(Reverse((estimate(start), 0, index(start))));
+
+    while let Some(Reverse((_, cost, current))) = open.pop() {
+        if cost > costs[current] {
+            continue; // already reached more cheaply
+        }
+        let tile = tile_at(current);
+        if tile == goal {
+            let mut path = vec![tile.as_uvec2()];
+            let mut step = current;
+            while let Some(previous) = came_from[step] {
+                path.push(tile_at(previous).as_uvec2());
+                step = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let orthogonal = ORTHOGONAL_STEPS.iter().map(|step| (*step, STEP_COST));
+        let diagonals = DIAGONAL_STEPS.iter()
+            .filter(|_| diagonal)
+            // Only past corners where both sides are open
+            .filter(|step| walkable(tile + IVec2::new(step.x, 0)) && walkable(tile + IVec2::new(0, step.y)))
+            .map(|step| (*step, DIAGONAL_STEP_COST));
+        for (step, step_cost) in orthogonal.chain(diagonals) {
+            let next = tile + step;
+            if !walkable(next) {
+                continue;
+            }
+            let next_cost = cost + step_cost;
+            if next_cost < costs[index(next)] {
+                costs[index(next)] = next_cost;
+                came_from[index(next)] = Some(current);
+                open.push(Reverse((next_cost + estimate(next), next_cost, index(next))));
+            }
+        }
+    }
+    None
+}
+
+fn find_paths(
+    mut commands: Commands,
+    grids: Query<(&CollisionGrid, &GlobalTransform)>,
+    mut query: Query<(Entity, &FindPath, &Position, Option<&Collider>, &mut MovePath)>,
+) {
+    for (entity, request, position, collider, mut path) in &mut query {
+        commands.entity(entity).remove::<FindPath>();
+
+        // Plan for the entity's feet, which are what collide with tiles
+        let feet_offset = feet(collider, position.current) - position.current;
+        let route = grids.iter().find_map(|(grid, transform)| {
+            let corner = transform.translation().truncate();
+            let start = grid.tile_at(position.current + feet_offset - corner)?;
+            let goal = grid.tile_at(request.target + feet_offset - corner)?;
+            Some((grid, corner, start, goal))
+        });
+        let (grid, corner, start, goal) = match route {
+            Some(route) => route,
+            None => {
+                path.go_to(request.target);
+                continue;
+            }
+        };
+        path.clear();
+        let tiles = match find_path(grid, start, goal, request.diagonal) {
+            Some(tiles) => tiles,
+            None => continue,
+        };
+        // Walk to each tile where the route turns, then to the target itself
+        for window in tiles.windows(3) {
+            let (before, tile, after) = (window[0].as_ivec2(), window[1].as_ivec2(), window[2].as_ivec2());
+            if tile - before != after - tile {
+                let center = corner + grid.tile_rect(window[1]).center();
+                path.waypoints.push_back(center - feet_offset);
+            }
+        }
+        path.waypoints.push_back(request.target);
+    }
+}
@@ -0,0 +1,177 @@
+// :: Pause menu ::
+// Menu (Escape, or Start on a gamepad) pauses the game and opens a menu
+// of Resume, Settings and Quit. MoveUp and MoveDown pick one, Interact
+// chooses it, and Menu resumes. Settings sends an OpenSettings event, for
+// the settings screen (see settings/ui.rs) to open on; the pause menu
+// waits behind it until it's closed.
+//
+// While it's paused, the game's clock stands still: every frame's
+// Time::delta is zero, so anything that moves, animates, thinks or
+// counts down with it (movement, animation, AI, timers) stops where it
+// is, without each of them having to check. The player can't act either.
+// Things that run off Time::elapsed_seconds keep going.
+//
+// The menu only opens when nothing else has hold of the player, so Menu
+// closes other screens (the inventory, a shop) before it pauses.
+use bevy::{app::AppExit, prelude::*, time::TimeSystem};
+
+use crate::{
+    input::{Action, Actions, InputSystem},
+    player::PlayerControlLock,
+    settings::SettingsScreen,
+    ui::{set_visible, spawn_menu_screen, FONT_SIZE, TITLE_SIZE, UI_FONT, UNSELECTED_COLOR},
+};
+
+const CONTROL_LOCK: &str = "paused";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PauseOption {
+    Resume,
+    Settings,
+    Quit,
+}
+impl PauseOption {
+    pub const ALL: [PauseOption; 3] = [PauseOption::Resume, PauseOption::Settings, PauseOption::Quit];
+
+    fn label(&self) -> &'static str {
+        match self {
+            PauseOption::Resume => "Resume",
+            PauseOption::Settings => "Settings",
+            PauseOption::Quit => "Quit",
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct PauseMenu {
+    paused: bool,
+    cursor: usize,
+    was_locked: bool, // whether something else had the player last frame
+}
+impl PauseMenu {
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.cursor = 0;
+    }
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+pub struct OpenSettings;
+
+// Systems for screens opened from the pause menu can run `.after(PauseMenuSystem)`
+#[derive(SystemLabel)]
+pub struct PauseMenuSystem;
+
+#[derive(Component)]
+struct PauseRoot;
+
+#[derive(Component)]
+struct PauseText;
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenu>()
+            .add_event::<OpenSettings>()
+            .add_startup_system(spawn_pause_menu)
+            .add_system_to_stage(CoreStage::First, stop_the_clock.after(TimeSystem))
+            .add_system(navigate_pause_menu.label(PauseMenuSystem).after(InputSystem))
+            .add_system(show_pause_menu.after(navigate_pause_menu))
+            .add_system(update_pause_menu.after(navigate_pause_menu));
+    }
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    spawn_menu_screen(&mut commands, PauseRoot, PauseText);
+}
+
+// Undo this frame's tick of the clock, right after it's ticked
+fn stop_the_clock(menu: Res<PauseMenu>, mut time: ResMut<Time>) {
+    if let (true, Some(last_update)) = (menu.is_paused(), time.last_update()) {
+        time.update_with_instant(last_update);
+    }
+}
+
+fn navigate_pause_menu(
+    actions: Res<Actions>,
+    control_lock: Res<PlayerControlLock>,
+    settings_screen: Res<SettingsScreen>,
+    mut menu: ResMut<PauseMenu>,
+    mut settings: EventWriter<OpenSettings>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if !menu.is_paused() {
+        // Something that let go of the player this frame may have been closed by this same press
+        let menu_state = menu.bypass_change_detection();
+        let was_locked = std::mem::replace(&mut menu_state.was_locked, control_lock.is_locked());
+        if !was_locked && !control_lock.is_locked() && actions.just_pressed(Action::Menu) {
+            menu.pause();
+        }
+        return;
+    }
+    if settings_screen.is_open() {
+        return;
+    }
+    if actions.just_pressed(Action::Menu) {
+        menu.resume();
+        return;
+    }
+    let options = PauseOption::ALL.len();
+    if actions.just_pressed(Action::MoveUp) {
+        menu.cursor = (menu.cursor + options - 1) % options;
+    } else if actions.just_pressed(Action::MoveDown) {
+        menu.cursor = (menu.cursor + 1) % options;
+    } else if actions.just_pressed(Action::Interact) {
+        match PauseOption::ALL[menu.cursor] {
+            PauseOption::Resume => menu.resume(),
+            PauseOption::Settings => settings.send(OpenSettings),
+            PauseOption::Quit => exit.send(AppExit),
+        }
+    }
+}
+
+fn show_pause_menu(
+    menu: Res<PauseMenu>,
+    settings_screen: Res<SettingsScreen>,
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut roots: Query<&mut Visibility, With<PauseRoot>>,
+) {
+    if menu.is_changed() {
+        if menu.is_paused() {
+            control_lock.lock(CONTROL_LOCK);
+        } else {
+            control_lock.unlock(CONTROL_LOCK);
+        }
+    }
+    let visible = menu.is_paused() && !settings_screen.is_open();
+    for mut visibility in &mut roots {
+        set_visible(&mut visibility, visible);
+    }
+}
+
+fn update_pause_menu(
+    asset_server: Res<AssetServer>,
+    menu: Res<PauseMenu>,
+    mut texts: Query<&mut Text, With<PauseText>>,
+) {
+    if !menu.is_changed() || !menu.is_paused() {
+        return;
+    }
+    let font = asset_server.load(UI_FONT);
+    let style = |size: f32, color: Color| TextStyle { font: font.clone(), font_size: size, color };
+    let mut sections = vec![TextSection::new("Paused\n\n", style(TITLE_SIZE, Color::WHITE))];
+    for (index, option) in PauseOption::ALL.iter().enumerate() {
+        let selected = index == menu.cursor;
+        let arrow = if selected { "> " } else { "  " };
+        let color = if selected { Color::WHITE } else { UNSELECTED_COLOR };
+        sections.push(TextSection::new(format!("{}{}\n", arrow, option.label()), style(FONT_SIZE, color)));
+    }
+    for mut text in &mut texts {
+        text.sections = sections.clone();
+    }
+}
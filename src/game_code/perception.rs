@@ -0,0 +1,195 @@
+// :: Perception ::
+// What enemies (or anything else watching) can see and hear. An entity
+// with Vision sees Perceivable entities in a cone in front of it (the way
+// its Direction faces), unless a solid tile is in the way. An entity with
+// Hearing hears Noise events close enough to it; Perceivable entities
+// make a noise with every Footstep, and anything can make one:
+//
+//     noises.send(Noise { position: crate_pos, radius: 96.0, source: None }); // a crate smashing
+//
+// What was last seen or heard is kept in the watcher's PerceivedTarget,
+// which AI (see EnemyAlert) acts on. Targets are found with the
+// SpatialHash, so the SpatialHashPlugin is needed too.
+use bevy::prelude::*;
+
+use crate::{
+    collision::{CollisionGrid, TileShape},
+    direction::Direction,
+    footsteps::Footstep,
+    spatial::SpatialHash,
+};
+
+const FOOTSTEP_NOISE: f32 = 48.0; // how far footsteps can be heard, in pixels
+
+// Something that can be seen and heard, like the player
+#[derive(Component, Default)]
+pub struct Perceivable;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Vision {
+    pub range: f32, // in pixels
+    pub angle: f32, // how wide the cone is, in degrees; 360.0 sees all around
+}
+impl Vision {
+    pub fn new(range: f32, angle: f32) -> Self {
+        Self { range, angle }
+    }
+    // Whether `offset` (from the watcher to a target) is inside the cone,
+    // for a watcher facing `facing`
+    pub fn covers(&self, facing: Vec2, offset: Vec2) -> bool {
+        if offset.length_squared() > self.range * self.range {
+            return false;
+        }
+        if self.angle >= 360.0 || offset == Vec2::ZERO {
+            return true;
+        }
+        facing.angle_between(offset).abs().to_degrees() <= self.angle / 2.0
+    }
+}
+
+// How well an entity hears; noises carry `sensitivity` times as far for it
+#[derive(Component, Clone, Copy, Debug, Deref, DerefMut)]
+pub struct Hearing(pub f32);
+impl Default for Hearing {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+pub struct Noise {
+    pub position: Vec2,
+    pub radius: f32, // how far away it can be heard, in pixels
+    pub source: Option<Entity>, // who made it, if it was someone
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sense {
+    Sight,
+    Hearing,
+}
+
+// The last thing an entity noticed. Added automatically to entities with
+// Vision or Hearing.
+#[derive(Component, Default, Debug)]
+pub struct PerceivedTarget {
+    pub entity: Option<Entity>, // who it was, if it was someone
+    pub last_known: Option<Vec2>, // where it was
+    pub sense: Option<Sense>, // how it's noticed right now; None once it's out of sight and quiet
+    pub time_since: f32, // seconds since it was last noticed
+}
+impl PerceivedTarget {
+    pub fn is_seen(&self) -> bool {
+        self.sense == Some(Sense::Sight)
+    }
+    pub fn forget(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// Systems that read PerceivedTarget should run `.after(PerceptionSystem)`
+#[derive(SystemLabel)]
+pub struct PerceptionSystem;
+
+pub struct PerceptionPlugin;
+impl Plugin for PerceptionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Noise>()
+            .add_system(add_perceived_targets)
+            .add_system(footstep_noises)
+            .add_system(perceive.label(PerceptionSystem).after(footstep_noises));
+    }
+}
+
+fn add_perceived_targets(
+    mut commands: Commands,
+    query: Query<Entity, (Or<(With<Vision>, With<Hearing>)>, Without<PerceivedTarget>)>,
+) {
+    for entity in &query {
+        commands.entity(entity).insert(PerceivedTarget::default());
+    }
+}
+
+fn footstep_noises(
+    mut footsteps: EventReader<Footstep>,
+    mut noises: EventWriter<Noise>,
+    perceivable: Query<(), With<Perceivable>>,
+) {
+    for step in footsteps.iter().filter(|step| perceivable.contains(step.entity)) {
+        noises.send(Noise { position: step.position, radius: FOOTSTEP_NOISE, source: Some(step.entity) });
+    }
+}
+
+// Whether a straight line between two points crosses a solid tile
+pub fn line_of_sight(grids: &Query<(&CollisionGrid, &GlobalTransform)>, from: Vec2, to: Vec2) -> bool {
+    grids.iter().all(|(grid, transform)| {
+        let corner = transform.translation().truncate();
+        // Check every quarter tile along the line
+        let spacing = grid.tile_size().min_element() / 4.0;
+        let samples = (from.distance(to) / spacing).ceil() as usize;
+        (0..=samples).all(|sample| {
+            let point = from.lerp(to, sample as f32 / samples.max(1) as f32) - corner;
+            grid.tile_at(point).map_or(true, |tile| grid.shape(tile) != TileShape::Solid)
+        })
+    })
+}
+
+fn perceive(
+    time: Res<Time>,
+    spatial_hash: Res<SpatialHash>,
+    mut noises: EventReader<Noise>,
+    grids: Query<(&CollisionGrid, &GlobalTransform)>,
+    targets: Query<(Entity, &GlobalTransform), With<Perceivable>>,
+    mut watchers: Query<(
+        Entity,
+        &GlobalTransform,
+        Option<&Vision>,
+        Option<&Hearing>,
+        Option<&Direction>,
+        &mut PerceivedTarget,
+    )>,
+) {
+    let noises: Vec<&Noise> = noises.iter().collect();
+    for (entity, transform, vision, hearing, direction, mut perceived) in &mut watchers {
+        let eye = transform.translation().truncate();
+
+        // The closest target in sight
+        let seen = vision.and_then(|vision| {
+            let facing = direction.map_or(Vec2::NEG_Y, Direction::to_vec2);
+            spatial_hash.query_radius(eye, vision.range).into_iter()
+                .filter(|target| *target != entity)
+                .filter_map(|target| targets.get(target).ok())
+                .map(|(target, target_transform)| (target, target_transform.translation().truncate()))
+                .filter(|(_, position)| vision.covers(facing, *position - eye))
+                .filter(|(_, position)| line_of_sight(&grids, eye, *position))
+                .min_by(|(_, a), (_, b)| a.distance_squared(eye).total_cmp(&b.distance_squared(eye)))
+        });
+        // Or failing that, the loudest noise heard
+        let heard = hearing.and_then(|hearing| {
+            noises.iter()
+                .filter(|noise| noise.source != Some(entity))
+                .filter(|noise| noise.position.distance(eye) <= noise.radius * hearing.0)
+                .max_by(|a, b| a.radius.total_cmp(&b.radius))
+                .map(|noise| (noise.source, noise.position))
+        });
+
+        match (seen, heard) {
+            (Some((target, position)), _) => {
+                perceived.entity = Some(target);
+                perceived.last_known = Some(position);
+                perceived.sense = Some(Sense::Sight);
+                perceived.time_since = 0.0;
+            },
+            (None, Some((source, position))) => {
+                perceived.entity = source.or(perceived.entity);
+                perceived.last_known = Some(position);
+                perceived.sense = Some(Sense::Hearing);
+                perceived.time_since = 0.0;
+            },
+            (None, None) if perceived.last_known.is_some() => {
+                perceived.sense = None;
+                perceived.time_since += time.delta_seconds();
+            },
+            (None, None) => {},
+        }
+    }
+}
@@ -0,0 +1,191 @@
+// :: Pickups ::
+// Items lying about in the world, bobbing gently, that go into the
+// player's Inventory when they walk over them:
+//
+//     commands.spawn((
+//         Pickup::new("apple", 2),
+//         SpatialBundle::from_transform(Transform::from_xyz(40.0, 20.0, 0.0)),
+//     ));
+//
+// A pickup is drawn with its item's icon, and gets a TriggerZone and a
+// YSort (unless it has them already); the player's Collider has to touch
+// the zone. Picking one up sends a PickedUp event (and a
+// QuestEvent::Collected), plays its sound from PickupSounds and shows a
+// "Got X!" toast. If there's only room for some of it, the rest stays
+// where it is.
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    collision::{TriggerEnter, TriggerZone},
+    inventory::{Inventory, ItemRegistry},
+    player::Player,
+    quest::{QuestEvent, QuestSystem},
+    settings::Settings,
+    toast::Toasts,
+    ysort::YSort,
+};
+
+const PICKUP_SIZE: f32 = 12.0;
+const BOB_HEIGHT: f32 = 2.0; // in pixels
+const BOB_PERIOD: f32 = 1.2; // in seconds
+
+#[derive(Component, Clone, Debug)]
+pub struct Pickup {
+    pub item_id: String,
+    pub count: u32,
+}
+impl Pickup {
+    pub fn new(item_id: &str, count: u32) -> Self {
+        Self { item_id: item_id.to_string(), count }
+    }
+}
+
+// The icon a pickup is drawn with, a child of the pickup so it can bob
+// without moving its TriggerZone
+#[derive(Component)]
+struct PickupIcon {
+    phase: f32, // so pickups next to each other don't bob in step
+}
+
+pub struct PickedUp {
+    pub collector: Entity,
+    pub item_id: String,
+    pub count: u32,
+}
+
+// The sound to play for picking up each item, and for items without one
+#[derive(Resource, Default)]
+pub struct PickupSounds {
+    pub sounds: HashMap<String, Handle<AudioSource>>,
+    pub default: Option<Handle<AudioSource>>,
+    pub volume: f32,
+}
+impl PickupSounds {
+    pub fn with_sound(mut self, item_id: &str, sound: Handle<AudioSource>) -> Self {
+        self.sounds.insert(item_id.to_string(), sound);
+        self
+    }
+    pub fn with_default(mut self, sound: Handle<AudioSource>) -> Self {
+        self.default = Some(sound);
+        self
+    }
+}
+
+pub struct PickupPlugin;
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PickedUp>()
+            .insert_resource(PickupSounds { volume: 0.5, ..default() })
+            .add_system(add_pickup_zones)
+            .add_system(add_pickup_icons)
+            .add_system(bob_pickups)
+            .add_system(collect_pickups.before(QuestSystem))
+            .add_system(pickup_feedback.after(collect_pickups));
+    }
+}
+
+fn add_pickup_zones(
+    mut commands: Commands,
+    pickups: Query<(Entity, Option<&TriggerZone>, Option<&YSort>), Added<Pickup>>,
+) {
+    for (entity, zone, ysort) in &pickups {
+        if zone.is_none() {
+            commands.entity(entity).insert(TriggerZone::new(Vec2::splat(PICKUP_SIZE)));
+        }
+        if ysort.is_none() {
+            commands.entity(entity).insert(YSort::new(-PICKUP_SIZE / 2.0));
+        }
+    }
+}
+
+// Once the item's icon is known (its catalog might still be loading)
+fn add_pickup_icons(
+    mut commands: Commands,
+    items: Res<ItemRegistry>,
+    pickups: Query<(Entity, &Pickup, &GlobalTransform, Option<&Children>)>,
+    icons: Query<(), With<PickupIcon>>,
+) {
+    for (entity, pickup, transform, children) in &pickups {
+        if children.map_or(false, |children| children.iter().any(|child| icons.contains(*child))) {
+            continue;
+        }
+        let item = match items.get(&pickup.item_id) {
+            Some(item) => item,
+            None => continue,
+        };
+        let position = transform.translation();
+        commands.entity(entity).with_children(|pickup| {
+            pickup.spawn((
+                PickupIcon { phase: (position.x + position.y) * 0.1 },
+                SpriteSheetBundle {
+                    texture_atlas: item.atlas.clone(),
+                    sprite: TextureAtlasSprite::new(item.icon),
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+fn bob_pickups(time: Res<Time>, mut icons: Query<(&PickupIcon, &mut Transform)>) {
+    let t = time.elapsed_seconds();
+    for (icon, mut transform) in &mut icons {
+        transform.translation.y = BOB_HEIGHT * (t * TAU / BOB_PERIOD + icon.phase).sin();
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    items: Res<ItemRegistry>,
+    mut trigger_enters: EventReader<TriggerEnter>,
+    mut picked_up: EventWriter<PickedUp>,
+    mut quest_events: EventWriter<QuestEvent>,
+    mut toasts: ResMut<Toasts>,
+    mut pickups: Query<&mut Pickup>,
+    mut players: Query<&mut Inventory, With<Player>>,
+) {
+    for enter in trigger_enters.iter() {
+        let (mut pickup, mut inventory) = match (pickups.get_mut(enter.zone), players.get_mut(enter.sensor)) {
+            (Ok(pickup), Ok(inventory)) => (pickup, inventory),
+            _ => continue,
+        };
+        if pickup.count == 0 {
+            continue; // already picked up this frame, by another player
+        }
+        let left = inventory.add(&items, &pickup.item_id, pickup.count);
+        let count = pickup.count - left;
+        if count == 0 {
+            toasts.show(format!("No room for {}", items.name(&pickup.item_id)));
+            continue;
+        }
+        picked_up.send(PickedUp { collector: enter.sensor, item_id: pickup.item_id.clone(), count });
+        quest_events.send(QuestEvent::Collected { item: pickup.item_id.clone(), count });
+        pickup.count = left;
+        if left == 0 {
+            commands.entity(enter.zone).despawn_recursive();
+        }
+    }
+}
+
+fn pickup_feedback(
+    audio: Res<Audio>,
+    sounds: Res<PickupSounds>,
+    settings: Res<Settings>,
+    items: Res<ItemRegistry>,
+    mut toasts: ResMut<Toasts>,
+    mut picked_up: EventReader<PickedUp>,
+) {
+    for event in picked_up.iter() {
+        let name = items.name(&event.item_id);
+        if event.count > 1 {
+            toasts.show(format!("Got {} x{}!", name, event.count));
+        } else {
+            toasts.show(format!("Got {}!", name));
+        }
+        if let Some(sound) = sounds.sounds.get(&event.item_id).or(sounds.default.as_ref()) {
+            audio.play_with_settings(sound.clone(), PlaybackSettings::ONCE.with_volume(sounds.volume * settings.effects_volume()));
+        }
+    }
+}
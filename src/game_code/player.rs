@@ -0,0 +1,147 @@
+// :: The player ::
+// What the player is doing, as a state machine. Only certain changes are
+// allowed (e.g., a cutscene can't start in the middle of a menu), and other
+// systems check the state rather than guessing from animations: player input
+// is ignored unless the player `can_move`.
+use bevy::{ecs::schedule::ShouldRun, prelude::*, utils::{HashMap, HashSet}};
+
+use crate::movement::{MoveIntent, MovementSystem};
+
+#[derive(Component)]
+pub struct Player;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum PlayerState {
+    #[default]
+    Idle,
+    Walking,
+    Interacting, // talking to an NPC, reading a sign, ...
+    Attacking, // swinging a weapon (see combat/melee.rs)
+    Cutscene,
+    Menu,
+    Dead, // until they're brought back (see game_over/mod.rs)
+}
+impl PlayerState {
+    // Whether the player can be moved by input
+    pub fn can_move(&self) -> bool {
+        matches!(self, PlayerState::Idle | PlayerState::Walking)
+    }
+
+    pub fn can_transition_to(&self, next: PlayerState) -> bool {
+        use PlayerState::*;
+        match (*self, next) {
+            (from, to) if from == to => true,
+            (_, Dead) => true, // whatever they were doing
+            (Idle | Walking, _) => true,
+            (Interacting, Idle | Cutscene | Menu) => true, // e.g. a conversation that starts a cutscene
+            (Attacking, Idle | Cutscene) => true,
+            (Cutscene, Idle) => true,
+            (Menu, Idle) => true,
+            (Dead, Idle) => true,
+            _ => false,
+        }
+    }
+
+    // Change state, if allowed
+    pub fn transition(&mut self, next: PlayerState) -> Result<(), PlayerStateError> {
+        if !self.can_transition_to(next) {
+            return Err(PlayerStateError { from: *self, to: next });
+        }
+        *self = next;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PlayerStateError {
+    pub from: PlayerState,
+    pub to: PlayerState,
+}
+impl std::fmt::Display for PlayerStateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "The player can't go from {:?} to {:?}", self.from, self.to)
+    }
+}
+impl std::error::Error for PlayerStateError {}
+
+// Takes control away from the player while anything holds a lock on it.
+// Dialogue boxes, cutscenes and menus each lock with their own reason, so
+// one ending (e.g., a conversation inside a cutscene) doesn't hand control
+// back while another is still going:
+//
+//     control_lock.lock("dialogue");
+//     ...
+//     control_lock.unlock("dialogue");
+//
+// Only movement is locked; Actions still work, so the player can
+// advance dialogue and pick menu options.
+#[derive(Resource, Default)]
+pub struct PlayerControlLock {
+    reasons: HashSet<&'static str>,
+}
+impl PlayerControlLock {
+    pub fn lock(&mut self, reason: &'static str) {
+        self.reasons.insert(reason);
+    }
+    pub fn unlock(&mut self, reason: &'static str) {
+        self.reasons.remove(reason);
+    }
+    pub fn is_locked(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+}
+
+// Run criteria for systems that should only run while the player has control,
+// e.g. `.with_run_criteria(player_has_control)`
+pub fn player_has_control(control_lock: Res<PlayerControlLock>) -> ShouldRun {
+    if control_lock.is_locked() { ShouldRun::No } else { ShouldRun::Yes }
+}
+
+// Sent whenever the player's state changes
+pub struct PlayerStateChanged {
+    pub entity: Entity,
+    pub from: PlayerState,
+    pub to: PlayerState,
+}
+
+pub struct PlayerPlugin;
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerControlLock>()
+            .add_event::<PlayerStateChanged>()
+            .add_system(update_walking_state.after(MovementSystem))
+            .add_system(send_state_changes.after(update_walking_state));
+    }
+}
+
+// Switch between Idle and Walking as the player starts and stops
+pub(crate) fn update_walking_state(mut query: Query<(&MoveIntent, &mut PlayerState), With<Player>>) {
+    for (intent, mut state) in &mut query {
+        let next = match *state {
+            PlayerState::Idle | PlayerState::Walking if intent.0 != Vec2::ZERO => PlayerState::Walking,
+            PlayerState::Walking => PlayerState::Idle,
+            _ => continue,
+        };
+        if *state != next {
+            *state = next;
+        }
+    }
+}
+
+fn send_state_changes(
+    mut events: EventWriter<PlayerStateChanged>,
+    mut last_states: Local<HashMap<Entity, PlayerState>>,
+    query: Query<(Entity, &PlayerState), Changed<PlayerState>>,
+    removed: RemovedComponents<Player>,
+) {
+    // Forget players that are gone
+    for entity in removed.iter() {
+        last_states.remove(&entity);
+    }
+    for (entity, state) in &query {
+        let from = last_states.insert(entity, *state).unwrap_or_default();
+        if from != *state {
+            events.send(PlayerStateChanged { entity, from, to: *state });
+        }
+    }
+}
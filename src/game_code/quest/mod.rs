@@ -0,0 +1,307 @@
+// :: Quests ::
+// Things for the player to do, tracked in the QuestLog. A quest is a list
+// of stages, done in order; a stage is done once all of its objectives
+// are, and the quest once its last stage is:
+//
+//     quest_log.start(QuestDef::new("apples", "An Apple a Day")
+//         .with_description("The shopkeeper sells the best apples in the valley.")
+//         .stage("Buy an apple", vec![
+//             Objective::talk_to("shopkeeper", "Visit the shopkeeper's stall"),
+//             Objective::flag("bought_apple", "Buy an apple"),
+//         ])
+//         .stage("Share it", vec![Objective::talk_to("villager", "Find someone to share it with")]));
+//
+// Objectives are met by gameplay: talking to an entity with that Name
+// (starting a conversation with it), a GameFlag being set, or a
+// QuestEvent sent by anything else, e.g. for picking up items:
+//
+//     quest_events.send(QuestEvent::Collected { item: "apple".to_string(), count: 1 });
+//
+// QuestUpdated events say when quests start, move on or finish, and a
+// toast tells the player. Press Journal to see the quest log.
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    dialogue::{DialogueStarted, DialogueSystem},
+    flags::GameFlags,
+    toast::Toasts,
+};
+
+mod ui;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub enum ObjectiveKind {
+    TalkTo(String), // an entity's Name
+    Collect { item: String, count: u32 },
+    Flag(String), // a GameFlag that has to be set
+    Event(String), // a QuestEvent::Custom with this name
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Objective {
+    pub description: String, // shown in the quest log
+    pub kind: ObjectiveKind,
+}
+impl Objective {
+    pub fn talk_to(name: &str, description: &str) -> Self {
+        Self::new(ObjectiveKind::TalkTo(name.to_string()), description)
+    }
+    pub fn collect(item: &str, count: u32, description: &str) -> Self {
+        Self::new(ObjectiveKind::Collect { item: item.to_string(), count }, description)
+    }
+    pub fn flag(flag: &str, description: &str) -> Self {
+        Self::new(ObjectiveKind::Flag(flag.to_string()), description)
+    }
+    pub fn event(name: &str, description: &str) -> Self {
+        Self::new(ObjectiveKind::Event(name.to_string()), description)
+    }
+    fn new(kind: ObjectiveKind, description: &str) -> Self {
+        Self { description: description.to_string(), kind }
+    }
+    // How many times it has to be done
+    pub fn needed(&self) -> u32 {
+        match &self.kind {
+            ObjectiveKind::Collect { count, .. } => *count,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct QuestStage {
+    pub description: String,
+    pub objectives: Vec<Objective>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct QuestDef {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    pub stages: Vec<QuestStage>,
+}
+impl QuestDef {
+    pub fn new(id: &str, title: &str) -> Self {
+        Self { id: id.to_string(), title: title.to_string(), description: String::new(), stages: Vec::new() }
+    }
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+    // Add a stage, after the others
+    pub fn stage(mut self, description: &str, objectives: Vec<Objective>) -> Self {
+        self.stages.push(QuestStage { description: description.to_string(), objectives });
+        self
+    }
+}
+
+// A quest the player has started
+#[derive(Clone, Debug)]
+pub struct Quest {
+    pub def: QuestDef,
+    stage: usize,
+    progress: Vec<u32>, // how far along each of the stage's objectives is
+}
+impl Quest {
+    fn new(def: QuestDef) -> Self {
+        let mut quest = Self { def, stage: 0, progress: Vec::new() };
+        quest.reset_progress();
+        quest
+    }
+    fn reset_progress(&mut self) {
+        self.progress = vec![0; self.stage().map_or(0, |stage| stage.objectives.len())];
+    }
+
+    // The stage the player is on, or None once the quest is done
+    pub fn stage(&self) -> Option<&QuestStage> {
+        self.def.stages.get(self.stage)
+    }
+    pub fn stage_index(&self) -> usize {
+        self.stage
+    }
+    pub fn is_completed(&self) -> bool {
+        self.stage >= self.def.stages.len()
+    }
+    // How far along an objective of the current stage is, out of how many
+    pub fn objective_progress(&self, index: usize) -> Option<(u32, u32)> {
+        let objective = self.stage()?.objectives.get(index)?;
+        Some((self.progress[index].min(objective.needed()), objective.needed()))
+    }
+
+    // Count towards every objective `matches` picks, by `amount`. True if
+    // anything changed.
+    fn count_towards(&mut self, amount: u32, matches: impl Fn(&ObjectiveKind) -> bool) -> bool {
+        let objectives = match self.def.stages.get(self.stage) {
+            Some(stage) => &stage.objectives,
+            None => return false,
+        };
+        let mut changed = false;
+        for (objective, progress) in objectives.iter().zip(self.progress.iter_mut()) {
+            if *progress < objective.needed() && matches(&objective.kind) {
+                *progress = (*progress + amount).min(objective.needed());
+                changed = true;
+            }
+        }
+        changed
+    }
+    // Move on to the next stage if this one's done. True if it did.
+    fn try_advance(&mut self) -> bool {
+        let done = match self.stage() {
+            Some(stage) => stage.objectives.iter().zip(&self.progress).all(|(objective, progress)| *progress >= objective.needed()),
+            None => false,
+        };
+        if done {
+            self.stage += 1;
+            self.reset_progress();
+        }
+        done
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+pub struct QuestLog {
+    quests: Vec<Quest>, // in the order they were started
+}
+impl QuestLog {
+    // Start a quest. Does nothing if one with the same id has been started
+    // before (whether or not it's done).
+    pub fn start(&mut self, def: QuestDef) {
+        if self.get(&def.id).is_none() {
+            self.quests.push(Quest::new(def));
+        }
+    }
+    pub fn get(&self, id: &str) -> Option<&Quest> {
+        self.quests.iter().find(|quest| quest.def.id == id)
+    }
+    pub fn is_active(&self, id: &str) -> bool {
+        self.get(id).map_or(false, |quest| !quest.is_completed())
+    }
+    pub fn is_completed(&self, id: &str) -> bool {
+        self.get(id).map_or(false, Quest::is_completed)
+    }
+    pub fn active(&self) -> impl Iterator<Item = &Quest> {
+        self.quests.iter().filter(|quest| !quest.is_completed())
+    }
+    pub fn completed(&self) -> impl Iterator<Item = &Quest> {
+        self.quests.iter().filter(|quest| quest.is_completed())
+    }
+}
+
+// Things that happened, that objectives might be waiting on
+#[derive(Clone, Debug)]
+pub enum QuestEvent {
+    TalkedTo(String), // sent for you when a conversation starts with something with a Name
+    Collected { item: String, count: u32 },
+    Custom(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QuestChange {
+    Started,
+    Progressed, // an objective moved along
+    StageCompleted(usize),
+    Completed,
+}
+
+pub struct QuestUpdated {
+    pub id: String,
+    pub change: QuestChange,
+}
+
+// Systems that read the QuestLog can run `.after(QuestSystem)`
+#[derive(SystemLabel)]
+pub struct QuestSystem;
+
+pub struct QuestPlugin;
+impl Plugin for QuestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<QuestLog>()
+            .add_event::<QuestEvent>()
+            .add_event::<QuestUpdated>()
+            .add_startup_system(ui::spawn_quest_screen)
+            .add_system(send_talked_to.after(DialogueSystem))
+            .add_system(track_quests.label(QuestSystem).after(send_talked_to))
+            .add_system(toast_quest_updates.after(QuestSystem))
+            .add_system(ui::toggle_quest_screen.after(QuestSystem))
+            .add_system(ui::update_quest_screen.after(ui::toggle_quest_screen));
+    }
+}
+
+fn send_talked_to(
+    mut started: EventReader<DialogueStarted>,
+    mut quest_events: EventWriter<QuestEvent>,
+    names: Query<&Name>,
+) {
+    for event in started.iter() {
+        if let Some(name) = event.speaker.and_then(|speaker| names.get(speaker).ok()) {
+            quest_events.send(QuestEvent::TalkedTo(name.to_string()));
+        }
+    }
+}
+
+fn track_quests(
+    mut quest_log: ResMut<QuestLog>,
+    flags: Res<GameFlags>,
+    mut quest_events: EventReader<QuestEvent>,
+    mut updates: EventWriter<QuestUpdated>,
+    mut known: Local<usize>, // how many quests had been started as of last frame
+) {
+    let log = quest_log.bypass_change_detection(); // only marked changed when something happens
+    let mut changed = false;
+    for quest in log.quests.iter().skip(*known) {
+        updates.send(QuestUpdated { id: quest.def.id.clone(), change: QuestChange::Started });
+        changed = true;
+    }
+    *known = log.quests.len();
+
+    let events: Vec<QuestEvent> = quest_events.iter().cloned().collect();
+    for quest in log.quests.iter_mut() {
+        let mut progressed = false;
+        for event in events.iter() {
+            progressed |= match event {
+                QuestEvent::TalkedTo(name) => quest.count_towards(1, |kind| matches!(kind, ObjectiveKind::TalkTo(n) if n == name)),
+                QuestEvent::Collected { item, count } => {
+                    quest.count_towards(*count, |kind| matches!(kind, ObjectiveKind::Collect { item: i, .. } if i == item))
+                },
+                QuestEvent::Custom(custom) => quest.count_towards(1, |kind| matches!(kind, ObjectiveKind::Event(e) if e == custom)),
+            };
+        }
+        // Flags are checked every frame, since they can be set by anything.
+        // A stage whose flags are already set is done as soon as it starts.
+        loop {
+            progressed |= quest.count_towards(1, |kind| matches!(kind, ObjectiveKind::Flag(flag) if flags.is_set(flag)));
+            let stage = quest.stage;
+            if !quest.try_advance() {
+                break;
+            }
+            let change = if quest.is_completed() { QuestChange::Completed } else { QuestChange::StageCompleted(stage) };
+            updates.send(QuestUpdated { id: quest.def.id.clone(), change });
+            progressed = false; // said already
+            changed = true;
+        }
+        if progressed {
+            updates.send(QuestUpdated { id: quest.def.id.clone(), change: QuestChange::Progressed });
+            changed = true;
+        }
+    }
+    if changed {
+        quest_log.set_changed();
+    }
+}
+
+fn toast_quest_updates(mut updates: EventReader<QuestUpdated>, quest_log: Res<QuestLog>, mut toasts: ResMut<Toasts>) {
+    for update in updates.iter() {
+        let title = match quest_log.get(&update.id) {
+            Some(quest) => &quest.def.title,
+            None => continue,
+        };
+        match update.change {
+            QuestChange::Started => toasts.show(format!("New quest: {}", title)),
+            QuestChange::StageCompleted(_) => toasts.show(format!("Quest updated: {}", title)),
+            QuestChange::Completed => toasts.show(format!("Quest complete: {}", title)),
+            QuestChange::Progressed => {},
+        }
+    }
+}
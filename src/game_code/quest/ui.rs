@@ -0,0 +1,120 @@
+// :: Quest log screen ::
+// Lists the active quests (with the current stage's objectives and how
+// far along they are) and then the completed ones. Journal opens it when
+// the player has control, and Journal or Menu closes it again; the player
+// can't move while it's open.
+use bevy::prelude::*;
+
+use super::QuestLog;
+use crate::{
+    input::{Action, Actions},
+    player::PlayerControlLock,
+    ui::{FONT_SIZE, HEADING_COLOR, MENU_LAYER, PADDING, SCREEN_COLOR, TITLE_SIZE, UI_FONT},
+};
+
+const MARGIN: f32 = 48.0;
+const CONTROL_LOCK: &str = "quest_log";
+const DONE_COLOR: Color = Color::rgb(0.5, 0.5, 0.5);
+
+#[derive(Component)]
+pub(super) struct QuestScreen;
+
+#[derive(Component)]
+pub(super) struct QuestScreenText;
+
+pub(super) fn spawn_quest_screen(mut commands: Commands) {
+    commands.spawn((
+        QuestScreen,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect::all(Val::Px(MARGIN)),
+                padding: UiRect::all(Val::Px(PADDING)),
+                align_items: AlignItems::FlexStart,
+                ..default()
+            },
+            background_color: SCREEN_COLOR.into(),
+            visibility: Visibility { is_visible: false },
+            z_index: MENU_LAYER,
+            ..default()
+        },
+    )).with_children(|screen| {
+        screen.spawn((
+            QuestScreenText,
+            TextBundle::from_sections([]).with_style(Style {
+                max_size: Size::new(Val::Percent(100.0), Val::Undefined),
+                ..default()
+            }),
+        ));
+    });
+}
+
+pub(super) fn toggle_quest_screen(
+    actions: Res<Actions>,
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut screens: Query<&mut Visibility, With<QuestScreen>>,
+) {
+    for mut visibility in &mut screens {
+        if visibility.is_visible {
+            if actions.just_pressed(Action::Journal) || actions.just_pressed(Action::Menu) {
+                visibility.is_visible = false;
+                control_lock.unlock(CONTROL_LOCK);
+            }
+        } else if actions.just_pressed(Action::Journal) && !control_lock.is_locked() {
+            visibility.is_visible = true;
+            control_lock.lock(CONTROL_LOCK);
+        }
+    }
+}
+
+pub(super) fn update_quest_screen(
+    asset_server: Res<AssetServer>,
+    quest_log: Res<QuestLog>,
+    screens: Query<&Visibility, (With<QuestScreen>, Changed<Visibility>)>,
+    mut texts: Query<&mut Text, With<QuestScreenText>>,
+) {
+    // Only rebuilt when the log changes, or the screen is opened
+    let opened = screens.iter().any(|visibility| visibility.is_visible);
+    if !quest_log.is_changed() && !opened {
+        return;
+    }
+    let font = asset_server.load(UI_FONT);
+    let style = |size: f32, color: Color| TextStyle { font: font.clone(), font_size: size, color };
+    let mut sections = vec![TextSection::new("Quests\n", style(TITLE_SIZE, Color::WHITE))];
+
+    let mut any = false;
+    for quest in quest_log.active() {
+        any = true;
+        sections.push(TextSection::new(format!("\n{}\n", quest.def.title), style(FONT_SIZE, HEADING_COLOR)));
+        if !quest.def.description.is_empty() {
+            sections.push(TextSection::new(format!("{}\n", quest.def.description), style(FONT_SIZE, Color::WHITE)));
+        }
+        let stage = match quest.stage() {
+            Some(stage) => stage,
+            None => continue,
+        };
+        sections.push(TextSection::new(format!("{}\n", stage.description), style(FONT_SIZE, Color::WHITE)));
+        for (index, objective) in stage.objectives.iter().enumerate() {
+            let (done, needed) = quest.objective_progress(index).unwrap_or((0, 1));
+            let check = if done >= needed { "x" } else { " " };
+            let count = if needed > 1 { format!(" ({}/{})", done, needed) } else { String::new() };
+            let color = if done >= needed { DONE_COLOR } else { Color::WHITE };
+            sections.push(TextSection::new(format!("  [{}] {}{}\n", check, objective.description, count), style(FONT_SIZE, color)));
+        }
+    }
+    if !any {
+        sections.push(TextSection::new("\nNothing to do, for now.\n", style(FONT_SIZE, DONE_COLOR)));
+    }
+
+    let completed: Vec<&str> = quest_log.completed().map(|quest| quest.def.title.as_str()).collect();
+    if !completed.is_empty() {
+        sections.push(TextSection::new("\nCompleted\n", style(FONT_SIZE, HEADING_COLOR)));
+        for title in completed {
+            sections.push(TextSection::new(format!("  {}\n", title), style(FONT_SIZE, DONE_COLOR)));
+        }
+    }
+
+    for mut text in &mut texts {
+        text.sections = sections.clone();
+    }
+}
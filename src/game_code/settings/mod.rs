@@ -0,0 +1,174 @@
+// :: Settings ::
+// The player's options: how loud things are, how the window's shown, how
+// fast dialogue types out. They're kept in the Settings resource, loaded
+// from SETTINGS_PATH when the game starts (or the defaults, if there's no
+// file yet), applied whenever they change, and saved again:
+//
+//     settings.effects_volume = 0.5;
+//
+// Sounds are played at their own volume times `master_volume` and
+// `effects_volume` (see `Settings::effects_volume()`). The settings screen
+// (see ui.rs) is opened from the pause menu, and is also where controls
+// are rebound (the bindings themselves are saved with the InputMap; see
+// input/map.rs).
+use std::{fs, io, path::Path};
+
+use bevy::{
+    prelude::*,
+    window::{PresentMode, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{dialogue::DialogueRunner, pause::PauseMenuSystem};
+
+mod ui;
+
+pub use ui::SettingsScreen;
+
+pub const SETTINGS_PATH: &str = "settings.ron";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    Borderless, // fullscreen, without changing the screen's resolution
+    Fullscreen,
+}
+impl WindowModeSetting {
+    pub const ALL: [WindowModeSetting; 3] =
+        [WindowModeSetting::Windowed, WindowModeSetting::Borderless, WindowModeSetting::Fullscreen];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WindowModeSetting::Windowed => "Windowed",
+            WindowModeSetting::Borderless => "Borderless",
+            WindowModeSetting::Fullscreen => "Fullscreen",
+        }
+    }
+    fn mode(&self) -> WindowMode {
+        match self {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum TextSpeed {
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+    Instant,
+}
+impl TextSpeed {
+    pub const ALL: [TextSpeed; 4] = [TextSpeed::Slow, TextSpeed::Normal, TextSpeed::Fast, TextSpeed::Instant];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextSpeed::Slow => "Slow",
+            TextSpeed::Normal => "Normal",
+            TextSpeed::Fast => "Fast",
+            TextSpeed::Instant => "Instant",
+        }
+    }
+    pub fn chars_per_second(&self) -> f32 {
+        match self {
+            TextSpeed::Slow => 20.0,
+            TextSpeed::Normal => 40.0,
+            TextSpeed::Fast => 80.0,
+            TextSpeed::Instant => 100_000.0, // a whole line in one frame
+        }
+    }
+}
+
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub master_volume: f32, // 0.0 to 1.0
+    pub effects_volume: f32, // 0.0 to 1.0
+    pub window_mode: WindowModeSetting,
+    pub vsync: bool,
+    pub text_speed: TextSpeed,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            effects_volume: 1.0,
+            window_mode: WindowModeSetting::Windowed,
+            vsync: true,
+            text_speed: TextSpeed::Normal,
+        }
+    }
+}
+impl Settings {
+    // What to multiply sound effects' volumes by
+    pub fn effects_volume(&self) -> f32 {
+        (self.master_volume * self.effects_volume).clamp(0.0, 1.0)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let text = fs::read_to_string(path)?;
+        ron::from_str(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let text = ron::ser::to_string_pretty(self, Default::default())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        fs::write(path, text)
+    }
+}
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_settings())
+            .init_resource::<SettingsScreen>()
+            .add_startup_system(ui::spawn_settings_screen)
+            .add_system(apply_settings)
+            .add_system(save_settings)
+            .add_system(ui::open_settings_screen.after(PauseMenuSystem))
+            .add_system(ui::navigate_settings_screen.after(ui::open_settings_screen))
+            .add_system(ui::show_settings_screen.after(ui::navigate_settings_screen))
+            .add_system(ui::update_settings_screen.after(ui::navigate_settings_screen));
+    }
+}
+
+// The saved settings if there are any, otherwise the defaults
+fn load_settings() -> Settings {
+    match Settings::load(SETTINGS_PATH) {
+        Ok(settings) => settings,
+        Err(err) => {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!("Couldn't load {}, using default settings: {}", SETTINGS_PATH, err);
+            }
+            Settings::default()
+        },
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    if settings.is_changed() && !settings.is_added() {
+        if let Err(err) = settings.save(SETTINGS_PATH) {
+            warn!("Couldn't save {}: {}", SETTINGS_PATH, err);
+        }
+    }
+}
+
+// Including when the game starts
+fn apply_settings(settings: Res<Settings>, mut windows: ResMut<Windows>, mut dialogue: ResMut<DialogueRunner>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        if window.mode() != settings.window_mode.mode() {
+            window.set_mode(settings.window_mode.mode());
+        }
+        let present_mode = if settings.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync };
+        if window.present_mode() != present_mode {
+            window.set_present_mode(present_mode);
+        }
+    }
+    dialogue.chars_per_second = settings.text_speed.chars_per_second();
+}
@@ -0,0 +1,255 @@
+// :: Settings screen ::
+// A list of the Settings, each shown with its value. MoveUp and MoveDown
+// pick one, MoveLeft and MoveRight change it, and Menu (or Back) closes
+// the screen, back to the pause menu.
+//
+// Controls opens a list of the Actions and what they're bound to. Interact
+// on one waits for the next key or gamepad button pressed, which replaces
+// the action's keys (or buttons); this is the one place the keyboard and
+// gamepads are read directly. "Reset controls" puts back the defaults.
+use bevy::prelude::*;
+
+use super::{Settings, TextSpeed, WindowModeSetting};
+use crate::{
+    input::{Action, Actions, InputBinding, InputMap},
+    pause::OpenSettings,
+    ui::{set_visible, spawn_menu_screen, FONT_SIZE, TITLE_SIZE, UI_FONT, UNSELECTED_COLOR},
+};
+
+const VOLUME_STEP: f32 = 0.1;
+const VALUE_COLOR: Color = Color::rgb(1.0, 0.85, 0.4);
+const HINT_COLOR: Color = Color::rgb(0.5, 0.5, 0.5);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Row {
+    MasterVolume,
+    EffectsVolume,
+    WindowMode,
+    Vsync,
+    TextSpeed,
+    Controls,
+    Back,
+}
+const ROWS: [Row; 7] =
+    [Row::MasterVolume, Row::EffectsVolume, Row::WindowMode, Row::Vsync, Row::TextSpeed, Row::Controls, Row::Back];
+
+// Whether the screen's open, and where
+#[derive(Resource, Default)]
+pub struct SettingsScreen {
+    open: bool,
+    cursor: usize,
+    controls: Option<ControlsList>, // when looking at the controls
+}
+struct ControlsList {
+    cursor: usize, // an index into Action::ALL, or one past it for "Reset controls"
+    waiting: bool, // for a key or button to bind
+}
+impl SettingsScreen {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.cursor = 0;
+        self.controls = None;
+    }
+    pub fn close(&mut self) {
+        self.open = false;
+        self.controls = None;
+    }
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+}
+
+#[derive(Component)]
+pub(super) struct SettingsRoot;
+
+#[derive(Component)]
+pub(super) struct SettingsText;
+
+pub(super) fn spawn_settings_screen(mut commands: Commands) {
+    spawn_menu_screen(&mut commands, SettingsRoot, SettingsText);
+}
+
+pub(super) fn open_settings_screen(mut requests: EventReader<OpenSettings>, mut screen: ResMut<SettingsScreen>) {
+    if requests.iter().count() > 0 && !screen.is_open() {
+        screen.open();
+    }
+}
+
+pub(super) fn navigate_settings_screen(
+    actions: Res<Actions>,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut screen: ResMut<SettingsScreen>,
+    mut settings: ResMut<Settings>,
+    mut input_map: ResMut<InputMap>,
+) {
+    if !screen.is_open() {
+        return;
+    }
+    if let Some(controls) = screen.controls.as_mut() {
+        if controls.waiting {
+            let binding = keyboard_input.get_just_pressed().next().map(|key| InputBinding::Key(*key))
+                .or_else(|| gamepad_buttons.get_just_pressed().next()
+                    .map(|button| InputBinding::GamepadButton(button.button_type)));
+            if let Some(binding) = binding {
+                rebind(&mut input_map, Action::ALL[controls.cursor], binding);
+                controls.waiting = false;
+            }
+            return;
+        }
+        let rows = Action::ALL.len() + 1;
+        if actions.just_pressed(Action::Menu) {
+            screen.controls = None;
+        } else if actions.just_pressed(Action::MoveUp) {
+            controls.cursor = (controls.cursor + rows - 1) % rows;
+        } else if actions.just_pressed(Action::MoveDown) {
+            controls.cursor = (controls.cursor + 1) % rows;
+        } else if actions.just_pressed(Action::Interact) {
+            if controls.cursor < Action::ALL.len() {
+                controls.waiting = true;
+            } else {
+                input_map.reset_to_defaults();
+            }
+        }
+        return;
+    }
+
+    if actions.just_pressed(Action::Menu) {
+        screen.close();
+        return;
+    }
+    if actions.just_pressed(Action::MoveUp) {
+        screen.cursor = (screen.cursor + ROWS.len() - 1) % ROWS.len();
+        return;
+    } else if actions.just_pressed(Action::MoveDown) {
+        screen.cursor = (screen.cursor + 1) % ROWS.len();
+        return;
+    }
+    let step = match (actions.just_pressed(Action::MoveLeft), actions.just_pressed(Action::MoveRight)) {
+        (true, false) => -1,
+        (false, true) => 1,
+        _ => 0,
+    };
+    match ROWS[screen.cursor] {
+        Row::MasterVolume if step != 0 => {
+            settings.master_volume = step_volume(settings.master_volume, step);
+        },
+        Row::EffectsVolume if step != 0 => {
+            settings.effects_volume = step_volume(settings.effects_volume, step);
+        },
+        Row::WindowMode if step != 0 => {
+            settings.window_mode = cycle(&WindowModeSetting::ALL, settings.window_mode, step);
+        },
+        Row::Vsync if step != 0 || actions.just_pressed(Action::Interact) => {
+            settings.vsync = !settings.vsync;
+        },
+        Row::TextSpeed if step != 0 => {
+            settings.text_speed = cycle(&TextSpeed::ALL, settings.text_speed, step);
+        },
+        Row::Controls if actions.just_pressed(Action::Interact) => {
+            screen.controls = Some(ControlsList { cursor: 0, waiting: false });
+        },
+        Row::Back if actions.just_pressed(Action::Interact) => screen.close(),
+        _ => {},
+    }
+}
+
+// Replace the action's keys, or its buttons, with the new one
+fn rebind(input_map: &mut InputMap, action: Action, binding: InputBinding) {
+    let same_kind = |other: &InputBinding| std::mem::discriminant(other) == std::mem::discriminant(&binding);
+    let mut bindings: Vec<InputBinding> = input_map.bindings(action).iter()
+        .copied()
+        .filter(|other| !same_kind(other))
+        .collect();
+    bindings.push(binding);
+    input_map.rebind(action, bindings);
+}
+
+fn step_volume(volume: f32, step: i32) -> f32 {
+    // Rounded, so steps don't drift away from tenths
+    ((volume + step as f32 * VOLUME_STEP).clamp(0.0, 1.0) * 10.0).round() / 10.0
+}
+
+// The value `step` places along from `current`, wrapping around
+fn cycle<T: Copy + PartialEq>(values: &[T], current: T, step: i32) -> T {
+    let index = values.iter().position(|value| *value == current).unwrap_or(0) as i32;
+    values[(index + step).rem_euclid(values.len() as i32) as usize]
+}
+
+pub(super) fn show_settings_screen(
+    screen: Res<SettingsScreen>,
+    mut roots: Query<&mut Visibility, With<SettingsRoot>>,
+) {
+    for mut visibility in &mut roots {
+        set_visible(&mut visibility, screen.is_open());
+    }
+}
+
+pub(super) fn update_settings_screen(
+    asset_server: Res<AssetServer>,
+    screen: Res<SettingsScreen>,
+    settings: Res<Settings>,
+    input_map: Res<InputMap>,
+    mut texts: Query<&mut Text, With<SettingsText>>,
+) {
+    if !screen.is_open() || !screen.is_changed() && !settings.is_changed() && !input_map.is_changed() {
+        return;
+    }
+    let font = asset_server.load(UI_FONT);
+    let style = |size: f32, color: Color| TextStyle { font: font.clone(), font_size: size, color };
+    let mut sections = Vec::new();
+    let mut row = |label: String, value: String, selected: bool| {
+        let arrow = if selected { "> " } else { "  " };
+        let color = if selected { Color::WHITE } else { UNSELECTED_COLOR };
+        sections.push(TextSection::new(format!("{}{:<16}", arrow, label), style(FONT_SIZE, color)));
+        sections.push(TextSection::new(format!("{}\n", value), style(FONT_SIZE, VALUE_COLOR)));
+    };
+
+    let hint = match &screen.controls {
+        Some(controls) => {
+            for (index, action) in Action::ALL.iter().enumerate() {
+                let selected = index == controls.cursor;
+                let value = if selected && controls.waiting {
+                    "press a key or button...".to_string()
+                } else {
+                    describe_bindings(input_map.bindings(*action))
+                };
+                row(format!("{:?}", action), value, selected);
+            }
+            row("Reset controls".to_string(), String::new(), controls.cursor == Action::ALL.len());
+            "Interact: rebind   Menu: back"
+        },
+        None => {
+            let percent = |volume: f32| format!("< {}% >", (volume * 100.0).round() as i32);
+            for (index, option) in ROWS.iter().enumerate() {
+                let selected = index == screen.cursor;
+                let (label, value) = match option {
+                    Row::MasterVolume => ("Volume", percent(settings.master_volume)),
+                    Row::EffectsVolume => ("Effects", percent(settings.effects_volume)),
+                    Row::WindowMode => ("Window", format!("< {} >", settings.window_mode.name())),
+                    Row::Vsync => ("VSync", format!("< {} >", if settings.vsync { "On" } else { "Off" })),
+                    Row::TextSpeed => ("Text speed", format!("< {} >", settings.text_speed.name())),
+                    Row::Controls => ("Controls", String::new()),
+                    Row::Back => ("Back", String::new()),
+                };
+                row(label.to_string(), value, selected);
+            }
+            "Left/Right: change   Menu: back"
+        },
+    };
+    let title = if screen.controls.is_some() { "Controls\n\n" } else { "Settings\n\n" };
+    sections.insert(0, TextSection::new(title, style(TITLE_SIZE, Color::WHITE)));
+    sections.push(TextSection::new(format!("\n{}", hint), style(FONT_SIZE, HINT_COLOR)));
+    for mut text in &mut texts {
+        text.sections = sections.clone();
+    }
+}
+
+// e.g. "E, South"
+fn describe_bindings(bindings: &[InputBinding]) -> String {
+    let names: Vec<String> = bindings.iter().map(|binding| match binding {
+        InputBinding::Key(key) => format!("{:?}", key),
+        InputBinding::GamepadButton(button) => format!("{:?}", button),
+    }).collect();
+    if names.is_empty() { "(none)".to_string() } else { names.join(", ") }
+}
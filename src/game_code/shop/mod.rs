@@ -0,0 +1,158 @@
+// :: Shops ::
+// Merchants sell from a stock written in a `.shop.ron` (or `.shop.json`)
+// file under `assets/shops/`, and buy back what the player carries for a
+// fraction of its worth. Prices default to each item's `price` (see
+// inventory/items.rs), and are paid in the Inventory's currency:
+//
+//     (
+//         name: "Meadow Stall",
+//         stock: [(item: "apple"), (item: "stick", price: Some(3))],
+//         sell_rate: 0.5, // what the merchant pays, as a fraction of the price
+//     )
+//
+// An Interactable with a Merchant opens its shop when used. If it also
+// has something to say, the shop opens once the conversation ends, if the
+// conversation sent a "shop" DialogueEvent (`<<event shop>>` in a script):
+//
+//     commands.spawn((
+//         Interactable::new(24.0, "Talk"),
+//         Merchant::new(asset_server.load("shops/meadow_stall.shop.ron")),
+//         DialogueSource::new(asset_server.load("dialogue/meadow.dialogue")).with_node("shopkeeper"),
+//         ..
+//     ));
+//
+// Buying something sets the GameFlag `bought_<item id>`, for dialogue and
+// quests to check.
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::Deserialize;
+
+use crate::{
+    asset_loader::RonOrJsonLoader,
+    dialogue::{Dialogue, DialogueEvent, DialogueSource, DialogueSystem, Readable},
+    input::InputSystem,
+    interaction::{InteractionEvent, InteractionSystem},
+    inventory::{ItemCategory, ItemRegistry},
+    player::Player,
+};
+
+mod ui;
+
+pub use ui::ShopScreen;
+
+const SHOP_EVENT: &str = "shop";
+const DEFAULT_SELL_RATE: f32 = 0.5;
+
+#[derive(Clone, Debug, Deserialize, TypeUuid)]
+#[uuid = "9b1f6a52-3e0c-4f7d-8d2a-5c4e7b9a1d63"]
+pub struct ShopDef {
+    pub name: String,
+    pub stock: Vec<ShopItem>,
+    #[serde(default = "default_sell_rate")]
+    pub sell_rate: f32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShopItem {
+    pub item: String,
+    #[serde(default)]
+    pub price: Option<u32>, // instead of the item's own price
+}
+
+fn default_sell_rate() -> f32 {
+    DEFAULT_SELL_RATE
+}
+
+impl ShopDef {
+    // What the merchant sells an item for, if they sell it
+    pub fn buy_price(&self, items: &ItemRegistry, item: &str) -> Option<u32> {
+        let stocked = self.stock.iter().find(|stocked| stocked.item == item)?;
+        let price = stocked.price.or_else(|| items.get(item).map(|def| def.price))?;
+        (price > 0).then_some(price)
+    }
+    // What the merchant pays for an item, if they'll take it. Key items
+    // and worthless things can't be sold.
+    pub fn sell_price(&self, items: &ItemRegistry, item: &str) -> Option<u32> {
+        let def = items.get(item)?;
+        if def.category == ItemCategory::Key || def.price == 0 {
+            return None;
+        }
+        Some(((def.price as f32 * self.sell_rate).floor() as u32).max(1))
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Merchant {
+    pub shop: Handle<ShopDef>,
+}
+impl Merchant {
+    pub fn new(shop: Handle<ShopDef>) -> Self {
+        Self { shop }
+    }
+}
+
+pub struct ItemBought {
+    pub buyer: Entity,
+    pub item: String,
+    pub price: u32,
+}
+
+pub struct ItemSold {
+    pub seller: Entity,
+    pub item: String,
+    pub price: u32,
+}
+
+pub struct ShopPlugin;
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<ShopDef>()
+            .add_asset_loader(RonOrJsonLoader::<ShopDef>::new(&["shop.ron", "shop.json"]))
+            .init_resource::<ShopScreen>()
+            .add_event::<ItemBought>()
+            .add_event::<ItemSold>()
+            .add_startup_system(ui::spawn_shop_screen)
+            .add_system(open_merchant_shops.after(InteractionSystem))
+            .add_system(open_shops_from_dialogue.after(DialogueSystem))
+            // Before the shop opens, so the press that opens it isn't
+            // also taken as buying something
+            .add_system(ui::navigate_shop_screen.after(InputSystem))
+            .add_system(ui::show_shop_screen
+                .after(ui::navigate_shop_screen)
+                .after(open_merchant_shops)
+                .after(open_shops_from_dialogue))
+            .add_system(ui::update_shop_screen.after(ui::show_shop_screen));
+    }
+}
+
+// Merchants with nothing to say open their shop straight away
+fn open_merchant_shops(
+    mut interactions: EventReader<InteractionEvent>,
+    mut screen: ResMut<ShopScreen>,
+    merchants: Query<&Merchant, (Without<Dialogue>, Without<DialogueSource>, Without<Readable>)>,
+) {
+    for event in interactions.iter() {
+        if let Ok(merchant) = merchants.get(event.target) {
+            screen.open(merchant.shop.clone(), event.player);
+        }
+    }
+}
+
+fn open_shops_from_dialogue(
+    mut dialogue_events: EventReader<DialogueEvent>,
+    mut screen: ResMut<ShopScreen>,
+    merchants: Query<&Merchant>,
+    players: Query<Entity, With<Player>>,
+) {
+    for event in dialogue_events.iter().filter(|event| event.name == SHOP_EVENT) {
+        let merchant = match event.speaker.and_then(|speaker| merchants.get(speaker).ok()) {
+            Some(merchant) => merchant,
+            None => {
+                warn!("A \"{}\" dialogue event came from something that isn't a Merchant", SHOP_EVENT);
+                continue;
+            },
+        };
+        if let Some(player) = players.iter().next() {
+            screen.open(merchant.shop.clone(), player);
+        }
+    }
+}
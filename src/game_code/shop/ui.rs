@@ -0,0 +1,225 @@
+// :: Shop screen ::
+// A list of what the merchant sells (or, on the other tab, what they'll
+// buy from the player), with prices and the player's money. MoveLeft and
+// MoveRight switch between buying and selling, MoveUp and MoveDown pick
+// an item, Interact buys or sells one of it, and Menu leaves the shop.
+// The player can't move while it's open.
+use bevy::prelude::*;
+
+use super::{ItemBought, ItemSold, ShopDef};
+use crate::{
+    flags::GameFlags,
+    input::{Action, Actions},
+    inventory::{Inventory, ItemRegistry},
+    player::PlayerControlLock,
+    ui::{
+        show_menu_screen, spawn_menu_screen, FONT_SIZE, HEADING_COLOR, MESSAGE_COLOR, TITLE_SIZE,
+        UI_FONT, UNSELECTED_COLOR,
+    },
+};
+
+const NAME_WIDTH: usize = 24; // in characters, for lining prices up
+const CONTROL_LOCK: &str = "shop";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ShopTab {
+    #[default]
+    Buy,
+    Sell,
+}
+
+// The shop being shown, if any
+#[derive(Resource, Default)]
+pub struct ShopScreen {
+    pending: Option<(Handle<ShopDef>, Entity)>, // opened once the player has control
+    open: Option<OpenShop>,
+}
+struct OpenShop {
+    shop: Handle<ShopDef>,
+    customer: Entity,
+    tab: ShopTab,
+    cursor: usize,
+    message: String, // what happened last, e.g. "Not enough money"
+}
+impl ShopScreen {
+    // Show a shop to `customer`, as soon as nothing else (e.g., a
+    // conversation) has hold of the player
+    pub fn open(&mut self, shop: Handle<ShopDef>, customer: Entity) {
+        self.pending = Some((shop, customer));
+    }
+    pub fn close(&mut self) {
+        self.pending = None;
+        self.open = None;
+    }
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+    pub fn tab(&self) -> Option<ShopTab> {
+        self.open.as_ref().map(|open| open.tab)
+    }
+}
+
+#[derive(Component)]
+pub(super) struct ShopRoot;
+
+#[derive(Component)]
+pub(super) struct ShopText;
+
+pub(super) fn spawn_shop_screen(mut commands: Commands) {
+    spawn_menu_screen(&mut commands, ShopRoot, ShopText);
+}
+
+// Open a pending shop once the player's free, and show or hide the screen
+pub(super) fn show_shop_screen(
+    mut screen: ResMut<ShopScreen>,
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut roots: Query<&mut Visibility, With<ShopRoot>>,
+) {
+    if screen.open.is_none() && screen.pending.is_some() && !control_lock.is_locked() {
+        let (shop, customer) = screen.pending.take().unwrap();
+        screen.open = Some(OpenShop { shop, customer, tab: ShopTab::Buy, cursor: 0, message: String::new() });
+    }
+    show_menu_screen(screen.is_open(), CONTROL_LOCK, &mut control_lock, &mut roots);
+}
+
+pub(super) fn navigate_shop_screen(
+    actions: Res<Actions>,
+    items: Res<ItemRegistry>,
+    shops: Res<Assets<ShopDef>>,
+    mut screen: ResMut<ShopScreen>,
+    mut flags: ResMut<GameFlags>,
+    mut inventories: Query<&mut Inventory>,
+    mut bought: EventWriter<ItemBought>,
+    mut sold: EventWriter<ItemSold>,
+) {
+    let open = match screen.open.as_mut() {
+        Some(open) => open,
+        None => return,
+    };
+    if actions.just_pressed(Action::Menu) {
+        screen.close();
+        return;
+    }
+    let (shop, mut inventory) = match (shops.get(&open.shop), inventories.get_mut(open.customer)) {
+        (Some(shop), Ok(inventory)) => (shop, inventory),
+        _ => return, // the shop's still loading
+    };
+
+    if actions.just_pressed(Action::MoveLeft) || actions.just_pressed(Action::MoveRight) {
+        open.tab = if open.tab == ShopTab::Buy { ShopTab::Sell } else { ShopTab::Buy };
+        open.cursor = 0;
+        open.message.clear();
+        return;
+    }
+    let rows = listed_items(shop, &items, &inventory, open.tab);
+    if rows.is_empty() {
+        return;
+    }
+    if actions.just_pressed(Action::MoveUp) {
+        open.cursor = (open.cursor + rows.len() - 1) % rows.len();
+    } else if actions.just_pressed(Action::MoveDown) {
+        open.cursor = (open.cursor + 1) % rows.len();
+    } else if actions.just_pressed(Action::Interact) {
+        let (item, price) = rows[open.cursor.min(rows.len() - 1)].clone();
+        let name = items.name(&item).to_string();
+        open.message = match open.tab {
+            ShopTab::Buy if inventory.space_for(&items, &item) == 0 => "No room for that".to_string(),
+            ShopTab::Buy if !inventory.spend_currency(price) => "Not enough money".to_string(),
+            ShopTab::Buy => {
+                inventory.add(&items, &item, 1);
+                flags.set(&format!("bought_{}", item), true);
+                bought.send(ItemBought { buyer: open.customer, item, price });
+                format!("Bought {}", name)
+            },
+            ShopTab::Sell => {
+                inventory.remove(&item, 1);
+                inventory.add_currency(price);
+                sold.send(ItemSold { seller: open.customer, item, price });
+                format!("Sold {}", name)
+            },
+        };
+    }
+}
+
+pub(super) fn update_shop_screen(
+    asset_server: Res<AssetServer>,
+    items: Res<ItemRegistry>,
+    shops: Res<Assets<ShopDef>>,
+    screen: Res<ShopScreen>,
+    inventories: Query<(&Inventory, ChangeTrackers<Inventory>)>,
+    mut texts: Query<&mut Text, With<ShopText>>,
+) {
+    let open = match &screen.open {
+        Some(open) => open,
+        None => return,
+    };
+    let (shop, (inventory, inventory_tracker)) = match (shops.get(&open.shop), inventories.get(open.customer)) {
+        (Some(shop), Ok(inventory)) => (shop, inventory),
+        _ => return,
+    };
+    if !screen.is_changed() && !inventory_tracker.is_changed() && !items.is_changed() {
+        return;
+    }
+
+    let font = asset_server.load(UI_FONT);
+    let style = |size: f32, color: Color| TextStyle { font: font.clone(), font_size: size, color };
+    let tab_color = |tab: ShopTab| if open.tab == tab { HEADING_COLOR } else { UNSELECTED_COLOR };
+    let mut sections = vec![
+        TextSection::new(format!("{}\n", shop.name), style(TITLE_SIZE, Color::WHITE)),
+        TextSection::new(format!("Money: {}\n\n", inventory.currency()), style(FONT_SIZE, Color::WHITE)),
+        TextSection::new("Buy", style(FONT_SIZE, tab_color(ShopTab::Buy))),
+        TextSection::new("  |  ", style(FONT_SIZE, UNSELECTED_COLOR)),
+        TextSection::new("Sell\n\n", style(FONT_SIZE, tab_color(ShopTab::Sell))),
+    ];
+
+    let rows = listed_items(shop, &items, inventory, open.tab);
+    if rows.is_empty() {
+        let empty = if open.tab == ShopTab::Buy { "Nothing for sale" } else { "Nothing they'll buy" };
+        sections.push(TextSection::new(format!("  {}\n", empty), style(FONT_SIZE, UNSELECTED_COLOR)));
+    }
+    for (index, (item, price)) in rows.iter().enumerate() {
+        let selected = index == open.cursor.min(rows.len() - 1);
+        let arrow = if selected { "> " } else { "  " };
+        let name = match open.tab {
+            ShopTab::Buy => items.name(item).to_string(),
+            ShopTab::Sell => format!("{} ({})", items.name(item), inventory.count(item)),
+        };
+        let color = if selected { Color::WHITE } else { UNSELECTED_COLOR };
+        sections.push(TextSection::new(
+            format!("{}{:<width$}{:>5}\n", arrow, name, price, width = NAME_WIDTH),
+            style(FONT_SIZE, color),
+        ));
+    }
+
+    sections.push(TextSection::new(format!("\n{}\n", open.message), style(FONT_SIZE, MESSAGE_COLOR)));
+    let verb = if open.tab == ShopTab::Buy { "buy" } else { "sell" };
+    sections.push(TextSection::new(
+        format!("Interact: {}   Left/Right: buy or sell   Menu: leave", verb),
+        style(FONT_SIZE, UNSELECTED_COLOR),
+    ));
+    for mut text in &mut texts {
+        text.sections = sections.clone();
+    }
+}
+
+// The items on a tab, with their prices: the merchant's stock, or
+// everything the customer has that the merchant will buy
+fn listed_items(shop: &ShopDef, items: &ItemRegistry, inventory: &Inventory, tab: ShopTab) -> Vec<(String, u32)> {
+    match tab {
+        ShopTab::Buy => shop.stock.iter()
+            .filter_map(|stocked| Some((stocked.item.clone(), shop.buy_price(items, &stocked.item)?)))
+            .collect(),
+        ShopTab::Sell => {
+            let mut listed: Vec<(String, u32)> = Vec::new();
+            for stack in inventory.slots().iter().flatten() {
+                if listed.iter().any(|(item, _)| *item == stack.item) {
+                    continue;
+                }
+                if let Some(price) = shop.sell_price(items, &stack.item) {
+                    listed.push((stack.item.clone(), price));
+                }
+            }
+            listed
+        },
+    }
+}
@@ -0,0 +1,148 @@
+// :: Spatial hash ::
+// Finds entities near a point without checking every entity. The world is
+// cut into square cells, and each frame every Collider, TriggerZone,
+// Interactable, moving entity and SpatialHashed entity is filed under the
+// cells its bounds cover. Asking what's near a point then only looks at a
+// cell or two:
+//
+//     for entity in spatial_hash.query_radius(enemy_pos, sight_range) {
+//         if let Ok(player) = players.get(entity) { ... }
+//     }
+//
+// Bounds are a Collider's or TriggerZone's box, or the circle an
+// Interactable can be used from; anything else is just its position.
+// The hash is rebuilt after transforms are updated, so systems running
+// in Update see where everything was at the end of the last frame.
+use bevy::{
+    math::Rect,
+    prelude::*,
+    transform::TransformSystem,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    collision::{Collider, TriggerZone},
+    interaction::Interactable,
+    movement::Position,
+};
+
+const DEFAULT_CELL_SIZE: f32 = 64.0; // in pixels; about the size of the things being looked for
+
+// Puts an entity in the SpatialHash that wouldn't be otherwise (e.g., so
+// enemies can see it)
+#[derive(Component, Default)]
+pub struct SpatialHashed;
+
+#[derive(Resource)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Rect)>>,
+}
+impl Default for SpatialHash {
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self { cell_size, cells: HashMap::new() }
+    }
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+    pub fn insert(&mut self, entity: Entity, bounds: Rect) {
+        let (first, last) = self.cell_range(bounds);
+        for y in first.y..=last.y {
+            for x in first.x..=last.x {
+                self.cells.entry(IVec2::new(x, y)).or_default().push((entity, bounds));
+            }
+        }
+    }
+
+    // Everything whose bounds contain `point`
+    pub fn query_point(&self, point: Vec2) -> Vec<Entity> {
+        let cell = (point / self.cell_size).floor().as_ivec2();
+        self.cells.get(&cell).map_or_else(Vec::new, |entries| {
+            entries.iter()
+                .filter(|(_, bounds)| bounds.contains(point))
+                .map(|(entity, _)| *entity)
+                .collect()
+        })
+    }
+    // Everything whose bounds overlap (or touch) `area`
+    pub fn query_rect(&self, area: Rect) -> Vec<Entity> {
+        self.collect_unique(area, |bounds| {
+            bounds.min.x <= area.max.x && bounds.max.x >= area.min.x
+                && bounds.min.y <= area.max.y && bounds.max.y >= area.min.y
+        })
+    }
+    // Everything whose bounds come within `radius` of `center`
+    pub fn query_radius(&self, center: Vec2, radius: f32) -> Vec<Entity> {
+        let area = Rect::from_center_size(center, Vec2::splat(radius * 2.0));
+        self.collect_unique(area, |bounds| {
+            center.clamp(bounds.min, bounds.max).distance_squared(center) <= radius * radius
+        })
+    }
+
+    fn collect_unique(&self, area: Rect, keep: impl Fn(Rect) -> bool) -> Vec<Entity> {
+        let (first, last) = self.cell_range(area);
+        let mut seen = HashSet::new();
+        let mut found = Vec::new();
+        for y in first.y..=last.y {
+            for x in first.x..=last.x {
+                let entries = match self.cells.get(&IVec2::new(x, y)) {
+                    Some(entries) => entries,
+                    None => continue,
+                };
+                for (entity, bounds) in entries {
+                    if keep(*bounds) && seen.insert(*entity) {
+                        found.push(*entity);
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    // The first and last cells an area covers
+    fn cell_range(&self, area: Rect) -> (IVec2, IVec2) {
+        ((area.min / self.cell_size).floor().as_ivec2(), (area.max / self.cell_size).floor().as_ivec2())
+    }
+}
+
+// Systems reading the SpatialHash in PostUpdate should run `.after(SpatialHashSystem)`
+#[derive(SystemLabel)]
+pub struct SpatialHashSystem;
+
+pub struct SpatialHashPlugin;
+impl Plugin for SpatialHashPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialHash>()
+            .add_system_to_stage(CoreStage::PostUpdate, update_spatial_hash
+                .label(SpatialHashSystem)
+                .after(TransformSystem::TransformPropagate));
+    }
+}
+
+type Hashed = Or<(With<SpatialHashed>, With<Collider>, With<TriggerZone>, With<Interactable>, With<Position>)>;
+
+fn update_spatial_hash(
+    mut spatial_hash: ResMut<SpatialHash>,
+    query: Query<(Entity, &GlobalTransform, Option<&Collider>, Option<&TriggerZone>, Option<&Interactable>), Hashed>,
+) {
+    spatial_hash.clear();
+    for (entity, transform, collider, trigger, interactable) in &query {
+        let position = transform.translation().truncate();
+        let bounds = [
+            collider.map(|collider| collider.rect_at(position)),
+            trigger.map(|trigger| trigger.rect_at(position)),
+            interactable.map(|interactable| Rect::from_center_size(position, Vec2::splat(interactable.radius * 2.0))),
+        ];
+        let bounds = bounds.into_iter().flatten().reduce(|a, b| a.union(b))
+            .unwrap_or_else(|| Rect::from_center_size(position, Vec2::ZERO));
+        spatial_hash.insert(entity, bounds);
+    }
+}
@@ -0,0 +1,162 @@
+// :: Spawners ::
+// Places on a map that keep it stocked with enemies, so maps can say
+// where enemies come from instead of them being spawned by hand. While a
+// player is within a Spawner's activation radius, it spawns an enemy of
+// its type every `respawn_delay` seconds, until `max_alive` of them are
+// about. Defeated enemies are replaced the same way, and a `limit` stops
+// a spawner for good after that many (e.g., a wave that can be cleared).
+//
+// What each enemy type looks like is up to the EnemyRegistry, which maps
+// type names to spawn functions:
+//
+//     registry.register("slime", move |enemy, position| {
+//         enemy.insert((Vision::new(64.0, 120.0), SpriteSheetBundle {
+//             texture_atlas: slime_atlas.clone(),
+//             transform: Transform::from_translation(position.extend(0.0)),
+//             ..default()
+//         }));
+//     });
+//
+// Spawned enemies get an Enemy and a SpawnedBy (pointing back at their
+// spawner) before the spawn function is called. They go when their
+// spawner does, e.g. when the player warps and its map is despawned.
+use bevy::{ecs::system::EntityCommands, prelude::*, utils::HashMap};
+
+use crate::{enemy::Enemy, player::Player};
+
+type SpawnFn = Box<dyn Fn(&mut EntityCommands, Vec2) + Send + Sync>;
+
+#[derive(Component, Clone, Debug)]
+pub struct Spawner {
+    pub enemy_type: String, // a name in the EnemyRegistry
+    pub max_alive: u32, // how many of its enemies can be about at once
+    pub respawn_delay: f32, // seconds between spawns
+    pub activation_radius: f32, // how close a player has to be, in pixels
+    pub limit: Option<u32>, // how many it spawns in total, if there's a limit
+    alive: Vec<Entity>,
+    spawned: u32,
+    cooldown: f32,
+}
+impl Spawner {
+    pub fn new(enemy_type: &str) -> Self {
+        Self {
+            enemy_type: enemy_type.to_string(),
+            max_alive: 1,
+            respawn_delay: 5.0,
+            activation_radius: 160.0,
+            limit: None,
+            alive: Vec::new(),
+            spawned: 0,
+            cooldown: 0.0,
+        }
+    }
+    pub fn with_max_alive(mut self, max_alive: u32) -> Self {
+        self.max_alive = max_alive;
+        self
+    }
+    pub fn with_respawn_delay(mut self, seconds: f32) -> Self {
+        self.respawn_delay = seconds;
+        self
+    }
+    pub fn with_activation_radius(mut self, radius: f32) -> Self {
+        self.activation_radius = radius;
+        self
+    }
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+    // How many of its enemies are about
+    pub fn alive(&self) -> usize {
+        self.alive.len()
+    }
+    // Whether it's spawned everything it ever will, and all of it is gone
+    pub fn is_cleared(&self) -> bool {
+        self.limit.map_or(false, |limit| self.spawned >= limit) && self.alive.is_empty()
+    }
+}
+
+// The Spawner an enemy came from
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SpawnedBy(pub Entity);
+
+// How to spawn each type of enemy, by name
+#[derive(Resource, Default)]
+pub struct EnemyRegistry {
+    spawners: HashMap<String, SpawnFn>,
+}
+impl EnemyRegistry {
+    // Replaces anything already registered for `enemy_type`
+    pub fn register(
+        &mut self,
+        enemy_type: &str,
+        spawn: impl Fn(&mut EntityCommands, Vec2) + Send + Sync + 'static,
+    ) {
+        self.spawners.insert(enemy_type.to_string(), Box::new(spawn));
+    }
+}
+
+pub struct SpawnerPlugin;
+impl Plugin for SpawnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EnemyRegistry>()
+            .add_system(run_spawners)
+            .add_system(despawn_orphans);
+    }
+}
+
+fn run_spawners(
+    mut commands: Commands,
+    time: Res<Time>,
+    registry: Res<EnemyRegistry>,
+    players: Query<&GlobalTransform, With<Player>>,
+    existing: Query<(), With<SpawnedBy>>,
+    mut spawners: Query<(Entity, &mut Spawner, &GlobalTransform)>,
+) {
+    for (spawner_entity, mut spawner, transform) in &mut spawners {
+        // Forget enemies that have been defeated (despawned), and wait
+        // before replacing them
+        if spawner.alive.iter().any(|enemy| !existing.contains(*enemy)) {
+            spawner.alive.retain(|enemy| existing.contains(*enemy));
+            spawner.cooldown = spawner.respawn_delay;
+        }
+        if spawner.cooldown > 0.0 {
+            spawner.cooldown -= time.delta_seconds();
+            continue;
+        }
+        let position = transform.translation().truncate();
+        let active = players.iter()
+            .any(|player| player.translation().truncate().distance(position) <= spawner.activation_radius);
+        let used_up = spawner.limit.map_or(false, |limit| spawner.spawned >= limit);
+        if !active || used_up || spawner.alive.len() >= spawner.max_alive as usize {
+            continue;
+        }
+        let spawn = match registry.spawners.get(&spawner.enemy_type) {
+            Some(spawn) => spawn,
+            None => {
+                warn!("No enemy type \"{}\" is registered", spawner.enemy_type);
+                spawner.cooldown = spawner.respawn_delay;
+                continue;
+            }
+        };
+        let mut enemy = commands.spawn((Enemy, SpawnedBy(spawner_entity)));
+        spawn(&mut enemy, position);
+        spawner.alive.push(enemy.id());
+        spawner.spawned += 1;
+        spawner.cooldown = spawner.respawn_delay;
+    }
+}
+
+// Enemies whose spawner is gone (along with its map) go with it, rather
+// than being left behind in whatever map's loaded next
+fn despawn_orphans(
+    mut commands: Commands,
+    enemies: Query<(Entity, &SpawnedBy)>,
+    spawners: Query<(), With<Spawner>>,
+) {
+    for (enemy, spawned_by) in &enemies {
+        if !spawners.contains(spawned_by.0) {
+            commands.entity(enemy).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// :: Swimming ::
+// Water that can be waded into. A Swimmer whose feet are on a Water tile
+// (see Terrain), or inside a WaterRegion, is swimming: it moves at
+// `speed_multiplier` of its usual speed, and `is_swimming` tells its
+// animation to switch to "swim-*" states.
+//
+// Swimmers that can't swim only last `breath` seconds in the water before
+// they're pulled back out to where they last stood on dry ground, and a
+// Drowned event is sent:
+//
+//     commands.spawn((Swimmer::default().without_swimming(2.0), ..));
+//     ...
+//     for drowned in drowned.iter() { health.get_mut(drowned.entity)?.0 -= 1; }
+//
+// Giving them the ability later (e.g., with flippers) is just setting
+// `can_swim`.
+use bevy::{math::Rect, prelude::*};
+
+use crate::{
+    collision::{feet, Collider},
+    footsteps::{find_ground_terrain, GroundTerrain},
+    movement::{Position, SpeedModifiers},
+    tilemap::Terrain,
+};
+
+const SPEED_REASON: &str = "swimming";
+const DEFAULT_SPEED_MULTIPLIER: f32 = 0.5;
+const DEFAULT_BREATH: f32 = 2.0; // in seconds
+
+#[derive(Component, Clone, Debug)]
+pub struct Swimmer {
+    pub speed_multiplier: f32,
+    pub can_swim: bool,
+    pub breath: f32, // seconds in the water before drowning, if it can't swim
+    swimming: bool,
+    time_in_water: f32,
+    last_dry: Option<Vec2>,
+}
+impl Default for Swimmer {
+    fn default() -> Self {
+        Self {
+            speed_multiplier: DEFAULT_SPEED_MULTIPLIER,
+            can_swim: true,
+            breath: DEFAULT_BREATH,
+            swimming: false,
+            time_in_water: 0.0,
+            last_dry: None,
+        }
+    }
+}
+impl Swimmer {
+    pub fn with_speed_multiplier(mut self, speed_multiplier: f32) -> Self {
+        self.speed_multiplier = speed_multiplier;
+        self
+    }
+    // Can't swim, and drowns after `breath` seconds in the water
+    pub fn without_swimming(mut self, breath: f32) -> Self {
+        self.can_swim = false;
+        self.breath = breath;
+        self
+    }
+    pub fn is_swimming(&self) -> bool {
+        self.swimming
+    }
+}
+
+// Water that isn't drawn with Water tiles, e.g. a river in an
+// object layer, or a pool that fills up
+#[derive(Component, Clone, Copy, Debug)]
+pub struct WaterRegion {
+    pub size: Vec2,
+    pub offset: Vec2, // from the entity's position to the region's center
+}
+impl WaterRegion {
+    pub fn new(size: Vec2) -> Self {
+        Self { size, offset: Vec2::ZERO }
+    }
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+    pub fn rect_at(&self, position: Vec2) -> Rect {
+        Rect::from_center_size(position + self.offset, self.size)
+    }
+}
+
+// Sent when a Swimmer that can't swim runs out of breath
+pub struct Drowned {
+    pub entity: Entity,
+}
+
+pub struct SwimmingPlugin;
+impl Plugin for SwimmingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Drowned>()
+            .add_system(update_swimmers.after(find_ground_terrain));
+    }
+}
+
+pub(crate) fn update_swimmers(
+    time: Res<Time>,
+    mut drowned: EventWriter<Drowned>,
+    regions: Query<(&WaterRegion, &GlobalTransform)>,
+    mut swimmers: Query<(
+        Entity,
+        &mut Swimmer,
+        &mut Position,
+        Option<&Collider>,
+        Option<&GroundTerrain>,
+        Option<&mut SpeedModifiers>,
+    )>,
+) {
+    for (entity, mut swimmer, mut position, collider, ground, modifiers) in &mut swimmers {
+        let feet = feet(collider, position.current);
+        let in_water = ground.and_then(|ground| ground.0) == Some(Terrain::Water)
+            || regions.iter().any(|(region, transform)| region.rect_at(transform.translation().truncate()).contains(feet));
+
+        if swimmer.swimming != in_water {
+            swimmer.swimming = in_water;
+        }
+        if let Some(mut modifiers) = modifiers {
+            let multiplier = in_water.then_some(swimmer.speed_multiplier);
+            if modifiers.get(SPEED_REASON) != multiplier {
+                match multiplier {
+                    Some(multiplier) => modifiers.set(SPEED_REASON, multiplier),
+                    None => modifiers.remove(SPEED_REASON),
+                }
+            }
+        }
+        if !in_water {
+            swimmer.time_in_water = 0.0;
+            swimmer.last_dry = Some(position.current);
+            continue;
+        }
+
+        swimmer.time_in_water += time.delta_seconds();
+        if swimmer.can_swim || swimmer.time_in_water < swimmer.breath {
+            continue;
+        }
+        // Out of breath; back to dry land
+        if let Some(last_dry) = swimmer.last_dry {
+            position.teleport(last_dry);
+        }
+        swimmer.time_in_water = 0.0;
+        drowned.send(Drowned { entity });
+    }
+}
@@ -0,0 +1,159 @@
+// :: Tilemap chunks ::
+// Big maps (a whole overworld) are too much to keep drawn all at once. Give
+// a Tilemap a TilemapChunks and it's split into square chunks of tiles,
+// each with its own mesh per layer; only chunks near what a camera can see
+// are drawn, and chunks that fall far enough behind are despawned:
+//
+//     commands.spawn((map, TilemapChunks::new(16), SpatialBundle::default()));
+//
+// Chunks load within `load_margin` of any camera's view, and unload once
+// they're more than `unload_margin` away, so walking back and forth over
+// a chunk's edge doesn't keep rebuilding it.
+use bevy::{
+    prelude::*,
+    render::view::RenderLayers,
+    sprite::MaterialMesh2dBundle,
+    utils::{HashMap, HashSet},
+};
+
+use super::{render, Tilemap};
+use crate::camera::half_view_size;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TilemapChunks {
+    pub chunk_size: u32, // in tiles, along each side
+    pub load_margin: f32, // in pixels, around the view
+    pub unload_margin: f32, // in pixels, around the view; at least load_margin
+}
+impl TilemapChunks {
+    pub fn new(chunk_size: u32) -> Self {
+        Self { chunk_size: chunk_size.max(1), load_margin: 64.0, unload_margin: 192.0 }
+    }
+    pub fn with_margins(mut self, load_margin: f32, unload_margin: f32) -> Self {
+        self.load_margin = load_margin;
+        self.unload_margin = unload_margin.max(load_margin);
+        self
+    }
+}
+
+// The mesh entities drawing each of a map's loaded chunks
+#[derive(Component, Default)]
+pub(super) struct LoadedChunks(HashMap<UVec2, Vec<Entity>>);
+
+pub(super) fn stream_chunks(
+    mut commands: Commands,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut atlas_events: EventReader<AssetEvent<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut tilemaps: Query<(Entity, &Tilemap, &TilemapChunks, &GlobalTransform,
+                         ChangeTrackers<Tilemap>, Option<&mut LoadedChunks>)>,
+    cameras: Query<(&Camera, &OrthographicProjection, &Transform, &GlobalTransform, Option<&RenderLayers>)>,
+) {
+    let modified_atlases: Vec<_> = atlas_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.clone()),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+    // What each camera that can see the map sees, as (center, half size).
+    // Cameras that only see other layers (e.g., the pixel-perfect screen)
+    // are left out.
+    let views: Vec<(Vec2, Vec2)> = cameras.iter()
+        .filter(|(camera, _, _, _, layers)| {
+            camera.is_active && layers.map_or(true, |layers| layers.intersects(&RenderLayers::default()))
+        })
+        .map(|(_, projection, transform, global, _)| {
+            (global.translation().truncate(), half_view_size(projection, transform))
+        })
+        .collect();
+
+    for (entity, tilemap, chunks, map_transform, tracker, loaded) in &mut tilemaps {
+        let atlas = match atlases.get(&tilemap.tileset) {
+            Some(atlas) => atlas,
+            None => continue, // not loaded yet; try again next frame
+        };
+        let mut loaded = match loaded {
+            Some(loaded) => loaded,
+            None => {
+                // Start with nothing drawn; the chunks are loaded next frame
+                commands.entity(entity).insert(LoadedChunks::default());
+                continue;
+            }
+        };
+        // A changed map redraws every chunk
+        if tracker.is_changed() || modified_atlases.contains(&tilemap.tileset) {
+            for (_, old) in loaded.0.drain() {
+                for mesh in old {
+                    commands.entity(mesh).despawn_recursive();
+                }
+            }
+        }
+
+        let corner = map_transform.translation().truncate();
+        let chunk_size = chunks.chunk_size.max(1); // the field's public, so it could have been set to 0
+        let chunk_pixels = Vec2::splat(chunk_size as f32) * tilemap.tile_size;
+        let chunk_count = (tilemap.size + UVec2::splat(chunk_size - 1)) / chunk_size;
+        if chunk_count.x == 0 || chunk_count.y == 0 {
+            continue;
+        }
+        // The chunks within `margin` of any view
+        let chunks_near = |margin: f32| -> HashSet<UVec2> {
+            let mut near = HashSet::new();
+            for (center, half_size) in views.iter() {
+                let half_size = *half_size + Vec2::splat(margin);
+                // Rows go down, so flip y to count chunks from the top
+                let min = Vec2::new(center.x - half_size.x - corner.x, corner.y - (center.y + half_size.y));
+                let max = Vec2::new(center.x + half_size.x - corner.x, corner.y - (center.y - half_size.y));
+                if max.x < 0.0 || max.y < 0.0 {
+                    continue;
+                }
+                let first = (min / chunk_pixels).floor().max(Vec2::ZERO).as_uvec2();
+                let last = (max / chunk_pixels).floor().as_uvec2().min(chunk_count - UVec2::ONE);
+                for y in first.y..=last.y {
+                    for x in first.x..=last.x {
+                        near.insert(UVec2::new(x, y));
+                    }
+                }
+            }
+            near
+        };
+
+        let keep = chunks_near(chunks.unload_margin);
+        let far: Vec<UVec2> = loaded.0.keys().filter(|chunk| !keep.contains(chunk)).copied().collect();
+        for chunk in far {
+            for mesh in loaded.0.remove(&chunk).unwrap_or_default() {
+                commands.entity(mesh).despawn_recursive();
+            }
+        }
+
+        let wanted = chunks_near(chunks.load_margin);
+        let missing: Vec<UVec2> = wanted.into_iter().filter(|chunk| !loaded.0.contains_key(chunk)).collect();
+        if missing.is_empty() {
+            continue;
+        }
+        let material = materials.add(ColorMaterial::from(atlas.texture.clone()));
+        let layer_z = render::layer_z(tilemap);
+        for chunk in missing {
+            let first = chunk * chunk_size;
+            let last = first + UVec2::splat(chunk_size);
+            let chunk_meshes: Vec<Entity> = tilemap.layers.iter().zip(layer_z.iter())
+                .filter(|(layer, _)| layer.iter_region(first, last).next().is_some())
+                .map(|(layer, z)| {
+                    let transform = Transform::from_xyz(0.0, 0.0, *z);
+                    commands.spawn(MaterialMesh2dBundle {
+                        mesh: meshes.add(render::layer_mesh(layer, tilemap.tile_size, atlas, first, last)).into(),
+                        material: material.clone(),
+                        transform,
+                        // Spawned after transforms are propagated, so place
+                        // it now rather than drawing it at the origin for a frame
+                        global_transform: map_transform.mul_transform(transform),
+                        ..default()
+                    }).id()
+                })
+                .collect();
+            commands.entity(entity).push_children(&chunk_meshes);
+            loaded.0.insert(chunk, chunk_meshes);
+        }
+    }
+}
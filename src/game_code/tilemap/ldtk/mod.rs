@@ -0,0 +1,470 @@
+// :: LDtk projects ::
+// Loads levels made in LDtk (https://ldtk.io) and spawns them. Which
+// levels to spawn is picked with an LdtkLevelSelection:
+//
+//     commands.spawn((
+//         asset_server.load::<LdtkProject, _>("maps/world.ldtk"),
+//         LdtkLevelSelection::WithNeighbours("Meadow".to_string()),
+//         SpatialBundle::default(),
+//     ));
+//
+// Each level is placed where it is in LDtk's world view, relative to the
+// project entity, so neighbouring levels line up.
+//
+// Tile, auto and IntGrid layers become Tilemaps. The bottom tile layer is
+// ground, layers with "overhang" in their name are overhangs, and the rest
+// are decoration. Tiles flipped in LDtk are drawn unflipped. Tiles tagged
+// (in the tileset's enum tags) with an enum value named after a Terrain,
+// like "Grass" or "Stone", are made of it. Cells in an IntGrid layer named
+// "Collision" block movement, with their value picking how (see
+// `TileShape::from_code`; 1 is solid).
+//
+// Entity instances are spawned at their center, with an LdtkEntity holding
+// their identifier, size and fields. What else they get is up to their
+// identifier, as a class (see objects.rs); an entity's name there is its
+// "name" field, or else its iid.
+// Editing the project in LDtk while the game runs respawns it.
+use std::path::Path;
+
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    math::Rect,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+
+use super::{MapObject, MapObjectRegistry, MapProperties, Terrain, TileLayerKind, Tilemap};
+use crate::collision::{CollisionGrid, TileShape};
+
+mod raw;
+
+use raw::{RawField, RawLayer, RawLevel, RawProject};
+
+#[derive(Debug)]
+pub struct LdtkError(String);
+impl std::fmt::Display for LdtkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Couldn't read LDtk project: {}", self.0)
+    }
+}
+impl std::error::Error for LdtkError {}
+
+// A level's or entity's fields, as set in LDtk
+#[derive(Component, Clone, Debug, Default)]
+pub struct LdtkFields(pub HashMap<String, serde_json::Value>);
+impl LdtkFields {
+    pub fn get(&self, name: &str) -> Option<&serde_json::Value> {
+        self.0.get(name)
+    }
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        self.get(name)?.as_bool()
+    }
+    pub fn get_f32(&self, name: &str) -> Option<f32> {
+        self.get(name)?.as_f64().map(|value| value as f32)
+    }
+    pub fn get_i64(&self, name: &str) -> Option<i64> {
+        self.get(name)?.as_i64()
+    }
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.get(name)?.as_str()
+    }
+
+    fn from_raw(fields: &[RawField]) -> Self {
+        Self(fields.iter()
+            .filter(|field| !field.value.is_null()) // unset fields
+            .map(|field| (field.identifier.clone(), field.value.clone()))
+            .collect())
+    }
+}
+
+impl MapProperties for LdtkFields {
+    fn get_bool(&self, name: &str) -> Option<bool> {
+        LdtkFields::get_bool(self, name)
+    }
+    fn get_f32(&self, name: &str) -> Option<f32> {
+        LdtkFields::get_f32(self, name)
+    }
+    fn get_i64(&self, name: &str) -> Option<i64> {
+        LdtkFields::get_i64(self, name)
+    }
+    fn get_str(&self, name: &str) -> Option<&str> {
+        LdtkFields::get_str(self, name)
+    }
+}
+
+// An entity instance from one of a level's Entities layers
+#[derive(Component, Clone, Debug)]
+pub struct LdtkEntity {
+    pub identifier: String,
+    pub iid: String,
+    pub size: Vec2,
+    pub fields: LdtkFields,
+}
+
+// :: The project asset ::
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum NeighbourDirection {
+    North,
+    South,
+    East,
+    West,
+    Other, // diagonal, or overlapping in depth
+}
+
+#[derive(Clone, Debug)]
+pub struct LdtkTileLayer {
+    pub identifier: String,
+    pub kind: TileLayerKind,
+    pub size: UVec2, // in cells
+    pub grid_size: f32,
+    pub tileset: Handle<TextureAtlas>,
+    pub terrain: HashMap<u32, Terrain>, // by index in the tileset
+    pub tiles: Vec<(UVec2, u32)>, // cell, index in the tileset; several may share a cell
+    pub int_grid: Vec<i32>, // IntGrid values, rows from the top; empty for other layers
+}
+
+#[derive(Clone, Debug)]
+pub struct LdtkEntityDef {
+    pub entity: LdtkEntity,
+    pub position: Vec2, // the entity's center, relative to the level's top-left corner
+}
+
+#[derive(Clone, Debug)]
+pub struct LdtkLevel {
+    pub identifier: String,
+    pub iid: String,
+    pub world_position: Vec2, // the top-left corner, in our coordinates (y up)
+    pub size: Vec2,
+    pub tile_layers: Vec<LdtkTileLayer>, // from the bottom up
+    pub entities: Vec<LdtkEntityDef>,
+    pub neighbours: Vec<(String, NeighbourDirection)>, // by iid
+    pub fields: LdtkFields,
+}
+impl LdtkLevel {
+    // The whole level, in the project's coordinates
+    pub fn rect(&self) -> Rect {
+        Rect::new(
+            self.world_position.x, self.world_position.y - self.size.y,
+            self.world_position.x + self.size.x, self.world_position.y)
+    }
+}
+
+#[derive(Clone, Debug, TypeUuid)]
+#[uuid = "7c3e9a51-2b8d-4f60-9e1a-5d4c3b2a1f0e"]
+pub struct LdtkProject {
+    pub levels: Vec<LdtkLevel>,
+}
+impl LdtkProject {
+    pub fn level(&self, identifier: &str) -> Option<&LdtkLevel> {
+        self.levels.iter().find(|level| level.identifier == identifier)
+    }
+    pub fn level_by_iid(&self, iid: &str) -> Option<&LdtkLevel> {
+        self.levels.iter().find(|level| level.iid == iid)
+    }
+    pub fn neighbours<'a>(&'a self, level: &'a LdtkLevel) -> impl Iterator<Item = (&'a LdtkLevel, NeighbourDirection)> {
+        level.neighbours.iter()
+            .filter_map(|(iid, direction)| Some((self.level_by_iid(iid)?, *direction)))
+    }
+}
+
+#[derive(Default)]
+pub struct LdtkLoader;
+impl AssetLoader for LdtkLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let project: RawProject = serde_json::from_slice(bytes)?;
+            let dir = load_context.path().parent().map(Path::to_path_buf).unwrap_or_default();
+
+            // Every tileset's image, cut into an atlas kept as a labeled asset
+            let mut tilesets = HashMap::new();
+            for tileset in project.defs.tilesets.iter() {
+                let rel_path = match &tileset.rel_path {
+                    Some(rel_path) => rel_path,
+                    None => continue,
+                };
+                let image_path = AssetPath::new(dir.join(rel_path), None);
+                let texture: Handle<Image> = load_context.get_handle(image_path.clone());
+                let atlas = TextureAtlas::from_grid(
+                    texture,
+                    Vec2::splat(tileset.tile_grid_size),
+                    tileset.columns,
+                    tileset.rows,
+                    Some(Vec2::splat(tileset.spacing)),
+                    Some(Vec2::splat(tileset.padding)),
+                );
+                let atlas = load_context.set_labeled_asset(
+                    &format!("tileset/{}", tileset.identifier),
+                    LoadedAsset::new(atlas).with_dependency(image_path));
+                // Tiles tagged with an enum value named after a Terrain
+                let terrain = tileset.enum_tags.iter()
+                    .filter_map(|tag| Terrain::from_name(&tag.enum_value_id).map(|terrain| (tag, terrain)))
+                    .flat_map(|(tag, terrain)| tag.tile_ids.iter().map(move |id| (*id, terrain)))
+                    .collect();
+                tilesets.insert(tileset.uid, LoadedTileset { atlas, terrain });
+            }
+
+            let mut levels = Vec::new();
+            for level in project.levels {
+                // Levels can be saved in their own .ldtkl files
+                let external_path = level.external_rel_path.clone().filter(|_| project.external_levels);
+                let level = match external_path {
+                    Some(rel_path) => {
+                        let bytes = load_context.read_asset_bytes(dir.join(rel_path)).await?;
+                        serde_json::from_slice(&bytes)?
+                    },
+                    None => level,
+                };
+                levels.push(build_level(level, &tilesets)?);
+            }
+            load_context.set_default_asset(LoadedAsset::new(LdtkProject { levels }));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtk"]
+    }
+}
+
+// A tileset, as the project's tile layers need it
+struct LoadedTileset {
+    atlas: Handle<TextureAtlas>,
+    terrain: HashMap<u32, Terrain>,
+}
+
+fn build_level(level: RawLevel, tilesets: &HashMap<i64, LoadedTileset>) -> Result<LdtkLevel, LdtkError> {
+    let layers = level.layer_instances
+        .ok_or_else(|| LdtkError(format!("level \"{}\" has no layers", level.identifier)))?;
+
+    let mut tile_layers = Vec::new();
+    let mut entities = Vec::new();
+    // LDtk lists layers from the top down
+    for layer in layers.into_iter().rev() {
+        if layer.kind == "Entities" {
+            entities.extend(layer.entity_instances.iter().map(|entity| {
+                let size = Vec2::new(entity.width, entity.height);
+                let top_left = Vec2::from(entity.px) - Vec2::from(entity.pivot) * size;
+                LdtkEntityDef {
+                    entity: LdtkEntity {
+                        identifier: entity.identifier.clone(),
+                        iid: entity.iid.clone(),
+                        size,
+                        fields: LdtkFields::from_raw(&entity.field_instances),
+                    },
+                    position: Vec2::new(top_left.x + size.x / 2.0, -(top_left.y + size.y / 2.0)),
+                }
+            }));
+            continue;
+        }
+        if let Some(tile_layer) = build_tile_layer(layer, tile_layers.is_empty(), tilesets) {
+            tile_layers.push(tile_layer);
+        }
+    }
+
+    let neighbours = level.neighbours.iter()
+        .map(|neighbour| {
+            let direction = match neighbour.dir.as_str() {
+                "n" => NeighbourDirection::North,
+                "s" => NeighbourDirection::South,
+                "e" => NeighbourDirection::East,
+                "w" => NeighbourDirection::West,
+                _ => NeighbourDirection::Other,
+            };
+            (neighbour.level_iid.clone(), direction)
+        })
+        .collect();
+
+    Ok(LdtkLevel {
+        identifier: level.identifier,
+        iid: level.iid,
+        world_position: Vec2::new(level.world_x, -level.world_y),
+        size: Vec2::new(level.px_wid, level.px_hei),
+        tile_layers,
+        entities,
+        neighbours,
+        fields: LdtkFields::from_raw(&level.field_instances),
+    })
+}
+
+fn build_tile_layer(
+    layer: RawLayer,
+    is_bottom: bool,
+    tilesets: &HashMap<i64, LoadedTileset>,
+) -> Option<LdtkTileLayer> {
+    // IntGrid layers without auto-tiles still hold their values, e.g. for collision
+    let (tileset, terrain) = match layer.tileset_uid.and_then(|uid| tilesets.get(&uid)) {
+        Some(tileset) => (tileset.atlas.clone(), tileset.terrain.clone()),
+        None if !layer.int_grid_csv.is_empty() => (Handle::default(), HashMap::new()),
+        None => return None,
+    };
+    let kind = if layer.identifier.to_lowercase().contains("overhang") {
+        TileLayerKind::Overhang
+    } else if is_bottom {
+        TileLayerKind::Ground
+    } else {
+        TileLayerKind::Decoration
+    };
+    let tiles = layer.grid_tiles.iter().chain(layer.auto_layer_tiles.iter())
+        .map(|tile| ((Vec2::from(tile.px) / layer.grid_size).as_uvec2(), tile.t))
+        .collect();
+    Some(LdtkTileLayer {
+        identifier: layer.identifier,
+        kind,
+        size: UVec2::new(layer.columns, layer.rows),
+        grid_size: layer.grid_size,
+        tileset,
+        terrain,
+        tiles,
+        int_grid: layer.int_grid_csv,
+    })
+}
+
+// :: Spawning ::
+
+// Which of a project's levels to spawn
+#[derive(Component, Clone, Debug)]
+pub enum LdtkLevelSelection {
+    One(String), // by identifier
+    WithNeighbours(String), // a level, and every level next to it
+    All,
+}
+
+// A spawned level
+#[derive(Component, Clone, Debug)]
+pub struct LdtkLevelInstance {
+    pub identifier: String,
+    pub iid: String,
+}
+
+// Everything spawned for a project, so it can be replaced when it changes
+#[derive(Component)]
+pub(crate) struct LdtkContents(Vec<Entity>);
+
+pub(crate) fn spawn_ldtk_levels(
+    mut commands: Commands,
+    mut project_events: EventReader<AssetEvent<LdtkProject>>,
+    projects: Res<Assets<LdtkProject>>,
+    registry: Res<MapObjectRegistry>,
+    query: Query<(
+        Entity,
+        &Handle<LdtkProject>,
+        &LdtkLevelSelection,
+        ChangeTrackers<LdtkLevelSelection>,
+        Option<&LdtkContents>,
+    )>,
+) {
+    let modified_projects: Vec<_> = project_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, handle, selection, selection_tracker, contents) in &query {
+        let needs_spawn = contents.is_none()
+            || selection_tracker.is_changed()
+            || modified_projects.contains(handle);
+        if !needs_spawn {
+            continue;
+        }
+        let project = match projects.get(handle) {
+            Some(project) => project,
+            None => continue, // not loaded yet; try again next frame
+        };
+        if let Some(LdtkContents(old)) = contents {
+            for child in old {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+
+        let levels: Vec<&LdtkLevel> = match selection {
+            LdtkLevelSelection::All => project.levels.iter().collect(),
+            LdtkLevelSelection::One(identifier) | LdtkLevelSelection::WithNeighbours(identifier) => {
+                match project.level(identifier) {
+                    Some(level) => {
+                        let mut levels = vec![level];
+                        if let LdtkLevelSelection::WithNeighbours(_) = selection {
+                            levels.extend(project.neighbours(level).map(|(neighbour, _)| neighbour));
+                        }
+                        levels
+                    },
+                    None => {
+                        warn!("There's no LDtk level named \"{}\"", identifier);
+                        Vec::new()
+                    },
+                }
+            },
+        };
+        let children: Vec<Entity> = levels.into_iter()
+            .map(|level| spawn_level(&mut commands, &registry, level))
+            .collect();
+        commands.entity(entity)
+            .push_children(&children)
+            .insert(LdtkContents(children));
+    }
+}
+
+fn spawn_level(commands: &mut Commands, registry: &MapObjectRegistry, level: &LdtkLevel) -> Entity {
+    let mut children = Vec::new();
+    for layer in level.tile_layers.iter() {
+        if layer.tiles.is_empty() {
+            continue;
+        }
+        // Auto-layers can stack several tiles in one cell, so each
+        // extra tile goes up into another layer of the same kind
+        let mut tilemap = Tilemap::new(layer.size, Vec2::splat(layer.grid_size), layer.tileset.clone());
+        tilemap.terrain = layer.terrain.clone();
+        for (cell, tile) in layer.tiles.iter() {
+            let free_layer = tilemap.layers.iter().position(|tile_layer| tile_layer.get(*cell).is_none());
+            let tile_layer = match free_layer {
+                Some(index) => &mut tilemap.layers[index],
+                None => tilemap.add_layer(&layer.identifier, layer.kind),
+            };
+            tile_layer.set(*cell, Some(*tile));
+        }
+        children.push(commands.spawn((tilemap, SpatialBundle::default())).id());
+    }
+    for def in level.entities.iter() {
+        let mut entity = commands.spawn((
+            def.entity.clone(),
+            SpatialBundle::from_transform(Transform::from_translation(def.position.extend(0.0))),
+        ));
+        registry.spawn(&def.entity.identifier, &mut entity, &MapObject {
+            name: def.entity.fields.get_str("name").unwrap_or(&def.entity.iid),
+            size: def.entity.size,
+            properties: &def.entity.fields,
+        });
+        children.push(entity.id());
+    }
+
+    let mut level_entity = commands.spawn((
+        LdtkLevelInstance { identifier: level.identifier.clone(), iid: level.iid.clone() },
+        level.fields.clone(),
+        SpatialBundle::from_transform(Transform::from_translation(level.world_position.extend(0.0))),
+    ));
+    level_entity.push_children(&children);
+    if let Some(grid) = collision_grid(level) {
+        level_entity.insert(grid);
+    }
+    level_entity.id()
+}
+
+// Shaped by the "Collision" IntGrid layer's values
+fn collision_grid(level: &LdtkLevel) -> Option<CollisionGrid> {
+    let layer = level.tile_layers.iter()
+        .find(|layer| layer.identifier.eq_ignore_ascii_case("collision") && !layer.int_grid.is_empty())?;
+    let mut grid = CollisionGrid::new(layer.size, Vec2::splat(layer.grid_size));
+    for (index, value) in layer.int_grid.iter().enumerate() {
+        if *value > 0 {
+            let shape = TileShape::from_code(*value as u32);
+            grid.set_shape(UVec2::new(index as u32 % layer.size.x, index as u32 / layer.size.x), shape);
+        }
+    }
+    Some(grid)
+}
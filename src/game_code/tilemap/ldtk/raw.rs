@@ -0,0 +1,127 @@
+// :: LDtk files, as written ::
+// The parts of an LDtk project (.ldtk) and external level (.ldtkl) file we
+// use. Names match the file format, which is camelCase, with computed
+// fields starting with "__".
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RawProject {
+    pub defs: RawDefs,
+    #[serde(default)]
+    pub levels: Vec<RawLevel>,
+    #[serde(default)]
+    pub external_levels: bool,
+}
+
+#[derive(Deserialize)]
+pub(super) struct RawDefs {
+    #[serde(default)]
+    pub tilesets: Vec<RawTileset>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RawTileset {
+    pub uid: i64,
+    pub identifier: String,
+    pub rel_path: Option<String>, // None for LDtk's built-in icons
+    pub tile_grid_size: f32,
+    #[serde(default)]
+    pub spacing: f32,
+    #[serde(default)]
+    pub padding: f32,
+    #[serde(rename = "__cWid")]
+    pub columns: usize,
+    #[serde(rename = "__cHei")]
+    pub rows: usize,
+    #[serde(default)]
+    pub enum_tags: Vec<RawEnumTag>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RawEnumTag {
+    pub enum_value_id: String,
+    #[serde(default)]
+    pub tile_ids: Vec<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RawLevel {
+    pub identifier: String,
+    pub iid: String,
+    pub world_x: f32,
+    pub world_y: f32,
+    pub px_wid: f32,
+    pub px_hei: f32,
+    #[serde(default)]
+    pub external_rel_path: Option<String>,
+    pub layer_instances: Option<Vec<RawLayer>>, // None when saved in a separate file
+    #[serde(default)]
+    pub field_instances: Vec<RawField>,
+    #[serde(rename = "__neighbours", default)]
+    pub neighbours: Vec<RawNeighbour>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RawNeighbour {
+    pub level_iid: String,
+    pub dir: String, // "n", "s", "e", "w", ...
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RawLayer {
+    #[serde(rename = "__identifier")]
+    pub identifier: String,
+    #[serde(rename = "__type")]
+    pub kind: String, // "Tiles", "AutoLayer", "IntGrid" or "Entities"
+    #[serde(rename = "__cWid")]
+    pub columns: u32,
+    #[serde(rename = "__cHei")]
+    pub rows: u32,
+    #[serde(rename = "__gridSize")]
+    pub grid_size: f32,
+    #[serde(rename = "__tilesetDefUid")]
+    pub tileset_uid: Option<i64>,
+    #[serde(default)]
+    pub grid_tiles: Vec<RawTile>,
+    #[serde(default)]
+    pub auto_layer_tiles: Vec<RawTile>,
+    #[serde(default)]
+    pub int_grid_csv: Vec<i32>,
+    #[serde(default)]
+    pub entity_instances: Vec<RawEntity>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct RawTile {
+    pub px: [f32; 2], // top-left corner, in the layer
+    pub t: u32, // index in the tileset
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct RawEntity {
+    #[serde(rename = "__identifier")]
+    pub identifier: String,
+    pub iid: String,
+    pub px: [f32; 2], // the pivot point, in the layer
+    #[serde(rename = "__pivot")]
+    pub pivot: [f32; 2], // 0.0 to 1.0, from the top-left
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub field_instances: Vec<RawField>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct RawField {
+    #[serde(rename = "__identifier")]
+    pub identifier: String,
+    #[serde(rename = "__value")]
+    pub value: serde_json::Value,
+}
@@ -0,0 +1,191 @@
+// :: Tilemaps ::
+// A map is a grid of tiles cut from one tileset (a TextureAtlas), in
+// layers drawn on top of each other:
+//
+//   - Ground: grass, paths, water; drawn under everything
+//   - Decoration: flowers, rocks; drawn on the ground, under characters
+//   - Overhang: treetops, roofs; drawn over characters, who walk behind them
+//
+// Each layer is drawn as a single mesh, rather than a sprite per tile,
+// so even big maps are cheap to draw. Spawn a Tilemap with a SpatialBundle;
+// its Transform places the map's top-left corner:
+//
+//     let mut map = Tilemap::new(UVec2::new(40, 30), Vec2::splat(16.0), tileset);
+//     map.add_layer("ground", TileLayerKind::Ground).fill(Some(0));
+//     commands.spawn((map, SpatialBundle::default()));
+//
+// Changing the Tilemap (or its tileset) redraws it. Big maps can be drawn
+// only near the cameras with TilemapChunks (see chunks.rs). Maps can also be
+// made in Tiled and loaded as a TiledMap (see tiled/mod.rs), or in LDtk
+// and loaded as an LdtkProject (see ldtk/mod.rs); the objects placed in
+// either become entities the same way (see objects.rs).
+use bevy::{math::Rect, prelude::*, transform::TransformSystem, utils::HashMap};
+
+mod chunks;
+mod ldtk;
+mod objects;
+mod render;
+mod terrain;
+mod tiled;
+
+pub use chunks::TilemapChunks;
+pub use terrain::Terrain;
+pub use ldtk::{
+    LdtkEntity, LdtkError, LdtkFields, LdtkLevel, LdtkLevelInstance, LdtkLevelSelection, LdtkProject,
+    NeighbourDirection,
+};
+pub use objects::{MapObject, MapObjectRegistry, MapProperties};
+pub use tiled::{
+    SpawnPoint, TiledError, TiledMap, TiledObject, TiledProperties, TiledProperty,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TileLayerKind {
+    Ground,
+    Decoration,
+    Overhang,
+}
+impl TileLayerKind {
+    // Ground and decorations go under y-sorted entities, overhangs over them
+    pub fn z(&self) -> f32 {
+        match self {
+            TileLayerKind::Ground => 0.0,
+            TileLayerKind::Decoration => 1.0,
+            TileLayerKind::Overhang => 900.0,
+        }
+    }
+}
+
+// One layer of tiles. Each tile is an index into the tileset, or None
+// for an empty spot. Rows go from the top of the map down.
+#[derive(Clone, Debug)]
+pub struct TileLayer {
+    pub name: String,
+    pub kind: TileLayerKind,
+    size: UVec2,
+    tiles: Vec<Option<u32>>,
+}
+impl TileLayer {
+    pub fn new(name: &str, kind: TileLayerKind, size: UVec2) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            size,
+            tiles: vec![None; (size.x * size.y) as usize],
+        }
+    }
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+    pub fn get(&self, tile: UVec2) -> Option<u32> {
+        self.index(tile).and_then(|index| self.tiles[index])
+    }
+    pub fn set(&mut self, tile: UVec2, value: Option<u32>) {
+        match self.index(tile) {
+            Some(index) => self.tiles[index] = value,
+            None => warn!("Tile {} is outside of the {}x{} layer \"{}\"",
+                          tile, self.size.x, self.size.y, self.name),
+        }
+    }
+    pub fn fill(&mut self, value: Option<u32>) -> &mut Self {
+        self.tiles.fill(value);
+        self
+    }
+    // Every non-empty tile, with its position
+    pub fn iter(&self) -> impl Iterator<Item = (UVec2, u32)> + '_ {
+        let width = self.size.x;
+        self.tiles.iter().enumerate().filter_map(move |(index, tile)| {
+            tile.map(|tile| (UVec2::new(index as u32 % width, index as u32 / width), tile))
+        })
+    }
+    // Every non-empty tile from `first` up to (not including) `last`
+    pub fn iter_region(&self, first: UVec2, last: UVec2) -> impl Iterator<Item = (UVec2, u32)> + '_ {
+        let last = last.min(self.size);
+        (first.y..last.y.max(first.y))
+            .flat_map(move |y| (first.x..last.x.max(first.x)).map(move |x| UVec2::new(x, y)))
+            .filter_map(|tile| self.get(tile).map(|index| (tile, index)))
+    }
+
+    fn index(&self, tile: UVec2) -> Option<usize> {
+        if tile.x < self.size.x && tile.y < self.size.y {
+            Some((tile.y * self.size.x + tile.x) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct Tilemap {
+    pub size: UVec2, // in tiles
+    pub tile_size: Vec2, // in pixels
+    pub tileset: Handle<TextureAtlas>,
+    pub layers: Vec<TileLayer>, // drawn in order, within each kind
+    pub terrain: HashMap<u32, Terrain>, // what each tile in the tileset is made of, if anything
+}
+impl Tilemap {
+    pub fn new(size: UVec2, tile_size: Vec2, tileset: Handle<TextureAtlas>) -> Self {
+        Self { size, tile_size, tileset, layers: Vec::new(), terrain: HashMap::new() }
+    }
+    // Add an empty layer on top of the others
+    pub fn add_layer(&mut self, name: &str, kind: TileLayerKind) -> &mut TileLayer {
+        self.layers.push(TileLayer::new(name, kind, self.size));
+        self.layers.last_mut().unwrap()
+    }
+    pub fn layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut TileLayer> {
+        self.layers.iter_mut().find(|layer| layer.name == name)
+    }
+
+    // The center of a tile, relative to the map's top-left corner
+    pub fn tile_center(&self, tile: UVec2) -> Vec2 {
+        Vec2::new(tile.x as f32 + 0.5, -(tile.y as f32 + 0.5)) * self.tile_size
+    }
+    // The tile under a point (relative to the map's top-left corner), if any
+    pub fn tile_at(&self, point: Vec2) -> Option<UVec2> {
+        let tile = Vec2::new(point.x, -point.y) / self.tile_size;
+        if tile.x < 0.0 || tile.y < 0.0 {
+            return None;
+        }
+        let tile = tile.as_uvec2();
+        if tile.x < self.size.x && tile.y < self.size.y { Some(tile) } else { None }
+    }
+    // What the ground is made of at a point (relative to the map's top-left
+    // corner): the terrain of the topmost tile there that has one, ignoring
+    // overhangs, which are above the ground
+    pub fn terrain_at(&self, point: Vec2) -> Option<Terrain> {
+        let tile = self.tile_at(point)?;
+        self.layers.iter().rev()
+            .filter(|layer| layer.kind != TileLayerKind::Overhang)
+            .filter_map(|layer| layer.get(tile))
+            .find_map(|index| self.terrain.get(&index).copied())
+    }
+
+    // The whole map, relative to its top-left corner; e.g., add the map's
+    // position to get CameraBounds
+    pub fn rect(&self) -> Rect {
+        let size = self.size.as_vec2() * self.tile_size;
+        Rect::new(0.0, -size.y, size.x, 0.0)
+    }
+}
+
+pub struct TilemapPlugin;
+impl Plugin for TilemapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<TiledMap>()
+            .init_asset_loader::<tiled::TiledMapLoader>()
+            .add_asset::<LdtkProject>()
+            .init_asset_loader::<ldtk::LdtkLoader>()
+            .init_resource::<MapObjectRegistry>()
+            .add_system(tiled::spawn_tiled_maps)
+            .add_system(ldtk::spawn_ldtk_levels)
+            .add_system(render::build_tilemaps
+                .after(tiled::spawn_tiled_maps)
+                .after(ldtk::spawn_ldtk_levels))
+            // After the cameras have moved, so no chunk is missing for a frame
+            .add_system_to_stage(CoreStage::PostUpdate, chunks::stream_chunks
+                .after(TransformSystem::TransformPropagate));
+    }
+}
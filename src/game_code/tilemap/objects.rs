@@ -0,0 +1,149 @@
+// :: Map objects ::
+// What the objects placed in a map editor (Tiled's objects, LDtk's
+// entities) get, shared by both importers. Each object's class in Tiled,
+// or identifier in LDtk, picks a spawn function from the
+// MapObjectRegistry. Classes are matched ignoring case and underscores, so
+// Tiled's "spawn_point" and LDtk's "SpawnPoint" are the same class:
+//
+//     registry.register("chest", |entity, object| {
+//         entity.insert(Chest::new(object.name, loot.clone()));
+//     });
+//
+// These are registered to start with:
+//
+//   - "spawn_point": a SpawnPoint with the object's name
+//   - "interactable": an Interactable, with the "prompt" property (default
+//     "Use") and "radius" property (default half the object's width)
+//   - "collider": a Collider the size of the object
+//   - "trigger": a TriggerZone the size of the object
+//   - "warp": a TriggerZone the size of the object, and a Warp to the
+//     "map" property's map, at its SpawnPoint named by the "spawn" property
+//   - "conveyor": a conveyor Surface the size of the object, moving things
+//     at ("velocity_x", "velocity_y") pixels per second, with y down as in
+//     the editors
+//   - "ice": an ice Surface the size of the object, with the "grip"
+//     property (default 2)
+//   - "water": a WaterRegion the size of the object
+//   - "spawner": a Spawner of the "enemy" property's enemy type, with the
+//     optional "max_alive", "respawn_delay", "radius" and "limit" properties
+//   - "sign": an Interactable like "interactable"'s (but with the default
+//     prompt "Read"), and a Readable of the "text" property, with a page
+//     for each paragraph
+use bevy::{ecs::system::EntityCommands, prelude::*, utils::HashMap};
+
+use super::SpawnPoint;
+use crate::{
+    collision::{Collider, Surface, TriggerZone},
+    dialogue::Readable,
+    interaction::Interactable,
+    spawner::Spawner,
+    swimming::WaterRegion,
+    warp::Warp,
+};
+
+const DEFAULT_INTERACT_PROMPT: &str = "Use";
+const DEFAULT_READ_PROMPT: &str = "Read";
+const DEFAULT_ICE_GRIP: f32 = 2.0;
+
+// An object's custom properties (Tiled) or fields (LDtk)
+pub trait MapProperties {
+    fn get_bool(&self, name: &str) -> Option<bool>;
+    fn get_f32(&self, name: &str) -> Option<f32>;
+    fn get_i64(&self, name: &str) -> Option<i64>;
+    fn get_str(&self, name: &str) -> Option<&str>;
+}
+
+// An object from either editor, as spawn functions see it
+pub struct MapObject<'a> {
+    pub name: &'a str, // in LDtk, the "name" field, or else the entity's iid
+    pub size: Vec2, // in pixels; zero for points
+    pub properties: &'a dyn MapProperties,
+}
+
+type SpawnFn = Box<dyn Fn(&mut EntityCommands, &MapObject) + Send + Sync>;
+
+// What to add to each class of map object
+#[derive(Resource)]
+pub struct MapObjectRegistry {
+    spawners: HashMap<String, SpawnFn>,
+}
+impl Default for MapObjectRegistry {
+    fn default() -> Self {
+        let mut registry = Self { spawners: HashMap::new() };
+        registry.register("spawn_point", |entity, object| {
+            entity.insert(SpawnPoint(object.name.to_string()));
+        });
+        registry.register("interactable", |entity, object| {
+            let prompt = object.properties.get_str("prompt").unwrap_or(DEFAULT_INTERACT_PROMPT);
+            let radius = object.properties.get_f32("radius").unwrap_or(object.size.x / 2.0);
+            entity.insert(Interactable::new(radius, prompt));
+        });
+        registry.register("collider", |entity, object| {
+            entity.insert(Collider::new(object.size));
+        });
+        registry.register("trigger", |entity, object| {
+            entity.insert(TriggerZone::new(object.size));
+        });
+        registry.register("warp", |entity, object| {
+            let map = object.properties.get_str("map").unwrap_or_default();
+            let spawn = object.properties.get_str("spawn").unwrap_or_default();
+            entity.insert((TriggerZone::new(object.size), Warp::new(map, spawn)));
+        });
+        registry.register("conveyor", |entity, object| {
+            let velocity = Vec2::new(
+                object.properties.get_f32("velocity_x").unwrap_or(0.0),
+                -object.properties.get_f32("velocity_y").unwrap_or(0.0), // y is down in the editors
+            );
+            entity.insert(Surface::conveyor(object.size, velocity));
+        });
+        registry.register("ice", |entity, object| {
+            let grip = object.properties.get_f32("grip").unwrap_or(DEFAULT_ICE_GRIP);
+            entity.insert(Surface::ice(object.size, grip));
+        });
+        registry.register("water", |entity, object| {
+            entity.insert(WaterRegion::new(object.size));
+        });
+        registry.register("spawner", |entity, object| {
+            let mut spawner = Spawner::new(object.properties.get_str("enemy").unwrap_or_default());
+            if let Some(max_alive) = object.properties.get_i64("max_alive") {
+                spawner.max_alive = max_alive.max(0) as u32;
+            }
+            if let Some(respawn_delay) = object.properties.get_f32("respawn_delay") {
+                spawner.respawn_delay = respawn_delay;
+            }
+            if let Some(radius) = object.properties.get_f32("radius") {
+                spawner.activation_radius = radius;
+            }
+            spawner.limit = object.properties.get_i64("limit").map(|limit| limit.max(0) as u32);
+            entity.insert(spawner);
+        });
+        registry.register("sign", |entity, object| {
+            let prompt = object.properties.get_str("prompt").unwrap_or(DEFAULT_READ_PROMPT);
+            let radius = object.properties.get_f32("radius").unwrap_or(object.size.x / 2.0);
+            let text = object.properties.get_str("text").unwrap_or_default();
+            entity.insert((Interactable::new(radius, prompt), Readable::from_text(text)));
+        });
+        registry
+    }
+}
+impl MapObjectRegistry {
+    // Replaces anything already registered for `class`
+    pub fn register(
+        &mut self,
+        class: &str,
+        spawn: impl Fn(&mut EntityCommands, &MapObject) + Send + Sync + 'static,
+    ) {
+        self.spawners.insert(class_key(class), Box::new(spawn));
+    }
+    // Give an object what its class gets, if the class is registered
+    pub fn spawn(&self, class: &str, entity: &mut EntityCommands, object: &MapObject) {
+        if let Some(spawn) = self.spawners.get(&class_key(class)) {
+            spawn(entity, object);
+        }
+    }
+}
+
+// "spawn_point" and "SpawnPoint" are both "spawnpoint"
+fn class_key(class: &str) -> String {
+    class.chars().filter(|c| *c != '_').flat_map(char::to_lowercase).collect()
+}
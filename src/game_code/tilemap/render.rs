@@ -0,0 +1,128 @@
+// :: Drawing tilemaps ::
+// Each layer becomes one mesh: a textured quad per tile, all sharing the
+// tileset's image, so the whole layer is drawn in a single batch. The
+// meshes are children of the Tilemap entity, and are rebuilt from scratch
+// whenever the Tilemap or its tileset changes. Maps with TilemapChunks are
+// drawn a chunk at a time instead (see chunks.rs).
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+    sprite::MaterialMesh2dBundle,
+    utils::HashMap,
+};
+
+use super::{TileLayer, Tilemap, TilemapChunks};
+
+// How far apart layers of the same kind are in z
+const LAYER_Z_STEP: f32 = 0.01;
+
+// The mesh entities drawing a Tilemap's layers
+#[derive(Component)]
+pub(super) struct TilemapLayers(Vec<Entity>);
+
+pub(super) fn build_tilemaps(
+    mut commands: Commands,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut atlas_events: EventReader<AssetEvent<TextureAtlas>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    tilemaps: Query<(Entity, &Tilemap, ChangeTrackers<Tilemap>, Option<&TilemapLayers>), Without<TilemapChunks>>,
+) {
+    let modified_atlases: Vec<_> = atlas_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle.clone()),
+            AssetEvent::Removed { .. } => None,
+        })
+        .collect();
+
+    for (entity, tilemap, tracker, layers) in &tilemaps {
+        let needs_build = layers.is_none()
+            || tracker.is_changed()
+            || modified_atlases.contains(&tilemap.tileset);
+        if !needs_build {
+            continue;
+        }
+        let atlas = match atlases.get(&tilemap.tileset) {
+            Some(atlas) => atlas,
+            None => continue, // not loaded yet; try again next frame
+        };
+
+        if let Some(TilemapLayers(old_layers)) = layers {
+            for layer in old_layers {
+                commands.entity(*layer).despawn_recursive();
+            }
+        }
+        let material = materials.add(ColorMaterial::from(atlas.texture.clone()));
+        let new_layers: Vec<Entity> = tilemap.layers.iter().zip(layer_z(tilemap))
+            .map(|(layer, z)| {
+                let mesh = layer_mesh(layer, tilemap.tile_size, atlas, UVec2::ZERO, layer.size());
+                commands.spawn(MaterialMesh2dBundle {
+                    mesh: meshes.add(mesh).into(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(0.0, 0.0, z),
+                    ..default()
+                }).id()
+            })
+            .collect();
+        commands.entity(entity)
+            .push_children(&new_layers)
+            .insert(TilemapLayers(new_layers));
+    }
+}
+
+// The z of each of a map's layers
+pub(super) fn layer_z(tilemap: &Tilemap) -> Vec<f32> {
+    let mut kind_counts = HashMap::new();
+    tilemap.layers.iter()
+        .map(|layer| {
+            let count = kind_counts.entry(layer.kind).or_insert(0);
+            let z = layer.kind.z() + *count as f32 * LAYER_Z_STEP;
+            *count += 1;
+            z
+        })
+        .collect()
+}
+
+// A quad for each tile in the layer from `first` up to (not including)
+// `last`, with the map's top-left corner at (0, 0)
+pub(super) fn layer_mesh(layer: &TileLayer, tile_size: Vec2, atlas: &TextureAtlas, first: UVec2, last: UVec2) -> Mesh {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut indices = Vec::new();
+    for (tile, index) in layer.iter_region(first, last) {
+        let rect = match atlas.textures.get(index as usize) {
+            Some(rect) => rect,
+            None => {
+                warn!("Tile layer \"{}\" uses tile {}, but its tileset only has {}",
+                      layer.name, index, atlas.textures.len());
+                continue;
+            }
+        };
+        let top_left = Vec2::new(tile.x as f32, -(tile.y as f32)) * tile_size;
+        let bottom_right = top_left + Vec2::new(tile_size.x, -tile_size.y);
+        let (uv_min, uv_max) = (rect.min / atlas.size, rect.max / atlas.size);
+
+        let first = positions.len() as u32;
+        positions.extend([
+            [top_left.x, top_left.y, 0.0],
+            [bottom_right.x, top_left.y, 0.0],
+            [bottom_right.x, bottom_right.y, 0.0],
+            [top_left.x, bottom_right.y, 0.0],
+        ]);
+        uvs.extend([
+            [uv_min.x, uv_min.y],
+            [uv_max.x, uv_min.y],
+            [uv_max.x, uv_max.y],
+            [uv_min.x, uv_max.y],
+        ]);
+        indices.extend([first, first + 2, first + 1, first, first + 3, first + 2]);
+    }
+
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
@@ -0,0 +1,30 @@
+// :: Terrain ::
+// What a tile is made of, for footsteps and anything else that cares what
+// the ground is like. Tiles are given a Terrain in the Tilemap's `terrain`
+// table, by their index in the tileset; maps from Tiled and LDtk fill it
+// in from the tileset (see tiled/mod.rs and ldtk/mod.rs).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Terrain {
+    Grass,
+    Dirt,
+    Sand,
+    Wood,
+    Stone,
+    Water,
+    Snow,
+}
+impl Terrain {
+    // From a name given in a map editor, in any case, e.g. "Grass"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "grass" => Some(Terrain::Grass),
+            "dirt" => Some(Terrain::Dirt),
+            "sand" => Some(Terrain::Sand),
+            "wood" => Some(Terrain::Wood),
+            "stone" => Some(Terrain::Stone),
+            "water" => Some(Terrain::Water),
+            "snow" => Some(Terrain::Snow),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,474 @@
+// :: Tiled maps ::
+// Loads maps made in the Tiled editor (https://www.mapeditor.org), in
+// either of its formats (.tmj JSON or .tmx XML), and spawns them:
+//
+//     commands.spawn((
+//         asset_server.load::<TiledMap, _>("maps/meadow.tmj"),
+//         SpatialBundle::default(), // places the map's top-left corner
+//     ));
+//
+// Tile layers become Tilemaps (one per tileset the map uses). Give a layer
+// a string property "kind" of "ground", "decoration" or "overhang" to pick
+// how it's drawn; otherwise the first layer is ground, and the rest are
+// decoration. Tiles flipped or rotated in Tiled are drawn unflipped. A
+// tile in a tileset with a string property "terrain" (or a class) of
+// "grass", "stone", etc. is made of that Terrain (see terrain.rs).
+// Layers hidden in Tiled aren't drawn. Give the map an int property
+// "chunk_size" to draw it in chunks of that many tiles (see chunks.rs),
+// for maps too big to draw at once. Every tile in a layer with a bool
+// property "collision" set is solid (see collision/grid.rs); this is
+// usually a hidden layer just for collision. If the layer also has a bool
+// property "collision_shapes", each tile's index in its tileset picks its
+// shape instead (see `TileShape::from_code`, which is one more than it).
+//
+// Each object in an object layer becomes an entity with a TiledObject and
+// its TiledProperties, placed at the object's center. What else it gets
+// is up to its class (see objects.rs).
+//
+// Tile objects are drawn as sprites, y-sorted by their bottom edge.
+// Editing the map in Tiled while the game runs respawns it.
+use std::path::Path;
+
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    math::Rect,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::{BoxedFuture, HashMap},
+};
+
+use super::{MapObject, MapObjectRegistry, MapProperties, Terrain, TileLayerKind, Tilemap, TilemapChunks};
+use crate::{
+    collision::{CollisionGrid, TileShape},
+    ysort::YSort,
+};
+
+mod raw;
+mod tmx;
+
+use raw::{RawLayer, RawMap, RawObject, RawProperty, RawTileData, RawTileset};
+
+// The top bits of a tile's id say how it's flipped
+const FLIP_FLAGS: u32 = 0xF000_0000;
+
+#[derive(Debug)]
+pub struct TiledError(String);
+impl std::fmt::Display for TiledError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Couldn't read Tiled map: {}", self.0)
+    }
+}
+impl std::error::Error for TiledError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TiledProperty {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String), // also colors ("#aarrggbb") and file paths
+}
+
+// An object's (or map's) custom properties
+#[derive(Component, Clone, Debug, Default)]
+pub struct TiledProperties(pub HashMap<String, TiledProperty>);
+impl TiledProperties {
+    pub fn get(&self, name: &str) -> Option<&TiledProperty> {
+        self.0.get(name)
+    }
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            TiledProperty::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn get_f32(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            TiledProperty::Float(value) => Some(*value as f32),
+            TiledProperty::Int(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+    pub fn get_i64(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            TiledProperty::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            TiledProperty::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn from_raw(properties: &[RawProperty]) -> Self {
+        let properties = properties.iter()
+            .filter_map(|property| {
+                let value = match (property.kind.as_str(), &property.value) {
+                    ("bool", serde_json::Value::Bool(value)) => TiledProperty::Bool(*value),
+                    ("int" | "object", value) => TiledProperty::Int(value.as_i64()?),
+                    ("float", value) => TiledProperty::Float(value.as_f64()?),
+                    (_, serde_json::Value::String(value)) => TiledProperty::String(value.clone()),
+                    _ => return None,
+                };
+                Some((property.name.clone(), value))
+            })
+            .collect();
+        Self(properties)
+    }
+}
+
+impl MapProperties for TiledProperties {
+    fn get_bool(&self, name: &str) -> Option<bool> {
+        TiledProperties::get_bool(self, name)
+    }
+    fn get_f32(&self, name: &str) -> Option<f32> {
+        TiledProperties::get_f32(self, name)
+    }
+    fn get_i64(&self, name: &str) -> Option<i64> {
+        TiledProperties::get_i64(self, name)
+    }
+    fn get_str(&self, name: &str) -> Option<&str> {
+        TiledProperties::get_str(self, name)
+    }
+}
+
+// Where players can be placed when a map is entered, e.g. "front_door"
+#[derive(Component, Clone, Debug)]
+pub struct SpawnPoint(pub String);
+
+// An object from one of a map's object layers
+#[derive(Component, Clone, Debug)]
+pub struct TiledObject {
+    pub name: String,
+    pub class: String,
+    pub size: Vec2, // in pixels; zero for points
+}
+
+// :: The map asset ::
+
+#[derive(Clone, Debug)]
+pub struct TiledTileset {
+    pub first_id: u32, // the map's id for this tileset's first tile
+    pub tile_count: u32,
+    pub atlas: Handle<TextureAtlas>,
+    pub terrain: HashMap<u32, Terrain>, // by index in the tileset
+}
+
+#[derive(Clone, Debug)]
+pub struct TiledTileLayer {
+    pub name: String,
+    pub kind: TileLayerKind,
+    pub visible: bool,
+    pub collision: bool, // whether its tiles block movement
+    pub collision_shapes: bool, // whether its tiles pick their TileShape, rather than being solid
+    pub tiles: Vec<u32>, // map-wide tile ids, 0 for empty, rows from the top
+}
+
+#[derive(Clone, Debug)]
+pub struct TiledObjectDef {
+    pub object: TiledObject,
+    pub position: Vec2, // the object's center, relative to the map's top-left corner
+    pub tile: Option<u32>, // for tile objects, the map-wide tile id
+    pub properties: TiledProperties,
+}
+
+#[derive(Clone, Debug, TypeUuid)]
+#[uuid = "b0f1e6a2-3c5d-4e7f-8a9b-1c2d3e4f5a6b"]
+pub struct TiledMap {
+    pub size: UVec2, // in tiles
+    pub tile_size: Vec2,
+    pub tilesets: Vec<TiledTileset>,
+    pub tile_layers: Vec<TiledTileLayer>,
+    pub objects: Vec<TiledObjectDef>,
+    pub properties: TiledProperties,
+}
+impl TiledMap {
+    // Which tileset a map-wide tile id is from, and its index in that tileset
+    pub fn find_tile(&self, id: u32) -> Option<(usize, u32)> {
+        let id = id & !FLIP_FLAGS;
+        self.tilesets.iter().enumerate()
+            .find(|(_, tileset)| id >= tileset.first_id && id < tileset.first_id + tileset.tile_count)
+            .map(|(index, tileset)| (index, id - tileset.first_id))
+    }
+    // The whole map, relative to its top-left corner
+    pub fn rect(&self) -> Rect {
+        let size = self.size.as_vec2() * self.tile_size;
+        Rect::new(0.0, -size.y, size.x, 0.0)
+    }
+}
+
+#[derive(Default)]
+pub struct TiledMapLoader;
+impl AssetLoader for TiledMapLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let path = load_context.path().to_path_buf();
+            let raw: RawMap = if is_xml(&path) {
+                tmx::parse_map(bytes)?
+            } else {
+                serde_json::from_slice(bytes)?
+            };
+            if raw.infinite {
+                return Err(TiledError("infinite maps aren't supported".to_string()).into());
+            }
+            let map_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+            let mut tilesets = Vec::new();
+            for (index, tileset) in raw.tilesets.iter().enumerate() {
+                // Tilesets in their own file have paths relative to that file
+                let (tileset, dir) = match &tileset.source {
+                    Some(source) => {
+                        let tileset_path = map_dir.join(source);
+                        let bytes = load_context.read_asset_bytes(&tileset_path).await?;
+                        let mut external: RawTileset = if is_xml(&tileset_path) {
+                            tmx::parse_tileset(&bytes)?
+                        } else {
+                            serde_json::from_slice(&bytes)?
+                        };
+                        external.firstgid = tileset.firstgid;
+                        let dir = tileset_path.parent().map(Path::to_path_buf).unwrap_or_default();
+                        (external, dir)
+                    },
+                    None => (tileset.clone(), map_dir.clone()),
+                };
+                tilesets.push(load_tileset(load_context, index, &tileset, &dir)?);
+            }
+
+            let mut map = TiledMap {
+                size: UVec2::new(raw.width, raw.height),
+                tile_size: Vec2::new(raw.tilewidth, raw.tileheight),
+                tilesets,
+                tile_layers: Vec::new(),
+                objects: Vec::new(),
+                properties: TiledProperties::from_raw(&raw.properties),
+            };
+            add_layers(&mut map, &raw.layers)?;
+            load_context.set_default_asset(LoadedAsset::new(map));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj", "tmx"]
+    }
+}
+
+fn is_xml(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext == "tmx" || ext == "tsx")
+}
+
+// Cut the tileset's image into an atlas, kept as a labeled asset of the map
+fn load_tileset(
+    load_context: &mut LoadContext,
+    index: usize,
+    tileset: &RawTileset,
+    dir: &Path,
+) -> Result<TiledTileset, TiledError> {
+    let image = tileset.image.as_ref()
+        .ok_or_else(|| TiledError("tilesets made from a collection of images aren't supported".to_string()))?;
+    if tileset.columns == 0 {
+        return Err(TiledError(format!("tileset {} has no columns", image)));
+    }
+    let image_path = AssetPath::new(dir.join(image), None);
+    let texture: Handle<Image> = load_context.get_handle(image_path.clone());
+    let rows = (tileset.tilecount + tileset.columns - 1) / tileset.columns;
+    let atlas = TextureAtlas::from_grid(
+        texture,
+        Vec2::new(tileset.tilewidth, tileset.tileheight),
+        tileset.columns as usize,
+        rows as usize,
+        Some(Vec2::splat(tileset.spacing)),
+        Some(Vec2::splat(tileset.margin)),
+    );
+    let atlas = load_context.set_labeled_asset(
+        &format!("tileset{}", index),
+        LoadedAsset::new(atlas).with_dependency(image_path));
+    // A tile's terrain is its "terrain" property, or else its class
+    let terrain = tileset.tiles.iter()
+        .filter_map(|tile| {
+            let properties = TiledProperties::from_raw(&tile.properties);
+            let name = properties.get_str("terrain").unwrap_or(&tile.class);
+            Terrain::from_name(name).map(|terrain| (tile.id, terrain))
+        })
+        .collect();
+    Ok(TiledTileset { first_id: tileset.firstgid, tile_count: tileset.tilecount, atlas, terrain })
+}
+
+// Add the layers in order, flattening groups
+fn add_layers(map: &mut TiledMap, layers: &[RawLayer]) -> Result<(), TiledError> {
+    for layer in layers {
+        match layer {
+            RawLayer::Tiles { name, visible, data, properties } => {
+                let tiles = match data {
+                    RawTileData::Gids(tiles) => tiles.clone(),
+                    RawTileData::Encoded(_) => return Err(TiledError(format!(
+                        "layer \"{}\" isn't saved as CSV (set Tile Layer Format to CSV in the map's properties)",
+                        name))),
+                };
+                let properties = TiledProperties::from_raw(properties);
+                let kind = match properties.get_str("kind") {
+                    Some("ground") => TileLayerKind::Ground,
+                    Some("decoration") => TileLayerKind::Decoration,
+                    Some("overhang") => TileLayerKind::Overhang,
+                    Some(other) => {
+                        warn!("Unknown kind \"{}\" for layer \"{}\"; using decoration", other, name);
+                        TileLayerKind::Decoration
+                    },
+                    None if map.tile_layers.is_empty() => TileLayerKind::Ground,
+                    None => TileLayerKind::Decoration,
+                };
+                map.tile_layers.push(TiledTileLayer {
+                    name: name.clone(),
+                    kind,
+                    visible: *visible,
+                    collision: properties.get_bool("collision").unwrap_or(false),
+                    collision_shapes: properties.get_bool("collision_shapes").unwrap_or(false),
+                    tiles,
+                });
+            },
+            RawLayer::Objects { objects, .. } => {
+                map.objects.extend(objects.iter().map(object_def));
+            },
+            RawLayer::Group { layers } => add_layers(map, layers)?,
+            RawLayer::Other => {},
+        }
+    }
+    Ok(())
+}
+
+fn object_def(object: &RawObject) -> TiledObjectDef {
+    let size = Vec2::new(object.width, object.height);
+    // Tiled measures y downwards, from the object's top edge
+    // (or bottom edge, for tile objects)
+    let top = if object.gid.is_some() { object.y - size.y } else { object.y };
+    TiledObjectDef {
+        object: TiledObject { name: object.name.clone(), class: object.class.clone(), size },
+        position: Vec2::new(object.x + size.x / 2.0, -(top + size.y / 2.0)),
+        tile: object.gid,
+        properties: TiledProperties::from_raw(&object.properties),
+    }
+}
+
+// :: Spawning ::
+
+// Everything spawned for a map, so it can be replaced when the map changes
+#[derive(Component)]
+pub(crate) struct TiledMapContents(Vec<Entity>);
+
+pub(crate) fn spawn_tiled_maps(
+    mut commands: Commands,
+    mut map_events: EventReader<AssetEvent<TiledMap>>,
+    maps: Res<Assets<TiledMap>>,
+    registry: Res<MapObjectRegistry>,
+    query: Query<(Entity, &Handle<TiledMap>, Option<&TiledMapContents>)>,
+) {
+    let modified_maps: Vec<_> = map_events.iter()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { handle } => Some(handle.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, handle, contents) in &query {
+        if contents.is_some() && !modified_maps.contains(handle) {
+            continue;
+        }
+        let map = match maps.get(handle) {
+            Some(map) => map,
+            None => continue, // not loaded yet; try again next frame
+        };
+        if let Some(TiledMapContents(old)) = contents {
+            for child in old {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+
+        let mut children = spawn_tile_layers(&mut commands, map);
+        for def in map.objects.iter() {
+            children.push(spawn_object(&mut commands, &registry, map, def));
+        }
+        let mut map_entity = commands.entity(entity);
+        map_entity.push_children(&children)
+            .insert((TiledMapContents(children), map.properties.clone()));
+        match collision_grid(map) {
+            Some(grid) => map_entity.insert(grid),
+            None => map_entity.remove::<CollisionGrid>(),
+        };
+    }
+}
+
+// Blocking wherever a collision layer has a tile
+fn collision_grid(map: &TiledMap) -> Option<CollisionGrid> {
+    let mut layers = map.tile_layers.iter().filter(|layer| layer.collision).peekable();
+    layers.peek()?;
+    let mut grid = CollisionGrid::new(map.size, map.tile_size);
+    for layer in layers {
+        for (index, id) in layer.tiles.iter().enumerate() {
+            if *id == 0 {
+                continue;
+            }
+            let shape = match map.find_tile(*id) {
+                Some((_, tile)) if layer.collision_shapes => TileShape::from_code(tile + 1),
+                _ => TileShape::Solid,
+            };
+            grid.set_shape(UVec2::new(index as u32 % map.size.x, index as u32 / map.size.x), shape);
+        }
+    }
+    Some(grid)
+}
+
+// One Tilemap per tileset, each with a layer for every layer in the map
+fn spawn_tile_layers(commands: &mut Commands, map: &TiledMap) -> Vec<Entity> {
+    map.tilesets.iter().enumerate()
+        .map(|(tileset_index, tileset)| {
+            let mut tilemap = Tilemap::new(map.size, map.tile_size, tileset.atlas.clone());
+            tilemap.terrain = tileset.terrain.clone();
+            for layer in map.tile_layers.iter().filter(|layer| layer.visible) {
+                let tile_layer = tilemap.add_layer(&layer.name, layer.kind);
+                for (index, id) in layer.tiles.iter().enumerate() {
+                    if let Some((tile_tileset, tile)) = map.find_tile(*id) {
+                        if tile_tileset == tileset_index {
+                            let position = UVec2::new(index as u32 % map.size.x, index as u32 / map.size.x);
+                            tile_layer.set(position, Some(tile));
+                        }
+                    }
+                }
+            }
+            let mut tilemap = commands.spawn((tilemap, SpatialBundle::default()));
+            if let Some(chunk_size) = map.properties.get_i64("chunk_size").filter(|size| *size > 0) {
+                tilemap.insert(TilemapChunks::new(chunk_size as u32));
+            }
+            tilemap.id()
+        })
+        .collect()
+}
+
+fn spawn_object(commands: &mut Commands, registry: &MapObjectRegistry, map: &TiledMap, def: &TiledObjectDef) -> Entity {
+    let mut object = commands.spawn((
+        def.object.clone(),
+        def.properties.clone(),
+        SpatialBundle::from_transform(Transform::from_translation(def.position.extend(0.0))),
+    ));
+    registry.spawn(&def.object.class, &mut object, &MapObject {
+        name: &def.object.name,
+        size: def.object.size,
+        properties: &def.properties,
+    });
+    if let Some((tileset, index)) = def.tile.and_then(|id| map.find_tile(id)) {
+        object.insert((
+            map.tilesets[tileset].atlas.clone(),
+            TextureAtlasSprite {
+                index: index as usize,
+                custom_size: Some(def.object.size),
+                ..default()
+            },
+            YSort::new(-def.object.size.y / 2.0),
+        ));
+    }
+    object.id()
+}
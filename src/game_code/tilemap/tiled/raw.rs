@@ -0,0 +1,124 @@
+// :: Tiled files, as written ::
+// The parts of a Tiled map (and tileset) file we use, matching the JSON
+// formats (.tmj/.tsj) field for field. XML files (.tmx/.tsx) are read
+// into the same structs (see tmx.rs).
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub(super) struct RawMap {
+    pub width: u32,
+    pub height: u32,
+    pub tilewidth: f32,
+    pub tileheight: f32,
+    #[serde(default)]
+    pub infinite: bool,
+    #[serde(default)]
+    pub tilesets: Vec<RawTileset>,
+    #[serde(default)]
+    pub layers: Vec<RawLayer>,
+    #[serde(default)]
+    pub properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub(super) struct RawTileset {
+    #[serde(default)]
+    pub firstgid: u32,
+    pub source: Option<String>, // for tilesets in their own file
+    pub image: Option<String>,
+    #[serde(default)]
+    pub tilewidth: f32,
+    #[serde(default)]
+    pub tileheight: f32,
+    #[serde(default)]
+    pub columns: u32,
+    #[serde(default)]
+    pub tilecount: u32,
+    #[serde(default)]
+    pub spacing: f32,
+    #[serde(default)]
+    pub margin: f32,
+    #[serde(default)]
+    pub tiles: Vec<RawTile>, // only tiles with something set on them
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub(super) struct RawTile {
+    pub id: u32,
+    // "type" before Tiled 1.9, "class" in 1.9
+    #[serde(default, alias = "type")]
+    pub class: String,
+    #[serde(default)]
+    pub properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(super) enum RawLayer {
+    #[serde(rename = "tilelayer")]
+    Tiles {
+        name: String,
+        #[serde(default = "visible_by_default")]
+        visible: bool,
+        #[serde(default)]
+        data: RawTileData,
+        #[serde(default)]
+        properties: Vec<RawProperty>,
+    },
+    #[serde(rename = "objectgroup")]
+    Objects {
+        name: String,
+        #[serde(default)]
+        objects: Vec<RawObject>,
+        #[serde(default)]
+        properties: Vec<RawProperty>,
+    },
+    Group {
+        #[serde(default)]
+        layers: Vec<RawLayer>,
+    },
+    #[serde(other)]
+    Other, // image layers
+}
+
+fn visible_by_default() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(super) enum RawTileData {
+    Gids(Vec<u32>),
+    Encoded(String), // base64; only CSV is supported
+}
+impl Default for RawTileData {
+    fn default() -> Self {
+        RawTileData::Gids(Vec::new())
+    }
+}
+
+#[derive(Deserialize)]
+pub(super) struct RawObject {
+    #[serde(default)]
+    pub name: String,
+    // "type" before Tiled 1.9, "class" in 1.9
+    #[serde(default, alias = "type")]
+    pub class: String,
+    pub x: f32,
+    pub y: f32,
+    #[serde(default)]
+    pub width: f32,
+    #[serde(default)]
+    pub height: f32,
+    pub gid: Option<u32>, // for tile objects
+    #[serde(default)]
+    pub properties: Vec<RawProperty>,
+}
+
+#[derive(Deserialize, Clone)]
+pub(super) struct RawProperty {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    pub value: serde_json::Value,
+}
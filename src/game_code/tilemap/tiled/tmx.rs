@@ -0,0 +1,154 @@
+// :: Tiled XML files ::
+// Reads .tmx maps and .tsx tilesets into the same structs as their JSON
+// versions. Only CSV tile data is supported (Tiled's default); maps saved
+// with Base64 layers should be switched to CSV in Map > Map Properties.
+use std::str::FromStr;
+
+use roxmltree::Node;
+
+use super::{
+    raw::{RawLayer, RawMap, RawObject, RawProperty, RawTile, RawTileData, RawTileset},
+    TiledError,
+};
+
+pub(super) fn parse_map(bytes: &[u8]) -> Result<RawMap, TiledError> {
+    let text = std::str::from_utf8(bytes).map_err(|err| TiledError(err.to_string()))?;
+    let doc = roxmltree::Document::parse(text).map_err(|err| TiledError(err.to_string()))?;
+    let map = doc.root_element();
+    if map.tag_name().name() != "map" {
+        return Err(TiledError(format!("Expected a <map>, found <{}>", map.tag_name().name())));
+    }
+    Ok(RawMap {
+        width: attr(map, "width")?,
+        height: attr(map, "height")?,
+        tilewidth: attr(map, "tilewidth")?,
+        tileheight: attr(map, "tileheight")?,
+        infinite: attr_or(map, "infinite", 0u8) == 1,
+        tilesets: children(map, "tileset").map(parse_tileset_node).collect::<Result<_, _>>()?,
+        layers: parse_layers(map)?,
+        properties: parse_properties(map),
+    })
+}
+
+pub(super) fn parse_tileset(bytes: &[u8]) -> Result<RawTileset, TiledError> {
+    let text = std::str::from_utf8(bytes).map_err(|err| TiledError(err.to_string()))?;
+    let doc = roxmltree::Document::parse(text).map_err(|err| TiledError(err.to_string()))?;
+    parse_tileset_node(doc.root_element())
+}
+
+fn parse_tileset_node(node: Node) -> Result<RawTileset, TiledError> {
+    Ok(RawTileset {
+        firstgid: attr_or(node, "firstgid", 0),
+        source: node.attribute("source").map(str::to_string),
+        image: children(node, "image").next()
+            .and_then(|image| image.attribute("source"))
+            .map(str::to_string),
+        tilewidth: attr_or(node, "tilewidth", 0.0),
+        tileheight: attr_or(node, "tileheight", 0.0),
+        columns: attr_or(node, "columns", 0),
+        tilecount: attr_or(node, "tilecount", 0),
+        spacing: attr_or(node, "spacing", 0.0),
+        margin: attr_or(node, "margin", 0.0),
+        tiles: children(node, "tile").map(parse_tile).collect::<Result<_, _>>()?,
+    })
+}
+
+fn parse_tile(node: Node) -> Result<RawTile, TiledError> {
+    Ok(RawTile {
+        id: attr(node, "id")?,
+        class: node.attribute("class").or_else(|| node.attribute("type")).unwrap_or_default().to_string(),
+        properties: parse_properties(node),
+    })
+}
+
+fn parse_layers(parent: Node) -> Result<Vec<RawLayer>, TiledError> {
+    let mut layers = Vec::new();
+    for node in parent.children().filter(Node::is_element) {
+        let name = node.attribute("name").unwrap_or_default().to_string();
+        let layer = match node.tag_name().name() {
+            "layer" => RawLayer::Tiles {
+                visible: attr_or(node, "visible", 1u8) == 1,
+                data: parse_tile_data(node, &name)?,
+                name,
+                properties: parse_properties(node),
+            },
+            "objectgroup" => RawLayer::Objects {
+                name,
+                objects: children(node, "object").map(parse_object).collect::<Result<_, _>>()?,
+                properties: parse_properties(node),
+            },
+            "group" => RawLayer::Group { layers: parse_layers(node)? },
+            _ => continue,
+        };
+        layers.push(layer);
+    }
+    Ok(layers)
+}
+
+fn parse_tile_data(layer: Node, name: &str) -> Result<RawTileData, TiledError> {
+    let data = match children(layer, "data").next() {
+        Some(data) => data,
+        None => return Ok(RawTileData::default()),
+    };
+    if data.attribute("encoding") != Some("csv") {
+        return Ok(RawTileData::Encoded(data.text().unwrap_or_default().to_string()));
+    }
+    data.text().unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|gid| !gid.is_empty())
+        .map(|gid| gid.parse().map_err(|_| TiledError(format!("Bad tile \"{}\" in layer \"{}\"", gid, name))))
+        .collect::<Result<_, _>>()
+        .map(RawTileData::Gids)
+}
+
+fn parse_object(node: Node) -> Result<RawObject, TiledError> {
+    Ok(RawObject {
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        class: node.attribute("class").or_else(|| node.attribute("type")).unwrap_or_default().to_string(),
+        x: attr(node, "x")?,
+        y: attr(node, "y")?,
+        width: attr_or(node, "width", 0.0),
+        height: attr_or(node, "height", 0.0),
+        gid: node.attribute("gid").and_then(|gid| gid.parse().ok()),
+        properties: parse_properties(node),
+    })
+}
+
+// Values are written as text, so turn them into the JSON values
+// the .tmj format would have
+fn parse_properties(node: Node) -> Vec<RawProperty> {
+    let properties = match children(node, "properties").next() {
+        Some(properties) => properties,
+        None => return Vec::new(),
+    };
+    children(properties, "property")
+        .map(|property| {
+            let kind = property.attribute("type").unwrap_or("string").to_string();
+            // Multi-line strings are written as the element's text instead
+            let text = property.attribute("value").or_else(|| property.text()).unwrap_or_default();
+            let value = match kind.as_str() {
+                "bool" => serde_json::Value::Bool(text == "true"),
+                "int" | "object" => text.parse::<i64>().map_or(serde_json::Value::Null, Into::into),
+                "float" => text.parse::<f64>().map_or(serde_json::Value::Null, Into::into),
+                _ => serde_json::Value::String(text.to_string()),
+            };
+            RawProperty { name: property.attribute("name").unwrap_or_default().to_string(), kind, value }
+        })
+        .collect()
+}
+
+fn children<'a, 'input>(node: Node<'a, 'input>, tag: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.has_tag_name(tag))
+}
+
+fn attr<T: FromStr>(node: Node, name: &str) -> Result<T, TiledError> {
+    let value = node.attribute(name)
+        .ok_or_else(|| TiledError(format!("<{}> is missing \"{}\"", node.tag_name().name(), name)))?;
+    value.parse()
+        .map_err(|_| TiledError(format!("<{}> has a bad \"{}\": {}", node.tag_name().name(), name, value)))
+}
+
+fn attr_or<T: FromStr>(node: Node, name: &str, default: T) -> T {
+    node.attribute(name).and_then(|value| value.parse().ok()).unwrap_or(default)
+}
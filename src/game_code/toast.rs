@@ -0,0 +1,186 @@
+// :: Toasts ::
+// Short messages that pop up at the top of the screen for a moment, then
+// slide away, e.g. "Got Apple!":
+//
+//     toasts.show("Got Apple!");
+//
+// Any system can show one without worrying about the others: up to
+// MAX_SHOWN are stacked under each other at once, and the rest wait their
+// turn, oldest first. Each drops in from just above its place, fading in,
+// and rises back out, fading away. Showing a message that's already up
+// starts its time over, rather than showing it twice.
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::ui::UI_FONT;
+
+const FONT_SIZE: f32 = 18.0;
+const MARGIN: f32 = 16.0;
+const PADDING: f32 = 8.0;
+const GAP: f32 = 4.0; // between toasts
+const DEFAULT_DURATION: f32 = 2.0;
+const MAX_SHOWN: usize = 3;
+const SLIDE_TIME: f32 = 0.3; // at the start and end of the duration
+const SLIDE_DISTANCE: f32 = 12.0; // in pixels
+const BOX_COLOR: Color = Color::rgba(0.05, 0.05, 0.1, 0.85);
+
+#[derive(Resource)]
+pub struct Toasts {
+    pub duration: f32, // how long each one shows for, in seconds
+    waiting: VecDeque<String>,
+    shown: Vec<ShownToast>, // oldest first
+}
+struct ShownToast {
+    message: String,
+    elapsed: f32,
+    nodes: Option<(Entity, Entity)>, // its box and text, once they're spawned
+}
+impl Default for Toasts {
+    fn default() -> Self {
+        Self { duration: DEFAULT_DURATION, waiting: VecDeque::new(), shown: Vec::new() }
+    }
+}
+impl Toasts {
+    pub fn show(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        if let Some(shown) = self.shown.iter_mut().find(|shown| shown.message == message) {
+            // Keep it fully in, rather than sliding in again
+            shown.elapsed = shown.elapsed.min(SLIDE_TIME);
+        } else if !self.waiting.contains(&message) {
+            self.waiting.push_back(message);
+        }
+    }
+    // The messages on the screen, oldest first
+    pub fn shown(&self) -> impl Iterator<Item = &str> {
+        self.shown.iter().map(|shown| shown.message.as_str())
+    }
+    pub fn is_empty(&self) -> bool {
+        self.shown.is_empty() && self.waiting.is_empty()
+    }
+}
+
+// Holds the toasts, stacked down from the top of the screen
+#[derive(Component)]
+struct ToastColumn;
+
+#[derive(Component)]
+struct ToastBox;
+
+#[derive(Component)]
+struct ToastText;
+
+pub struct ToastPlugin;
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Toasts>()
+            .add_startup_system(spawn_toast_column)
+            .add_system(update_toasts);
+    }
+}
+
+fn spawn_toast_column(mut commands: Commands) {
+    // Full-width, to center the toasts in
+    commands.spawn((
+        ToastColumn,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect { left: Val::Px(0.0), right: Val::Px(0.0), top: Val::Px(MARGIN), ..default() },
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            z_index: ZIndex::Global(i32::MAX - 4), // over the world's UI, under menus
+            ..default()
+        },
+    ));
+}
+
+fn update_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut toasts: ResMut<Toasts>,
+    columns: Query<Entity, With<ToastColumn>>,
+    mut boxes: Query<(&mut Style, &mut BackgroundColor), With<ToastBox>>,
+    mut texts: Query<&mut Text, With<ToastText>>,
+) {
+    if toasts.is_empty() {
+        return;
+    }
+    let toasts = toasts.bypass_change_detection(); // only changed by new toasts
+    while toasts.shown.len() < MAX_SHOWN {
+        match toasts.waiting.pop_front() {
+            Some(message) => toasts.shown.push(ShownToast { message, elapsed: 0.0, nodes: None }),
+            None => break,
+        }
+    }
+
+    let duration = toasts.duration;
+    for shown in &mut toasts.shown {
+        shown.elapsed += time.delta_seconds();
+        let (box_entity, text_entity) = match shown.nodes {
+            Some(nodes) => nodes,
+            None => {
+                let column = match columns.iter().next() {
+                    Some(column) => column,
+                    None => continue,
+                };
+                shown.nodes = Some(spawn_toast(&mut commands, &asset_server, column, &shown.message));
+                continue;
+            },
+        };
+        // How far in it is, from 0.0 (out of sight) to 1.0
+        let slide_in = (shown.elapsed / SLIDE_TIME).min(1.0);
+        let slide_out = ((duration - shown.elapsed) / SLIDE_TIME).clamp(0.0, 1.0);
+        let shown_by = slide_in.min(slide_out);
+        if let Ok((mut style, mut color)) = boxes.get_mut(box_entity) {
+            style.position.top = Val::Px(-SLIDE_DISTANCE * (1.0 - shown_by));
+            color.0.set_a(BOX_COLOR.a() * shown_by);
+        }
+        if let Ok(mut text) = texts.get_mut(text_entity) {
+            text.sections[0].style.color.set_a(shown_by);
+        }
+    }
+
+    toasts.shown.retain(|shown| {
+        let done = shown.elapsed >= duration;
+        if let (true, Some((box_entity, _))) = (done, shown.nodes) {
+            commands.entity(box_entity).despawn_recursive();
+        }
+        !done
+    });
+}
+
+// A toast's box and text, out of sight until it slides in
+fn spawn_toast(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    column: Entity,
+    message: &str,
+) -> (Entity, Entity) {
+    let text = commands.spawn((
+        ToastText,
+        TextBundle::from_section(message, TextStyle {
+            font: asset_server.load(UI_FONT),
+            font_size: FONT_SIZE,
+            color: Color::rgba(1.0, 1.0, 1.0, 0.0),
+        }),
+    )).id();
+    let toast = commands.spawn((
+        ToastBox,
+        NodeBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(PADDING)),
+                margin: UiRect::bottom(Val::Px(GAP)),
+                position: UiRect { top: Val::Px(-SLIDE_DISTANCE), ..default() },
+                ..default()
+            },
+            background_color: Color::rgba(BOX_COLOR.r(), BOX_COLOR.g(), BOX_COLOR.b(), 0.0).into(),
+            ..default()
+        },
+    )).add_child(text).id();
+    commands.entity(column).add_child(toast);
+    (toast, text)
+}
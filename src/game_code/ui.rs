@@ -0,0 +1,88 @@
+// :: UI ::
+// What the game's UI has in common, so every screen and label looks the
+// same. Load the font wherever there's text:
+//
+//     TextStyle { font: asset_server.load(UI_FONT), font_size: FONT_SIZE, color: Color::WHITE }
+//
+// Menu screens (shops, crafting, ...) are a panel in the middle of the
+// screen with a text in it, hidden until they're opened. Spawn one with
+// markers to find its root and text by, then show it for as long as it's
+// open, which holds the player still:
+//
+//     spawn_menu_screen(&mut commands, ShopRoot, ShopText);
+//
+//     show_menu_screen(screen.is_open(), CONTROL_LOCK, &mut control_lock, &mut roots);
+use bevy::prelude::*;
+
+use crate::player::PlayerControlLock;
+
+// DejaVu Sans Mono (see assets/fonts/DejaVuSansMono-LICENSE.txt)
+pub const UI_FONT: &str = "fonts/DejaVuSansMono.ttf";
+
+// For menu screens
+pub const TITLE_SIZE: f32 = 24.0;
+pub const FONT_SIZE: f32 = 18.0;
+pub const PADDING: f32 = 24.0;
+pub const MENU_LAYER: ZIndex = ZIndex::Global(i32::MAX - 3); // over other UI, but under dialogue and fades
+pub const SCREEN_COLOR: Color = Color::rgba(0.05, 0.05, 0.1, 0.92);
+pub const HEADING_COLOR: Color = Color::rgb(1.0, 0.85, 0.4);
+pub const UNSELECTED_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+pub const MESSAGE_COLOR: Color = Color::rgb(0.5, 0.9, 0.5);
+
+// A hidden, full-screen root with a panel centered in it, and an empty
+// text in the panel for the screen's contents. Returns the root.
+pub fn spawn_menu_screen(commands: &mut Commands, root: impl Bundle, text: impl Bundle) -> Entity {
+    commands.spawn((
+        root,
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            z_index: MENU_LAYER,
+            ..default()
+        },
+    )).with_children(|root| {
+        root.spawn(NodeBundle {
+            style: Style { padding: UiRect::all(Val::Px(PADDING)), ..default() },
+            background_color: SCREEN_COLOR.into(),
+            ..default()
+        }).with_children(|panel| {
+            panel.spawn((text, TextBundle::from_sections([])));
+        });
+    }).id()
+}
+
+// Show or hide a node, returning whether that changed anything. It's left
+// untouched (and so not marked changed) when it's already right.
+pub fn set_visible(visibility: &mut Mut<Visibility>, visible: bool) -> bool {
+    if visibility.is_visible == visible {
+        return false;
+    }
+    visibility.is_visible = visible;
+    true
+}
+
+// Show a menu screen's roots while it's open and hide them once it's not,
+// locking the player's controls for `lock_reason` while it's shown
+pub fn show_menu_screen<'a>(
+    open: bool,
+    lock_reason: &'static str,
+    control_lock: &mut PlayerControlLock,
+    roots: impl IntoIterator<Item = Mut<'a, Visibility>>,
+) {
+    for mut visibility in roots {
+        if !set_visible(&mut visibility, open) {
+            continue;
+        }
+        if open {
+            control_lock.lock(lock_reason);
+        } else {
+            control_lock.unlock(lock_reason);
+        }
+    }
+}
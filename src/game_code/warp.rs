@@ -0,0 +1,221 @@
+// :: Warps ::
+// Doors, stairs and cave mouths that lead to another map. A Warp goes on
+// an entity with a TriggerZone; when a player walks into it, the screen
+// fades to black (see fade.rs), the CurrentMap is despawned, the target
+// map is loaded, and the player is placed at the target map's SpawnPoint
+// with the given name before the screen fades back in:
+//
+//     commands.spawn((
+//         Warp::new("maps/house.tmj", "front_door"),
+//         TriggerZone::new(Vec2::new(16.0, 8.0)),
+//         SpatialBundle::from_transform(Transform::from_xyz(120.0, 64.0, 0.0)),
+//     ));
+//
+// The target map is a Tiled map, or an LDtk project; "maps/world.ldtk#Cave"
+// loads just the level "Cave" from it. The player entity itself is kept,
+// so its animation, direction and everything else carry over, and cameras
+// following it jump to the new map instead of sliding there.
+use bevy::{math::Rect, prelude::*};
+
+use crate::{
+    camera::{CameraBounds, CameraFollow, CameraSystem},
+    collision::{TriggerEnter, TriggerSensor},
+    fade::{fade_in, fade_out, FadeSystem, ScreenFade},
+    movement::{MovePath, Position},
+    player::{Player, PlayerControlLock},
+    tilemap::{LdtkLevelSelection, LdtkProject, SpawnPoint, TiledMap, Tilemap},
+};
+
+const FADE_TIME: f32 = 0.3; // seconds to fade out, and again to fade in
+const LOAD_TIMEOUT: f32 = 10.0; // seconds to wait for the target map before giving up on it
+const CONTROL_LOCK: &str = "warp";
+
+#[derive(Component, Clone, Debug)]
+pub struct Warp {
+    pub target_map: String, // an asset path
+    pub target_spawn: String, // the name of a SpawnPoint in the target map
+}
+impl Warp {
+    pub fn new(target_map: &str, target_spawn: &str) -> Self {
+        Self { target_map: target_map.to_string(), target_spawn: target_spawn.to_string() }
+    }
+}
+
+// The map the player is on, which is replaced when they warp. Spawning a
+// map with this (rather than a plain SpatialBundle) lets warps leave it.
+#[derive(Component)]
+pub struct CurrentMap;
+
+// Where a point in the world is on a map, for spawning things there as the
+// map's children (so they go when it does)
+pub fn map_local(map: &GlobalTransform, position: Vec3) -> Vec3 {
+    map.affine().inverse().transform_point3(position)
+}
+
+// Sent once the player has been placed in the new map, while the screen
+// is still black
+pub struct WarpFinished {
+    pub player: Entity,
+    pub map: String,
+    pub spawn: String,
+}
+
+// How far along a warp is
+#[derive(Resource, Default)]
+enum WarpTransition {
+    #[default]
+    None,
+    FadingOut { player: Entity, warp: Warp },
+    Loading { player: Entity, warp: Warp, elapsed: f32 },
+    FadingIn,
+}
+
+pub struct WarpPlugin;
+impl Plugin for WarpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WarpTransition>()
+            .add_event::<WarpFinished>()
+            .add_system(start_warps)
+            .add_system(update_warps.after(start_warps).after(FadeSystem).before(CameraSystem));
+    }
+}
+
+fn start_warps(
+    mut enters: EventReader<TriggerEnter>,
+    mut transition: ResMut<WarpTransition>,
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut screen_fade: ResMut<ScreenFade>,
+    warps: Query<&Warp>,
+    mut players: Query<Option<&mut MovePath>, (With<Player>, With<TriggerSensor>)>,
+) {
+    for enter in enters.iter() {
+        // Arriving on top of a warp doesn't set it off; the player has
+        // to step off it and back on
+        if !matches!(*transition, WarpTransition::None) {
+            continue;
+        }
+        let (warp, path) = match (warps.get(enter.zone), players.get_mut(enter.sensor)) {
+            (Ok(warp), Ok(path)) => (warp, path),
+            _ => continue,
+        };
+        if let Some(mut path) = path {
+            path.clear();
+        }
+        control_lock.lock(CONTROL_LOCK);
+        screen_fade.play(fade_out(FADE_TIME));
+        *transition = WarpTransition::FadingOut { player: enter.sensor, warp: warp.clone() };
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_warps(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut transition: ResMut<WarpTransition>,
+    mut control_lock: ResMut<PlayerControlLock>,
+    mut screen_fade: ResMut<ScreenFade>,
+    mut finished: EventWriter<WarpFinished>,
+    current_maps: Query<Entity, With<CurrentMap>>,
+    spawn_points: Query<(&SpawnPoint, &GlobalTransform)>,
+    tilemaps: Query<(&Tilemap, &GlobalTransform)>,
+    mut players: Query<(&mut Transform, Option<&mut Position>), (With<Player>, Without<CameraFollow>)>,
+    mut cameras: Query<(&CameraFollow, &mut Transform, Option<&mut CameraBounds>), Without<Player>>,
+) {
+    let next = match &mut *transition {
+        WarpTransition::None => return,
+        WarpTransition::FadingOut { player, warp } => {
+            if !screen_fade.is_covered() {
+                return;
+            }
+            for map in &current_maps {
+                commands.entity(map).despawn_recursive();
+            }
+            spawn_map(&mut commands, &asset_server, &warp.target_map);
+            WarpTransition::Loading { player: *player, warp: warp.clone(), elapsed: 0.0 }
+        }
+        WarpTransition::Loading { player, warp, elapsed } => {
+            *elapsed += time.delta_seconds();
+            // Maps are spawned a frame after they load, and their transforms
+            // placed the frame after that, so by the time a spawn point
+            // turns up here it's in the right place
+            let spawn = spawn_points.iter()
+                .find(|(spawn, _)| spawn.0 == warp.target_spawn)
+                .map(|(_, transform)| transform.translation().truncate());
+            match spawn {
+                Some(spawn) => {
+                    if let Ok((mut transform, position)) = players.get_mut(*player) {
+                        if let Some(mut position) = position {
+                            position.teleport(spawn);
+                        }
+                        transform.translation.x = spawn.x;
+                        transform.translation.y = spawn.y;
+                    }
+                    let bounds = tilemaps.iter()
+                        .map(|(tilemap, transform)| {
+                            let corner = transform.translation().truncate();
+                            let rect = tilemap.rect();
+                            Rect::from_corners(rect.min + corner, rect.max + corner)
+                        })
+                        .reduce(|a, b| a.union(b));
+                    for (follow, mut transform, camera_bounds) in &mut cameras {
+                        if follow.target != *player {
+                            continue;
+                        }
+                        transform.translation.x = spawn.x;
+                        transform.translation.y = spawn.y;
+                        if let (Some(mut camera_bounds), Some(bounds)) = (camera_bounds, bounds) {
+                            camera_bounds.0 = bounds;
+                        }
+                    }
+                    finished.send(WarpFinished {
+                        player: *player,
+                        map: warp.target_map.clone(),
+                        spawn: warp.target_spawn.clone(),
+                    });
+                }
+                None if *elapsed >= LOAD_TIMEOUT => {
+                    warn!("Couldn't warp to \"{}\" in {}: it didn't load, or has no spawn point with that name",
+                          warp.target_spawn, warp.target_map);
+                }
+                None => return,
+            }
+            screen_fade.play(fade_in(FADE_TIME));
+            WarpTransition::FadingIn
+        }
+        WarpTransition::FadingIn => {
+            if screen_fade.is_fading() {
+                return;
+            }
+            control_lock.unlock(CONTROL_LOCK);
+            WarpTransition::None
+        }
+    };
+    *transition = next;
+}
+
+// A Tiled map, or an LDtk project (optionally "#Level" for one level of it)
+fn spawn_map(commands: &mut Commands, asset_server: &AssetServer, target_map: &str) {
+    let (path, level) = match target_map.split_once('#') {
+        Some((path, level)) => (path, Some(level)),
+        None => (target_map, None),
+    };
+    if path.ends_with(".ldtk") {
+        let selection = match level {
+            Some(level) => LdtkLevelSelection::One(level.to_string()),
+            None => LdtkLevelSelection::All,
+        };
+        commands.spawn((
+            CurrentMap,
+            asset_server.load::<LdtkProject, _>(path),
+            selection,
+            SpatialBundle::default(),
+        ));
+    } else {
+        commands.spawn((
+            CurrentMap,
+            asset_server.load::<TiledMap, _>(path),
+            SpatialBundle::default(),
+        ));
+    }
+}
@@ -0,0 +1,57 @@
+// :: Y-sorting ::
+// In a top-down world, things lower on the screen are closer to the
+// viewer, so they should be drawn in front. Anything with a YSort gets
+// its z set from its y every frame: the player walking below a tree is
+// drawn over it, and walking above it is drawn behind it.
+//
+// The sort point is the entity's position plus `offset`, which should be
+// where it touches the ground (e.g., -16.0 for the feet of a 32-pixel-tall
+// sprite anchored at its center).
+use bevy::prelude::*;
+
+use crate::movement::MovementSystem;
+
+// The z of something sorted at y = 0; lower things go above this, higher
+// things below. Keeps sorted entities well inside the camera's z range.
+const YSORT_BASE_Z: f32 = 500.0;
+// How much z changes per pixel of y. Small enough that maps thousands
+// of pixels tall still fit in the camera's z range.
+const YSORT_Z_PER_PIXEL: f32 = 0.01;
+
+#[derive(Component, Clone, Copy, Default)]
+pub struct YSort {
+    pub offset: f32, // from the entity's position to its sort point, in pixels
+}
+impl YSort {
+    pub fn new(offset: f32) -> Self {
+        Self { offset }
+    }
+}
+
+// Systems that move sorted entities should run `.before(YSortSystem)`
+#[derive(SystemLabel)]
+pub struct YSortSystem;
+
+pub struct YSortPlugin;
+impl Plugin for YSortPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(y_sort.label(YSortSystem).after(MovementSystem));
+    }
+}
+
+fn y_sort(
+    mut query: Query<(&YSort, &mut Transform, Option<&Parent>)>,
+    parents: Query<&GlobalTransform>,
+) {
+    for (ysort, mut transform, parent) in &mut query {
+        // Children (e.g., objects in a map) are sorted by where they are in
+        // the world, so they sort against everything else
+        let parent_pos = parent.and_then(|parent| parents.get(parent.get()).ok())
+            .map_or(Vec3::ZERO, |parent| parent.translation());
+        let y = parent_pos.y + transform.translation.y + ysort.offset;
+        let z = YSORT_BASE_Z - y * YSORT_Z_PER_PIXEL - parent_pos.z;
+        if transform.translation.z != z {
+            transform.translation.z = z;
+        }
+    }
+}